@@ -0,0 +1,70 @@
+//! Rough comparison of a lock-sized header against a cache line sized one, under contention.
+//!
+//! Reader threads take and release read locks as fast as they can, while a writer thread keeps bumping a counter at
+//! the start of the data without ever touching the lock. With the bare lock, the counter shares a cache line with the
+//! lock word and every bump gets in the readers' way. Run with `cargo bench -p winmmf --bench header`.
+
+use std::{
+    hint::black_box,
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+    thread,
+    time::Instant,
+};
+use winmmf::{
+    padded::Padded,
+    states::{MMFLock, RWLock},
+};
+
+/// Amount of lock/unlock pairs per reader thread.
+const ROUNDS: u32 = 2_000_000;
+/// Amount of reader threads.
+const READERS: usize = 3;
+
+/// Room for the largest header and the data behind it, starting on a cache line.
+#[repr(C, align(64))]
+struct Region([u8; 128]);
+
+fn bench<const HEADER: usize>() {
+    let mut region = Region([0; 128]);
+    let base = region.0.as_mut_ptr() as usize;
+    let header = Padded::<RWLock, HEADER>::lock_size();
+    unsafe { Padded::<RWLock, HEADER>::from_raw(base as *mut u8).initialize() };
+    let done = AtomicBool::new(false);
+
+    let elapsed = thread::scope(|scope| {
+        scope.spawn(|| {
+            // Safety: the counter lies in the region behind the header, and is only ever touched atomically.
+            let counter = unsafe { &*((base + header) as *const AtomicU64) };
+            while !done.load(Ordering::Relaxed) {
+                counter.fetch_add(1, Ordering::Relaxed);
+            }
+        });
+        let start = Instant::now();
+        let readers: Vec<_> = (0..READERS)
+            .map(|_| {
+                scope.spawn(|| {
+                    let lock = unsafe { Padded::<RWLock, HEADER>::from_existing(base as *mut u8) };
+                    for _ in 0..ROUNDS {
+                        while black_box(lock.try_lock_read()).is_err() {}
+                        black_box(lock.unlock_read()).unwrap();
+                    }
+                })
+            })
+            .collect();
+        readers.into_iter().for_each(|reader| reader.join().unwrap());
+        let elapsed = start.elapsed();
+        done.store(true, Ordering::Relaxed);
+        elapsed
+    });
+
+    println!(
+        "{header:>2} byte header: {:.2} ns/pair over {READERS} readers",
+        elapsed.as_nanos() as f64 / (ROUNDS as usize * READERS) as f64
+    );
+}
+
+fn main() {
+    // A requested header of 4 bytes is as small as the RWLock lets it get
+    bench::<4>();
+    bench::<64>();
+}
@@ -0,0 +1,40 @@
+//! Rough comparison of the lock's ordering profiles.
+//!
+//! No fancy harness here, just a hot loop of lock and unlock pairs per profile. Run with `cargo bench -p winmmf`.
+
+use std::{hint::black_box, time::Instant};
+use winmmf::states::{MMFLock, OrderingProfile, RWLock};
+
+/// Amount of lock/unlock pairs to run per profile.
+const ROUNDS: u32 = 5_000_000;
+
+fn bench(profile: OrderingProfile) {
+    let mut buf = vec![0u32; RWLock::SIZE / 4];
+    let lock = unsafe { RWLock::from_raw(buf.as_mut_ptr().cast()).initialize().with_ordering(profile) };
+
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        black_box(lock.try_lock_read()).unwrap();
+        black_box(lock.unlock_read()).unwrap();
+    }
+    let read = start.elapsed();
+
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        black_box(lock.try_lock_write()).unwrap();
+        black_box(lock.unlock_write()).unwrap();
+    }
+    let write = start.elapsed();
+
+    println!(
+        "{profile:?}: read {:.2} ns/pair, write {:.2} ns/pair",
+        read.as_nanos() as f64 / ROUNDS as f64,
+        write.as_nanos() as f64 / ROUNDS as f64
+    );
+}
+
+fn main() {
+    for profile in [OrderingProfile::Strict, OrderingProfile::Default, OrderingProfile::Relaxed] {
+        bench(profile);
+    }
+}
@@ -0,0 +1,104 @@
+#![deny(clippy::missing_docs_in_private_items)]
+#![deny(missing_docs)]
+//! # Waiting without blocking
+//!
+//! Every way to wait for a lock so far keeps the thread busy: spinning, yielding or sleeping, the thread doesn't get to
+//! do anything else in the meantime. That's fine for a thread of your own, and very much not fine for a thread of an
+//! async runtime, which has other tasks to run. The [`AsyncMMFLock`] trait defined here waits by awaiting instead,
+//! handing the thread back to the runtime in between attempts. Only available with the `async_tokio` feature, which
+//! also adds [`read_borrow_async`][crate::mmf::MemoryMappedFile::read_borrow_async] and
+//! [`write_with_async`][crate::mmf::MemoryMappedFile::write_with_async] to MMFs.
+//!
+//! ## Polling, not waking
+//! Nothing tells the runtime when a lock in shared memory is released. MMFs don't come with a handle to wait on, so
+//! there's no IOCP or other readiness event to hook into. These methods poll instead: [`try_lock_read`] or
+//! [`try_lock_write`], and if that doesn't work out, a short nap with [`tokio::time::sleep`] before trying again. The
+//! first few naps are just a [yield][tokio::task::yield_now], after that they double up to [`MAX_BACKOFF`].
+//!
+//! So the lock can be free for a while before a waiting task notices, and a task polling for a busy lock still costs a
+//! wakeup every so often. Keep the locks short, like always, and this won't matter much.
+//!
+//! [`try_lock_read`]: MMFLock::try_lock_read
+//! [`try_lock_write`]: MMFLock::try_lock_write
+
+use std::{
+    future::Future,
+    time::{Duration, Instant},
+};
+
+use super::{
+    err::{Error, MMFResult},
+    states::MMFLock,
+};
+
+/// How many attempts only [yield][tokio::task::yield_now] in between, before the sleeping starts.
+const YIELDS: u32 = 4;
+
+/// The longest nap in between attempts. Tokio's timer works in milliseconds, so the shortest one is 1 ms.
+pub const MAX_BACKOFF: Duration = Duration::from_millis(16);
+
+/// Waiting for an [`MMFLock`] from async code, without blocking the thread. See [the module docs][self].
+///
+/// Implemented for every lock that can be shared between threads. The futures are `Send`, so they can be spawned on a
+/// multi-threaded runtime. The locks themselves keep track of what they hold per instance, not per task, so hand each
+/// task an instance of its own, like you would with threads.
+///
+/// ## Usage
+/// ```no_run
+/// # use winmmf::{async_tokio::*, states::*};
+/// # async fn f() {
+/// let mut bop = vec![0u32; RWLock::SIZE / 4];
+/// let lock = unsafe { RWLock::from_raw(bop.as_mut_ptr().cast()).initialize() };
+/// lock.lock_write_async().await.unwrap();
+/// // Other tasks on this thread keep running while somebody else waits for it
+/// lock.unlock_write().unwrap();
+/// # }
+/// ```
+pub trait AsyncMMFLock: MMFLock + Sync {
+    /// Take a read lock, awaiting it for as long as it takes. Errors like [`lock_read`][MMFLock::lock_read] does, for
+    /// errors that won't go away by waiting.
+    fn lock_read_async(&self) -> impl Future<Output = MMFResult<()>> + Send + '_;
+
+    /// Take the write lock, awaiting it for as long as it takes. See [`lock_read_async`][Self::lock_read_async].
+    fn lock_write_async(&self) -> impl Future<Output = MMFResult<()>> + Send + '_;
+}
+
+impl<L: MMFLock + Sync> AsyncMMFLock for L {
+    fn lock_read_async(&self) -> impl Future<Output = MMFResult<()>> + Send + '_ {
+        acquire(move || self.try_lock_read(), None)
+    }
+
+    fn lock_write_async(&self) -> impl Future<Output = MMFResult<()>> + Send + '_ {
+        acquire(move || self.try_lock_write(), None)
+    }
+}
+
+/// Keep trying `attempt` until it succeeds or fails for good, awaiting a backoff in between. Gives up with
+/// [`Error::Timeout`] once `timeout` has passed, if there is one.
+pub(crate) async fn acquire<T>(attempt: impl Fn() -> MMFResult<T>, timeout: Option<Duration>) -> MMFResult<T> {
+    let started = Instant::now();
+    let mut tries = 0;
+    loop {
+        match attempt() {
+            Err(e) if e.worth_waiting() => {
+                if timeout.is_some_and(|timeout| started.elapsed() >= timeout) {
+                    return Err(Error::Timeout);
+                }
+                backoff(tries).await;
+                tries += 1;
+            }
+            res => return res,
+        }
+    }
+}
+
+/// Hand the thread back to the runtime for a bit, longer the more `tries` have failed already.
+async fn backoff(tries: u32) {
+    match tries.checked_sub(YIELDS) {
+        None => tokio::task::yield_now().await,
+        Some(naps) => {
+            let nap = Duration::from_millis(1u64 << naps.min(8)).min(MAX_BACKOFF);
+            tokio::time::sleep(nap).await
+        }
+    }
+}
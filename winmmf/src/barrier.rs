@@ -0,0 +1,127 @@
+#![deny(clippy::missing_docs_in_private_items)]
+#![deny(missing_docs)]
+//! # Barriers in shared memory
+//!
+//! Sometimes everyone needs to wait for everyone else: benchmarks that should start hammering the MMF at the same time,
+//! or startup protocols where nobody may go on before all processes are done setting up. The [`Barrier`] defined here
+//! holds everyone back until the last participant arrives, and lives in an MMF so the participants can be in any
+//! process.
+
+use std::{
+    num::NonZeroU32,
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+use super::{
+    err::{Error, MMFResult},
+    states::SpinStrategy,
+};
+
+/// A barrier for a fixed amount of participants, living in shared memory. See [the module docs][self].
+///
+/// Takes up [`Barrier::SIZE`] bytes: the amount of participants that arrived so far, and a generation that's bumped
+/// every time everyone made it through. That's what lets the barrier be reused right away: whoever arrives for the
+/// next round counts towards the next generation, while the stragglers of the previous round are still on their way
+/// out.
+///
+/// The amount of participants isn't stored, every instance brings its own. Make sure everyone agrees, or the barrier
+/// either opens too early or never at all.
+///
+/// ## Usage
+/// ```
+/// # use winmmf::barrier::*;
+/// # use std::num::NonZeroU32;
+/// let mut bop = vec![0u32; Barrier::SIZE / 4];
+/// let ptr = bop.as_mut_ptr() as usize;
+/// let leaders = std::thread::scope(|s| {
+///     let racers: Vec<_> = (0..4)
+///         .map(|_| {
+///             s.spawn(move || {
+///                 let barrier = unsafe { Barrier::new((ptr as *mut u32).cast(), NonZeroU32::new(4).unwrap()) };
+///                 barrier.wait().unwrap()
+///             })
+///         })
+///         .collect();
+///     racers.into_iter().filter(|r| r.join().unwrap()).count()
+/// });
+/// assert_eq!(leaders, 1);
+/// ```
+#[derive(Debug)]
+pub struct Barrier<'a> {
+    /// How many participants are waiting in the current generation.
+    arrived: &'a AtomicU32,
+    /// Bumped by the last participant to arrive, which lets everyone else go.
+    generation: &'a AtomicU32,
+    /// How many participants have to arrive before anyone may go on.
+    participants: u32,
+    /// What to do in between checks while waiting.
+    strategy: SpinStrategy,
+}
+
+impl Barrier<'_> {
+    /// The amount of bytes claimed by the barrier: the amount of participants that arrived, and the generation.
+    pub const SIZE: usize = 8;
+
+    /// Attach to the barrier at `pointer`, for `participants` participants.
+    ///
+    /// A barrier nobody used yet is all zeroes, which is what a fresh MMF is. There's no separate initialization, so
+    /// every participant can call this no matter who gets there first.
+    ///
+    /// # Safety
+    /// The pointer must be non-null, aligned to 4 and valid for [`Self::SIZE`] bytes for as long as the barrier lives.
+    /// The bytes must be zeroed or belong to a barrier for the same amount of participants.
+    pub unsafe fn new<'a>(pointer: *mut u8, participants: NonZeroU32) -> Barrier<'a> {
+        let words = pointer.cast::<u32>();
+        Barrier {
+            arrived: AtomicU32::from_ptr(words),
+            generation: AtomicU32::from_ptr(words.add(1)),
+            participants: participants.get(),
+            strategy: SpinStrategy::Busy,
+        }
+    }
+
+    /// Chainable setter for the [`SpinStrategy`] used while waiting. Only affects this instance.
+    pub fn with_spin_strategy(mut self, strategy: SpinStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Get the amount of participants this instance waits for.
+    pub fn participants(&self) -> u32 {
+        self.participants
+    }
+
+    /// Get the current generation, i.e. how often everyone made it through the barrier. Wraps around eventually.
+    pub fn generation(&self) -> u32 {
+        self.generation.load(Ordering::Acquire)
+    }
+
+    /// Arrive at the barrier and wait until all participants did, spinning according to the [`SpinStrategy`].
+    ///
+    /// Returns true for exactly one participant per generation, the last one to arrive, and false for everyone else.
+    /// Handy when somebody has to do the cleanup. There's no timeout, a participant that never shows up keeps everyone
+    /// waiting forever.
+    ///
+    /// Errors with [`Error::LockViolation`] if more participants arrived than the barrier was made for, which means
+    /// somebody disagrees on the amount. The extra arrival is undone in that case.
+    pub fn wait(&self) -> MMFResult<bool> {
+        // Before arriving, or the last one might bump the generation before we get a look at it.
+        let generation = self.generation.load(Ordering::Acquire);
+        let arrived = self.arrived.fetch_add(1, Ordering::AcqRel) + 1;
+        if arrived > self.participants {
+            self.arrived.fetch_sub(1, Ordering::AcqRel);
+            return Err(Error::LockViolation);
+        } else if arrived == self.participants {
+            // Nobody can arrive for the next generation until it's bumped, so resetting first is safe.
+            self.arrived.store(0, Ordering::Release);
+            self.generation.fetch_add(1, Ordering::AcqRel);
+            return Ok(true);
+        }
+        let mut tries = 0;
+        while self.generation.load(Ordering::Acquire) == generation {
+            tries += 1;
+            self.strategy.pause(tries);
+        }
+        Ok(false)
+    }
+}
@@ -0,0 +1,134 @@
+#![deny(clippy::missing_docs_in_private_items)]
+#![deny(missing_docs)]
+//! # Catching corrupted data
+//!
+//! The lock keeps well-behaved processes from seeing each other's writes halfway done. It doesn't help against a
+//! writer dying halfway through, somebody scribbling on the data without the lock, or the odd flipped bit. Nothing
+//! tells you the bytes are off, you just read garbage. The [`MMFWithChecksum`] defined here keeps a CRC-32 of the data
+//! next to it, updated with every write and checked with every read, so garbage shows up as an error instead.
+
+use super::{
+    err::{Error, MMFResult},
+    mmf::{MemoryMappedFile, ReadGuard},
+    states::MMFLock,
+};
+
+/// Lookup table for the CRC-32 used by zip, PNG and friends (reflected, polynomial `0xEDB88320`).
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// Compute the CRC-32 of `data`, the same one zip and PNG use.
+///
+/// ```
+/// # use winmmf::checksum::crc32;
+/// assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+/// ```
+pub fn crc32(data: &[u8]) -> u32 {
+    !data
+        .iter()
+        .fold(!0u32, |crc, &byte| CRC_TABLE[((crc ^ byte as u32) & 0xFF) as usize] ^ (crc >> 8))
+}
+
+/// A [`MemoryMappedFile`] with a CRC-32 of its data, see [the module docs][self].
+///
+/// The checksum takes up the first [`HEADER_SIZE`][MMFWithChecksum::HEADER_SIZE] bytes of the data, little endian, and
+/// the payload is everything behind it. That's all of the data, or the part in front of the lock if it lives at an
+/// [offset][MemoryMappedFile::new_with_lock_offset], like [`as_bytes`][MemoryMappedFile::as_bytes] hands it out.
+/// Everyone has to agree on that layout, so wrap the MMF on every end or none of them.
+///
+/// The checksum covers the whole payload, not just what was written last, and is computed while holding the lock.
+/// That's cheap for a few kilobytes and very much not for a few hundred megabytes, so keep that in mind. A fresh MMF
+/// doesn't have a valid checksum until somebody [commits][MMFWithChecksum::write_committed] something, reading before
+/// then errors.
+///
+/// ## Usage
+/// ```no_run
+/// # use winmmf::{checksum::MMFWithChecksum, states::RWLock, *};
+/// # use std::num::NonZeroUsize;
+/// let mmf = MemoryMappedFile::<RWLock>::new(NonZeroUsize::new(64).unwrap(), "ledger", Namespace::LOCAL).unwrap();
+/// let ledger = MMFWithChecksum::new(mmf);
+/// ledger.write_committed(b"Balance: 42").unwrap();
+/// match ledger.read_validated() {
+///     Ok(data) => println!("{:?}", &data[..11]),
+///     Err(Error::ChecksumMismatch) => eprintln!("Somebody broke the ledger"),
+///     Err(e) => eprintln!("Couldn't read: {e}"),
+/// }
+/// ```
+#[derive(Debug)]
+pub struct MMFWithChecksum<LOCK: MMFLock>(MemoryMappedFile<LOCK>);
+
+impl<LOCK: MMFLock> MMFWithChecksum<LOCK> {
+    /// The amount of bytes in front of the payload, holding the checksum.
+    pub const HEADER_SIZE: usize = 4;
+
+    /// Wrap an MMF. Nothing is checked or written until you read or write.
+    pub fn new(mmf: MemoryMappedFile<LOCK>) -> Self {
+        Self(mmf)
+    }
+
+    /// Borrow the payload, after checking it against the checksum. The read lock is held until the guard is dropped.
+    ///
+    /// Errors with [`Error::ChecksumMismatch`] if the payload doesn't match, and with
+    /// [`Error::NotEnoughMemory`] if there's no room for the checksum at all. Anything else is what
+    /// [`read_borrow`][MemoryMappedFile::read_borrow] errors with, including for MMFs
+    /// [mapped read-only][crate::mmf::Access::ReadOnly].
+    pub fn read_validated(&self) -> MMFResult<ReadGuard<'_, LOCK>> {
+        let guard = self.0.read_borrow()?;
+        if guard.len() < Self::HEADER_SIZE {
+            return Err(Error::NotEnoughMemory);
+        }
+        let (stored, payload) = guard.split_at(Self::HEADER_SIZE);
+        if crc32(payload).to_le_bytes() != stored {
+            return Err(Error::ChecksumMismatch);
+        }
+        Ok(guard.skip(Self::HEADER_SIZE))
+    }
+
+    /// Write `data` to the start of the payload and update the checksum, all under one write lock.
+    ///
+    /// Readers never see the new data without the matching checksum or the other way around. Errors with
+    /// [`Error::NotEnoughMemory`] if `data` doesn't fit in the payload, and with anything
+    /// [`write_with`][MemoryMappedFile::write_with] errors with.
+    pub fn write_committed(&self, data: &[u8]) -> MMFResult<()> {
+        let mut fits = false;
+        self.0.write_with(|region| {
+            if region.len() >= Self::HEADER_SIZE + data.len() {
+                let (stored, payload) = region.split_at_mut(Self::HEADER_SIZE);
+                payload[..data.len()].copy_from_slice(data);
+                stored.copy_from_slice(&crc32(payload).to_le_bytes());
+                fits = true;
+            }
+            0
+        })?;
+        fits.then_some(()).ok_or(Error::NotEnoughMemory)
+    }
+
+    /// Check the payload against the checksum without borrowing it, see [`read_validated`][Self::read_validated].
+    pub fn validate(&self) -> MMFResult<()> {
+        self.read_validated().map(|_| ())
+    }
+
+    /// Get the MMF, for reading without checking. [`Mmf`][crate::mmf::Mmf] reads see the checksum at the start of the
+    /// data. Writing through it leaves the checksum behind, so don't.
+    pub fn inner(&self) -> &MemoryMappedFile<LOCK> {
+        &self.0
+    }
+
+    /// Get the MMF back. The checksum stays where it is.
+    pub fn into_inner(self) -> MemoryMappedFile<LOCK> {
+        self.0
+    }
+}
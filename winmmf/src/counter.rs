@@ -0,0 +1,90 @@
+#![deny(clippy::missing_docs_in_private_items)]
+#![deny(missing_docs)]
+//! # Counters shared between processes
+//!
+//! Hit counters, worker counts and sequence numbers don't need a lock, a single atomic does the job just fine. The
+//! [`AtomicCounter`] defined here is exactly that, living in an MMF so every process sees the same number.
+
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use super::err::{Error, MMFResult};
+#[cfg(feature = "impl_mmf")]
+use super::{mmf::MemoryMappedFile, states::MMFLock};
+
+/// A `u32` counter in shared memory, see [the module docs][self].
+///
+/// Takes up 4 bytes, which have to be aligned to 4. A fresh MMF is all zeroes, so counters start at 0 without anybody
+/// setting them up. Every operation is a single atomic one, and the counter wraps around at both ends.
+///
+/// ## Usage
+/// ```
+/// # use winmmf::counter::*;
+/// let mut bop = 0u32;
+/// let counter = unsafe { AtomicCounter::from_ptr((&mut bop as *mut u32).cast()) }.unwrap();
+/// assert_eq!(counter.increment(), 1);
+/// assert_eq!(counter.increment(), 2);
+/// assert_eq!(counter.decrement(), 1);
+/// assert_eq!(counter.compare_exchange(1, 42), Ok(1));
+/// assert_eq!(counter.load(), 42);
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct AtomicCounter<'a> {
+    /// The counter itself.
+    value: &'a AtomicU32,
+}
+
+impl AtomicCounter<'_> {
+    /// The amount of bytes claimed by the counter.
+    pub const SIZE: usize = 4;
+
+    /// Attach to the counter at `pointer`. Errors with [`Error::MisalignedData`] if it isn't aligned to 4.
+    ///
+    /// # Safety
+    /// The pointer must be non-null and valid for [`Self::SIZE`] bytes for as long as the counter lives, and everyone
+    /// else must only touch those bytes atomically.
+    pub unsafe fn from_ptr<'a>(pointer: *mut u8) -> MMFResult<AtomicCounter<'a>> {
+        if pointer as usize % 4 != 0 {
+            return Err(Error::MisalignedData);
+        }
+        Ok(AtomicCounter { value: AtomicU32::from_ptr(pointer.cast()) })
+    }
+
+    /// Attach to the counter `offset` bytes into the data of `mmf`, counted like for
+    /// [`ptr`][MemoryMappedFile::ptr].
+    ///
+    /// Errors with [`Error::NotEnoughMemory`] if it doesn't fit in the data (or runs into the lock, if that lives at an
+    /// offset), and with [`Error::MisalignedData`] if it isn't aligned to 4. The counter bypasses the MMF's lock, which
+    /// is fine as long as nobody writes those bytes through the MMF.
+    #[cfg(feature = "impl_mmf")]
+    pub fn from_mmf<L: MMFLock>(mmf: &MemoryMappedFile<L>, offset: usize) -> MMFResult<AtomicCounter<'_>> {
+        let pointer = mmf.data_region(offset, Self::SIZE, 4)?;
+        // Safety: the region was checked to be in the data and aligned, and it lives as long as the MMF.
+        unsafe { Self::from_ptr(pointer) }
+    }
+
+    /// Add one, and get the new value.
+    pub fn increment(&self) -> u32 {
+        self.value.fetch_add(1, Ordering::AcqRel).wrapping_add(1)
+    }
+
+    /// Subtract one, and get the new value.
+    pub fn decrement(&self) -> u32 {
+        self.value.fetch_sub(1, Ordering::AcqRel).wrapping_sub(1)
+    }
+
+    /// Get the current value.
+    pub fn load(&self) -> u32 {
+        self.value.load(Ordering::Acquire)
+    }
+
+    /// Overwrite the current value.
+    pub fn store(&self, val: u32) {
+        self.value.store(val, Ordering::Release)
+    }
+
+    /// Set the counter to `new` if it's currently `expected`. Returns the value before, wrapped in `Ok` if it was
+    /// replaced and `Err` if it wasn't, like [`AtomicU32::compare_exchange`].
+    pub fn compare_exchange(&self, expected: u32, new: u32) -> Result<u32, u32> {
+        self.value.compare_exchange(expected, new, Ordering::AcqRel, Ordering::Acquire)
+    }
+}
@@ -7,40 +7,242 @@
 
 use microseh::Exception;
 use std::{borrow::Cow, error::Error as stderr, fmt};
-use windows::core::{Error as WErr, HRESULT};
+use windows::{
+    core::{Error as WErr, HRESULT},
+    Win32::Foundation::{ERROR_ACCESS_DENIED, ERROR_PRIVILEGE_NOT_HELD},
+};
 
 /// Errors used with Memory-Mapped Files.
+///
+/// New variants get added whenever there's something worth telling apart, like the
+/// [local][Error::LocalReaderLimit] and [global][Error::MaxReaders] reader limits. So this is non-exhaustive: match
+/// on what you care about, and use [`is_retryable`][Error::is_retryable] and friends for the rest.
 #[allow(non_camel_case_types)]
 #[derive(Debug, Clone)]
 #[repr(u8)]
+#[non_exhaustive]
 pub enum Error {
     /// Readlocked, don't write.
+    ///
+    /// Retryable: the readers will leave eventually.
     ReadLocked = 0,
     /// Writelocked, don't touch.
+    ///
+    /// Retryable: the writer will leave eventually.
     WriteLocked = 1,
     /// Uninitialized, who's to say what's in there?
     /// Although MMFs created in this crate will just be nulls.
+    ///
+    /// Fatal: retrying won't initialize anything.
     Uninitialized = 2,
-    /// 127+ concurrent readers, wtf
+    /// (2^24) - 1 concurrent readers across everyone using the lock, wtf
+    ///
+    /// Fatal: the system is in a pathological state, somebody is leaking locks on a massive scale.
     MaxReaders = 3,
     /// It's too big ~~onii-chan~~
+    ///
+    /// Neither retryable nor fatal, the data just doesn't fit.
     NotEnoughMemory = 4,
     /// These are not the bytes you're looking for
+    ///
+    /// Neither retryable nor fatal, open or create an MMF first.
     MMF_NotFound = 5,
     /// Something else was racing you, this is scary.
+    ///
+    /// Fatal: spinning already gave up, or the lock state changed in ways it shouldn't.
     LockViolation = 6,
+    /// Read and write locked at the same time. Somebody broke the rules, and the data can't be trusted.
+    ///
+    /// Fatal: until somebody clears the poison.
+    Poisoned = 7,
+    /// Waited for the lock, but it took too long.
+    ///
+    /// Retryable: maybe you'll be luckier next time.
+    Timeout = 8,
+    /// The previous holder of the lock died without releasing it. The data might be in an inconsistent state. Only the
+    /// first writer after the cleanup gets this, see
+    /// [`RWLock::recover_abandoned`][crate::states::RWLock::recover_abandoned].
+    ///
+    /// Retryable: the lock has been cleaned up, the data hasn't. The lock methods that wait never retry on their own
+    /// after this one, so you get to hear about it.
+    Abandoned = 9,
+    /// Tried to release a lock that wasn't held.
+    ///
+    /// Neither retryable nor fatal, just a bug on the caller's end.
+    NotLocked = 10,
+    /// The OS said no. Whatever was asked for, be it creating, opening or mapping the MMF, isn't something this
+    /// process is allowed to do with it. The global namespace is one way to end up here, but far from the only one.
+    ///
+    /// An OS error, see [`Error::is_os_error`].
+    AccessDenied = 11,
+    /// The lock would end up at an address that isn't aligned for its atomics.
+    ///
+    /// Neither retryable nor fatal, pick a different offset.
+    MisalignedLock = 12,
+    /// This instance already holds as many read locks as it's allowed to, see
+    /// [`RWLock::with_local_limit`][crate::states::RWLock::with_local_limit].
+    ///
+    /// Fatal: release some of the locks you're holding first, your own code is probably leaking them.
+    LocalReaderLimit = 13,
+    /// The data in the MMF isn't aligned for the type you wanted to view it as.
+    ///
+    /// Neither retryable nor fatal, pick a type with a smaller alignment or move the data.
+    MisalignedData = 14,
+    /// Flushing the view to the backing file didn't work, the original error is kept around.
+    ///
+    /// An OS error, see [`Error::is_os_error`]. The data is still in memory, so flushing again later might work.
+    FlushFailed(WErr) = 15,
+    /// The view is [mapped read-only][crate::mmf::Access::ReadOnly], the OS won't let anyone write to it. Not even
+    /// to take the lock. Also used for [copy-on-write][crate::mmf::Access::CopyOnWrite] views when trying to change
+    /// the MMF for everyone, like resizing it.
+    ///
+    /// Neither retryable nor fatal, open the MMF for writing if you need to write.
+    ReadOnly = 16,
+    /// There's no room for the message right now, see [`MessageQueue::send`][crate::ipc::MessageQueue::send].
+    ///
+    /// Retryable: the receiver will make room eventually.
+    BufferFull = 17,
+    /// The process doesn't hold a privilege the OS wants for this. Large pages have [their own
+    /// error][Error::LargePageUnavailable].
+    ///
+    /// An OS error, see [`Error::is_os_error`]. Granting privileges is up to whoever manages the machine.
+    MissingPrivilege = 18,
+    /// All permits of a [`Semaphore`][crate::semaphore::Semaphore] are taken.
+    ///
+    /// Retryable: somebody will give some back eventually.
+    Exhausted = 19,
+    /// [Large pages][crate::mmf::CreateOptions::large_pages] can't be used: the account doesn't have
+    /// `SeLockMemoryPrivilege`, or the hardware doesn't support large pages at all.
+    ///
+    /// Neither retryable nor fatal, create the MMF without large pages or get the privilege granted. Holds the Win32
+    /// code the OS turned us down with, which [`Error::raw_os_error`] reports too: `ERROR_NOT_ALL_ASSIGNED` when the
+    /// privilege couldn't be enabled, `ERROR_PRIVILEGE_NOT_HELD` when creating the MMF still wanted it. [`None`] if the
+    /// hardware is to blame, as the OS doesn't consider that an error.
+    LargePageUnavailable(Option<i32>) = 20,
+    /// [Resizing][crate::mmf::MemoryMappedFile::resize] can only grow an MMF, this would have cut off data.
+    ///
+    /// Neither retryable nor fatal, create a new MMF if you really want a smaller one.
+    ShrinkNotAllowed = 21,
+    /// A value couldn't be serialized into or deserialized out of an MMF, with the `serde` feature. Holds what the
+    /// codec had to say about it.
+    ///
+    /// Neither retryable nor fatal, the data doesn't match the type or was never written.
+    Serialization(String) = 22,
+    /// The data doesn't match its checksum, see [`MMFWithChecksum`][crate::checksum::MMFWithChecksum]. Either nobody
+    /// wrote a checksum yet, or the data was corrupted: by a writer dying halfway, one not updating the checksum, or
+    /// plain old bit rot.
+    ///
+    /// Neither retryable nor fatal, but don't trust the data until somebody writes it again.
+    ChecksumMismatch = 23,
+    /// The MMF doesn't start with the [magic bytes][crate::header::MMF_MAGIC] of a
+    /// [`VersionedMMF`][crate::header::VersionedMMF], so it was made by something else entirely, or without a header.
+    ///
+    /// Neither retryable nor fatal, but this isn't the MMF you're looking for.
+    MagicMismatch = 24,
+    /// The MMF was made with a [header version][crate::header::MMF_CURRENT_VERSION] this build can't read. Holds the
+    /// version found in the header.
+    ///
+    /// Neither retryable nor fatal, one of the two ends needs an upgrade.
+    IncompatibleVersion(u16) = 25,
+    /// The data of a [`GrowableMMF`][crate::growable::GrowableMMF] moved to a bigger mapping, nothing happens in this
+    /// one anymore.
+    ///
+    /// Neither retryable nor fatal, [follow][crate::growable::GrowableMMF::follow] it and try again there.
+    Moved = 26,
+    /// Asked a [`Semaphore`][crate::semaphore::Semaphore] for more permits than it has in total, or gave back more than
+    /// that. Nothing was taken or given back.
+    ///
+    /// Neither retryable nor fatal, waiting won't make more permits exist. Giving back too many means somebody released
+    /// permits they never took.
+    PermitsExceeded = 27,
     /// No explanation, only errors
+    ///
+    /// Neither retryable nor fatal, as we have no clue.
     GeneralFailure = 253,
-    /// Generic OS error that we can't do much with other than catching and forwarding
+    /// Generic OS error that we can't do much with other than catching and forwarding. The original error is kept
+    /// around, see [`Error::raw_os_error`] for its code.
+    ///
+    /// An OS error, see [`Error::is_os_error`].
     OS_Err(WErr) = 254,
     /// Yes, Windows provides an error when everything is OK. Task failed successfully.
+    ///
+    /// An OS error, see [`Error::is_os_error`].
     OS_OK(WErr) = 255,
 }
 
+impl Error {
+    /// Check if trying again later might succeed, e.g. because somebody else was holding the lock.
+    ///
+    /// True for [`ReadLocked`][Error::ReadLocked], [`WriteLocked`][Error::WriteLocked], [`Timeout`][Error::Timeout],
+    /// [`Abandoned`][Error::Abandoned], [`BufferFull`][Error::BufferFull] and [`Exhausted`][Error::Exhausted].
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            Self::ReadLocked | Self::WriteLocked | Self::Timeout | Self::Abandoned | Self::BufferFull | Self::Exhausted
+        )
+    }
+
+    /// Check if waiting for the lock might make this go away, so the lock methods that wait should keep at it. That's
+    /// every retryable error except [`Abandoned`][Error::Abandoned], which the caller has to hear about.
+    pub(crate) fn worth_waiting(&self) -> bool {
+        self.is_retryable() && !matches!(self, Self::Abandoned)
+    }
+
+    /// Check if this error means the lock can't be used as is, and retrying won't change that.
+    ///
+    /// True for [`Uninitialized`][Error::Uninitialized], [`MaxReaders`][Error::MaxReaders],
+    /// [`LocalReaderLimit`][Error::LocalReaderLimit], [`LockViolation`][Error::LockViolation] and
+    /// [`Poisoned`][Error::Poisoned].
+    pub fn is_fatal(&self) -> bool {
+        matches!(
+            self,
+            Self::Uninitialized | Self::MaxReaders | Self::LocalReaderLimit | Self::LockViolation | Self::Poisoned
+        )
+    }
+
+    /// Check if this error was forwarded from the OS, rather than produced in this crate.
+    pub fn is_os_error(&self) -> bool {
+        matches!(
+            self,
+            Self::OS_Err(_) | Self::OS_OK(_) | Self::AccessDenied | Self::MissingPrivilege | Self::FlushFailed(_)
+        )
+    }
+
+    /// Wrap an error from the OS, keeping the original around. Access denied and missing privileges get their own
+    /// variants, as those are the ones you'll most likely want to handle.
+    pub(crate) fn from_os(value: WErr) -> Self {
+        if value.code() == ERROR_ACCESS_DENIED.to_hresult() {
+            Self::AccessDenied
+        } else if value.code() == ERROR_PRIVILEGE_NOT_HELD.to_hresult() {
+            Self::MissingPrivilege
+        } else {
+            Self::OS_Err(value)
+        }
+    }
+
+    /// Get the error code the OS gave us, if this error came from the OS.
+    ///
+    /// Like [`std::io::Error::raw_os_error`], this is the `GetLastError` value for Win32 errors (e.g. 183 for
+    /// `ERROR_ALREADY_EXISTS`, 5 for `ERROR_ACCESS_DENIED`). Any other `HRESULT` is returned as is.
+    pub fn raw_os_error(&self) -> Option<i32> {
+        match self {
+            Self::OS_Err(e) | Self::OS_OK(e) | Self::FlushFailed(e) => {
+                let code = e.code().0;
+                // HRESULT_FROM_WIN32 puts the Win32 code in the lower 16 bits, under facility 7 with the failure bit
+                Some(if (code as u32 & 0xFFFF_0000) == 0x8007_0000 { code & 0xFFFF } else { code })
+            }
+            Self::AccessDenied => Some(ERROR_ACCESS_DENIED.0 as i32),
+            Self::MissingPrivilege => Some(ERROR_PRIVILEGE_NOT_HELD.0 as i32),
+            Self::LargePageUnavailable(code) => *code,
+            _ => None,
+        }
+    }
+}
+
 impl stderr for Error {
     fn source(&self) -> Option<&(dyn stderr + 'static)> {
         match self {
-            Self::OS_Err(w) => Some(w),
+            Self::OS_Err(w) | Self::FlushFailed(w) => Some(w),
             _ => None,
         }
     }
@@ -95,10 +297,34 @@ impl fmt::Display for Error {
             Self::WriteLocked => Cow::from("Memory Mapped File was locked for writing"),
             Self::ReadLocked => Cow::from("Memory Mapped File was locked for reading"),
             Self::LockViolation => Cow::from("MMF was locked between checking and acquiring the lock!"),
+            Self::Poisoned => Cow::from("The lock was poisoned, it's both read and write locked"),
+            Self::Timeout => Cow::from("Timed out waiting for the lock"),
+            Self::Abandoned => Cow::from("The lock was abandoned by a process that exited while holding it"),
+            Self::NotLocked => Cow::from("Tried to release a lock that isn't held"),
+            Self::MisalignedLock => Cow::from("The lock's offset must be a multiple of 4"),
+            Self::MisalignedData => Cow::from("The data isn't aligned for the requested type"),
+            Self::FlushFailed(_) => Cow::from("Failed to flush the view to the backing file"),
+            Self::ReadOnly => Cow::from("The MMF is mapped read-only, writing isn't allowed"),
+            Self::Exhausted => Cow::from("All permits of the semaphore are taken"),
+            Self::ShrinkNotAllowed => Cow::from("Resizing can only grow the MMF, not shrink it"),
+            Self::ChecksumMismatch => Cow::from("The data doesn't match its checksum, it might be corrupted"),
+            Self::MagicMismatch => Cow::from("The MMF doesn't start with the expected magic bytes, it isn't ours"),
+            Self::IncompatibleVersion(v) => Cow::from(format!("The MMF has incompatible layout version {v:#06x}")),
+            Self::Moved => Cow::from("The data moved to a bigger mapping, follow it there"),
+            Self::PermitsExceeded => Cow::from("That's more permits than the semaphore has in total"),
+            Self::Serialization(e) => Cow::from(format!("Failed to (de)serialize the value ({e})")),
+            Self::LargePageUnavailable(Some(c)) => {
+                Cow::from(format!("E{c:02}: Large pages need SeLockMemoryPrivilege"))
+            }
+            Self::LargePageUnavailable(None) => Cow::from("The processor doesn't support large pages"),
+            Self::BufferFull => Cow::from("The queue is full, wait for the receiver to catch up"),
+            Self::AccessDenied => Cow::from("Access denied by the OS"),
+            Self::MissingPrivilege => Cow::from("A required privilege isn't held"),
             Self::NotEnoughMemory => Cow::from("The requested write was larger than the buffer size"),
             Self::MMF_NotFound => Cow::from("E002: No memory mapped file has been opened yet!"),
             Self::Uninitialized => Cow::from("Memory Mapped File was not yet initialized"),
             Self::MaxReaders => Cow::from("The maximum amount of readers is already registered"),
+            Self::LocalReaderLimit => Cow::from("This instance already holds the maximum amount of read locks"),
             Self::GeneralFailure => Cow::from("No idea what the hell happened here..."),
             Self::OS_Err(c) => Cow::from(format!("E{c:02}: Generic OS Error")),
         };
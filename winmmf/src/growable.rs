@@ -0,0 +1,298 @@
+#![deny(clippy::missing_docs_in_private_items)]
+#![deny(missing_docs)]
+//! # Growing a shared MMF
+//!
+//! Windows can't resize a file mapping, and [`MemoryMappedFile::resize`] can only recreate it once everyone else let
+//! go of it. The [`GrowableMMF`] defined here takes the other way out: it moves to a new, larger mapping under a name
+//! of its own, and leaves a note in the old one telling everyone where it went. They follow whenever they get around
+//! to it, nobody has to close anything first.
+//!
+//! ## The protocol
+//! Every mapping starts with a [header][GrowableMMF::HEADER_SIZE] of 16 bytes, little endian:
+//!
+//! | Bytes   | Meaning                                                                    |
+//! |---------|----------------------------------------------------------------------------|
+//! | `0..4`  | The version of this mapping, 0 for the original.                           |
+//! | `4..8`  | The version the data moved to, or 0 if this mapping is still the one.      |
+//! | `8..16` | The payload size of the mapping it moved to.                               |
+//!
+//! Version 0 lives under the name the MMF was created with, version `n` under that name with `~n` tacked on. To grow,
+//! the resizer takes the write lock of the current mapping, creates the next version, copies the payload over and
+//! writes the new version and size into the header of the old mapping, all before letting go of the lock. Everyone
+//! else finds the note the next time they look, and [follows][GrowableMMF::follow] it. Reading or writing a mapping
+//! that moved errors with [`Error::Moved`], so nobody writes into a mapping nobody reads anymore.
+//!
+//! The original mapping is the signpost for newcomers: right after every move, its header is pointed at the new
+//! version too. To keep it around, every [`GrowableMMF`] holds on to it for as long as it lives, even after moving on.
+//! Only the original stays mapped that way, the versions in between go away once everyone followed. That makes its
+//! note a hint at best, a step behind while a move is being wrapped up, so everyone who's already in follows the notes
+//! from version to version, and only skips ahead through the original when a version in between is gone.
+
+use std::num::NonZeroUsize;
+
+use fixedstr::ztr64;
+use windows::Win32::Foundation::ERROR_FILE_NOT_FOUND;
+
+use super::{
+    err::{Error, MMFResult},
+    mmf::{MemoryMappedFile, Mmf, Namespace},
+    states::MMFLock,
+};
+
+/// A [`MemoryMappedFile`] that can grow while others are using it, see [the module docs][self].
+///
+/// Sizes and offsets count from the start of the payload, the header is out of the way. Everyone using the MMF has to
+/// use this wrapper, as the header is part of the layout. The name needs room for the version suffix too: up to 11
+/// bytes, on top of the namespace prefix, within the 63 bytes any name gets. Longer names error with
+/// [`Error::NotEnoughMemory`].
+///
+/// ## Usage
+/// ```no_run
+/// # use winmmf::{growable::GrowableMMF, states::RWLock, *};
+/// # use std::num::NonZeroUsize;
+/// let mut ours = GrowableMMF::<RWLock>::create(NonZeroUsize::new(64).unwrap(), "catalog", Namespace::LOCAL).unwrap();
+/// let mut theirs = GrowableMMF::<RWLock>::open(NonZeroUsize::new(64).unwrap(), "catalog", Namespace::LOCAL).unwrap();
+/// ours.resize(NonZeroUsize::new(4096).unwrap()).unwrap();
+///
+/// let mut buf = [0; 8];
+/// if let Err(Error::Moved) = theirs.read_at(0, &mut buf) {
+///     theirs.follow().unwrap();
+/// }
+/// assert_eq!(theirs.size(), 4096);
+/// ```
+#[derive(Debug)]
+pub struct GrowableMMF<LOCK: MMFLock> {
+    /// The name the MMF was created with, without the namespace or a version suffix.
+    name: ztr64,
+    /// The namespace every version lives in.
+    namespace: Namespace,
+    /// The mapping currently in use.
+    mmf: MemoryMappedFile<LOCK>,
+    /// The version of [`GrowableMMF::mmf`].
+    version: u32,
+    /// The original mapping, kept around as a signpost once we moved on. `None` while it's still the one in use.
+    root: Option<MemoryMappedFile<LOCK>>,
+}
+
+impl<LOCK: MMFLock> GrowableMMF<LOCK> {
+    /// The amount of bytes in front of the payload, holding the header.
+    pub const HEADER_SIZE: usize = 16;
+    /// Where in the header the version the data moved to is.
+    const MOVED_AT: usize = 4;
+
+    /// Create a new MMF with room for `size` bytes of payload. Errors like [`MemoryMappedFile::new`] does.
+    pub fn create(size: NonZeroUsize, name: &str, namespace: Namespace) -> MMFResult<Self> {
+        let name = Self::check_name(name, namespace)?;
+        let mmf = MemoryMappedFile::new(Self::total(size)?, name, namespace)?;
+        // Fresh MMFs are zeroed, which is exactly the header of the original that didn't move anywhere.
+        Ok(Self { name, namespace, mmf, version: 0, root: None })
+    }
+
+    /// Open an existing MMF created with [`GrowableMMF::create`], following it to the latest version right away.
+    ///
+    /// `size` is the payload size it was created with, or anything smaller. Where it went from there is in the header.
+    /// Errors like [`MemoryMappedFile::open_write`] does, or like [`follow`][GrowableMMF::follow].
+    pub fn open(size: NonZeroUsize, name: &str, namespace: Namespace) -> MMFResult<Self> {
+        let name = Self::check_name(name, namespace)?;
+        let mmf = MemoryMappedFile::open_write(Self::total(size)?, name.as_str(), namespace)?;
+        let mut this = Self { name, namespace, mmf, version: 0, root: None };
+        this.follow()?;
+        Ok(this)
+    }
+
+    /// Make sure the name still fits with the longest version suffix there is.
+    fn check_name(name: &str, namespace: Namespace) -> MMFResult<ztr64> {
+        let longest = format!("{name}~{}", u32::MAX);
+        if namespace.format_name(&longest).len() != namespace.prefix().len() + longest.len() {
+            return Err(Error::NotEnoughMemory);
+        }
+        Ok(ztr64::make(name))
+    }
+
+    /// The size of the whole data for `size` bytes of payload.
+    fn total(size: NonZeroUsize) -> MMFResult<NonZeroUsize> {
+        size.checked_add(Self::HEADER_SIZE).ok_or(Error::NotEnoughMemory)
+    }
+
+    /// The name of `version`, without the namespace.
+    fn versioned_name(&self, version: u32) -> String {
+        match version {
+            0 => self.name.to_string(),
+            _ => format!("{}~{version}", self.name),
+        }
+    }
+
+    /// Read where `mmf` moved to from its header: the version and the payload size, or `None` if it didn't move.
+    fn moved_to(mmf: &MemoryMappedFile<LOCK>) -> MMFResult<Option<(u32, NonZeroUsize)>> {
+        let mut note = [0; 12];
+        mmf.read_at(Self::MOVED_AT, &mut note)?;
+        let version = u32::from_le_bytes([note[0], note[1], note[2], note[3]]);
+        let size = u64::from_le_bytes([note[4], note[5], note[6], note[7], note[8], note[9], note[10], note[11]]);
+        // A note without a size is no note at all, and it never fits anywhere if it's too large for us.
+        Ok(NonZeroUsize::new(size as usize).filter(|_| version != 0).map(|size| (version, size)))
+    }
+
+    /// Lay out a note saying the data moved to `version`, with room for `size` bytes of payload.
+    fn note(version: u32, size: NonZeroUsize) -> [u8; 12] {
+        let mut note = [0; 12];
+        note[..4].copy_from_slice(&version.to_le_bytes());
+        note[4..].copy_from_slice(&(size.get() as u64).to_le_bytes());
+        note
+    }
+
+    /// Check whether the data moved to a newer mapping, which [`follow`][GrowableMMF::follow] would switch to.
+    pub fn moved(&self) -> MMFResult<bool> {
+        Self::moved_to(&self.mmf).map(|moved| moved.is_some())
+    }
+
+    /// Switch to the latest version if the data moved. Returns whether it did.
+    ///
+    /// Follows the note in the mapping in use, which is written under its lock, from one version to the next until it
+    /// catches up. A version everyone already left is gone, in which case this skips ahead to wherever the original
+    /// mapping points, as long as that's further along. Errors like [`MemoryMappedFile::open_write`] does when the next
+    /// version can't be opened either way.
+    pub fn follow(&mut self) -> MMFResult<bool> {
+        let mut followed = false;
+        while let Some((next_version, next_size)) = Self::moved_to(&self.mmf)? {
+            let (version, next) = match self.open_version(next_version, next_size) {
+                Ok(next) => (next_version, next),
+                Err(e) if e.raw_os_error() == Some(ERROR_FILE_NOT_FOUND.0 as i32) => {
+                    // The original's note is only a hint, written after the fact, so it's only used to skip ahead.
+                    match Self::moved_to(self.root.as_ref().unwrap_or(&self.mmf))? {
+                        Some((latest, size)) if latest > next_version => (latest, self.open_version(latest, size)?),
+                        _ => return Err(e),
+                    }
+                }
+                Err(e) => return Err(e),
+            };
+            let previous = std::mem::replace(&mut self.mmf, next);
+            // Hang on to the original, drop anything in between.
+            if self.root.is_none() {
+                self.root = Some(previous);
+            }
+            self.version = version;
+            followed = true;
+        }
+        Ok(followed)
+    }
+
+    /// Open `version`, with room for `size` bytes of payload.
+    fn open_version(&self, version: u32, size: NonZeroUsize) -> MMFResult<MemoryMappedFile<LOCK>> {
+        MemoryMappedFile::open_write(Self::total(size)?, &self.versioned_name(version), self.namespace)
+    }
+
+    /// Grow the payload to `new_size` bytes by moving to a new mapping, see [the module docs][self]. Shrinking errors
+    /// with [`Error::ShrinkNotAllowed`], resizing to the current size does nothing.
+    ///
+    /// Holds the write lock of the current mapping while copying, so waits for it like
+    /// [`write_with`][MemoryMappedFile::write_with] does. If somebody else moved the data in the meantime, this
+    /// follows them first and grows from there. The new bytes are zeroed. Errors like [`MemoryMappedFile::new`] does
+    /// if the new mapping can't be created, in which case nothing moved.
+    pub fn resize(&mut self, new_size: NonZeroUsize) -> MMFResult<()> {
+        loop {
+            self.follow()?;
+            if new_size.get() < self.size() {
+                return Err(Error::ShrinkNotAllowed);
+            } else if new_size.get() == self.size() {
+                return Ok(());
+            }
+            let version = self.version.checked_add(1).ok_or(Error::NotEnoughMemory)?;
+            let name = self.versioned_name(version);
+            let mut next = None;
+            let mut res = Ok(());
+            self.mmf.write_with(|data| {
+                // Somebody beat us to it while we waited for the lock, start over from where they went.
+                if data[Self::MOVED_AT..Self::MOVED_AT + 4] != [0; 4] {
+                    return 0;
+                }
+                let created = Self::total(new_size)
+                    .and_then(|total| MemoryMappedFile::<LOCK>::new(total, name.as_str(), self.namespace))
+                    .and_then(|mmf| {
+                        mmf.write_at(0, &version.to_le_bytes())?;
+                        mmf.write_at(Self::HEADER_SIZE, &data[Self::HEADER_SIZE..])?;
+                        Ok(mmf)
+                    });
+                match created {
+                    Ok(mmf) => {
+                        data[Self::MOVED_AT..Self::HEADER_SIZE].copy_from_slice(&Self::note(version, new_size));
+                        next = Some(mmf);
+                    }
+                    Err(e) => res = Err(e),
+                }
+                Self::HEADER_SIZE
+            })?;
+            res?;
+            let Some(next) = next else {
+                continue;
+            };
+            // Newcomers find their way through the original, so its note has to point here too.
+            if let Some(root) = &self.root {
+                root.write_at(Self::MOVED_AT, &Self::note(version, new_size))?;
+            }
+            let previous = std::mem::replace(&mut self.mmf, next);
+            if self.root.is_none() {
+                self.root = Some(previous);
+            }
+            self.version = version;
+            return Ok(());
+        }
+    }
+
+    /// Get the version of the mapping in use, 0 for the original.
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Get the amount of payload bytes in the mapping in use.
+    pub fn size(&self) -> usize {
+        self.mmf.size() - Self::HEADER_SIZE
+    }
+
+    /// Fill `buf` with the payload starting `offset` bytes in, like [`MemoryMappedFile::read_at`] does for the data.
+    ///
+    /// Errors with [`Error::Moved`] if the data moved to a newer mapping, [`follow`][GrowableMMF::follow] it and try
+    /// again. The check is done under the same read lock as the copy, so the data is never stale.
+    pub fn read_at(&self, offset: usize, buf: &mut [u8]) -> MMFResult<usize> {
+        let start = offset.checked_add(Self::HEADER_SIZE).ok_or(Error::NotEnoughMemory)?;
+        let data = self.mmf.read_borrow()?;
+        if data[Self::MOVED_AT..Self::MOVED_AT + 4] != [0; 4] {
+            return Err(Error::Moved);
+        }
+        let source = start
+            .checked_add(buf.len())
+            .and_then(|end| data.get(start..end))
+            .ok_or(Error::NotEnoughMemory)?;
+        buf.copy_from_slice(source);
+        Ok(buf.len())
+    }
+
+    /// Write all of `data` into the payload starting `offset` bytes in, like [`MemoryMappedFile::write_at`] does for
+    /// the data.
+    ///
+    /// Errors with [`Error::Moved`] if the data moved to a newer mapping, without writing anything. Nobody would ever
+    /// read it there. [`follow`][GrowableMMF::follow] it and try again.
+    pub fn write_at(&self, offset: usize, data: &[u8]) -> MMFResult<usize> {
+        let start = offset.checked_add(Self::HEADER_SIZE).ok_or(Error::NotEnoughMemory)?;
+        let mut res = Err(Error::Moved);
+        self.mmf.write_with(|region| {
+            if region[Self::MOVED_AT..Self::MOVED_AT + 4] != [0; 4] {
+                return 0;
+            }
+            res = match start.checked_add(data.len()).and_then(|end| region.get_mut(start..end)) {
+                Some(target) => {
+                    target.copy_from_slice(data);
+                    Ok(data.len())
+                }
+                None => Err(Error::NotEnoughMemory),
+            };
+            0
+        })?;
+        res
+    }
+
+    /// Get the mapping in use. Its reads and writes count from the start of the data, so they see the header. Leave it
+    /// be.
+    pub fn inner(&self) -> &MemoryMappedFile<LOCK> {
+        &self.mmf
+    }
+}
@@ -0,0 +1,201 @@
+#![deny(clippy::missing_docs_in_private_items)]
+#![deny(missing_docs)]
+//! # Knowing what you opened
+//!
+//! An MMF is just bytes, and nothing stops version 2 of your app from opening one made by version 1, or something else
+//! entirely that happens to use the same name. Reading it anyway gets you garbage at best. The [`VersionedMMF`]
+//! defined here puts an [`MMFHeader`] in front of the data, with magic bytes telling it's ours and a version telling
+//! what layout to expect, and refuses to open anything that doesn't check out.
+//!
+//! ## Versioning policy
+//! The version is split like a stripped down semver: the upper byte is the major version, the lower byte the minor
+//! one. [`MMF_CURRENT_VERSION`] is what this build writes, and it opens anything with the same major version.
+//!
+//! - The minor version goes up for changes older readers can live with: new [flags][MMFHeader::flags] they can
+//!   ignore, or fields added to the end of the header. New fields push the payload back, and older readers find it
+//!   through [`data_offset`][MMFHeader::data_offset] regardless. Readers can't tell what a newer minor version added,
+//!   so don't put anything in there they can't do without.
+//! - The major version goes up for anything else: fields moving around or changing meaning, or the payload no longer
+//!   starting at `data_offset`. Readers refuse other major versions with [`Error::IncompatibleVersion`], older ones and
+//!   newer ones alike.
+//!
+//! The header is this crate's business, the payload is yours. Version that separately if it can change, a flag or a
+//! version field at the start of the payload does the trick.
+
+use super::{
+    err::{Error, MMFResult},
+    mmf::{MemoryMappedFile, Mmf},
+    states::MMFLock,
+};
+
+/// The magic bytes at the very start of every [`MMFHeader`].
+pub const MMF_MAGIC: [u8; 4] = *b"WMMF";
+
+/// The version of the [`MMFHeader`] layout this build writes, 1.0. See [the module docs][self] on what's compatible.
+pub const MMF_CURRENT_VERSION: u16 = 0x0100;
+
+/// The header in front of the payload of a [`VersionedMMF`], at the start of the data (right behind the lock, unless it
+/// lives at an [offset][MemoryMappedFile::new_with_lock_offset]).
+///
+/// Stored in [`MMFHeader::SIZE`] bytes, little endian, in the order of the fields.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MMFHeader {
+    /// Should be [`MMF_MAGIC`], anything else isn't ours.
+    pub magic: [u8; 4],
+    /// The layout version, see [the module docs][self].
+    pub version: u16,
+    /// Free for applications to use, this crate doesn't look at them.
+    pub flags: u16,
+    /// Where the payload starts, counted from the start of the data. Never less than [`MMFHeader::SIZE`].
+    pub data_offset: u32,
+}
+
+impl MMFHeader {
+    /// The amount of bytes taken up by the header itself.
+    pub const SIZE: usize = 12;
+    /// Where the payload starts in MMFs made by this build: right behind the header, rounded up to 16 bytes.
+    pub const DATA_OFFSET: u32 = 16;
+
+    /// A header for the current version, with the given flags.
+    pub fn new(flags: u16) -> Self {
+        Self { magic: MMF_MAGIC, version: MMF_CURRENT_VERSION, flags, data_offset: Self::DATA_OFFSET }
+    }
+
+    /// Get the major version, the upper byte of [`version`][MMFHeader::version].
+    pub fn major(&self) -> u8 {
+        (self.version >> 8) as u8
+    }
+
+    /// Get the minor version, the lower byte of [`version`][MMFHeader::version].
+    pub fn minor(&self) -> u8 {
+        self.version as u8
+    }
+
+    /// Check this is a header this build can work with.
+    ///
+    /// Errors with [`Error::MagicMismatch`] if the magic bytes are off or the payload would start inside the header,
+    /// and with [`Error::IncompatibleVersion`] for any other major version.
+    pub fn validate(&self) -> MMFResult<()> {
+        if self.magic != MMF_MAGIC || (self.data_offset as usize) < Self::SIZE {
+            Err(Error::MagicMismatch)
+        } else if self.major() != (MMF_CURRENT_VERSION >> 8) as u8 {
+            Err(Error::IncompatibleVersion(self.version))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Lay the header out the way it's stored.
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        let mut bytes = [0; Self::SIZE];
+        bytes[..4].copy_from_slice(&self.magic);
+        bytes[4..6].copy_from_slice(&self.version.to_le_bytes());
+        bytes[6..8].copy_from_slice(&self.flags.to_le_bytes());
+        bytes[8..].copy_from_slice(&self.data_offset.to_le_bytes());
+        bytes
+    }
+
+    /// Read a header the way it's stored. Doesn't check anything, see [`validate`][MMFHeader::validate] for that.
+    pub fn from_bytes(bytes: &[u8; Self::SIZE]) -> Self {
+        Self {
+            magic: [bytes[0], bytes[1], bytes[2], bytes[3]],
+            version: u16::from_le_bytes([bytes[4], bytes[5]]),
+            flags: u16::from_le_bytes([bytes[6], bytes[7]]),
+            data_offset: u32::from_le_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]),
+        }
+    }
+}
+
+/// A [`MemoryMappedFile`] with an [`MMFHeader`] in front of the data, see [the module docs][self].
+///
+/// Whoever creates the MMF [writes the header][VersionedMMF::create], everyone else [checks it][VersionedMMF::open].
+/// The reads and writes count their offsets from the start of the payload, so the header is out of the way.
+///
+/// ## Usage
+/// ```no_run
+/// # use winmmf::{header::*, states::RWLock, *};
+/// # use std::num::NonZeroUsize;
+/// let size = NonZeroUsize::new(64).unwrap();
+/// let mmf = MemoryMappedFile::<RWLock>::new(size, "settings", Namespace::LOCAL).unwrap();
+/// let ours = VersionedMMF::create(mmf, 0).unwrap();
+/// ours.write_at(0, b"volume=11").unwrap();
+///
+/// match VersionedMMF::open(MemoryMappedFile::<RWLock>::open_read(size, "settings", Namespace::LOCAL).unwrap()) {
+///     Ok(theirs) => assert_eq!(theirs.header().version, MMF_CURRENT_VERSION),
+///     Err(Error::IncompatibleVersion(v)) => eprintln!("Made by an incompatible version: {v:#06x}"),
+///     Err(e) => eprintln!("Not ours: {e}"),
+/// }
+/// ```
+#[derive(Debug)]
+pub struct VersionedMMF<LOCK: MMFLock> {
+    /// The MMF with the header and the payload.
+    mmf: MemoryMappedFile<LOCK>,
+    /// The header, as read or written when wrapping the MMF.
+    header: MMFHeader,
+}
+
+impl<LOCK: MMFLock> VersionedMMF<LOCK> {
+    /// Wrap a freshly created MMF, writing a header for the [current version][MMF_CURRENT_VERSION] with `flags`.
+    ///
+    /// Overwrites whatever is at the start of the data, so only use this on MMFs you just made. Errors with
+    /// [`Error::NotEnoughMemory`] if there's no room for a payload behind the header, and with anything
+    /// [`write_at`][MemoryMappedFile::write_at] errors with.
+    pub fn create(mmf: MemoryMappedFile<LOCK>, flags: u16) -> MMFResult<Self> {
+        let header = MMFHeader::new(flags);
+        if mmf.size() <= header.data_offset as usize {
+            return Err(Error::NotEnoughMemory);
+        }
+        mmf.write_at(0, &header.to_bytes())?;
+        Ok(Self { mmf, header })
+    }
+
+    /// Wrap an existing MMF, after checking its header. See [`MMFHeader::validate`] for what's checked.
+    ///
+    /// Errors with [`Error::NotEnoughMemory`] if the MMF is too small to hold the header or the payload would start
+    /// past its end, and with anything [`read_at`][MemoryMappedFile::read_at] errors with.
+    pub fn open(mmf: MemoryMappedFile<LOCK>) -> MMFResult<Self> {
+        let mut bytes = [0; MMFHeader::SIZE];
+        mmf.read_at(0, &mut bytes)?;
+        let header = MMFHeader::from_bytes(&bytes);
+        header.validate()?;
+        if header.data_offset as usize > mmf.size() {
+            return Err(Error::NotEnoughMemory);
+        }
+        Ok(Self { mmf, header })
+    }
+
+    /// Get the header, as it was when the MMF was wrapped.
+    pub fn header(&self) -> &MMFHeader {
+        &self.header
+    }
+
+    /// Get the amount of payload bytes, which is the data minus the header.
+    pub fn size(&self) -> usize {
+        self.mmf.size() - self.header.data_offset as usize
+    }
+
+    /// Where `offset` bytes into the payload is in the data.
+    fn offset(&self, offset: usize) -> MMFResult<usize> {
+        offset.checked_add(self.header.data_offset as usize).ok_or(Error::NotEnoughMemory)
+    }
+
+    /// Fill `buf` with the payload starting `offset` bytes in, like [`MemoryMappedFile::read_at`] does for the data.
+    pub fn read_at(&self, offset: usize, buf: &mut [u8]) -> MMFResult<usize> {
+        self.mmf.read_at(self.offset(offset)?, buf)
+    }
+
+    /// Write `data` into the payload starting `offset` bytes in, like [`MemoryMappedFile::write_at`] does for the data.
+    pub fn write_at(&self, offset: usize, data: &[u8]) -> MMFResult<usize> {
+        self.mmf.write_at(self.offset(offset)?, data)
+    }
+
+    /// Get the MMF. Its reads and writes count from the start of the data, so they see the header. Leave it be.
+    pub fn inner(&self) -> &MemoryMappedFile<LOCK> {
+        &self.mmf
+    }
+
+    /// Get the MMF back. The header stays where it is.
+    pub fn into_inner(self) -> MemoryMappedFile<LOCK> {
+        self.mmf
+    }
+}
@@ -0,0 +1,111 @@
+#![deny(clippy::missing_docs_in_private_items)]
+#![deny(missing_docs)]
+//! # Timing the lock
+//!
+//! Counting how often a lock is contended, like the `metrics` feature does, tells you _that_ something is waiting. Not
+//! for how long, and not who's to blame. The [`LockHooks`] defined here get told how long every acquisition waited and
+//! how long the lock was held, as it happens. Hand them to an [`RWLock`] with [`RWLock::with_hooks`], and forward the
+//! numbers to whatever monitoring you already have. Only available with the `lock_hooks` feature.
+//!
+//! Locks without hooks skip all of this after checking there are none, and without the feature there's nothing to
+//! check at all.
+
+use std::{
+    panic::RefUnwindSafe,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        OnceLock,
+    },
+    time::{Duration, Instant},
+};
+
+#[cfg(doc)]
+use super::states::RWLock;
+
+/// Callbacks an [`RWLock`] calls as it's acquired and released, see [`RWLock::with_hooks`].
+///
+/// Every method does nothing by default, implement the ones you care about. They're called on the thread taking or
+/// releasing the lock, while it's held or right after, so keep them quick: whatever they take is added to the very
+/// latency they're measuring. They have to be [`RefUnwindSafe`] so the lock stays that way, which atomics and a
+/// [`Mutex`][std::sync::Mutex] are.
+///
+/// ## Usage
+/// ```
+/// # use std::{sync::{Arc, atomic::{AtomicU64, Ordering}}, time::Duration};
+/// # use winmmf::{hooks::*, states::*};
+/// #[derive(Default)]
+/// struct SlowWrites(AtomicU64);
+///
+/// impl LockHooks for SlowWrites {
+///     fn on_release_write(&self, held: Duration) {
+///         if held > Duration::from_millis(5) {
+///             self.0.fetch_add(1, Ordering::Relaxed);
+///         }
+///     }
+/// }
+///
+/// let slow = Arc::new(SlowWrites::default());
+/// let mut bop = vec![0u32; RWLock::SIZE / 4];
+/// let lock = unsafe { RWLock::from_raw(bop.as_mut_ptr().cast()).initialize() }.with_hooks(slow.clone());
+/// lock.with_write(|| ()).unwrap();
+/// assert_eq!(slow.0.load(Ordering::Relaxed), 0);
+/// ```
+pub trait LockHooks: Send + Sync + RefUnwindSafe {
+    /// A read lock was taken, after `waited` since the first attempt that found it taken. Zero if nobody was in the
+    /// way.
+    fn on_acquire_read(&self, _waited: Duration) {}
+    /// The instance let go of its last read lock, after holding at least one for `held`.
+    fn on_release_read(&self, _held: Duration) {}
+    /// The write lock was taken, after `waited` since the first attempt that found it taken. Zero if nobody was in the
+    /// way.
+    fn on_acquire_write(&self, _waited: Duration) {}
+    /// The write lock was released, or handed off, after holding it for `held`.
+    fn on_release_write(&self, _held: Duration) {}
+}
+
+/// When things started, for working out the durations handed to the [`LockHooks`]. One per lock instance.
+///
+/// Every mark is the time since [`epoch`] in nanoseconds, plus one so 0 can mean there's nothing to time.
+#[derive(Debug, Default)]
+pub(crate) struct HookClock {
+    /// Since when a read lock has been waited for.
+    pub(crate) read_waiting: AtomicU64,
+    /// Since when the write lock has been waited for.
+    pub(crate) write_waiting: AtomicU64,
+    /// Since when the instance has been holding a read lock.
+    pub(crate) read_held: AtomicU64,
+    /// Since when the instance has been holding the write lock.
+    pub(crate) write_held: AtomicU64,
+}
+
+/// The moment all marks count from, the first time anything was timed.
+fn epoch() -> Instant {
+    /// Set once, the first time it's needed.
+    static EPOCH: OnceLock<Instant> = OnceLock::new();
+    *EPOCH.get_or_init(Instant::now)
+}
+
+/// Now, as a mark.
+fn now() -> u64 {
+    epoch().elapsed().as_nanos() as u64 + 1
+}
+
+impl HookClock {
+    /// Start timing at `mark`, from now.
+    pub(crate) fn start(mark: &AtomicU64) {
+        mark.store(now(), Ordering::Relaxed);
+    }
+
+    /// Start timing at `mark`, unless it's timing already.
+    pub(crate) fn start_once(mark: &AtomicU64) {
+        _ = mark.compare_exchange(0, now(), Ordering::Relaxed, Ordering::Relaxed);
+    }
+
+    /// Stop timing at `mark`, returning how long it was timing for. Zero if it wasn't.
+    pub(crate) fn stop(mark: &AtomicU64) -> Duration {
+        match mark.swap(0, Ordering::Relaxed) {
+            0 => Duration::ZERO,
+            since => Duration::from_nanos(now().saturating_sub(since)),
+        }
+    }
+}
@@ -0,0 +1,217 @@
+#![deny(clippy::missing_docs_in_private_items)]
+#![deny(missing_docs)]
+//! # Locks with contention statistics
+//!
+//! Tuning how long a writer holds on to the lock is guesswork without knowing how often everyone else bounces off it.
+//! The [`InstrumentedLock`] defined here wraps any [`MMFLock`] and counts what happens to it: acquisitions, failed
+//! attempts and spins. The counters are process-local atomics living in the wrapper, not in the MMF, so other
+//! processes neither see nor influence them.
+//!
+//! [`RWLock`][crate::states::RWLock] has [metrics][crate::states::RWLock::metrics] of its own with the same feature.
+//! Those only count contention, these count every failure, and work for locks that aren't an `RWLock`.
+
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use super::{err::MMFResult, states::MMFLock};
+
+/// Counts of lock operations on a single [`InstrumentedLock`], see [`InstrumentedLock::stats`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct LockStats {
+    /// Successfully acquired read locks.
+    pub read_acquired: u64,
+    /// Read lock attempts that failed, for whatever reason.
+    pub read_failed: u64,
+    /// Successfully acquired write locks.
+    pub write_acquired: u64,
+    /// Write lock attempts that failed, for whatever reason.
+    pub write_failed: u64,
+    /// Calls to [`spin`][MMFLock::spin], i.e. the amount of spin iterations so far.
+    pub spins: u64,
+}
+
+/// The live counters behind [`LockStats`].
+#[derive(Debug, Default)]
+struct StatCounters {
+    /// See [`LockStats::read_acquired`].
+    read_acquired: AtomicU64,
+    /// See [`LockStats::read_failed`].
+    read_failed: AtomicU64,
+    /// See [`LockStats::write_acquired`].
+    write_acquired: AtomicU64,
+    /// See [`LockStats::write_failed`].
+    write_failed: AtomicU64,
+    /// See [`LockStats::spins`].
+    spins: AtomicU64,
+}
+
+/// Bump `acquired` or `failed` depending on how a locking attempt went, and pass the result on.
+fn record(res: MMFResult<()>, acquired: &AtomicU64, failed: &AtomicU64) -> MMFResult<()> {
+    match res {
+        Ok(_) => _ = acquired.fetch_add(1, Ordering::Relaxed),
+        Err(_) => _ = failed.fetch_add(1, Ordering::Relaxed),
+    }
+    res
+}
+
+/// A lock that counts what happens to the lock it wraps.
+///
+/// The `try_` versions, unlocking, [`spin`][MMFLock::spin] and everything that only looks at the lock are forwarded to
+/// the wrapped lock. The waiting versions ([`lock_read`][MMFLock::lock_read],
+/// [`spin_and_lock_read`][MMFLock::spin_and_lock_read] and friends) are not: they're the trait's defaults, built on
+/// this wrapper's own `try_` versions and spinning, so every attempt and every spin in between is counted. That also
+/// means a lock with a better way to wait than spinning, like a [`KernelLock`][crate::kernel_lock::KernelLock], spins
+/// when wrapped.
+///
+/// ## Usage
+/// ```
+/// # use winmmf::{instrumented::*, states::*};
+/// let mut bop = vec![0u32; RWLock::SIZE / 4];
+/// let lock = unsafe { InstrumentedLock::<RWLock>::from_raw(bop.as_mut_ptr().cast()).initialize() };
+/// lock.try_lock_write().unwrap();
+/// assert!(lock.try_lock_read().is_err());
+/// assert_eq!(lock.stats(), LockStats { write_acquired: 1, read_failed: 1, ..Default::default() });
+/// ```
+#[derive(Debug)]
+pub struct InstrumentedLock<L: MMFLock> {
+    /// The lock doing the actual work.
+    inner: L,
+    /// What happened to it so far.
+    counters: StatCounters,
+}
+
+impl<L: MMFLock> InstrumentedLock<L> {
+    /// Start counting for an existing lock.
+    pub fn new(inner: L) -> Self {
+        Self { inner, counters: StatCounters::default() }
+    }
+
+    /// Get a snapshot of the counters.
+    pub fn stats(&self) -> LockStats {
+        LockStats {
+            read_acquired: self.counters.read_acquired.load(Ordering::Relaxed),
+            read_failed: self.counters.read_failed.load(Ordering::Relaxed),
+            write_acquired: self.counters.write_acquired.load(Ordering::Relaxed),
+            write_failed: self.counters.write_failed.load(Ordering::Relaxed),
+            spins: self.counters.spins.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Get the wrapped lock.
+    pub fn inner(&self) -> &L {
+        &self.inner
+    }
+
+    /// Stop counting and get the wrapped lock back.
+    pub fn into_inner(self) -> L {
+        self.inner
+    }
+}
+
+impl<L: MMFLock> MMFLock for InstrumentedLock<L> {
+    fn initialized(&self) -> bool {
+        self.inner.initialized()
+    }
+
+    fn readlocked(&self) -> bool {
+        self.inner.readlocked()
+    }
+
+    fn writelocked(&self) -> bool {
+        self.inner.writelocked()
+    }
+
+    fn locked(&self) -> bool {
+        self.inner.locked()
+    }
+
+    fn is_poisoned(&self) -> bool {
+        self.inner.is_poisoned()
+    }
+
+    fn generation(&self) -> Option<u32> {
+        self.inner.generation()
+    }
+
+    fn try_lock_read(&self) -> MMFResult<()> {
+        record(self.inner.try_lock_read(), &self.counters.read_acquired, &self.counters.read_failed)
+    }
+
+    fn unlock_read(&self) -> MMFResult<()> {
+        self.inner.unlock_read()
+    }
+
+    fn try_lock_write(&self) -> MMFResult<()> {
+        record(self.inner.try_lock_write(), &self.counters.write_acquired, &self.counters.write_failed)
+    }
+
+    fn unlock_write(&self) -> MMFResult<()> {
+        self.inner.unlock_write()
+    }
+
+    fn spin(&self, tries: &mut usize) -> MMFResult<bool> {
+        self.counters.spins.fetch_add(1, Ordering::Relaxed);
+        self.inner.spin(tries)
+    }
+
+    fn spin_budget(&self, tries: &mut usize, max: usize) -> MMFResult<bool> {
+        self.counters.spins.fetch_add(1, Ordering::Relaxed);
+        self.inner.spin_budget(tries, max)
+    }
+
+    /// Takes the lock like [`lock_read_or_spin`][MMFLock::lock_read_or_spin], so every attempt is counted.
+    fn spin_and_lock_read(lock: &Self, max_tries: usize) -> MMFResult<()> {
+        lock.lock_read_or_spin(max_tries)
+    }
+
+    /// Takes the lock like [`lock_write_or_spin`][MMFLock::lock_write_or_spin], so every attempt is counted.
+    fn spin_and_lock_write(lock: &Self, max_tries: usize) -> MMFResult<()> {
+        lock.lock_write_or_spin(max_tries)
+    }
+
+    fn wait_until_unlocked(&self, timeout: Duration) -> MMFResult<()> {
+        self.inner.wait_until_unlocked(timeout)
+    }
+
+    unsafe fn from_existing(pointer: *mut u8) -> Self {
+        Self::new(L::from_existing(pointer))
+    }
+
+    unsafe fn from_raw(pointer: *mut u8) -> Self {
+        Self::new(L::from_raw(pointer))
+    }
+
+    unsafe fn from_existing_named(pointer: *mut u8, name: &str) -> Self {
+        Self::new(L::from_existing_named(pointer, name))
+    }
+
+    unsafe fn from_raw_named(pointer: *mut u8, name: &str) -> Self {
+        Self::new(L::from_raw_named(pointer, name))
+    }
+
+    fn set_init(&self) {
+        self.inner.set_init()
+    }
+
+    fn initialize(self) -> Self {
+        Self { inner: self.inner.initialize(), counters: self.counters }
+    }
+
+    fn try_claim_init(&self) -> bool {
+        self.inner.try_claim_init()
+    }
+
+    fn init_or_wait(&self, timeout: Duration) -> MMFResult<bool> {
+        self.inner.init_or_wait(timeout)
+    }
+
+    fn wait_initialized(&self, timeout: Duration) -> MMFResult<()> {
+        self.inner.wait_initialized(timeout)
+    }
+
+    fn lock_size() -> usize {
+        L::lock_size()
+    }
+}
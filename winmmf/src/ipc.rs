@@ -0,0 +1,486 @@
+#![deny(clippy::missing_docs_in_private_items)]
+#![deny(missing_docs)]
+//! # Lock-free IPC on top of an MMF
+//!
+//! Taking a lock for every message is a lot of ceremony when there's exactly one process writing and exactly one
+//! process reading. The [`RingBuffer`] defined here skips the lock entirely: the producer only ever moves the head, the
+//! consumer only ever moves the tail, and both live in the MMF itself so each side can see how far the other got.
+//!
+//! ## Layout
+//! The first [`HEADER_SIZE`] bytes of the MMF hold three `u32`s: the head, the tail and the capacity, followed by 4
+//! bytes that are reserved for now. The data follows right behind. Head and tail count from 0 up to twice the
+//! capacity before wrapping around, which is how a full buffer is told apart from an empty one without wasting a byte.
+//!
+//! ## Memory ordering
+//! Each side loads its own index `Relaxed`, as nobody else ever writes it. The other side's index is loaded with
+//! `Acquire`, and each side publishes its own index with `Release` after it's done copying. That pairs up so that the
+//! consumer never sees a head that's ahead of the data, and the producer never sees a tail that's ahead of the reads,
+//! which is all SPSC needs. On x86 these are plain loads and stores, the orderings only keep the compiler in line.
+//!
+//! ## Messages
+//! Bytes are all a [`RingBuffer`] knows, it's up to you to figure out where one message ends and the next begins. The
+//! [`MessageQueue`] does that for you: every message is stored as a `u32` length followed by the message itself. It
+//! uses the same layout, with a sequence number in the reserved bytes, but sits behind the [`RWLock`] of the MMF. That
+//! costs a lock for every message, and buys any amount of senders and receivers.
+//!
+//! The [`LockedRingBuffer`] sits in between: bytes like a [`RingBuffer`], behind the lock like a [`MessageQueue`].
+
+use std::{
+    num::NonZeroUsize,
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+#[cfg(feature = "impl_lock")]
+use super::states::{MMFLock, RWLock};
+use super::{
+    err::{Error, MMFResult},
+    mmf::{MemoryMappedFile, Namespace},
+    states::NoLock,
+};
+
+/// The bytes reserved for the ring buffer's bookkeeping at the start of the MMF.
+pub const HEADER_SIZE: usize = 16;
+
+/// Offset of the head, where the producer writes next.
+const HEAD: usize = 0;
+/// Offset of the tail, where the consumer reads next.
+const TAIL: usize = 4;
+/// Offset of the capacity, so opening the buffer doesn't require knowing it.
+const CAPACITY: usize = 8;
+/// Offset of a [`MessageQueue`]'s sequence number.
+#[cfg(feature = "impl_lock")]
+const SEQUENCE: usize = 12;
+/// The length in front of every message in a [`MessageQueue`].
+#[cfg(feature = "impl_lock")]
+const FRAME_HEADER: usize = 4;
+
+/// The largest capacity a ring buffer can have, as the indices have to count up to twice the capacity in a `u32`.
+pub const MAX_CAPACITY: usize = (u32::MAX / 2) as usize;
+
+/// A single-producer, single-consumer byte queue living in an MMF, see [the module docs][self].
+///
+/// Bytes go in with [`push`][RingBuffer::push] and come out in the same order with [`pop`][RingBuffer::pop]. There's
+/// no message framing, a pop takes whatever bytes are available. Both take `&mut self`, so an instance can only ever be
+/// one side at a time. Keeping it to one producer and one consumer across processes is up to you though, a second
+/// producer will happily overwrite what the first one pushed.
+///
+/// The MMF is opened with a [`NoLock`], as the lock would only get in the way.
+#[derive(Debug)]
+pub struct RingBuffer {
+    /// The MMF holding the header and the data.
+    mmf: MemoryMappedFile<NoLock>,
+    /// The amount of data bytes, as read from or written to the header.
+    capacity: u32,
+}
+
+impl RingBuffer {
+    /// Create a new ring buffer that can hold `capacity` bytes.
+    ///
+    /// Errors with [`Error::NotEnoughMemory`] if the capacity is larger than [`MAX_CAPACITY`], and with anything
+    /// [`MemoryMappedFile::new`] can error with. That includes the MMF already existing.
+    pub fn create(name: &str, namespace: Namespace, capacity: NonZeroUsize) -> MMFResult<Self> {
+        if capacity.get() > MAX_CAPACITY {
+            return Err(Error::NotEnoughMemory);
+        }
+        let size = capacity.checked_add(HEADER_SIZE).ok_or(Error::NotEnoughMemory)?;
+        let ring = Self { mmf: MemoryMappedFile::new(size, name, namespace)?, capacity: capacity.get() as u32 };
+        // A fresh MMF is all zeroes, so the head and tail are fine as is. The capacity goes last, it's what tells
+        // anyone opening the buffer that it's ready.
+        ring.header(CAPACITY).store(ring.capacity, Ordering::Release);
+        Ok(ring)
+    }
+
+    /// Open a ring buffer somebody else created. The capacity is read from the header.
+    ///
+    /// Errors with [`Error::Uninitialized`] if the creator hasn't gotten around to writing the capacity yet, and with
+    /// anything [`MemoryMappedFile::open`] can error with.
+    pub fn open(name: &str, namespace: Namespace) -> MMFResult<Self> {
+        // Peek at the header first, to find out how much there is to map.
+        let header_size = NonZeroUsize::new(HEADER_SIZE).unwrap();
+        let header = Self { mmf: MemoryMappedFile::open(header_size, name, namespace, false)?, capacity: 0 };
+        let capacity = header.header(CAPACITY).load(Ordering::Acquire);
+        if capacity == 0 || capacity as usize > MAX_CAPACITY {
+            return Err(Error::Uninitialized);
+        }
+        let size = header_size.saturating_add(capacity as usize);
+        Ok(Self { mmf: MemoryMappedFile::open(size, name, namespace, false)?, capacity })
+    }
+
+    /// The amount of bytes the buffer can hold.
+    pub fn capacity(&self) -> usize {
+        self.capacity as usize
+    }
+
+    /// The amount of bytes waiting to be popped. Only a snapshot, the other side might be busy changing it.
+    pub fn len(&self) -> MMFResult<usize> {
+        let head = self.header(HEAD).load(Ordering::Acquire);
+        let tail = self.header(TAIL).load(Ordering::Acquire);
+        self.ring().used(head, tail).map(|used| used as usize)
+    }
+
+    /// Check if there's nothing to pop. Same caveats as [`len`][RingBuffer::len].
+    pub fn is_empty(&self) -> MMFResult<bool> {
+        self.len().map(|len| len == 0)
+    }
+
+    /// Push as many bytes of `data` as fit, returning how many that were. Pushing to a full buffer pushes nothing and
+    /// returns 0, it's up to you to try again later.
+    ///
+    /// Errors with [`Error::Poisoned`] if the head or tail in the header make no sense, meaning somebody else wrote to
+    /// it.
+    pub fn push(&mut self, data: &[u8]) -> MMFResult<usize> {
+        let head = self.header(HEAD).load(Ordering::Relaxed);
+        // Pairs with the consumer's release, so it's done reading whatever it freed up.
+        let tail = self.header(TAIL).load(Ordering::Acquire);
+        let ring = self.ring();
+        let count = data.len().min((self.capacity - ring.used(head, tail)?) as usize);
+        // Safety: nobody else writes to the free part.
+        let head = unsafe { ring.copy_in(head, &data[..count]) };
+        // Pairs with the consumer's acquire, so it never sees the new head before the data.
+        self.header(HEAD).store(head, Ordering::Release);
+        Ok(count)
+    }
+
+    /// Pop as many bytes as are available into `buf`, up to its length, returning how many that were. Popping from an
+    /// empty buffer pops nothing and returns 0.
+    ///
+    /// Errors like [`push`][RingBuffer::push].
+    pub fn pop(&mut self, buf: &mut [u8]) -> MMFResult<usize> {
+        let tail = self.header(TAIL).load(Ordering::Relaxed);
+        // Pairs with the producer's release, so the data behind the head is all there.
+        let head = self.header(HEAD).load(Ordering::Acquire);
+        let ring = self.ring();
+        let count = buf.len().min(ring.used(head, tail)? as usize);
+        // Safety: the producer doesn't touch the used part until we move the tail past it.
+        let tail = unsafe { ring.copy_out(tail, &mut buf[..count]) };
+        // Pairs with the producer's acquire, so it never overwrites bytes we're still copying out.
+        self.header(TAIL).store(tail, Ordering::Release);
+        Ok(count)
+    }
+
+    /// Get one of the `u32`s in the header.
+    fn header(&self, offset: usize) -> &AtomicU32 {
+        // Safety: the header lives as long as the MMF does, the view starts on a page boundary so it's aligned, and
+        // it's only ever accessed atomically.
+        unsafe { AtomicU32::from_ptr(self.mmf.ptr().add(offset).cast()) }
+    }
+
+    /// The data region behind the header.
+    fn ring(&self) -> Ring {
+        Ring { data: self.mmf.ptr().wrapping_add(HEADER_SIZE), capacity: self.capacity }
+    }
+}
+
+/// The MMF behind a [`MessageQueue`] or [`LockedRingBuffer`]: the lock, the header and the data, with the header only
+/// ever touched while holding the lock.
+#[cfg(feature = "impl_lock")]
+#[derive(Debug)]
+struct LockedRing {
+    /// The MMF holding the lock, the header and the data.
+    mmf: MemoryMappedFile<RWLock<'static>>,
+    /// The amount of data bytes, as read from or written to the header.
+    capacity: u32,
+}
+
+#[cfg(feature = "impl_lock")]
+impl LockedRing {
+    /// Create the MMF with room for `capacity` bytes of data, and write the capacity to the header.
+    fn create(name: &str, namespace: Namespace, capacity: NonZeroUsize) -> MMFResult<Self> {
+        if capacity.get() > MAX_CAPACITY {
+            return Err(Error::NotEnoughMemory);
+        }
+        let size = capacity.checked_add(HEADER_SIZE).ok_or(Error::NotEnoughMemory)?;
+        let ring = Self { mmf: MemoryMappedFile::new(size, name, namespace)?, capacity: capacity.get() as u32 };
+        ring.locked(true, |header| {
+            // Safety: we hold the write lock.
+            unsafe { header.add(CAPACITY).cast::<u32>().write(ring.capacity) };
+            Ok(())
+        })?;
+        Ok(ring)
+    }
+
+    /// Open the MMF somebody else created, reading the capacity from the header.
+    fn open(name: &str, namespace: Namespace) -> MMFResult<Self> {
+        let header_size = NonZeroUsize::new(HEADER_SIZE).unwrap();
+        let header = Self { mmf: MemoryMappedFile::open(header_size, name, namespace, false)?, capacity: 0 };
+        // Safety: we hold the read lock.
+        let capacity = header.locked(false, |header| Ok(unsafe { header.add(CAPACITY).cast::<u32>().read() }))?;
+        if capacity == 0 || capacity as usize > MAX_CAPACITY {
+            return Err(Error::Uninitialized);
+        }
+        let size = header_size.saturating_add(capacity as usize);
+        Ok(Self { mmf: MemoryMappedFile::open(size, name, namespace, false)?, capacity })
+    }
+
+    /// Run `f` on a pointer to the header while holding the write lock, or the read lock if `write` is false.
+    fn locked<T>(&self, write: bool, f: impl FnOnce(*mut u8) -> MMFResult<T>) -> MMFResult<T> {
+        let lock = self.mmf.lock();
+        let timeout = self.mmf.lock_timeout();
+        let acquired = if write { lock.lock_write_timeout(timeout)? } else { lock.lock_read_timeout(timeout)? };
+        if !acquired {
+            return Err(Error::Timeout);
+        }
+        let res = f(self.mmf.ptr());
+        if write {
+            lock.unlock_write()?;
+        } else {
+            lock.unlock_read()?;
+        }
+        res
+    }
+
+    /// Read the head and tail from the header.
+    ///
+    /// # Safety
+    /// The lock must be held, and `header` must come from [`LockedRing::locked`].
+    unsafe fn indices(header: *mut u8) -> (u32, u32) {
+        (header.add(HEAD).cast::<u32>().read(), header.add(TAIL).cast::<u32>().read())
+    }
+
+    /// The data region behind the header.
+    fn ring(&self) -> Ring {
+        Ring { data: self.mmf.ptr().wrapping_add(HEADER_SIZE), capacity: self.capacity }
+    }
+}
+
+/// A queue of messages of any length living in an MMF, see [the module docs][self].
+///
+/// Every call takes the lock of the MMF for as long as it takes to copy a message in or out, waiting for it up to the
+/// default [lock timeout][crate::mmf::LOCK_TIMEOUT]. Receiving moves the tail, so it takes the write lock just like
+/// sending does. Messages come out in the order they went in, no matter how many senders and receivers there are.
+#[cfg(feature = "impl_lock")]
+#[derive(Debug)]
+pub struct MessageQueue(LockedRing);
+
+#[cfg(feature = "impl_lock")]
+impl MessageQueue {
+    /// Create a new message queue with room for `capacity` bytes. Every message takes 4 bytes on top of its length.
+    ///
+    /// Errors like [`RingBuffer::create`].
+    pub fn create(name: &str, namespace: Namespace, capacity: NonZeroUsize) -> MMFResult<Self> {
+        LockedRing::create(name, namespace, capacity).map(Self)
+    }
+
+    /// Open a message queue somebody else created. The capacity is read from the header.
+    ///
+    /// Errors like [`RingBuffer::open`].
+    pub fn open(name: &str, namespace: Namespace) -> MMFResult<Self> {
+        LockedRing::open(name, namespace).map(Self)
+    }
+
+    /// The amount of bytes the queue can hold, including the lengths of the messages.
+    pub fn capacity(&self) -> usize {
+        self.0.capacity as usize
+    }
+
+    /// Get the amount of messages ever sent to this queue, wrapping around after `u32::MAX`. Handy for checking if
+    /// anything was sent since the last time you looked.
+    pub fn sequence(&self) -> MMFResult<u32> {
+        // Safety: we hold the read lock.
+        self.0.locked(false, |header| Ok(unsafe { header.add(SEQUENCE).cast::<u32>().read() }))
+    }
+
+    /// Send a message, all of it or nothing at all.
+    ///
+    /// Errors with [`Error::BufferFull`] if there's no room for it right now, and with [`Error::NotEnoughMemory`] if
+    /// there never will be because the message is larger than the queue. Also errors like
+    /// [`RingBuffer::push`], or with [`Error::Timeout`] if the lock stays held for too long.
+    pub fn send(&self, msg: &[u8]) -> MMFResult<()> {
+        if msg.len() > self.capacity() - FRAME_HEADER.min(self.capacity()) {
+            return Err(Error::NotEnoughMemory);
+        }
+        self.0.locked(true, |header| {
+            let ring = self.0.ring();
+            // Safety: we hold the write lock, so the header and the free part of the data are ours.
+            unsafe {
+                let (head, tail) = LockedRing::indices(header);
+                if ((ring.capacity - ring.used(head, tail)?) as usize) < FRAME_HEADER + msg.len() {
+                    return Err(Error::BufferFull);
+                }
+                let head = ring.copy_in(head, &(msg.len() as u32).to_le_bytes());
+                header.add(HEAD).cast::<u32>().write(ring.copy_in(head, msg));
+                let sequence = header.add(SEQUENCE).cast::<u32>();
+                sequence.write(sequence.read().wrapping_add(1));
+            }
+            Ok(())
+        })
+    }
+
+    /// Receive the oldest message into `buf`, replacing whatever was in it. Returns whether there was a message, an
+    /// empty queue leaves `buf` alone.
+    ///
+    /// Errors like [`MessageQueue::send`], except for the queue being full.
+    pub fn recv(&self, buf: &mut Vec<u8>) -> MMFResult<bool> {
+        self.0.locked(true, |header| {
+            let ring = self.0.ring();
+            // Safety: we hold the write lock, and only copy out as much as was used.
+            unsafe {
+                let (head, tail) = LockedRing::indices(header);
+                let used = ring.used(head, tail)? as usize;
+                if used == 0 {
+                    return Ok(false);
+                } else if used < FRAME_HEADER {
+                    return Err(Error::Poisoned);
+                }
+                let mut len = [0; FRAME_HEADER];
+                let tail = ring.copy_out(tail, &mut len);
+                let len = u32::from_le_bytes(len) as usize;
+                if len > used - FRAME_HEADER {
+                    return Err(Error::Poisoned);
+                }
+                buf.clear();
+                buf.resize(len, 0);
+                header.add(TAIL).cast::<u32>().write(ring.copy_out(tail, buf));
+            }
+            Ok(true)
+        })
+    }
+}
+
+/// A byte queue living in an MMF like a [`RingBuffer`], with the head and tail behind the [`RWLock`] of the MMF.
+///
+/// The lock is what a [`RingBuffer`] does without, and it buys the same as it does for a [`MessageQueue`]: any amount
+/// of producers and consumers, through a shared reference. Unlike a [`RingBuffer`], pushing is all or nothing, so
+/// bytes pushed in one go stay together even with several producers around. Popping takes whatever is there, so
+/// several consumers do split up the stream between them. There's no framing, use a [`MessageQueue`] if you need it.
+///
+/// The layout is the same as a [`MessageQueue`], minus the sequence number. Don't open one as the other though, the
+/// data won't make sense.
+#[cfg(feature = "impl_lock")]
+#[derive(Debug)]
+pub struct LockedRingBuffer(LockedRing);
+
+#[cfg(feature = "impl_lock")]
+impl LockedRingBuffer {
+    /// Create a new ring buffer that can hold `capacity` bytes.
+    ///
+    /// Errors like [`RingBuffer::create`].
+    pub fn create(name: &str, namespace: Namespace, capacity: NonZeroUsize) -> MMFResult<Self> {
+        LockedRing::create(name, namespace, capacity).map(Self)
+    }
+
+    /// Open a ring buffer somebody else created. The capacity is read from the header.
+    ///
+    /// Errors like [`RingBuffer::open`].
+    pub fn open(name: &str, namespace: Namespace) -> MMFResult<Self> {
+        LockedRing::open(name, namespace).map(Self)
+    }
+
+    /// The amount of bytes the buffer can hold.
+    pub fn capacity(&self) -> usize {
+        self.0.capacity as usize
+    }
+
+    /// The amount of bytes waiting to be popped. Only a snapshot, the lock is let go of before this returns.
+    pub fn len(&self) -> MMFResult<usize> {
+        self.0.locked(false, |header| {
+            // Safety: we hold the read lock.
+            let (head, tail) = unsafe { LockedRing::indices(header) };
+            self.0.ring().used(head, tail).map(|used| used as usize)
+        })
+    }
+
+    /// Check if there's nothing to pop. Same caveats as [`len`][LockedRingBuffer::len].
+    pub fn is_empty(&self) -> MMFResult<bool> {
+        self.len().map(|len| len == 0)
+    }
+
+    /// Push all of `data`, or nothing at all.
+    ///
+    /// Errors with [`Error::BufferFull`] if there's no room for it right now, and with [`Error::NotEnoughMemory`] if
+    /// there never will be because it's larger than the buffer. Also errors like [`MessageQueue::send`].
+    pub fn push(&self, data: &[u8]) -> MMFResult<()> {
+        if data.len() > self.capacity() {
+            return Err(Error::NotEnoughMemory);
+        }
+        self.0.locked(true, |header| {
+            let ring = self.0.ring();
+            // Safety: we hold the write lock, so the header and the free part of the data are ours.
+            unsafe {
+                let (head, tail) = LockedRing::indices(header);
+                if ((ring.capacity - ring.used(head, tail)?) as usize) < data.len() {
+                    return Err(Error::BufferFull);
+                }
+                header.add(HEAD).cast::<u32>().write(ring.copy_in(head, data));
+            }
+            Ok(())
+        })
+    }
+
+    /// Pop as many bytes as are available into `buf`, up to its length, returning how many that were. Popping from an
+    /// empty buffer pops nothing and returns 0.
+    ///
+    /// Errors like [`MessageQueue::recv`].
+    pub fn pop(&self, buf: &mut [u8]) -> MMFResult<usize> {
+        self.0.locked(true, |header| {
+            let ring = self.0.ring();
+            // Safety: we hold the write lock, and only copy out as much as was used.
+            unsafe {
+                let (head, tail) = LockedRing::indices(header);
+                let count = buf.len().min(ring.used(head, tail)? as usize);
+                header.add(TAIL).cast::<u32>().write(ring.copy_out(tail, &mut buf[..count]));
+                Ok(count)
+            }
+        })
+    }
+}
+
+/// The circular data region of any of the ring buffers in here, with the arithmetic for indices into it.
+///
+/// Indices count from 0 up to twice the capacity before wrapping around, see [the module docs][self].
+struct Ring {
+    /// The start of the region.
+    data: *mut u8,
+    /// The size of the region in bytes.
+    capacity: u32,
+}
+
+impl Ring {
+    /// The amount of bytes between the tail and the head, or [`Error::Poisoned`] if they're nonsense.
+    fn used(&self, head: u32, tail: u32) -> MMFResult<u32> {
+        let wrap = self.capacity * 2;
+        if head >= wrap || tail >= wrap {
+            return Err(Error::Poisoned);
+        }
+        let used = if head >= tail { head - tail } else { wrap - tail + head };
+        if used > self.capacity {
+            Err(Error::Poisoned)
+        } else {
+            Ok(used)
+        }
+    }
+
+    /// Move an index `count` bytes ahead.
+    fn advance(&self, index: u32, count: usize) -> u32 {
+        // The sum can get up to three times the capacity, which doesn't fit in a `u32` for the largest buffers.
+        ((u64::from(index) + count as u64) % (u64::from(self.capacity) * 2)) as u32
+    }
+
+    /// Turn an index into an offset in the data, and the amount of the `count` bytes that fit before wrapping around.
+    fn split(&self, index: u32, count: usize) -> (usize, usize) {
+        let start = (index % self.capacity) as usize;
+        (start, count.min(self.capacity as usize - start))
+    }
+
+    /// Copy `src` into the region starting at `index`, wrapping around the end if needed. Returns the index behind it.
+    ///
+    /// # Safety
+    /// `src` must fit in the free part of the region, and nobody else may be touching that part.
+    unsafe fn copy_in(&self, index: u32, src: &[u8]) -> u32 {
+        let (start, first) = self.split(index, src.len());
+        src.as_ptr().copy_to_nonoverlapping(self.data.add(start), first);
+        src.as_ptr().add(first).copy_to_nonoverlapping(self.data, src.len() - first);
+        self.advance(index, src.len())
+    }
+
+    /// Fill `dst` from the region starting at `index`, wrapping around the end if needed. Returns the index behind it.
+    ///
+    /// # Safety
+    /// `dst` must not be larger than the used part of the region, and nobody else may be touching that part.
+    unsafe fn copy_out(&self, index: u32, dst: &mut [u8]) -> u32 {
+        let (start, first) = self.split(index, dst.len());
+        self.data.add(start).copy_to_nonoverlapping(dst.as_mut_ptr(), first);
+        self.data.copy_to_nonoverlapping(dst.as_mut_ptr().add(first), dst.len() - first);
+        self.advance(index, dst.len())
+    }
+}
@@ -0,0 +1,460 @@
+#![deny(clippy::missing_docs_in_private_items)]
+#![deny(missing_docs)]
+//! # Locks backed by kernel objects
+//!
+//! The [`RWLock`] spins. That's great when locks are held for a handful of instructions, and not so great when a writer
+//! hangs on to the lock for a while and everyone else is burning CPU time waiting for it. The [`KernelLock`] defined
+//! here lets the OS do the waiting instead: writers are serialized through a named `Mutex`, and readers wait on a named
+//! manual-reset `Event` that's reset for as long as a writer holds (or is waiting for) the lock.
+//!
+//! The read counter still lives in the MMF, exactly where an [`RWLock`] would put it. Kernel waits alone can't tell
+//! how many readers there are, and it means a process using plain [`RWLock`]s on the same MMF will still see the lock
+//! being held. It won't be waiting on the kernel objects though, so mixing the two is "detect each other", not "play
+//! nice with each other".
+//!
+//! If creating the kernel objects fails for whatever reason, the lock quietly falls back to spinning like an
+//! [`RWLock`]. Check [`KernelLock::is_kernel_backed`] if you care.
+
+use std::{
+    ffi::CString,
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, Instant},
+};
+
+use windows::{
+    core::{Error as WErr, PCSTR},
+    Win32::{
+        Foundation::{CloseHandle, HANDLE, WAIT_ABANDONED, WAIT_ABANDONED_0, WAIT_EVENT, WAIT_OBJECT_0, WAIT_TIMEOUT},
+        System::Threading::{
+            CreateEventA, CreateMutexA, ReleaseMutex, ResetEvent, SetEvent, WaitForMultipleObjects,
+            WaitForSingleObject, INFINITE,
+        },
+    },
+};
+
+use super::{
+    err::{Error, MMFResult},
+    states::{MMFLock, RWLock},
+};
+
+/// What waiting on the event and the mutex together returns when it got the mutex.
+const GOT_MUTEX: WAIT_EVENT = WAIT_EVENT(WAIT_OBJECT_0.0 + 1);
+/// What waiting on the event and the mutex together returns when it got the mutex from a writer that died holding it.
+const GOT_ABANDONED_MUTEX: WAIT_EVENT = WAIT_EVENT(WAIT_ABANDONED_0.0 + 1);
+
+/// How long [`KernelLock::wait_until_unlocked`] polls for, when there's nothing in the kernel to wait on, before
+/// checking whether there is again.
+const POLL_SLICE: Duration = Duration::from_millis(1);
+
+/// `duration` in milliseconds, as `WaitForSingleObject` wants it. Never [`INFINITE`], that's for no timeout at all.
+fn as_ms(duration: Duration) -> u32 {
+    duration.as_millis().min((INFINITE - 1) as u128) as u32
+}
+
+/// The named kernel objects backing a [`KernelLock`]. Closes both handles when dropped.
+#[derive(Debug)]
+struct KernelObjects {
+    /// Held by the writer, for as long as it holds the lock.
+    mutex: HANDLE,
+    /// Signaled while readers may enter, reset while a writer holds or waits for the lock.
+    event: HANDLE,
+}
+
+impl KernelObjects {
+    /// Create or open the objects belonging to the MMF with the given name.
+    ///
+    /// Kernel objects share a namespace with file mappings, so the names get a suffix to not collide with the MMF.
+    fn new(name: &str) -> Option<Self> {
+        let mutex_name = CString::new(format!("{name}_wmmf_mutex")).ok()?;
+        let event_name = CString::new(format!("{name}_wmmf_event")).ok()?;
+        let mutex = unsafe { CreateMutexA(None, false, PCSTR(mutex_name.as_ptr().cast())) }.ok()?;
+        // Starts out signaled, as nobody is writing yet. If the event already exists, the state is left alone.
+        match unsafe { CreateEventA(None, true, true, PCSTR(event_name.as_ptr().cast())) } {
+            Ok(event) => Some(Self { mutex, event }),
+            Err(_) => {
+                _ = unsafe { CloseHandle(mutex) };
+                None
+            }
+        }
+    }
+}
+
+impl Drop for KernelObjects {
+    fn drop(&mut self) {
+        _ = unsafe { CloseHandle(self.event) };
+        _ = unsafe { CloseHandle(self.mutex) };
+    }
+}
+
+/// A lock that waits on named kernel objects instead of spinning.
+///
+/// The state in the MMF is laid out exactly like an [`RWLock`], so [`KernelLock::lock_size`][MMFLock::lock_size] is
+/// [`RWLock::SIZE`] bytes. The kernel objects are derived from the name of the MMF, which means a lock constructed
+/// through [`from_raw`][MMFLock::from_raw] or [`from_existing`][MMFLock::from_existing] doesn't have any and behaves
+/// like a plain [`RWLock`]. [`MemoryMappedFile`][crate::mmf::MemoryMappedFile] uses the `_named` constructors.
+///
+/// Waits are infinite by default, use [`KernelLock::with_timeout`] to change that. A wait that times out returns
+/// [`Error::Timeout`]. If a writer died while holding the lock, the next writer gets [`Error::Abandoned`] instead of
+/// the lock. The dead writer's state in the MMF is cleaned up at that point, so trying again should work. Whether the
+/// data is still any good is up to you. Readers waiting for the dead writer notice too, and are let back in.
+///
+/// The mutex lets the thread holding it right back in, so an instance holding the write lock errors with
+/// [`Error::WriteLocked`] when asked for it again, like an [`RWLock`] does.
+///
+/// ## Threads
+/// The mutex belongs to the thread that took it, and only that thread can release it. So unlike an [`RWLock`], the
+/// write lock can't change threads: release it on the thread that took it. Unlocking anywhere else clears the lock in
+/// the MMF, but fails to release the mutex and returns the OS error, leaving every other writer stuck until this
+/// thread exits. The instance itself is free to move between threads and be shared while it doesn't hold the write
+/// lock. For the same reason, don't hand the write lock to another instance with
+/// [`state().transfer_write`][RWLock::transfer_write]: the mutex doesn't go along.
+#[derive(Debug)]
+pub struct KernelLock<'a> {
+    /// The lock state in the MMF. Also used as the fallback if there are no kernel objects.
+    inner: RWLock<'a>,
+    /// The kernel objects, if they could be created.
+    kernel: Option<KernelObjects>,
+    /// How long to wait on the kernel objects, `None` to wait forever.
+    timeout: Option<Duration>,
+    /// Whether this instance owns the mutex, which is what has to be released when unlocking. Not the lock in the MMF,
+    /// as somebody might have forced that open in the meantime.
+    owns_mutex: AtomicBool,
+}
+
+impl KernelLock<'_> {
+    /// Set how long this instance waits for the lock before giving up with [`Error::Timeout`].
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Get the timeout for this instance, if any.
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// Check if this lock is backed by kernel objects, or fell back to spinning.
+    pub fn is_kernel_backed(&self) -> bool {
+        self.kernel.is_some()
+    }
+
+    /// Get the lock state in the MMF, as seen by any [`RWLock`].
+    pub fn state(&self) -> &RWLock<'_> {
+        &self.inner
+    }
+
+    /// Wrap an [`RWLock`], creating the kernel objects for `name` if there is one.
+    fn wrap<'a>(inner: RWLock<'a>, name: Option<&str>) -> KernelLock<'a> {
+        KernelLock {
+            inner,
+            kernel: name.and_then(KernelObjects::new),
+            timeout: None,
+            owns_mutex: AtomicBool::new(false),
+        }
+    }
+
+    /// The timeout in milliseconds, as `WaitForSingleObject` wants it.
+    fn timeout_ms(&self) -> u32 {
+        self.timeout.map_or(INFINITE, as_ms)
+    }
+
+    /// What's left of the timeout since `started` in milliseconds, or [`Error::Timeout`] if nothing is.
+    fn left_ms(&self, started: Instant) -> MMFResult<u32> {
+        match self.timeout {
+            None => Ok(INFINITE),
+            Some(timeout) => timeout.checked_sub(started.elapsed()).map(as_ms).ok_or(Error::Timeout),
+        }
+    }
+
+    /// Wait on one of the kernel objects for `timeout_ms` milliseconds, mapping the result onto our errors.
+    fn wait(&self, handle: HANDLE, timeout_ms: u32) -> MMFResult<()> {
+        match unsafe { WaitForSingleObject(handle, timeout_ms) } {
+            WAIT_OBJECT_0 => Ok(()),
+            WAIT_TIMEOUT => Err(Error::Timeout),
+            WAIT_ABANDONED => Err(Error::Abandoned),
+            _ => Err(WErr::from_win32().into()),
+        }
+    }
+
+    /// Wait for readers to be let in, within the timeout.
+    ///
+    /// A writer that dies holding the lock leaves the event reset, with nobody around to set it again. So this waits on
+    /// the mutex too: getting it means no writer holds the lock, and whoever got it cleans up after a dead writer if
+    /// there was one, sets the event and lets go again.
+    fn wait_readable(&self, kernel: &KernelObjects, timeout_ms: u32) -> MMFResult<()> {
+        match unsafe { WaitForMultipleObjects(&[kernel.event, kernel.mutex], false, timeout_ms) } {
+            WAIT_OBJECT_0 => Ok(()),
+            WAIT_TIMEOUT => Err(Error::Timeout),
+            got @ (GOT_MUTEX | GOT_ABANDONED_MUTEX) => {
+                if got == GOT_ABANDONED_MUTEX {
+                    _ = self.inner.force_unlock_if_dead();
+                } else if self.inner.writelocked() {
+                    // The mutex is recursive, so getting it with the lock held means this thread is the writer,
+                    // through another instance. Nothing to clean up, wait for the event like we would without it.
+                    _ = unsafe { ReleaseMutex(kernel.mutex) };
+                    return self.wait(kernel.event, timeout_ms);
+                }
+                let set = unsafe { SetEvent(kernel.event) };
+                _ = unsafe { ReleaseMutex(kernel.mutex) };
+                set.map_err(Into::into)
+            }
+            _ => Err(WErr::from_win32().into()),
+        }
+    }
+
+    /// Check if this instance holds the write lock. The mutex is recursive, so waiting on it again would let us right
+    /// back in, only to wait for ourselves to leave.
+    fn holds_write(&self) -> bool {
+        self.owns_mutex.load(Ordering::Acquire) || self.inner.snapshot().local_write_held
+    }
+
+    /// Acquire the mutex within `mutex_ms` milliseconds, stop new readers from coming in, then wait for the existing
+    /// ones to leave within `drain`. See [`KernelLock::drain_readers`].
+    fn acquire_write(&self, kernel: &KernelObjects, mutex_ms: u32, drain: Option<Duration>) -> MMFResult<()> {
+        if !self.inner.initialized() {
+            return Err(Error::Uninitialized);
+        } else if self.holds_write() {
+            return Err(Error::WriteLocked);
+        }
+        match self.wait(kernel.mutex, mutex_ms) {
+            Ok(()) => {}
+            Err(Error::Abandoned) => {
+                // We do own the mutex now, but the caller should know the data might be bad. Clean up after the dead
+                // writer and let go, so the next attempt gets a clean lock.
+                _ = self.inner.force_unlock_if_dead();
+                _ = unsafe { SetEvent(kernel.event) };
+                _ = unsafe { ReleaseMutex(kernel.mutex) };
+                return Err(Error::Abandoned);
+            }
+            Err(e) => return Err(e),
+        }
+        if let Err(e) = unsafe { ResetEvent(kernel.event) } {
+            _ = unsafe { ReleaseMutex(kernel.mutex) };
+            return Err(e.into());
+        }
+        match self.drain_readers(drain) {
+            Ok(()) => {
+                self.owns_mutex.store(true, Ordering::Release);
+                Ok(())
+            }
+            Err(e) => {
+                _ = unsafe { SetEvent(kernel.event) };
+                _ = unsafe { ReleaseMutex(kernel.mutex) };
+                Err(e)
+            }
+        }
+    }
+
+    /// Take the write lock in the MMF while holding the mutex. Readers that got in before the event was reset still
+    /// need to leave, so this spins until they do or the timeout runs out. A zero timeout only tries once, and returns
+    /// whatever stopped it.
+    fn drain_readers(&self, timeout: Option<Duration>) -> MMFResult<()> {
+        let started = std::time::Instant::now();
+        let mut tries = 0;
+        loop {
+            match self.inner.try_lock_write() {
+                Ok(()) => return Ok(()),
+                Err(Error::Uninitialized) => return Err(Error::Uninitialized),
+                Err(e) if timeout.is_some_and(|t| started.elapsed() >= t) => {
+                    return Err(if timeout == Some(Duration::ZERO) { e } else { Error::Timeout })
+                }
+                Err(_) => {
+                    tries += 1;
+                    self.inner.spin_strategy().pause(tries);
+                }
+            }
+        }
+    }
+}
+
+impl MMFLock for KernelLock<'_> {
+    fn initialized(&self) -> bool {
+        self.inner.initialized()
+    }
+
+    fn readlocked(&self) -> bool {
+        self.inner.readlocked()
+    }
+
+    fn writelocked(&self) -> bool {
+        self.inner.writelocked()
+    }
+
+    fn locked(&self) -> bool {
+        self.inner.locked()
+    }
+
+    fn is_poisoned(&self) -> bool {
+        self.inner.is_poisoned()
+    }
+
+    fn generation(&self) -> Option<u32> {
+        self.inner.generation()
+    }
+
+    /// Take a read lock in the MMF right away, like an [`RWLock`] would.
+    fn try_lock_read(&self) -> MMFResult<()> {
+        self.inner.try_lock_read()
+    }
+
+    /// Wait for the event to be signaled, then take a read lock in the MMF.
+    ///
+    /// A writer can still sneak in between the wait and taking the lock, in which case this goes back to waiting, for
+    /// whatever is left of the timeout. Errors with [`Error::WriteLocked`] right away if this instance holds the write
+    /// lock, as it would be waiting for itself.
+    fn lock_read(&self) -> MMFResult<()> {
+        let kernel = match &self.kernel {
+            Some(_) if self.holds_write() => return Err(Error::WriteLocked),
+            Some(kernel) => kernel,
+            None => return self.inner.lock_read(),
+        };
+        let started = Instant::now();
+        let mut tries = 0;
+        loop {
+            self.wait_readable(kernel, self.left_ms(started)?)?;
+            match self.inner.try_lock_read() {
+                // Writers that don't use the kernel objects leave the event signaled, so don't go hot on those.
+                Err(e) if e.worth_waiting() => {
+                    tries += 1;
+                    self.inner.spin_strategy().pause(tries);
+                }
+                res => return res,
+            }
+        }
+    }
+
+    fn unlock_read(&self) -> MMFResult<()> {
+        self.inner.unlock_read()
+    }
+
+    /// Take the write lock right away if nobody holds or waits for it, without waiting on anything.
+    fn try_lock_write(&self) -> MMFResult<()> {
+        match &self.kernel {
+            Some(kernel) => self.acquire_write(kernel, 0, Some(Duration::ZERO)).map_err(|e| match e {
+                Error::Timeout => Error::WriteLocked,
+                e => e,
+            }),
+            None => self.inner.try_lock_write(),
+        }
+    }
+
+    /// Acquire the mutex, stop new readers from coming in, then wait for the existing ones to leave.
+    fn lock_write(&self) -> MMFResult<()> {
+        match &self.kernel {
+            Some(kernel) => self.acquire_write(kernel, self.timeout_ms(), self.timeout),
+            None => self.inner.lock_write(),
+        }
+    }
+
+    /// Release the write lock in the MMF, let readers back in and release the mutex.
+    ///
+    /// The mutex is released whenever this instance took it, even if the lock in the MMF was forced open in the
+    /// meantime or letting readers back in failed. Returns the first error any of those ran into. Has to be called on
+    /// the thread that took the lock, see [the struct docs][KernelLock].
+    fn unlock_write(&self) -> MMFResult<()> {
+        let owned = self.owns_mutex.swap(false, Ordering::AcqRel);
+        let res = self.inner.unlock_write();
+        match &self.kernel {
+            Some(kernel) if owned => {
+                let set = unsafe { SetEvent(kernel.event) };
+                let release = unsafe { ReleaseMutex(kernel.mutex) };
+                res.and(set.map_err(Into::into)).and(release.map_err(Into::into))
+            }
+            _ => res,
+        }
+    }
+
+    fn spin(&self, tries: &mut usize) -> MMFResult<bool> {
+        self.inner.spin(tries)
+    }
+
+    /// Wait on the event for writers to leave, so waiting for a long write doesn't burn CPU time. This is what
+    /// [`MemoryMappedFile`][crate::mmf::MemoryMappedFile] waits with.
+    ///
+    /// Readers don't hold up the event, so once there are only readers left this waits for them like an [`RWLock`]
+    /// does. The same goes for writers that don't use the kernel objects. Errors with [`Error::WriteLocked`] right away
+    /// if this instance holds the write lock, as it would be waiting for itself.
+    fn wait_until_unlocked(&self, timeout: Duration) -> MMFResult<()> {
+        let kernel = match &self.kernel {
+            Some(_) if self.holds_write() => return Err(Error::WriteLocked),
+            Some(kernel) => kernel,
+            None => return self.inner.wait_until_unlocked(timeout),
+        };
+        let started = Instant::now();
+        while self.inner.locked() {
+            let left = timeout.checked_sub(started.elapsed()).ok_or(Error::Timeout)?;
+            if self.inner.writelocked() {
+                self.wait_readable(kernel, as_ms(left))?;
+            }
+            if self.inner.locked() {
+                let left = timeout.checked_sub(started.elapsed()).ok_or(Error::Timeout)?;
+                match self.inner.wait_until_unlocked(left.min(POLL_SLICE)) {
+                    Err(Error::Timeout) => {}
+                    res => res?,
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Kernel backed locks already wait for the lock, so this only spins when falling back to an [`RWLock`].
+    fn spin_and_lock_read(lock: &Self, max_tries: usize) -> MMFResult<()> {
+        match lock.kernel {
+            Some(_) => lock.lock_read(),
+            None => RWLock::spin_and_lock_read(&lock.inner, max_tries),
+        }
+    }
+
+    /// Kernel backed locks already wait for the lock, so this only spins when falling back to an [`RWLock`].
+    fn spin_and_lock_write(lock: &Self, max_tries: usize) -> MMFResult<()> {
+        match lock.kernel {
+            Some(_) => lock.lock_write(),
+            None => RWLock::spin_and_lock_write(&lock.inner, max_tries),
+        }
+    }
+
+    /// Construct a lock without kernel objects. See [`RWLock::from_existing`][MMFLock::from_existing].
+    unsafe fn from_existing(pointer: *mut u8) -> Self {
+        Self::wrap(RWLock::from_existing(pointer), None)
+    }
+
+    /// Construct a fresh lock without kernel objects. See [`RWLock::from_raw`][MMFLock::from_raw].
+    unsafe fn from_raw(pointer: *mut u8) -> Self {
+        Self::wrap(RWLock::from_raw(pointer), None)
+    }
+
+    /// Construct a lock, creating or opening the kernel objects for the MMF with this name.
+    unsafe fn from_existing_named(pointer: *mut u8, name: &str) -> Self {
+        Self::wrap(RWLock::from_existing(pointer), Some(name))
+    }
+
+    /// Construct a fresh lock, creating or opening the kernel objects for the MMF with this name.
+    unsafe fn from_raw_named(pointer: *mut u8, name: &str) -> Self {
+        Self::wrap(RWLock::from_raw(pointer), Some(name))
+    }
+
+    fn set_init(&self) {
+        self.inner.set_init()
+    }
+
+    fn initialize(self) -> Self {
+        self.set_init();
+        self
+    }
+
+    fn try_claim_init(&self) -> bool {
+        self.inner.try_claim_init()
+    }
+
+    fn init_or_wait(&self, timeout: Duration) -> MMFResult<bool> {
+        self.inner.init_or_wait(timeout)
+    }
+
+    fn wait_initialized(&self, timeout: Duration) -> MMFResult<()> {
+        self.inner.wait_initialized(timeout)
+    }
+
+    fn lock_size() -> usize {
+        RWLock::SIZE
+    }
+}
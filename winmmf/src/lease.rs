@@ -0,0 +1,294 @@
+#![deny(clippy::missing_docs_in_private_items)]
+#![deny(missing_docs)]
+//! # Locks with a lease
+//!
+//! A writer that crashes while holding an [`RWLock`] locks everyone out until somebody notices and calls
+//! [`force_unlock_if_dead`][RWLock::force_unlock_if_dead]. That only helps if the writer's process is actually gone, a
+//! writer stuck in an endless loop or waiting on a debugger is still very much alive. The [`LeasedLock`] defined here
+//! doesn't care about processes: the writer promises to be done or check in by a certain time, and if it doesn't, the
+//! next writer gets to take the lock.
+//!
+//! The lease is an expiry time stored right behind the lock state, in ticks of `GetTickCount64`. Tick counts are the
+//! same for every process on the machine, so there's no clock skew to worry about. They only wrap around after half a
+//! billion years, but expiry is checked with wrapping arithmetic anyway.
+
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use windows::Win32::System::SystemInformation::GetTickCount64;
+
+use super::{
+    err::{Error, MMFResult},
+    states::{MMFLock, RWLock},
+};
+
+/// How long a lease lasts unless [configured otherwise][LeasedLock::with_lease].
+pub const DEFAULT_LEASE: Duration = Duration::from_secs(5);
+
+/// A write lock that expires unless the holder renews it, see [the module docs][self].
+///
+/// The lock state is laid out like an [`RWLock`], followed by 16 bytes holding the lease, so
+/// [`LeasedLock::lock_size`][MMFLock::lock_size] is [`LeasedLock::SIZE`] bytes. That's 8 bytes more than the lease
+/// needs, to keep the data behind it aligned to 16 bytes and the lease itself aligned to 8 at any valid lock offset.
+///
+/// Acquiring the write lock stamps the lease. A holder that needs more time than its lease calls
+/// [`LeasedLock::renew_lease`], and anyone trying to take the write lock while the lease has expired takes it over
+/// instead. The data might be halfway through an update at that point, this only makes sure nobody is locked out for
+/// good. The previous holder finds out the next time it renews or unlocks, which error with
+/// [`Error::LockViolation`].
+///
+/// Only writers reclaim expired leases. Readers waiting on a stale writer keep waiting until a writer comes along, and
+/// read locks don't have a lease at all.
+#[derive(Debug)]
+pub struct LeasedLock<'a> {
+    /// The lock state in the MMF.
+    inner: RWLock<'a>,
+    /// The expiry time of the current lease, or 0 if there's no lease.
+    lease: &'a AtomicU64,
+    /// The expiry time this instance stamped, or 0 if it isn't holding the write lock.
+    held: AtomicU64,
+    /// How long each lease lasts.
+    duration: Duration,
+}
+
+impl LeasedLock<'_> {
+    /// The amount of bytes claimed by the lock: the [`RWLock`] state, and the lease with its padding.
+    pub const SIZE: usize = RWLock::SIZE + 16;
+
+    /// Chainable setter for how long a lease lasts, [`DEFAULT_LEASE`] by default. Only affects leases stamped by this
+    /// instance, everyone using the lock can pick their own.
+    pub fn with_lease(mut self, duration: Duration) -> Self {
+        self.duration = duration;
+        self
+    }
+
+    /// Get how long a lease stamped by this instance lasts.
+    pub fn lease(&self) -> Duration {
+        self.duration
+    }
+
+    /// Get the lock state in the MMF, as seen by any [`RWLock`].
+    pub fn state(&self) -> &RWLock<'_> {
+        &self.inner
+    }
+
+    /// Check if the write lock is held under a lease that ran out.
+    pub fn lease_expired(&self) -> bool {
+        self.inner.writelocked() && expired(self.lease.load(Ordering::Acquire), now())
+    }
+
+    /// Push the expiry of the lease held by this instance to a full [lease][LeasedLock::lease] from now.
+    ///
+    /// Errors with [`Error::NotLocked`] if this instance doesn't hold the write lock, and with [`Error::LockViolation`]
+    /// if somebody else took it over after the lease expired. This instance no longer holds the lock in that case.
+    pub fn renew_lease(&self) -> MMFResult<()> {
+        let held = self.held.load(Ordering::Acquire);
+        if held == 0 {
+            return Err(Error::NotLocked);
+        }
+        let renewed = expiry(self.duration);
+        // Whoever takes the lock over swaps the lease first, so losing this race means we lost the lock.
+        match self.lease.compare_exchange(held, renewed, Ordering::AcqRel, Ordering::Acquire) {
+            Ok(_) => {
+                self.held.store(renewed, Ordering::Release);
+                Ok(())
+            }
+            Err(_) => {
+                self.forget();
+                Err(Error::LockViolation)
+            }
+        }
+    }
+
+    /// Build the lock around an existing [`RWLock`] at `pointer`.
+    ///
+    /// # Safety
+    /// `pointer` must be valid for [`LeasedLock::SIZE`] bytes, and aligned to 4.
+    unsafe fn wrap<'a>(inner: RWLock<'a>, pointer: *mut u8) -> LeasedLock<'a> {
+        // The padding leaves room to align the lease no matter where the lock lives.
+        let lease = pointer.add(RWLock::SIZE);
+        let lease = lease.add(lease.align_offset(8));
+        LeasedLock { inner, lease: AtomicU64::from_ptr(lease.cast()), held: AtomicU64::new(0), duration: DEFAULT_LEASE }
+    }
+
+    /// Stamp a fresh lease right after acquiring the write lock.
+    fn stamp(&self) {
+        let lease = expiry(self.duration);
+        self.lease.store(lease, Ordering::Release);
+        self.held.store(lease, Ordering::Release);
+    }
+
+    /// Forget this instance ever held the write lock, as somebody else took it over.
+    fn forget(&self) {
+        self.held.store(0, Ordering::Release);
+        self.inner.forget_write();
+    }
+
+    /// Take the write lock over if the lease of whoever holds it has expired. Returns whether it was taken over.
+    ///
+    /// A write lock without a lease belongs to a writer that didn't get around to stamping it yet, or one that died
+    /// before it could. The latter are cleaned up like [`RWLock::force_unlock_if_dead`] does, leaving the lock free
+    /// rather than taken over.
+    fn reclaim(&self) -> MMFResult<bool> {
+        let lease = self.lease.load(Ordering::Acquire);
+        if lease == 0 {
+            self.inner.force_unlock_if_dead()?;
+            return Ok(false);
+        } else if !expired(lease, now()) {
+            return Ok(false);
+        }
+        // The holder must see a different lease than the one it stamped, even within the same tick.
+        let renewed = match expiry(self.duration) {
+            same if same == lease => same.wrapping_add(1).max(1),
+            renewed => renewed,
+        };
+        // Swapping the lease is what takes the lock over. If the holder renewed or released it in the meantime, the
+        // lease changed and this fails.
+        if self.lease.compare_exchange(lease, renewed, Ordering::AcqRel, Ordering::Acquire).is_err() {
+            return Ok(false);
+        }
+        if !self.inner.adopt_write() {
+            // Released the write lock without clearing the lease first, which our own unlocking never does. Nothing to
+            // take over then, put the lease back the way it was.
+            _ = self.lease.compare_exchange(renewed, 0, Ordering::AcqRel, Ordering::Acquire);
+            return Ok(false);
+        }
+        self.held.store(renewed, Ordering::Release);
+        Ok(true)
+    }
+}
+
+/// Get the current tick count.
+fn now() -> u64 {
+    // Safety: no arguments, no failure modes.
+    unsafe { GetTickCount64() }
+}
+
+/// Get the expiry time for a lease lasting `duration` from now. Never 0, as that means there's no lease at all.
+fn expiry(duration: Duration) -> u64 {
+    now().wrapping_add(duration.as_millis().min(u64::MAX as u128 / 2) as u64).max(1)
+}
+
+/// Check if `lease` has expired at tick `now`. Works across the tick count wrapping around, as long as leases last
+/// less than half the range.
+fn expired(lease: u64, now: u64) -> bool {
+    lease != 0 && (now.wrapping_sub(lease) as i64) >= 0
+}
+
+impl MMFLock for LeasedLock<'_> {
+    fn initialized(&self) -> bool {
+        self.inner.initialized()
+    }
+
+    fn readlocked(&self) -> bool {
+        self.inner.readlocked()
+    }
+
+    fn writelocked(&self) -> bool {
+        self.inner.writelocked()
+    }
+
+    fn locked(&self) -> bool {
+        self.inner.locked()
+    }
+
+    fn is_poisoned(&self) -> bool {
+        self.inner.is_poisoned()
+    }
+
+    fn generation(&self) -> Option<u32> {
+        self.inner.generation()
+    }
+
+    fn try_lock_read(&self) -> MMFResult<()> {
+        self.inner.try_lock_read()
+    }
+
+    fn unlock_read(&self) -> MMFResult<()> {
+        self.inner.unlock_read()
+    }
+
+    /// Take the write lock if it's free, or take it over if the holder's lease expired.
+    fn try_lock_write(&self) -> MMFResult<()> {
+        match self.inner.try_lock_write() {
+            Ok(()) => {
+                self.stamp();
+                Ok(())
+            }
+            // Held by this instance already, nothing to reclaim.
+            Err(Error::WriteLocked) if self.held.load(Ordering::Acquire) != 0 => Err(Error::WriteLocked),
+            Err(Error::WriteLocked) if self.reclaim()? => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Clear the lease and release the write lock.
+    ///
+    /// Errors with [`Error::LockViolation`] if the lease expired and somebody else took the lock over. The lock isn't
+    /// touched in that case, it's theirs now.
+    fn unlock_write(&self) -> MMFResult<()> {
+        let held = self.held.load(Ordering::Acquire);
+        if held == 0 {
+            return self.inner.unlock_write();
+        }
+        // Clearing the lease first means nobody can take the lock over while we're releasing it.
+        if self.lease.compare_exchange(held, 0, Ordering::AcqRel, Ordering::Acquire).is_err() {
+            self.forget();
+            return Err(Error::LockViolation);
+        }
+        self.held.store(0, Ordering::Release);
+        self.inner.unlock_write()
+    }
+
+    fn spin(&self, tries: &mut usize) -> MMFResult<bool> {
+        self.inner.spin(tries)
+    }
+
+    fn spin_and_lock_read(lock: &Self, max_tries: usize) -> MMFResult<()> {
+        RWLock::spin_and_lock_read(&lock.inner, max_tries)
+    }
+
+    /// Spins on this lock rather than the inner one, so expired leases are reclaimed along the way.
+    fn spin_and_lock_write(lock: &Self, max_tries: usize) -> MMFResult<()> {
+        lock.lock_write_or_spin(max_tries)
+    }
+
+    /// See [`RWLock::from_existing`][MMFLock::from_existing], for [`LeasedLock::SIZE`] bytes.
+    unsafe fn from_existing(pointer: *mut u8) -> Self {
+        Self::wrap(RWLock::from_existing(pointer), pointer)
+    }
+
+    /// See [`RWLock::from_raw`][MMFLock::from_raw], for [`LeasedLock::SIZE`] bytes. Clears the lease.
+    unsafe fn from_raw(pointer: *mut u8) -> Self {
+        let lock = Self::wrap(RWLock::from_raw(pointer), pointer);
+        lock.lease.store(0, Ordering::Release);
+        lock
+    }
+
+    fn set_init(&self) {
+        self.inner.set_init()
+    }
+
+    fn initialize(self) -> Self {
+        self.set_init();
+        self
+    }
+
+    fn try_claim_init(&self) -> bool {
+        self.inner.try_claim_init()
+    }
+
+    fn init_or_wait(&self, timeout: Duration) -> MMFResult<bool> {
+        self.inner.init_or_wait(timeout)
+    }
+
+    fn wait_initialized(&self, timeout: Duration) -> MMFResult<()> {
+        self.inner.wait_initialized(timeout)
+    }
+
+    fn lock_size() -> usize {
+        Self::SIZE
+    }
+}
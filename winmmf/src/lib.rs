@@ -1,8 +1,44 @@
+#[cfg(feature = "async_tokio")]
+pub mod async_tokio;
+#[cfg(feature = "impl_lock")]
+pub mod barrier;
+#[cfg(feature = "impl_mmf")]
+pub mod checksum;
+pub mod counter;
 pub mod err;
+#[cfg(feature = "impl_mmf")]
+pub mod growable;
+#[cfg(feature = "impl_mmf")]
+pub mod header;
+#[cfg(feature = "lock_hooks")]
+pub mod hooks;
+#[cfg(feature = "metrics")]
+pub mod instrumented;
+#[cfg(feature = "impl_mmf")]
+pub mod ipc;
+#[cfg(feature = "impl_lock")]
+pub mod kernel_lock;
+#[cfg(feature = "impl_lock")]
+pub mod lease;
 pub mod mmf;
+#[cfg(feature = "impl_mmf")]
+pub mod notify;
+#[cfg(feature = "impl_lock")]
+pub mod once;
+pub mod padded;
+#[cfg(feature = "lock_api")]
+pub mod raw_lock;
+#[cfg(feature = "impl_lock")]
+pub mod semaphore;
+#[cfg(feature = "impl_lock")]
+pub mod seqlock;
 pub mod states;
+#[cfg(feature = "impl_lock")]
+pub mod ticket;
 
 pub use err::*;
+#[cfg(feature = "impl_mmf")]
+pub use header::MMF_CURRENT_VERSION;
 pub use mmf::*;
 
 #[cfg(test)]
@@ -22,8 +22,12 @@ use super::{
     err::{Error as MMFError, MMFResult},
     states::MMFLock,
 };
+#[cfg(feature = "bytemuck")]
+use bytemuck::Pod;
 use fixedstr::ztr64;
 use microseh::try_seh;
+#[cfg(feature = "serde")]
+use serde::{de::DeserializeOwned, Serialize};
 use windows::{
     core::Error as WErr,
     Win32::{
@@ -31,16 +35,54 @@ use windows::{
         System::Memory::{UnmapViewOfFile, MEMORY_MAPPED_VIEW_ADDRESS},
     },
 };
+#[cfg(feature = "zerocopy")]
+use zerocopy::{AsBytes, FromBytes};
 
-use std::cell::Cell;
 #[cfg(feature = "impl_mmf")]
-use std::{fmt, num::NonZeroUsize};
+use super::states::UnlockGuard;
+use std::{cell::Cell, time::Duration};
+#[cfg(feature = "impl_mmf")]
+use std::{
+    fmt,
+    io::{self, Read, Seek, SeekFrom, Write},
+    marker::PhantomData,
+    mem::ManuallyDrop,
+    num::NonZeroUsize,
+    ops::Deref,
+    os::windows::ffi::OsStrExt,
+    path::Path,
+    sync::atomic::{fence, Ordering},
+    time::Instant,
+};
 #[cfg(feature = "impl_mmf")]
 use windows::{
-    core::PCSTR,
+    core::{PCSTR, PCWSTR},
     Win32::{
-        Foundation::{CloseHandle, GetLastError, INVALID_HANDLE_VALUE},
-        System::Memory::{CreateFileMappingA, MapViewOfFile, OpenFileMappingA, FILE_MAP_ALL_ACCESS, PAGE_READWRITE},
+        Foundation::{
+            CloseHandle, DuplicateHandle, GetHandleInformation, GetLastError, LocalFree, SetHandleInformation,
+            DUPLICATE_SAME_ACCESS, ERROR_ALREADY_EXISTS, ERROR_FILE_NOT_FOUND, ERROR_NOT_ALL_ASSIGNED,
+            ERROR_PRIVILEGE_NOT_HELD, GENERIC_READ, GENERIC_WRITE, HANDLE_FLAGS, HANDLE_FLAG_INHERIT, HLOCAL,
+            INVALID_HANDLE_VALUE,
+        },
+        Security::{
+            AdjustTokenPrivileges,
+            Authorization::{ConvertStringSecurityDescriptorToSecurityDescriptorW, SDDL_REVISION_1},
+            LookupPrivilegeValueW, PSECURITY_DESCRIPTOR, SECURITY_ATTRIBUTES, SE_LOCK_MEMORY_NAME,
+            SE_PRIVILEGE_ENABLED, TOKEN_ADJUST_PRIVILEGES, TOKEN_PRIVILEGES, TOKEN_QUERY,
+        },
+        Storage::FileSystem::{
+            CreateFileW, FlushFileBuffers, GetFileSizeEx, FILE_ATTRIBUTE_NORMAL, FILE_SHARE_READ, FILE_SHARE_WRITE,
+            OPEN_ALWAYS, OPEN_EXISTING,
+        },
+        System::{
+            Memory::{
+                CreateFileMappingA, FlushViewOfFile, GetLargePageMinimum, MapViewOfFile, OpenFileMappingA, FILE_MAP,
+                FILE_MAP_ALL_ACCESS, FILE_MAP_COPY, FILE_MAP_LARGE_PAGES, FILE_MAP_READ, FILE_MAP_WRITE,
+                PAGE_PROTECTION_FLAGS, PAGE_READONLY, PAGE_READWRITE, PAGE_WRITECOPY, SEC_COMMIT, SEC_LARGE_PAGES,
+            },
+            SystemInformation::{GetSystemInfo, SYSTEM_INFO},
+            Threading::{GetCurrentProcess, OpenProcessToken},
+        },
     },
 };
 #[cfg(feature = "impl_mmf")]
@@ -54,9 +96,28 @@ pub const LOCAL_NAMESPACE: ztr64 = ztr64::const_make("Local\\");
 /// for more info
 pub const GLOBAL_NAMESPACE: ztr64 = ztr64::const_make("Global\\");
 
+/// How long reads and writes wait for the lock by default, see [`MemoryMappedFile::with_lock_timeout`].
+#[cfg(feature = "impl_mmf")]
+pub const LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How many tries the spinners passed to [`Mmf::read_spin`] and friends get by default, see
+/// [`MemoryMappedFile::with_spin_budget`].
+#[cfg(feature = "impl_mmf")]
+pub const SPIN_BUDGET: usize = 1_000_000;
+
+/// How long opening or creating an MMF waits for another process to finish initializing its lock.
+#[cfg(feature = "impl_mmf")]
+pub const INIT_TIMEOUT: Duration = Duration::from_secs(1);
+
 /// Namespaces as an enum, to unambiguously represent relevant information.
+///
+/// Creating an MMF in the [global namespace][Namespace::GLOBAL] requires the `SeCreateGlobalPrivilege`, which services
+/// and administrators have but regular desktop applications usually don't. Opening one that already exists only
+/// requires access to the MMF itself. If the privilege is missing, creating the MMF fails with
+/// [`Error::AccessDenied`][crate::err::Error::AccessDenied]. So for a service talking to a desktop app, let the service
+/// create the MMF and have the app open it.
 #[cfg(feature = "namespaces")]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
 pub enum Namespace {
     /// Local namespace, always allowed and sharable with children
@@ -65,34 +126,418 @@ pub enum Namespace {
     GLOBAL = 1,
     /// Custom namespace, makes it private unless you share/leak handles yourself.
     CUSTOM = 2,
+    /// The local namespace of another terminal services session, by session ID. Subject to the same privilege rules as
+    /// the global namespace when it isn't your own session.
+    SESSION(u32) = 3,
 }
 
 /// We do a little transmutation, I'm an aclhemist!
+///
+/// Only the namespaces without data can be made from a `u8`, [`Namespace::SESSION`] needs its ID.
 impl TryFrom<u8> for Namespace {
     /// Unit type, as we only need it for checking and never for more info.
     type Error = ();
-    /// This can only fail on invalid values. Check validity and transmute safely.
+    /// This can only fail on invalid values.
     fn try_from(value: u8) -> Result<Namespace, Self::Error> {
         match value {
-            ..=2 => Ok(unsafe { std::mem::transmute::<u8, Self>(value) }),
+            0 => Ok(Self::LOCAL),
+            1 => Ok(Self::GLOBAL),
+            2 => Ok(Self::CUSTOM),
             _ => Err(()),
         }
     }
 }
 
+#[cfg(feature = "namespaces")]
+impl Namespace {
+    /// The prefix to put in front of a name to place it in this namespace. Empty for [`Namespace::CUSTOM`].
+    pub fn prefix(&self) -> ztr64 {
+        match self {
+            Self::LOCAL => LOCAL_NAMESPACE,
+            Self::GLOBAL => GLOBAL_NAMESPACE,
+            Self::CUSTOM => ztr64::new(),
+            Self::SESSION(id) => ztr64::make(&format!("Session\\{id}\\")),
+        }
+    }
+
+    /// Build the full name of an object called `name` in this namespace.
+    pub fn format_name(&self, name: &str) -> ztr64 {
+        ztr64::make(&format!("{}{name}", self.prefix()))
+    }
+
+    /// Split a full object name into its namespace and the name within it. Names without a recognized prefix are
+    /// considered to be in a [custom namespace][Namespace::CUSTOM].
+    pub fn parse_name(full: &str) -> (Self, &str) {
+        if let Some(name) = full.strip_prefix(LOCAL_NAMESPACE.as_str()) {
+            (Self::LOCAL, name)
+        } else if let Some(name) = full.strip_prefix(GLOBAL_NAMESPACE.as_str()) {
+            (Self::GLOBAL, name)
+        } else if let Some((id, name)) = full.strip_prefix("Session\\").and_then(|rest| rest.split_once('\\')) {
+            id.parse().map_or((Self::CUSTOM, full), |id| (Self::SESSION(id), name))
+        } else {
+            (Self::CUSTOM, full)
+        }
+    }
+}
+
 /// Mostly for debug purposes
 #[cfg(feature = "namespaces")]
 impl fmt::Display for Namespace {
     /// Presents the namespace, or a useless message for custom namespaces
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Self::LOCAL => write!(f, "{LOCAL_NAMESPACE}"),
-            Self::GLOBAL => write!(f, "{GLOBAL_NAMESPACE}"),
-            _ => write!(f, "A custom namespace was used here."),
+            Self::CUSTOM => write!(f, "A custom namespace was used here."),
+            _ => write!(f, "{}", self.prefix()),
+        }
+    }
+}
+
+/// Get the granularity the OS allocates virtual memory in, which is what offsets passed to
+/// [`MemoryMappedFile::map_region`] must be a multiple of. Usually 64 KiB.
+#[cfg(feature = "impl_mmf")]
+pub fn allocation_granularity() -> u32 {
+    let mut info = SYSTEM_INFO::default();
+    // Safety: this only ever writes to the struct we hand it.
+    unsafe { GetSystemInfo(&mut info) };
+    info.dwAllocationGranularity
+}
+
+/// Get the size of a large page, which is what [large-page MMFs][MemoryMappedFile::new_large_pages] are rounded up to.
+/// Usually 2 MiB. [`None`] if the processor doesn't support large pages.
+#[cfg(feature = "impl_mmf")]
+pub fn large_page_minimum() -> Option<NonZeroUsize> {
+    // Safety: no arguments, no failure modes. Zero means no support.
+    NonZeroUsize::new(unsafe { GetLargePageMinimum() })
+}
+
+/// Enable `SeLockMemoryPrivilege` on this process' token, which creating large-page MMFs requires.
+///
+/// Having it assigned isn't enough, it has to be enabled too, and it's disabled by default. Errors with
+/// [`MMFError::LargePageUnavailable`] if the account doesn't have it at all. Granting it is a matter of local security
+/// policy ("Lock pages in memory"), and takes a fresh logon to apply.
+#[cfg(feature = "impl_mmf")]
+fn enable_lock_memory_privilege() -> MMFResult<()> {
+    let mut token = HANDLE::default();
+    // Safety: the pseudo handle for our own process is always valid, and the token handle is ours to close.
+    unsafe { OpenProcessToken(GetCurrentProcess(), TOKEN_ADJUST_PRIVILEGES | TOKEN_QUERY, &mut token) }
+        .map_err(MMFError::from_os)?;
+    let mut privileges = TOKEN_PRIVILEGES { PrivilegeCount: 1, ..Default::default() };
+    privileges.Privileges[0].Attributes = SE_PRIVILEGE_ENABLED;
+    // Safety: the token is open, and the LUID is written into the struct we own.
+    let res = unsafe { LookupPrivilegeValueW(PCWSTR::null(), SE_LOCK_MEMORY_NAME, &mut privileges.Privileges[0].Luid) }
+        .and_then(|_| unsafe { AdjustTokenPrivileges(token, false, Some(&privileges), 0, None, None) })
+        // Adjusting "succeeds" when it couldn't enable anything, only the last error tells the difference.
+        .map(|_| unsafe { GetLastError() } != ERROR_NOT_ALL_ASSIGNED);
+    unsafe { _ = CloseHandle(token) };
+    match res {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(MMFError::LargePageUnavailable(Some(ERROR_NOT_ALL_ASSIGNED.0 as i32))),
+        Err(e) => Err(MMFError::from_os(e)),
+    }
+}
+
+/// How an MMF is mapped into this process, see [`MemoryMappedFile::open_with_access`].
+#[cfg(feature = "impl_mmf")]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    /// Mapped with `FILE_MAP_READ`, the OS won't let anything write to it. Not even the lock, so readers can't register
+    /// themselves with it. They check for a writer before and after copying the data instead, and try again if there
+    /// was one. Anything that would write errors with [`MMFError::ReadOnly`] instead of faulting.
+    ReadOnly,
+    /// Mapped for reading and writing, like any MMF not opened through [`MemoryMappedFile::open_with_access`].
+    #[default]
+    ReadWrite,
+    /// Mapped with `FILE_MAP_COPY`. The first write to a page gives this process a private copy of it, and from then on
+    /// that page is ours alone: others don't see what we write to it, and we don't see what they write to it. Pages we
+    /// never wrote to keep following the original.
+    ///
+    /// Taking the lock in the view would be a write too, making the lock and any data on its page private the moment
+    /// we first read. So the lock in the view is left alone, and this instance gets a lock of its own on the heap
+    /// instead. That keeps threads sharing the instance in line. Writes only take that one: they never touch what
+    /// anyone else sees, so there's no reason to keep the writers elsewhere waiting, which is kind of the point.
+    ///
+    /// Reads do care about writers elsewhere, as they can see a page we didn't make private yet halfway through being
+    /// written. MMFs opened by name watch the shared lock through a second, read-only view of just the lock, which
+    /// keeps following the original after our writes made the page with the lock in the main view private. Reads
+    /// then copy the data like [read-only][Access::ReadOnly] views do, checking for writers before and after. That
+    /// goes for [`read`][Mmf::read], [`read_at`][MemoryMappedFile::read_at] and everything built on them, but not for
+    /// anything handing out references to the view, like [`read_borrow`][MemoryMappedFile::read_borrow] and
+    /// [`as_bytes`][MemoryMappedFile::as_bytes]. MMFs backed by a file don't watch anything, as there's no telling
+    /// whether anyone ever set up the lock in the file.
+    ///
+    /// ## Commit charge
+    /// Mapping copy-on-write doesn't cost anything up front. Every page we write to does though: the private copy is
+    /// charged against the system commit limit (RAM plus page files) the moment it's made, and stays charged until the
+    /// view is unmapped. Writing all over a large MMF costs as much as having a private copy of it, and can fail with
+    /// an access violation when the system is out of commit. Keep the writes to the pages you need.
+    CopyOnWrite,
+}
+
+#[cfg(feature = "impl_mmf")]
+impl Access {
+    /// The access to ask for when opening the handle and mapping views. Reading and writing asks for just that, so
+    /// opening works with no more than read and write access granted, see [`SecurityAttributes::shared_with_users`].
+    fn map_flags(&self) -> FILE_MAP {
+        match self {
+            Self::ReadOnly => FILE_MAP_READ,
+            Self::ReadWrite => FILE_MAP_READ | FILE_MAP_WRITE,
+            Self::CopyOnWrite => FILE_MAP_COPY,
+        }
+    }
+
+    /// The protection to create a mapping with, so it can be mapped like this.
+    fn protection(&self) -> PAGE_PROTECTION_FLAGS {
+        match self {
+            Self::ReadOnly => PAGE_READONLY,
+            Self::ReadWrite => PAGE_READWRITE,
+            Self::CopyOnWrite => PAGE_WRITECOPY,
+        }
+    }
+}
+
+/// How a file is mapped by [`MemoryMappedFile::from_file_with_access`]. Files and MMFs opened by name are mapped the
+/// same ways, so this is just another name for [`Access`]: `FileAccess::ReadOnly`, `FileAccess::ReadWrite` and
+/// `FileAccess::CopyOnWrite`.
+#[cfg(feature = "impl_mmf")]
+pub type FileAccess = Access;
+
+/// What [`MemoryMappedFile::open_or_create`] ended up doing.
+#[cfg(feature = "impl_mmf")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Disposition {
+    /// The MMF didn't exist yet, and was created and initialized by this call.
+    Created,
+    /// Somebody else created the MMF already, and this call opened it.
+    Opened,
+}
+
+/// Who gets to access an MMF, and whether child processes inherit its handle. See
+/// [`MemoryMappedFile::new_with_security`].
+///
+/// Without any, an MMF gets the default security descriptor of the process creating it, which usually means only that
+/// account, administrators and `SYSTEM` get in. Services talking to desktop apps tend to need something else. The
+/// descriptor is the security descriptor of the mapping object, so it only applies when the MMF is created. Opening an
+/// existing MMF is checked against whatever descriptor it got back then.
+///
+/// ## Custom DACLs
+/// The easy way is [SDDL](https://learn.microsoft.com/en-us/windows/win32/secauthz/security-descriptor-string-format),
+/// through [`SecurityAttributes::from_sddl`]. Some strings to start from:
+///
+/// - `D:P(A;;GA;;;SY)(A;;GA;;;BA)`: only `SYSTEM` and administrators, nobody else.
+/// - `D:P(A;;GA;;;SY)(A;;GA;;;BA)(A;;GR;;;IU)`: the same, plus read access for interactive users. Open with
+///   [`open_read_only`][MemoryMappedFile::open_read_only] on their end. Anything else maps the view for writing too,
+///   even [`open_read`][MemoryMappedFile::open_read] as it still takes the lock, and gets denied.
+/// - `D:(A;;GA;;;WD)S:(ML;;NW;;;LW)`: everyone, including low integrity processes like sandboxed browsers.
+///
+/// If you'd rather build the descriptor yourself (`InitializeSecurityDescriptor`, `SetSecurityDescriptorDacl` and
+/// friends), hand it over with [`SecurityAttributes::with_descriptor`].
+#[cfg(feature = "impl_mmf")]
+#[derive(Debug)]
+pub struct SecurityAttributes {
+    /// Whether child processes inherit handles created with these.
+    inherit: bool,
+    /// The descriptor to create with, null for the default one.
+    descriptor: PSECURITY_DESCRIPTOR,
+    /// Whether we allocated the descriptor, and have to free it again.
+    owned: bool,
+}
+
+#[cfg(feature = "impl_mmf")]
+impl Default for SecurityAttributes {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "impl_mmf")]
+impl SecurityAttributes {
+    /// The default descriptor and no inheritance, which is what MMFs get without any attributes.
+    pub fn new() -> Self {
+        Self { inherit: false, descriptor: PSECURITY_DESCRIPTOR::default(), owned: false }
+    }
+
+    /// Build a security descriptor from an SDDL string, see [the struct docs][SecurityAttributes] for some examples.
+    ///
+    /// Errors with whatever the OS thinks of the string if it doesn't parse.
+    pub fn from_sddl(sddl: &str) -> MMFResult<Self> {
+        let wide: Vec<u16> = sddl.encode_utf16().chain(Some(0)).collect();
+        let mut descriptor = PSECURITY_DESCRIPTOR::default();
+        // Safety: the string is null terminated and outlives the call, and the OS allocates the descriptor for us.
+        try_seh(|| unsafe {
+            ConvertStringSecurityDescriptorToSecurityDescriptorW(
+                PCWSTR::from_raw(wide.as_ptr()),
+                SDDL_REVISION_1,
+                &mut descriptor,
+                None,
+            )
+        })?
+        .map_err(MMFError::from_os)?;
+        Ok(Self { inherit: false, descriptor, owned: true })
+    }
+
+    /// Full access for `SYSTEM` and administrators, and read access for interactive users. Or read and write access,
+    /// if `write` is set.
+    ///
+    /// Made for services sharing with apps in a user's session: create the MMF with these in the
+    /// [global namespace][Namespace::GLOBAL] from the service, and open it from the app with
+    /// [`open_read_only`][MemoryMappedFile::open_read_only] or [`open_write`][MemoryMappedFile::open_write] to match.
+    /// [`open_read`][MemoryMappedFile::open_read] needs write access too, as it still takes the lock. Asking for more
+    /// than was granted errors with [`MMFError::AccessDenied`].
+    pub fn shared_with_users(write: bool) -> MMFResult<Self> {
+        Self::from_sddl(if write {
+            "D:P(A;;GA;;;SY)(A;;GA;;;BA)(A;;GRGW;;;IU)"
+        } else {
+            "D:P(A;;GA;;;SY)(A;;GA;;;BA)(A;;GR;;;IU)"
+        })
+    }
+
+    /// Use a security descriptor you built yourself. It stays yours, so free it after you're done with the attributes.
+    ///
+    /// # Safety
+    /// `descriptor` must be null or a valid security descriptor, and stay that way for as long as these attributes are
+    /// around.
+    pub unsafe fn with_descriptor(mut self, descriptor: PSECURITY_DESCRIPTOR) -> Self {
+        self.free();
+        self.descriptor = descriptor;
+        self.owned = false;
+        self
+    }
+
+    /// Chainable setter for whether child processes inherit the handle. Off by default.
+    pub fn inheritable(mut self, inherit: bool) -> Self {
+        self.inherit = inherit;
+        self
+    }
+
+    /// Check whether child processes inherit the handle.
+    pub fn is_inheritable(&self) -> bool {
+        self.inherit
+    }
+
+    /// Get the security descriptor, null if the default one is used.
+    pub fn descriptor(&self) -> PSECURITY_DESCRIPTOR {
+        self.descriptor
+    }
+
+    /// The struct the OS wants. Only valid for as long as `self` is.
+    fn raw(&self) -> SECURITY_ATTRIBUTES {
+        SECURITY_ATTRIBUTES {
+            nLength: std::mem::size_of::<SECURITY_ATTRIBUTES>() as u32,
+            lpSecurityDescriptor: self.descriptor.0,
+            bInheritHandle: self.inherit.into(),
+        }
+    }
+
+    /// Free the descriptor if [`SecurityAttributes::from_sddl`] allocated it.
+    fn free(&mut self) {
+        if self.owned {
+            // Safety: the OS allocated it with `LocalAlloc`, and nothing else holds on to it.
+            unsafe { _ = LocalFree(HLOCAL(self.descriptor.0)) };
+            self.owned = false;
         }
     }
 }
 
+#[cfg(feature = "impl_mmf")]
+impl Drop for SecurityAttributes {
+    fn drop(&mut self) {
+        self.free();
+    }
+}
+
+/// Everything that can be set when creating an MMF, for when the `new_with_*` functions don't cover the combination
+/// you need.
+///
+/// ## Usage
+/// ```no_run
+/// # use winmmf::{*, states::RWLock};
+/// # use std::num::NonZeroUsize;
+/// let page = large_page_minimum().expect("No large pages here").get();
+/// let size = NonZeroUsize::new(page * 4 - RWLock::SIZE).unwrap();
+/// let mmf = CreateOptions::new().large_pages(true).create::<RWLock>(size, "big", Namespace::LOCAL).unwrap();
+/// ```
+#[cfg(feature = "impl_mmf")]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CreateOptions<'a> {
+    /// Where the lock lives, see [`MemoryMappedFile::new_with_lock_offset`].
+    lock_offset: usize,
+    /// Whether to back the MMF with large pages.
+    large_pages: bool,
+    /// Whether child processes inherit the handle, on top of whatever `security` says.
+    inherit: bool,
+    /// Who gets access, see [`MemoryMappedFile::new_with_security`].
+    security: Option<&'a SecurityAttributes>,
+}
+
+#[cfg(feature = "impl_mmf")]
+impl<'a> CreateOptions<'a> {
+    /// The same options [`MemoryMappedFile::new`] uses.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the offset of the lock, see [`MemoryMappedFile::new_with_lock_offset`]. Defaults to 0.
+    pub fn lock_offset(mut self, lock_offset: usize) -> Self {
+        self.lock_offset = lock_offset;
+        self
+    }
+
+    /// Set whether the MMF is backed by large pages, with all the strings attached listed at
+    /// [`MemoryMappedFile::new_large_pages`]. Off by default.
+    ///
+    /// Unlike [`MemoryMappedFile::new_large_pages`], the size isn't rounded up for you: the total size including the
+    /// lock has to be a multiple of [`large_page_minimum`] already, or creating errors with
+    /// [`MMFError::MisalignedData`]. Without the privilege or hardware support, it errors with
+    /// [`MMFError::LargePageUnavailable`].
+    pub fn large_pages(mut self, large_pages: bool) -> Self {
+        self.large_pages = large_pages;
+        self
+    }
+
+    /// Set whether child processes inherit the handle to the MMF, so it can be shared with them without a name to look
+    /// it up by. Off by default. Turning it on here wins over [`SecurityAttributes::inheritable`].
+    ///
+    /// Hand the child the value of [`MemoryMappedFile::handle`] (on the command line, through an environment variable,
+    /// or however you like) and have it build its end with [`MemoryMappedFile::from_raw_handle`]:
+    ///
+    /// ```no_run
+    /// # use winmmf::{*, states::RWLock};
+    /// # use std::num::NonZeroUsize;
+    /// # use windows::Win32::Foundation::HANDLE;
+    /// let size = NonZeroUsize::new(64).unwrap();
+    /// // In the parent
+    /// let mmf = CreateOptions::new().inheritable(true).create::<RWLock>(size, "family", Namespace::LOCAL).unwrap();
+    /// let value = (mmf.handle().0 as usize).to_string();
+    /// std::process::Command::new("child.exe").env("MMF_HANDLE", value).spawn().unwrap();
+    /// // In the child
+    /// let value: usize = std::env::var("MMF_HANDLE").unwrap().parse().unwrap();
+    /// let mmf = unsafe { MemoryMappedFile::<RWLock>::from_raw_handle(HANDLE(value as _), size, Access::ReadWrite) };
+    /// ```
+    pub fn inheritable(mut self, inherit: bool) -> Self {
+        self.inherit = inherit;
+        self
+    }
+
+    /// Set the [`SecurityAttributes`] to create with, see [`MemoryMappedFile::new_with_security`].
+    ///
+    /// This is how to combine them with the other options: the [large-page][MemoryMappedFile::new_large_pages] and
+    /// [lock offset][MemoryMappedFile::new_with_lock_offset] constructors take none of their own.
+    pub fn security(mut self, security: &'a SecurityAttributes) -> Self {
+        self.security = Some(security);
+        self
+    }
+
+    /// Create an MMF with these options. See [`MemoryMappedFile::new_with_options`].
+    pub fn create<LOCK: MMFLock>(
+        &self,
+        size: NonZeroUsize,
+        name: impl Into<ztr64>,
+        namespace: Namespace,
+    ) -> MMFResult<MemoryMappedFile<LOCK>> {
+        MemoryMappedFile::new_with_options(size, name, namespace, self)
+    }
+}
+
 /// Basic trait for Memory Mapped Files.
 ///
 /// Implementing this is ensures you have the bare minimum to actually use your MMF and this _might_ at some point be
@@ -146,6 +591,127 @@ pub trait Mmf {
     fn write_spin<F>(&self, buffer: &[u8], spinner: Option<F>) -> MMFResult<()>
     where
         F: FnMut(&dyn MMFLock, usize) -> MMFResult<()>;
+    /// Read `count` bytes of data starting `offset` bytes in, taking the same lock as [`read`][Mmf::read].
+    ///
+    /// Offset 0 is the first byte of data, not the start of the view. Errors with [`MMFError::NotEnoughMemory`] if the
+    /// range runs past the end of the data, rather than returning fewer bytes.
+    ///
+    /// The default reads everything up to the end of the range with [`read_to_raw`][Mmf::read_to_raw] and throws the
+    /// first `offset` bytes away. Override it if you can do better.
+    fn read_vec_at(&self, offset: usize, count: usize) -> MMFResult<Vec<u8>> {
+        let end = offset.checked_add(count).filter(|&end| end <= self.size()).ok_or(MMFError::NotEnoughMemory)?;
+        let mut buf = vec![0; end];
+        // Safety: the buffer holds exactly the `end` bytes being read into it.
+        unsafe { self.read_to_raw(buf.as_mut_ptr(), end)? };
+        buf.drain(..offset);
+        Ok(buf)
+    }
+    /// Write all of `data` starting `offset` bytes into the data, taking the same lock as [`write`][Mmf::write].
+    ///
+    /// Offsets and bounds work like for [`read_vec_at`][Mmf::read_vec_at], nothing is written if it doesn't all fit.
+    ///
+    /// The default reads the first `offset` bytes and [`write`][Mmf::write]s them back in front of `data`. Those are
+    /// two separate locks, so a write landing in between gets its part of the prefix undone. Override it if that
+    /// matters, like [`MemoryMappedFile`] does.
+    fn write_all_at(&self, offset: usize, data: &[u8]) -> MMFResult<()> {
+        let end = offset
+            .checked_add(data.len())
+            .filter(|&end| end <= self.size())
+            .ok_or(MMFError::NotEnoughMemory)?;
+        let mut buf = if offset > 0 { self.read(offset)? } else { Vec::with_capacity(end) };
+        buf.extend_from_slice(data);
+        self.write(&buf)
+    }
+}
+
+/// Check the lock can live `lock_offset` bytes into an MMF holding `size` bytes of data.
+#[cfg(feature = "impl_mmf")]
+fn check_lock_offset(size: usize, lock_offset: usize) -> MMFResult<()> {
+    if lock_offset % 4 != 0 {
+        Err(MMFError::MisalignedLock)
+    } else if lock_offset > size {
+        Err(MMFError::NotEnoughMemory)
+    } else {
+        Ok(())
+    }
+}
+
+/// Copy `count` bytes of data from `src` into the view, starting `at` bytes into the data and skipping over the lock.
+///
+/// # Safety
+/// `view` must be valid for `at` + `count` bytes plus the lock, and `src` for `count` bytes.
+#[cfg(feature = "impl_mmf")]
+unsafe fn copy_to_data(view: *mut u8, lock_offset: usize, lock_size: usize, at: usize, src: *const u8, count: usize) {
+    let head = count.min(lock_offset.saturating_sub(at));
+    src.copy_to(view.add(at), head);
+    src.add(head).copy_to(view.add(at + head + lock_size), count - head);
+}
+
+/// Copy `count` bytes of data from the view into `dst`, starting `at` bytes into the data and skipping over the lock.
+///
+/// # Safety
+/// `view` must be valid for `at` + `count` bytes plus the lock, and `dst` for `count` bytes.
+#[cfg(feature = "impl_mmf")]
+unsafe fn copy_from_data(view: *const u8, lock_offset: usize, lock_size: usize, at: usize, dst: *mut u8, count: usize) {
+    let head = count.min(lock_offset.saturating_sub(at));
+    view.add(at).copy_to(dst, head);
+    view.add(at + head + lock_size).copy_to(dst.add(head), count - head);
+}
+
+/// Take `lock` with `acquire`, waiting for it to be unlocked in between attempts. Gives up after `timeout`.
+///
+/// This is how MMFs wait, never through [`MMFLock::lock_read`] or [`MMFLock::lock_write`]. Those wait as long as it
+/// takes, or for as long as the lock's own spin limit feels like, and there's no telling them about a deadline. So a
+/// lock that overrides them to wait smarter doesn't get to do that here: it's tried with `acquire` and waited for with
+/// [`MMFLock::wait_until_unlocked`], which is what to override to change how MMFs wait.
+#[cfg(feature = "impl_mmf")]
+fn wait_for_lock<LOCK: MMFLock>(
+    lock: &LOCK,
+    timeout: Duration,
+    acquire: impl Fn(&LOCK) -> MMFResult<()>,
+) -> MMFResult<()> {
+    let started = Instant::now();
+    loop {
+        match acquire(lock) {
+            Err(e) if e.worth_waiting() => {
+                let left = timeout.checked_sub(started.elapsed()).ok_or(MMFError::Timeout)?;
+                lock.wait_until_unlocked(left)?;
+            }
+            res => return res,
+        }
+    }
+}
+
+/// Map `len` bytes of the mapping behind `handle`, starting `offset` bytes in. The offset has to be a multiple of
+/// [`allocation_granularity`], and the window has to be within the mapping.
+#[cfg(feature = "impl_mmf")]
+fn map_window(handle: HANDLE, access: FILE_MAP, offset: u64, len: usize) -> MMFResult<MemoryMappedView> {
+    let (off_low, off_high) = offset.split();
+    // Safety: failure gives us a null pointer, which is checked for right away.
+    let view = try_seh(|| unsafe { MapViewOfFile(handle, access, off_high, off_low, len) })?;
+    if view.Value.is_null() {
+        return Err(MMFError::from_os(WErr::from_win32()));
+    }
+    Ok(view.into())
+}
+
+/// Get a handle of our own to whatever `handle` refers to, with the same access. Close it when done.
+#[cfg(feature = "impl_mmf")]
+fn duplicate_handle(handle: HANDLE) -> MMFResult<HANDLE> {
+    // Safety: the pseudo handle for our own process never needs closing.
+    duplicate_handle_to(handle, unsafe { GetCurrentProcess() }, false)
+}
+
+/// Get a handle to whatever `handle` refers to into `process`, with the same access. Closing it is up to `process`.
+#[cfg(feature = "impl_mmf")]
+fn duplicate_handle_to(handle: HANDLE, process: HANDLE, inherit: bool) -> MMFResult<HANDLE> {
+    let mut duplicate = HANDLE::default();
+    // Safety: the OS checks both handles, and the pseudo handle for our own process never needs closing.
+    try_seh(|| unsafe {
+        DuplicateHandle(GetCurrentProcess(), handle, process, &mut duplicate, 0, inherit, DUPLICATE_SAME_ACCESS)
+    })?
+    .map_err(MMFError::from_os)?;
+    Ok(duplicate)
 }
 
 /// A simple struct wrapping a [Memory Mapped File](https://learn.microsoft.com/en-us/windows/win32/memory/creating-named-shared-memory).
@@ -162,6 +728,8 @@ pub trait Mmf {
 pub struct MemoryMappedFile<LOCK: MMFLock> {
     /// The [`HANDLE`] to the created mapping
     handle: HANDLE,
+    /// The file backing the mapping, see [`MemoryMappedFile::from_file`]. `None` for MMFs backed by the page file.
+    file: Option<HANDLE>,
     /// the "filename" portion
     name: ztr64,
     /// The higher order bits for the size of the opened file.
@@ -176,12 +744,28 @@ pub struct MemoryMappedFile<LOCK: MMFLock> {
     lock: LOCK,
     /// The original MemoryMappedView; We need to keep this around for unmapping it.
     map_view: Option<MemoryMappedView>,
-    /// The pointer we can actually write into without fucking up the lock
-    write_ptr: *mut u8,
+    /// The start of the view. The data is everything in it that isn't the lock.
+    view_ptr: *mut u8,
+    /// Where in the view the lock lives, see [`MemoryMappedFile::new_with_lock_offset`].
+    lock_offset: usize,
     /// A one-way changing cell to prevent using the MMF after closing it.
     closed: Cell<bool>,
     /// A bool to prevent writing through an MMF opened for reading
     readonly: bool,
+    /// How long reads and writes wait for the lock, see [`MemoryMappedFile::with_lock_timeout`].
+    lock_timeout: Duration,
+    /// How many tries spinners get, see [`MemoryMappedFile::with_spin_budget`].
+    spin_budget: usize,
+    /// How the view is mapped, see [`Access`].
+    #[cfg(feature = "impl_mmf")]
+    access: Access,
+    /// The memory behind the lock of a [copy-on-write][Access::CopyOnWrite] MMF or a file mapped without write access,
+    /// which isn't in the view. Only kept around so it lives as long as the lock.
+    #[allow(dead_code)]
+    private_lock: Option<Box<[u64]>>,
+    /// The lock everyone else takes, in a read-only view of its own, for [copy-on-write][Access::CopyOnWrite] reads to
+    /// watch. The lock comes first, so it's dropped before the view it points into is unmapped.
+    shared_lock: Option<(LOCK, MemoryMappedView)>,
 }
 
 #[cfg(feature = "impl_mmf")]
@@ -191,7 +775,7 @@ impl<LOCK: MMFLock> MemoryMappedFile<LOCK> {
     /// The size will be automatically divided into the upper and lower halves, as the function to allocate this memory
     /// requires them to be split. The name of the file should be any one of:
     ///
-    /// 1. Just a filename, if the namespace is either [`Namespace::GLOBAL`] or [`Namespace::LOCAL`]
+    /// 1. Just a filename, if the namespace is [`Namespace::GLOBAL`], [`Namespace::LOCAL`] or [`Namespace::SESSION`]
     /// 2. A namespaced filename if using [`Namespace::CUSTOM`] and you know what you're doing
     /// 3. Just a filename if using [`Namespace::CUSTOM`] and you don't need other processes to access it.
     ///
@@ -201,143 +785,1704 @@ impl<LOCK: MMFLock> MemoryMappedFile<LOCK> {
     ///
     /// The size MUST be a non-zero value; allocating zero bytes errors on the OS end of things. Allocating too much
     /// will make a part of the file inaccessible to other code trying to read it from a 32-bit process.
-    /// The total size allocated will be 4 bytes larger than the specified size, but only after checking the input size
-    /// is non-zero.
+    /// The total size allocated will be [`MMFLock::lock_size`] bytes larger than the specified size, but only after
+    /// checking the input size is non-zero.
     pub fn new(size: NonZeroUsize, name: impl Into<ztr64>, namespace: Namespace) -> MMFResult<Self> {
+        Self::new_with_lock_offset(size, name, namespace, 0)
+    }
+
+    /// Like [`MemoryMappedFile::new`], except the lock lives `lock_offset` bytes into the MMF instead of at the start.
+    ///
+    /// Meant for interoperating with existing layouts, where the lock word can't be the first thing in the MMF. The
+    /// data is everything that isn't the lock: the first `lock_offset` bytes, followed by whatever comes after the
+    /// lock. Reads and writes skip over the lock as if it isn't there. The offset must be a multiple of 4 for the
+    /// lock's atomics to be aligned, or this errors with [`MMFError::MisalignedLock`]. An offset past the end of the
+    /// data errors with [`MMFError::NotEnoughMemory`].
+    ///
+    /// The lock takes up all of its [`MMFLock::lock_size`] bytes from the offset on, not just one word. For an
+    /// [`RWLock`][crate::states::RWLock] that's [`RWLock::SIZE`][crate::states::RWLock::SIZE], 32 bytes, so a layout
+    /// that only leaves room for a single lock word doesn't fit one. Whatever it put behind that word gets trampled.
+    /// Pick a smaller lock like the 8 byte [`TicketLock`][crate::ticket::TicketLock] if that's all the room there is.
+    ///
+    /// For [`SecurityAttributes`] or any other option on top, see [`CreateOptions::lock_offset`].
+    pub fn new_with_lock_offset(
+        size: NonZeroUsize,
+        name: impl Into<ztr64>,
+        namespace: Namespace,
+        lock_offset: usize,
+    ) -> MMFResult<Self> {
+        Self::new_with_options(size, name, namespace, &CreateOptions::new().lock_offset(lock_offset))
+    }
+
+    /// Like [`MemoryMappedFile::new`], with [`SecurityAttributes`] to control who gets to open the MMF and whether
+    /// child processes inherit the handle. `None` is the same as calling [`MemoryMappedFile::new`].
+    pub fn new_with_security(
+        size: NonZeroUsize,
+        name: impl Into<ztr64>,
+        namespace: Namespace,
+        security: Option<&SecurityAttributes>,
+    ) -> MMFResult<Self> {
+        Self::new_with_options(size, name, namespace, &CreateOptions { security, ..Default::default() })
+    }
+
+    /// Like [`MemoryMappedFile::new`], except the MMF is backed by large pages.
+    ///
+    /// Large pages cut down on TLB misses for big MMFs that are accessed all over the place, but they come with strings
+    /// attached:
+    ///
+    /// - The total size, including the lock, is rounded up to a multiple of [`large_page_minimum`]. The extra space
+    ///   goes to the data, so [`Mmf::size`] may be a lot larger than what you asked for.
+    /// - Large pages are never paged out. The whole MMF is committed up front and stays resident in physical memory
+    ///   until the last handle to it is closed, whether anyone touches it or not.
+    /// - The physical memory has to be contiguous. On a machine that's been up for a while it may be too fragmented,
+    ///   in which case this fails with an OS error even though there's plenty of memory free.
+    /// - The process needs `SeLockMemoryPrivilege` ("Lock pages in memory" in the local security policy). This enables
+    ///   it if the account has it, and errors with [`MMFError::LargePageUnavailable`] if it doesn't. The same error is
+    ///   used when the processor doesn't support large pages at all.
+    ///
+    /// Other processes open it like any other MMF, passing the rounded size to get all of it. For large pages without
+    /// the rounding, or combined with other options, see [`CreateOptions::large_pages`].
+    pub fn new_large_pages(size: NonZeroUsize, name: impl Into<ztr64>, namespace: Namespace) -> MMFResult<Self> {
+        let page = large_page_minimum().ok_or(MMFError::LargePageUnavailable(None))?.get();
+        let total = (size.get() + LOCK::lock_size()).div_ceil(page) * page;
+        // Rounding up never ends up smaller than what we started with.
+        let size = NonZeroUsize::new(total - LOCK::lock_size()).unwrap_or(size);
+        Self::new_with_options(size, name, namespace, &CreateOptions::new().large_pages(true))
+    }
+
+    /// Like [`MemoryMappedFile::new`], with all the [`CreateOptions`] you can think of.
+    pub fn new_with_options(
+        size: NonZeroUsize,
+        name: impl Into<ztr64>,
+        namespace: Namespace,
+        options: &CreateOptions<'_>,
+    ) -> MMFResult<Self> {
+        if options.large_pages {
+            let page = large_page_minimum().ok_or(MMFError::LargePageUnavailable(None))?.get();
+            if (size.get() + LOCK::lock_size()) % page != 0 {
+                return Err(MMFError::MisalignedData);
+            }
+            enable_lock_memory_privilege()?;
+        }
+        Self::create_impl(size.get(), name.into(), namespace, options)
+    }
+
+    /// The guts of [`MemoryMappedFile::new_with_options`] and its siblings. `size` must be non-zero, and a suitable
+    /// multiple if large pages are asked for.
+    fn create_impl(size: usize, name: ztr64, namespace: Namespace, options: &CreateOptions<'_>) -> MMFResult<Self> {
+        let CreateOptions { lock_offset, large_pages, inherit, security } = *options;
+        check_lock_offset(size, lock_offset)?;
         // Build the name to use for the MMF
-        let init_name = match namespace {
-            Namespace::GLOBAL => GLOBAL_NAMESPACE,
-            Namespace::LOCAL => LOCAL_NAMESPACE,
-            Namespace::CUSTOM => ztr64::new(),
-        } + name.into();
+        let init_name = namespace.prefix() + name;
 
         // fuckin' windows
         let mmf_name = PCSTR::from_raw(init_name.to_ptr());
-        let (dw_low, dw_high) = (size.get() + 4).split();
+        let lock_size = LOCK::lock_size();
+        let (dw_low, dw_high) = (size + lock_size).split();
+        let (protection, map_flags) = if large_pages {
+            (
+                PAGE_READWRITE | PAGE_PROTECTION_FLAGS(SEC_COMMIT.0 | SEC_LARGE_PAGES.0),
+                FILE_MAP_ALL_ACCESS | FILE_MAP_LARGE_PAGES,
+            )
+        } else {
+            (PAGE_READWRITE, FILE_MAP_ALL_ACCESS)
+        };
+
+        // Spelled out even without any, so handles are never inherited by accident.
+        let mut attributes = security.map_or_else(|| SecurityAttributes::new().raw(), SecurityAttributes::raw);
+        if inherit {
+            attributes.bInheritHandle = true.into();
+        }
 
         // Safety: handled through microSEH and we check the last error status later. Failure here is failure there.
         let handle = try_seh(|| unsafe {
-            CreateFileMappingA(INVALID_HANDLE_VALUE, None, PAGE_READWRITE, dw_high, dw_low, mmf_name)
-        })??;
+            CreateFileMappingA(INVALID_HANDLE_VALUE, Some(&attributes), protection, dw_high, dw_low, mmf_name)
+        })?
+        .map_err(|e| match MMFError::from_os(e) {
+            // Enabling the privilege worked, yet the OS still wants it. Same fallback for the caller either way.
+            MMFError::MissingPrivilege if large_pages => {
+                MMFError::LargePageUnavailable(Some(ERROR_PRIVILEGE_NOT_HELD.0 as i32))
+            }
+            e => e,
+        })?;
 
         // Unsafe because `MapViewOfFile` is marked as such, but it should return a NULL pointer when failing; and set
         // the last error state correspondingly.
-        let map_view = try_seh(|| unsafe { MapViewOfFile(handle, FILE_MAP_ALL_ACCESS, 0, 0, size.get() + 4) })?;
+        let map_view = try_seh(|| unsafe { MapViewOfFile(handle, map_flags, 0, 0, size + lock_size) })?;
 
         // Explicit check to make sure we have something that works (later is now)
         if unsafe { GetLastError() }.is_err() {
-            return Err(WErr::from_win32().into());
+            return Err(MMFError::from_os(WErr::from_win32()));
         }
 
-        // Waste some time to ensure the memory is zeroed out - I learned the importance of this the hard way.
-        let zeroing = vec![0; size.get() + 4];
-        // safety: we're writing zeroes into memory we just got back from the OS
-        unsafe { std::ptr::copy(zeroing.as_ptr(), map_view.Value.cast(), zeroing.len()) };
-
-        // safety: we just zeroed this memory out and we're initializing it freshly
-        let lock = unsafe { LOCK::from_raw(map_view.Value.cast()).initialize() };
-        let write_ptr = unsafe { map_view.Value.cast::<u8>().add(4) };
+        let view_ptr = map_view.Value.cast::<u8>();
+        // safety: this memory is fresh from the OS and we're initializing it freshly
+        let lock = unsafe { LOCK::from_raw_named(view_ptr.add(lock_offset), init_name.as_str()) };
+        if lock.try_claim_init() {
+            // Waste some time to ensure the memory is zeroed out - I learned the importance of this the hard way.
+            let zeroing = vec![0; size];
+            // safety: we're writing zeroes into memory we just got back from the OS
+            unsafe { copy_to_data(view_ptr, lock_offset, lock_size, 0, zeroing.as_ptr(), zeroing.len()) };
+            lock.set_init();
+        } else if let Err(e) = lock.init_or_wait(INIT_TIMEOUT) {
+            unsafe {
+                _ = UnmapViewOfFile(map_view);
+                _ = CloseHandle(handle);
+            }
+            return Err(e);
+        }
         Ok(Self {
             handle,
+            file: None,
             name: init_name,
             size_high_order: dw_high,
             size_low_order: dw_low,
-            size: size.get(),
+            size,
             map_view: Some(map_view.into()),
             lock,
-            write_ptr,
+            view_ptr,
+            lock_offset,
             closed: Cell::new(false),
             readonly: false,
+            lock_timeout: LOCK_TIMEOUT,
+            spin_budget: SPIN_BUDGET,
+            access: Access::ReadWrite,
+            private_lock: None,
+            shared_lock: None,
         })
     }
 
-    /// Open an existing MMF, if it exists.
+    /// Open an MMF if it already exists, or create it if it doesn't. Tells you which of the two it was.
     ///
-    /// Defaults to read and write permissions, use the exposed wrappers to open R or RW
-    /// I have no idea what happens if you call this on a fake name. Code responsibly.
-    /// In all reality though, it should return an error that you can handle.
-    pub fn open(size: NonZeroUsize, name: &str, namespace: Namespace, readonly: bool) -> MMFResult<Self> {
-        // Build the name to use for the MMF
-        let init_name = match namespace {
-            Namespace::GLOBAL => ztr64::make(&format!("{GLOBAL_NAMESPACE}{name}")),
-            Namespace::LOCAL => ztr64::make(&format!("{LOCAL_NAMESPACE}{name}")),
-            Namespace::CUSTOM => ztr64::make(name),
-        };
-        // fuckin' windows
-        let mmf_name = PCSTR::from_raw(init_name.to_ptr());
-        let (dw_low, dw_high) = (size.get() + 4).split();
+    /// This is the way to go when you don't know which process gets there first: creating and opening happen in a
+    /// single call, so there's no window for another process to sneak in between. The lock is only initialized and the
+    /// data only zeroed if the MMF was [created][Disposition::Created], an [opened][Disposition::Opened] MMF is left
+    /// alone. The same naming rules as for [`MemoryMappedFile::new`] apply. The size of an existing MMF isn't checked,
+    /// so make sure everyone agrees on it.
+    pub fn open_or_create(
+        size: NonZeroUsize,
+        name: impl Into<ztr64>,
+        namespace: Namespace,
+    ) -> MMFResult<(Self, Disposition)> {
+        Self::open_or_create_impl(None, size, name.into(), namespace, None, Access::ReadWrite, 0)
+    }
 
-        // Safety: Issues here are issues later, and we check for them later.
-        let handle = try_seh(|| unsafe { OpenFileMappingA(FILE_MAP_ALL_ACCESS.0, false, mmf_name) })??;
+    /// Like [`MemoryMappedFile::open_or_create`], with [`SecurityAttributes`]. The descriptor only applies if the MMF
+    /// gets [created][Disposition::Created], inheritance applies either way.
+    pub fn open_or_create_with_security(
+        size: NonZeroUsize,
+        name: impl Into<ztr64>,
+        namespace: Namespace,
+        security: Option<&SecurityAttributes>,
+    ) -> MMFResult<(Self, Disposition)> {
+        Self::open_or_create_impl(None, size, name.into(), namespace, security, Access::ReadWrite, 0)
+    }
 
-        // Unsafe because `MapViewOfFile` is marked as such, but it should return a NULL pointer when failing; and set
-        // the last error state correspondingly.
-        let map_view = try_seh(|| unsafe { MapViewOfFile(handle, FILE_MAP_ALL_ACCESS, 0, 0, size.get() + 4) })?;
+    /// Like [`MemoryMappedFile::open_or_create`], for an MMF with its lock `lock_offset` bytes in. See
+    /// [`MemoryMappedFile::new_with_lock_offset`], everyone has to agree on the offset just like on the size.
+    pub fn open_or_create_with_lock_offset(
+        size: NonZeroUsize,
+        name: impl Into<ztr64>,
+        namespace: Namespace,
+        lock_offset: usize,
+    ) -> MMFResult<(Self, Disposition)> {
+        check_lock_offset(size.get(), lock_offset)?;
+        Self::open_or_create_impl(None, size, name.into(), namespace, None, Access::ReadWrite, lock_offset)
+    }
 
-        // Explicit check to make sure we have something that works (later is now)
-        if unsafe { GetLastError() }.is_err() {
-            return Err(WErr::from_win32().into());
-        }
+    /// Map a file on disk, so whatever is written to the MMF ends up in the file and survives everyone closing it.
+    ///
+    /// The file is opened for reading and writing, and created if it doesn't exist yet. Other processes can open the
+    /// mapping by name like any other MMF, or map the same file themselves. Passing a `size` of 0 maps the whole file,
+    /// which then has to be larger than [`MMFLock::lock_size`] or this errors with [`MMFError::NotEnoughMemory`].
+    /// Anything larger than what's in the file grows it to fit.
+    ///
+    /// The lock lives in the file too, in its first [`MMFLock::lock_size`] bytes, with the data right behind it. So
+    /// the file has to be laid out like that, and the data starts [`MMFLock::lock_size`] bytes in when reading the
+    /// file some other way. If the mapping was [created][Disposition::Created] rather than opened, whatever lock state
+    /// a previous run left in the file is stale and gets reset. Unlike [`MemoryMappedFile::new`], the data is never
+    /// zeroed. That's the whole point of having a file.
+    ///
+    /// Use [`flush`][MemoryMappedFile::flush] to make sure the data actually hit the disk.
+    pub fn from_file(
+        path: &Path,
+        size: usize,
+        name: impl Into<ztr64>,
+        namespace: Namespace,
+    ) -> MMFResult<(Self, Disposition)> {
+        Self::from_file_with_access(path, size, name, namespace, Access::ReadWrite)
+    }
 
-        // Safety: We know where these bytes come from (ideally, they were opened by this lib)
-        let lock = unsafe { LOCK::from_existing(map_view.Value.cast()) };
-        let write_ptr = unsafe { map_view.Value.cast::<u8>().add(4) };
-        Ok(Self {
-            handle,
-            name: init_name,
-            size_high_order: dw_high,
-            size_low_order: dw_low,
-            size: size.get(),
-            lock,
-            map_view: Some(map_view.into()),
-            write_ptr,
-            closed: Cell::new(false),
-            readonly,
-        })
+    /// Like [`MemoryMappedFile::from_file`], with [`SecurityAttributes`] for the mapping. Like for
+    /// [`open_or_create_with_security`][MemoryMappedFile::open_or_create_with_security], the descriptor only applies
+    /// if the mapping gets [created][Disposition::Created]. The file itself is opened with the defaults either way, its
+    /// own ACL is a matter for the file system.
+    pub fn from_file_with_security(
+        path: &Path,
+        size: usize,
+        name: impl Into<ztr64>,
+        namespace: Namespace,
+        security: Option<&SecurityAttributes>,
+    ) -> MMFResult<(Self, Disposition)> {
+        Self::open_file(path, size, name.into(), namespace, Access::ReadWrite, false, security)
     }
 
-    /// Open an MMF for reading
+    /// Like [`MemoryMappedFile::from_file`], mapping the file as `access` says.
     ///
-    /// Wrapper around [`open`][Self::open] that always passes true
-    pub fn open_read(size: NonZeroUsize, name: &str, namespace: Namespace) -> MMFResult<Self> {
-        Self::open(size, name, namespace, true)
+    /// [`Access::ReadWrite`] is the same as [`MemoryMappedFile::from_file`]. The others only open the file for reading,
+    /// so it has to exist already and be large enough for `size`, or this errors. [`Access::ReadOnly`] has the OS
+    /// refuse any writes, and [`Access::CopyOnWrite`] keeps them in this process, so the file on disk never changes.
+    ///
+    /// Without write access, the lock in the file can't be taken or reset, so this MMF gets a private lock like a
+    /// [copy-on-write][Access::CopyOnWrite] MMF does. The one exception is reading a mapping somebody else
+    /// [created][Disposition::Created] with write access, where readers wait for the writer in the file like
+    /// [`open_with_access`][MemoryMappedFile::open_with_access] does. Either way, nobody waits for us.
+    pub fn from_file_with_access(
+        path: &Path,
+        size: usize,
+        name: impl Into<ztr64>,
+        namespace: Namespace,
+        access: FileAccess,
+    ) -> MMFResult<(Self, Disposition)> {
+        Self::open_file(path, size, name.into(), namespace, access, false, None)
     }
 
-    /// Open an MMF for reading and writing
+    /// Like [`MemoryMappedFile::from_file_with_access`], with the lock behind the data instead of in front of it.
     ///
-    /// Wrapper around [`open`][Self::open] that always passes false
-    pub fn open_write(size: NonZeroUsize, name: &str, namespace: Namespace) -> MMFResult<Self> {
-        Self::open(size, name, namespace, false)
+    /// The file then starts with the data, so tools that know nothing about the lock can read it as is, and only have
+    /// to ignore the last [`MMFLock::lock_size`] bytes. Those are still there: processes sharing the file need the
+    /// lock somewhere, and this way they keep the same lock discipline as any other MMF. A `size` of 0 maps the whole
+    /// file, treating its last bytes as the lock. Everything else works like [`MemoryMappedFile::from_file`], including
+    /// growing the file to fit and resetting a stale lock when the mapping is created.
+    ///
+    /// The lock's atomics need it to be aligned, so the data has to be a multiple of 4 bytes long, or this errors with
+    /// [`MMFError::MisalignedLock`] before touching the file. The same goes for the data in a file mapped as a whole,
+    /// which is only checked once the file is open.
+    ///
+    /// Everyone mapping the file has to agree on where the lock is, so don't mix this with the other constructors.
+    /// [Resizing][MemoryMappedFile::resize] keeps the lock where it is, which is no longer at the end afterwards.
+    pub fn from_file_with_trailing_lock(
+        path: &Path,
+        size: usize,
+        name: impl Into<ztr64>,
+        namespace: Namespace,
+        access: Access,
+    ) -> MMFResult<(Self, Disposition)> {
+        Self::open_file(path, size, name.into(), namespace, access, true, None)
     }
 
-    /// Check if this MMF can be written to
-    pub fn is_writable(&self) -> bool {
-        !self.readonly && !self.closed.get() && self.lock.initialized()
+    /// Open the file at `path` for [`MemoryMappedFile::from_file_with_access`] and its sibling, and map it.
+    fn open_file(
+        path: &Path,
+        size: usize,
+        name: ztr64,
+        namespace: Namespace,
+        access: Access,
+        trailing_lock: bool,
+        security: Option<&SecurityAttributes>,
+    ) -> MMFResult<(Self, Disposition)> {
+        // No point in creating a file we can't use. A size of 0 gets checked once we know how big the file is.
+        if trailing_lock {
+            check_lock_offset(size, size)?;
+        }
+        let wide: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+        let (rights, creation) = match access {
+            Access::ReadWrite => (GENERIC_READ | GENERIC_WRITE, OPEN_ALWAYS),
+            Access::ReadOnly | Access::CopyOnWrite => (GENERIC_READ, OPEN_EXISTING),
+        };
+        // Safety: the path is null terminated and outlives the call.
+        let file = try_seh(|| unsafe {
+            CreateFileW(
+                PCWSTR::from_raw(wide.as_ptr()),
+                rights.0,
+                FILE_SHARE_READ | FILE_SHARE_WRITE,
+                None,
+                creation,
+                FILE_ATTRIBUTE_NORMAL,
+                None,
+            )
+        })?
+        .map_err(MMFError::from_os)?;
+        Self::file_impl(file, size, name, namespace, access, trailing_lock, security)
     }
 
-    /// Check if this MMF can be read from
-    pub fn is_readable(&self) -> bool {
-        !self.closed.get() && self.lock.initialized()
+    /// Like [`MemoryMappedFile::from_file`], for a file you already opened. The handle needs read and write access.
+    ///
+    /// The MMF works on a duplicate of the handle, so it's still yours to close whenever you like.
+    pub fn from_file_handle(
+        file: HANDLE,
+        size: usize,
+        name: impl Into<ztr64>,
+        namespace: Namespace,
+    ) -> MMFResult<(Self, Disposition)> {
+        Self::file_impl(duplicate_handle(file)?, size, name.into(), namespace, Access::ReadWrite, false, None)
     }
 
-    /// Get the namespace of the file, if any. If an empty string is returned, it's Local.
-    pub fn namespace(&self) -> String {
-        self.name.split_once('\\').unwrap_or_default().0.to_owned()
+    /// The guts of [`MemoryMappedFile::from_file`] and [`MemoryMappedFile::from_file_handle`]. Takes ownership of
+    /// `file`, and closes it if anything goes wrong.
+    fn file_impl(
+        file: HANDLE,
+        size: usize,
+        name: ztr64,
+        namespace: Namespace,
+        access: Access,
+        trailing_lock: bool,
+        security: Option<&SecurityAttributes>,
+    ) -> MMFResult<(Self, Disposition)> {
+        let size = match size {
+            0 => {
+                let mut file_size = 0i64;
+                // Safety: the handle is ours, and the size lives long enough.
+                try_seh(|| unsafe { GetFileSizeEx(file, &mut file_size) })
+                    .map_err(MMFError::from)
+                    .and_then(|res| res.map_err(MMFError::from_os))
+                    .and_then(|_| {
+                        (file_size as u64 as usize).checked_sub(LOCK::lock_size()).ok_or(MMFError::NotEnoughMemory)
+                    })
+            }
+            size => Ok(size),
+        };
+        let res = size
+            .and_then(|size| NonZeroUsize::new(size).ok_or(MMFError::NotEnoughMemory))
+            .and_then(|size| {
+                let lock_offset = if trailing_lock { size.get() } else { 0 };
+                check_lock_offset(size.get(), lock_offset)?;
+                Self::open_or_create_impl(Some(file), size, name, namespace, security, access, lock_offset)
+            });
+        if res.is_err() {
+            // Safety: it's ours, and nothing else got to keep it.
+            unsafe { _ = CloseHandle(file) };
+        }
+        res
     }
 
-    /// Return the filename the MMF is bound to, which is only the whole name if no namespace is provided.
-    pub fn filename(&self) -> String {
-        self.name.split_once('\\').map(|s| s.1.to_owned()).unwrap_or(self.name.to_string())
-    }
+    /// The guts of [`MemoryMappedFile::open_or_create`] and [`MemoryMappedFile::file_impl`]. Maps `file` if there is
+    /// one, and the page file otherwise. Only the page file gets its data zeroed. Doesn't close `file` on errors.
+    ///
+    /// Only files are mapped with anything but [`Access::ReadWrite`], the page file would have nobody to write to it.
+    fn open_or_create_impl(
+        file: Option<HANDLE>,
+        size: NonZeroUsize,
+        name: ztr64,
+        namespace: Namespace,
+        security: Option<&SecurityAttributes>,
+        access: Access,
+        lock_offset: usize,
+    ) -> MMFResult<(Self, Disposition)> {
+        // Build the name to use for the MMF
+        let init_name = namespace.prefix() + name;
 
-    /// Returns the stored name, which should be `[Namespace\]<FileName>`
+        // fuckin' windows
+        let mmf_name = PCSTR::from_raw(init_name.to_ptr());
+        let lock_size = LOCK::lock_size();
+        let (dw_low, dw_high) = (size.get() + lock_size).split();
+
+        // Spelled out even without any, so handles are never inherited by accident.
+        let attributes = security.map_or_else(|| SecurityAttributes::new().raw(), SecurityAttributes::raw);
+
+        // Safety: handled through microSEH and we check the last error status later. Failure here is failure there.
+        let handle = try_seh(|| unsafe {
+            CreateFileMappingA(
+                file.unwrap_or(INVALID_HANDLE_VALUE),
+                Some(&attributes),
+                access.protection(),
+                dw_high,
+                dw_low,
+                mmf_name,
+            )
+        })?
+        .map_err(MMFError::from_os)?;
+        // This has to be checked right away, before anything else gets to touch the last error.
+        let disposition = match unsafe { GetLastError() } {
+            ERROR_ALREADY_EXISTS => Disposition::Opened,
+            _ => Disposition::Created,
+        };
+
+        // Unsafe because `MapViewOfFile` is marked as such, but it should return a NULL pointer when failing; and set
+        // the last error state correspondingly.
+        let map_view = try_seh(|| unsafe { MapViewOfFile(handle, access.map_flags(), 0, 0, size.get() + lock_size) })?;
+        if map_view.Value.is_null() {
+            let err = MMFError::from_os(WErr::from_win32());
+            unsafe { _ = CloseHandle(handle) };
+            return Err(err);
+        }
+
+        let view_ptr = map_view.Value.cast::<u8>();
+        // Without write access, the lock in the view is somebody else's business at best, and stale at worst.
+        let watch_view = access == Access::ReadOnly && disposition == Disposition::Opened;
+        if access != Access::ReadWrite && !watch_view {
+            let (lock, buf) = Self::private_lock();
+            return Ok((
+                Self::from_parts(
+                    handle,
+                    file,
+                    init_name,
+                    size,
+                    map_view,
+                    view_ptr,
+                    lock_offset,
+                    lock,
+                    access,
+                    Some(buf),
+                ),
+                disposition,
+            ));
+        }
+        // Safety: either the memory is fresh from the OS, or it was created by somebody else using this lib.
+        let lock = unsafe {
+            match disposition {
+                Disposition::Created => LOCK::from_raw_named(view_ptr.add(lock_offset), init_name.as_str()),
+                Disposition::Opened => LOCK::from_existing_named(view_ptr.add(lock_offset), init_name.as_str()),
+            }
+        };
+        if watch_view {
+            return Ok((
+                Self::from_parts(handle, file, init_name, size, map_view, view_ptr, lock_offset, lock, access, None),
+                disposition,
+            ));
+        } else if disposition == Disposition::Created && lock.try_claim_init() {
+            // A file's contents are worth keeping, the page file's are not.
+            if file.is_none() {
+                // Waste some time to ensure the memory is zeroed out - I learned the importance of this the hard way.
+                let zeroing = vec![0; size.get()];
+                // safety: we're writing zeroes into memory we just got back from the OS
+                unsafe { copy_to_data(view_ptr, lock_offset, lock_size, 0, zeroing.as_ptr(), zeroing.len()) };
+            }
+            lock.set_init();
+        } else if let Err(e) = lock.init_or_wait(INIT_TIMEOUT) {
+            unsafe {
+                _ = UnmapViewOfFile(map_view);
+                _ = CloseHandle(handle);
+            }
+            return Err(e);
+        }
+        Ok((
+            Self::from_parts(handle, file, init_name, size, map_view, view_ptr, lock_offset, lock, access, None),
+            disposition,
+        ))
+    }
+
+    /// Put together an MMF for [`MemoryMappedFile::open_or_create_impl`], with the default settings.
+    #[allow(clippy::too_many_arguments)]
+    fn from_parts(
+        handle: HANDLE,
+        file: Option<HANDLE>,
+        name: ztr64,
+        size: NonZeroUsize,
+        map_view: MEMORY_MAPPED_VIEW_ADDRESS,
+        view_ptr: *mut u8,
+        lock_offset: usize,
+        lock: LOCK,
+        access: Access,
+        private_lock: Option<Box<[u64]>>,
+    ) -> Self {
+        let (size_low_order, size_high_order) = (size.get() + LOCK::lock_size()).split();
+        Self {
+            handle,
+            file,
+            name,
+            size_high_order,
+            size_low_order,
+            size: size.get(),
+            map_view: Some(map_view.into()),
+            lock,
+            view_ptr,
+            lock_offset,
+            closed: Cell::new(false),
+            readonly: access == Access::ReadOnly,
+            lock_timeout: LOCK_TIMEOUT,
+            spin_budget: SPIN_BUDGET,
+            access,
+            private_lock,
+            shared_lock: None,
+        }
+    }
+
+    /// Check if an MMF with this name exists, without creating or mapping anything.
+    ///
+    /// Like [`Path::exists`], this only tells you about right now: the MMF can be created or disappear the moment
+    /// after. So don't use it to decide between creating and opening, [`open_or_create`][Self::open_or_create] does
+    /// that without the race. It's meant for polling until somebody else created the MMF. An MMF you're not allowed to
+    /// open does exist, so that's `true`. Any other error opening it is returned as is.
+    pub fn exists(name: &str, namespace: Namespace) -> MMFResult<bool> {
+        let init_name = namespace.format_name(name);
+        let mmf_name = PCSTR::from_raw(init_name.to_ptr());
+        // Safety: the name is null terminated and outlives the call, and the handle is closed right away.
+        match try_seh(|| unsafe { OpenFileMappingA(FILE_MAP_READ.0, false, mmf_name) })? {
+            Ok(handle) => {
+                unsafe { _ = CloseHandle(handle) };
+                Ok(true)
+            }
+            Err(e) if e.code() == ERROR_FILE_NOT_FOUND.to_hresult() => Ok(false),
+            Err(e) => match MMFError::from_os(e) {
+                MMFError::AccessDenied => Ok(true),
+                e => Err(e),
+            },
+        }
+    }
+
+    /// Open an existing MMF, if it exists.
+    ///
+    /// Defaults to read and write permissions, use the exposed wrappers to open R or RW
+    /// I have no idea what happens if you call this on a fake name. Code responsibly.
+    /// In all reality though, it should return an error that you can handle.
+    /// Turns out it does: an OS error with `ERROR_FILE_NOT_FOUND` as its [code][MMFError::raw_os_error] if there's no
+    /// such MMF, and [`MMFError::AccessDenied`] if there is one, but its [security descriptor][SecurityAttributes]
+    /// doesn't let you in.
+    pub fn open(size: NonZeroUsize, name: &str, namespace: Namespace, readonly: bool) -> MMFResult<Self> {
+        Self::open_with_lock_offset(size, name, namespace, readonly, 0)
+    }
+
+    /// Like [`MemoryMappedFile::open`], for an MMF with its lock `lock_offset` bytes in. See
+    /// [`MemoryMappedFile::new_with_lock_offset`].
+    pub fn open_with_lock_offset(
+        size: NonZeroUsize,
+        name: &str,
+        namespace: Namespace,
+        readonly: bool,
+        lock_offset: usize,
+    ) -> MMFResult<Self> {
+        Self::open_impl(size, name, namespace, readonly, lock_offset, Access::ReadWrite)
+    }
+
+    /// Like [`MemoryMappedFile::open`], mapping the view as `access` says. See [`Access`] for what each mode does.
+    ///
+    /// Unlike [opening for reading][MemoryMappedFile::open_read], which only keeps this crate from writing,
+    /// [`Access::ReadOnly`] has the OS enforce it. Meant for untrusted readers attaching to somebody else's data.
+    /// There's no creating an MMF like this, somebody has to be able to write to it to initialize the lock.
+    pub fn open_with_access(size: NonZeroUsize, name: &str, namespace: Namespace, access: Access) -> MMFResult<Self> {
+        Self::open_impl(size, name, namespace, access == Access::ReadOnly, 0, access)
+    }
+
+    /// Does the actual opening for all the public ways to open an MMF.
+    fn open_impl(
+        size: NonZeroUsize,
+        name: &str,
+        namespace: Namespace,
+        readonly: bool,
+        lock_offset: usize,
+        access: Access,
+    ) -> MMFResult<Self> {
+        check_lock_offset(size.get(), lock_offset)?;
+        // Build the name to use for the MMF
+        let init_name = namespace.format_name(name);
+        // fuckin' windows
+        let mmf_name = PCSTR::from_raw(init_name.to_ptr());
+
+        // Safety: Issues here are issues later, and we check for them later.
+        let handle = try_seh(|| unsafe { OpenFileMappingA(access.map_flags().0, false, mmf_name) })?
+            .map_err(MMFError::from_os)?;
+        Self::map_handle(handle, size, init_name, readonly, lock_offset, access)
+    }
+
+    /// Build an MMF around an existing mapping, like one inherited from the parent process.
+    ///
+    /// This is the other end of [`CreateOptions::inheritable`] and [`MemoryMappedFile::duplicate_into`]: the handle is
+    /// mapped like [`open_with_access`][MemoryMappedFile::open_with_access] would after looking it up by name, and
+    /// the lock is expected at the start. The MMF doesn't know its name, so [`fullname`][MemoryMappedFile::fullname]
+    /// is empty.
+    ///
+    /// # Safety
+    /// The handle must be an open handle to a file mapping created by this crate, with at least the access asked for,
+    /// and `size` must not be larger than it. The MMF takes ownership and closes it when dropped, even when this
+    /// errors, so nothing else may close it.
+    pub unsafe fn from_raw_handle(handle: HANDLE, size: NonZeroUsize, access: Access) -> MMFResult<Self> {
+        Self::map_handle(handle, size, ztr64::default(), access == Access::ReadOnly, 0, access)
+    }
+
+    /// The part of [`MemoryMappedFile::open_impl`] after getting a handle: map it and attach to the lock. Takes
+    /// ownership of the handle.
+    fn map_handle(
+        handle: HANDLE,
+        size: NonZeroUsize,
+        init_name: ztr64,
+        readonly: bool,
+        lock_offset: usize,
+        access: Access,
+    ) -> MMFResult<Self> {
+        let lock_size = LOCK::lock_size();
+        let (dw_low, dw_high) = (size.get() + lock_size).split();
+
+        // Unsafe because `MapViewOfFile` is marked as such, but it should return a NULL pointer when failing; and set
+        // the last error state correspondingly.
+        let map_view = try_seh(|| unsafe { MapViewOfFile(handle, access.map_flags(), 0, 0, size.get() + lock_size) })?;
+
+        // Explicit check to make sure we have something that works (later is now)
+        if unsafe { GetLastError() }.is_err() {
+            let err = MMFError::from_os(WErr::from_win32());
+            unsafe { _ = CloseHandle(handle) };
+            return Err(err);
+        }
+
+        let view_ptr = map_view.Value.cast::<u8>();
+        // Safety: We know where these bytes come from (ideally, they were opened by this lib)
+        let lock = unsafe { LOCK::from_existing_named(view_ptr.add(lock_offset), init_name.as_str()) };
+        // Whoever created the MMF might still be setting it up, give them a moment before reading garbage. Offering to
+        // initialize it ourselves writes to the lock, which a read-only view can't and a copy-on-write view shouldn't,
+        // so those just watch.
+        let waited = match access {
+            Access::ReadOnly | Access::CopyOnWrite => lock.wait_initialized(INIT_TIMEOUT).map(|_| false),
+            _ => lock.init_or_wait(INIT_TIMEOUT),
+        };
+        if let Err(e) = waited {
+            unsafe {
+                _ = UnmapViewOfFile(map_view);
+                _ = CloseHandle(handle);
+            }
+            return Err(e);
+        }
+        let (lock, private_lock, shared_lock) = match access {
+            Access::CopyOnWrite => {
+                let shared_lock = match Self::watch_lock(handle, lock_offset, &init_name) {
+                    Ok(shared_lock) => shared_lock,
+                    Err(e) => {
+                        unsafe {
+                            _ = UnmapViewOfFile(map_view);
+                            _ = CloseHandle(handle);
+                        }
+                        return Err(e);
+                    }
+                };
+                let (lock, buf) = Self::private_lock();
+                (lock, Some(buf), Some(shared_lock))
+            }
+            _ => (lock, None, None),
+        };
+        Ok(Self {
+            handle,
+            file: None,
+            name: init_name,
+            size_high_order: dw_high,
+            size_low_order: dw_low,
+            size: size.get(),
+            lock,
+            map_view: Some(map_view.into()),
+            view_ptr,
+            lock_offset,
+            closed: Cell::new(false),
+            readonly,
+            lock_timeout: LOCK_TIMEOUT,
+            spin_budget: SPIN_BUDGET,
+            access,
+            private_lock,
+            shared_lock,
+        })
+    }
+
+    /// Open an MMF for reading
+    ///
+    /// Wrapper around [`open`][Self::open] that always passes true
+    pub fn open_read(size: NonZeroUsize, name: &str, namespace: Namespace) -> MMFResult<Self> {
+        Self::open(size, name, namespace, true)
+    }
+
+    /// Open an MMF for reading and writing
+    ///
+    /// Wrapper around [`open`][Self::open] that always passes false
+    pub fn open_write(size: NonZeroUsize, name: &str, namespace: Namespace) -> MMFResult<Self> {
+        Self::open(size, name, namespace, false)
+    }
+
+    /// Open an MMF copy-on-write, mapping the view with `FILE_MAP_COPY`.
+    ///
+    /// Wrapper around [`open_with_access`][Self::open_with_access] with [`Access::CopyOnWrite`]. Reads see what
+    /// everyone else wrote until this instance writes to the same page, writes are only ever seen by this instance.
+    /// Handy for scribbling on shared data for some local computation without bothering anyone else. Writes only take
+    /// a lock private to this instance, reads watch the shared one too. See [`Access::CopyOnWrite`] for what that
+    /// means, and what the scribbling costs.
+    pub fn open_copy_on_write(size: NonZeroUsize, name: &str, namespace: Namespace) -> MMFResult<Self> {
+        Self::open_with_access(size, name, namespace, Access::CopyOnWrite)
+    }
+
+    /// Open an existing MMF [mapped read-only][Access::ReadOnly], as a [`ReaderMMF`] that has no way to write at all.
+    ///
+    /// For consumers that shouldn't be able to scribble on the data, not even by accident: the OS refuses writes to
+    /// the view, and the type doesn't offer any. Reads can't register with the lock, see [`ReaderMMF`] for how they
+    /// stay out of the writers' way regardless.
+    pub fn open_read_only(size: NonZeroUsize, name: &str, namespace: Namespace) -> MMFResult<ReaderMMF<LOCK>> {
+        Self::open_with_access(size, name, namespace, Access::ReadOnly).map(ReaderMMF)
+    }
+
+    /// Open only a window of an existing MMF, as a [`RegionMMF`], for MMFs too large to have in the address space all
+    /// at once. Especially in 32-bit processes, where a couple of gigabytes is all there is.
+    ///
+    /// The window holds `len` bytes of data, starting `offset` bytes into the data like for
+    /// [`read_at`][MemoryMappedFile::read_at]. That's counted from behind the lock, so only MMFs with the lock at the
+    /// start can be opened like this. Views can only start at a multiple of [`allocation_granularity`], so the view
+    /// starts at the last one before the window, and the region skips whatever's in between. `offset` can be anything.
+    ///
+    /// `lock` picks which lock guards the window, see [`RegionLock`]. Errors with an OS error if there's no MMF by that
+    /// name or the window reaches past its end, with [`MMFError::MisalignedLock`] if a lock of its own wouldn't be
+    /// aligned to 4 bytes, and with [`MMFError::Uninitialized`] if the lock isn't set up in time. The window is always
+    /// mapped for reading and writing.
+    pub fn open_region(
+        name: &str,
+        namespace: Namespace,
+        offset: u64,
+        len: NonZeroUsize,
+        lock: RegionLock,
+    ) -> MMFResult<RegionMMF<LOCK>> {
+        let init_name = namespace.format_name(name);
+        let mmf_name = PCSTR::from_raw(init_name.to_ptr());
+        // Safety: failure is an error, which we check for right away.
+        let handle = try_seh(|| unsafe { OpenFileMappingA(Access::ReadWrite.map_flags().0, false, mmf_name) })?
+            .map_err(MMFError::from_os)?;
+        RegionMMF::map(handle, init_name, offset, len, lock).map_err(|e| {
+            unsafe { _ = CloseHandle(handle) };
+            e
+        })
+    }
+
+    /// Chainable setter for how long reads and writes wait for the lock before giving up with [`MMFError::Timeout`].
+    /// Defaults to [`LOCK_TIMEOUT`], use [`Duration::ZERO`] to only try once.
+    ///
+    /// The waiting is done by trying the lock and [waiting for it to be unlocked][MMFLock::wait_until_unlocked] in
+    /// turns. The lock's blocking [`lock_read`][MMFLock::lock_read] and [`lock_write`][MMFLock::lock_write] are never
+    /// used, as they can't be told when to give up.
+    pub fn with_lock_timeout(mut self, timeout: Duration) -> Self {
+        self.lock_timeout = timeout;
+        self
+    }
+
+    /// Get how long reads and writes wait for the lock.
+    pub fn lock_timeout(&self) -> Duration {
+        self.lock_timeout
+    }
+
+    /// Chainable setter for the maximum amount of tries handed to the spinner passed to [`Mmf::read_spin`] and friends,
+    /// like [`RWLock::spin_and_lock_read`][crate::states::RWLock]. Defaults to [`SPIN_BUDGET`], which is large enough
+    /// to ride out any sane amount of contention and small enough to not wait forever on a lock nobody is releasing.
+    /// Reads and writes without a spinner go by the [lock timeout][MemoryMappedFile::with_lock_timeout] instead.
+    pub fn with_spin_budget(mut self, budget: usize) -> Self {
+        self.spin_budget = budget.max(1);
+        self
+    }
+
+    /// Get the maximum amount of tries spinners get.
+    pub fn spin_budget(&self) -> usize {
+        self.spin_budget
+    }
+
+    /// Map a read-only view of the start of the mapping behind `handle`, up to and including the lock, and attach to
+    /// the lock in it. For [copy-on-write][Access::CopyOnWrite] reads to watch what everyone else is up to.
+    fn watch_lock(handle: HANDLE, lock_offset: usize, name: &ztr64) -> MMFResult<(LOCK, MemoryMappedView)> {
+        // A length of 0 maps everything, so a lock without bytes still gets a byte to point at.
+        let len = (lock_offset + LOCK::lock_size()).max(1);
+        // Safety: failure gives us a null pointer, and the view is checked to reach past the lock by the main view.
+        let view = try_seh(|| unsafe { MapViewOfFile(handle, FILE_MAP_READ, 0, 0, len) })?;
+        if view.Value.is_null() {
+            return Err(MMFError::from_os(WErr::from_win32()));
+        }
+        // Safety: the lock is in the view, and initialized before we ever got here. It's only ever looked at.
+        let lock = unsafe { LOCK::from_existing_named(view.Value.cast::<u8>().add(lock_offset), name.as_str()) };
+        Ok((lock, view.into()))
+    }
+
+    /// Get the lock writers elsewhere take: the [watched one][MemoryMappedFile::watch_lock] if there is one.
+    fn shared_lock(&self) -> &LOCK {
+        self.shared_lock.as_ref().map_or(&self.lock, |(lock, _)| lock)
+    }
+
+    /// Build a fresh lock in memory of its own on the heap, for [copy-on-write][Access::CopyOnWrite] MMFs. The lock
+    /// points into the returned buffer, which has to be kept around for as long as the lock is.
+    fn private_lock() -> (LOCK, Box<[u64]>) {
+        let mut buf = vec![0u64; LOCK::lock_size().div_ceil(8)].into_boxed_slice();
+        // Safety: the buffer is large enough and aligned for any lock, and moving the box doesn't move what's in it.
+        let lock = unsafe { LOCK::from_raw(buf.as_mut_ptr().cast()) }.initialize();
+        (lock, buf)
+    }
+
+    /// Get the lock, for building things that need to hold it across more than a single read or write.
+    #[cfg(feature = "impl_lock")]
+    pub(crate) fn lock(&self) -> &LOCK {
+        &self.lock
+    }
+
+    /// Get the amount of data bytes, which is what you can actually use. Same as [`Mmf::size`].
+    ///
+    /// This is the size the MMF was created or opened with, the lock comes on top of it. The read and write helpers,
+    /// like [`read_at`][MemoryMappedFile::read_at] and [`write`][Mmf::write], count their offsets from the first data
+    /// byte and run up to this, skipping over the lock wherever it lives. The odd one out is
+    /// [`map_region`][MemoryMappedFile::map_region], which counts from the start of the mapping and runs up to
+    /// [`total_size`][MemoryMappedFile::total_size].
+    pub fn capacity(&self) -> usize {
+        self.size
+    }
+
+    /// Get the size of the whole mapping: the [capacity][MemoryMappedFile::capacity] plus [`MMFLock::lock_size`]
+    /// bytes of lock. This is what the OS sees, and what a file backing the MMF needs to hold.
+    pub fn total_size(&self) -> usize {
+        self.size + LOCK::lock_size()
+    }
+
+    /// Get how the view is mapped. Always [`Access::ReadWrite`] unless opened through
+    /// [`MemoryMappedFile::open_with_access`].
+    pub fn access(&self) -> Access {
+        self.access
+    }
+
+    /// Error with [`MMFError::ReadOnly`] if the OS won't let us write to the view.
+    fn check_writable(&self) -> MMFResult<()> {
+        match self.access {
+            Access::ReadOnly => Err(MMFError::ReadOnly),
+            _ => Ok(()),
+        }
+    }
+
+    /// Get the write generation of the lock, see [`MMFLock::generation`]. [`None`] if the lock doesn't count writes.
+    ///
+    /// For a [copy-on-write][Access::CopyOnWrite] MMF, this is the generation of its private lock, so it only counts
+    /// the writes made through this instance.
+    pub fn generation(&self) -> Option<u32> {
+        self.lock.generation()
+    }
+
+    /// Check if anything was written since the [generation][MemoryMappedFile::generation] was `since`, without taking
+    /// the lock. Always true if the lock doesn't count writes, as there's no telling.
+    ///
+    /// Comparisons wrap around along with the generation, so this is only fooled by exactly a multiple of 2^32 writes.
+    pub fn has_changed(&self, since: u32) -> bool {
+        self.generation().map_or(true, |now| now.wrapping_sub(since) != 0)
+    }
+
+    /// Check if this MMF can be written to
+    pub fn is_writable(&self) -> bool {
+        !self.readonly && !self.closed.get() && self.lock.initialized()
+    }
+
+    /// Check if this MMF can be read from
+    pub fn is_readable(&self) -> bool {
+        !self.closed.get() && self.lock.initialized()
+    }
+
+    /// Choose whether child processes inherit the handle to the MMF, and to the file backing it if there is one.
+    ///
+    /// Handles are never inherited unless asked for, either through this or [`SecurityAttributes::inheritable`].
+    /// Even then, a child only inherits them if it's started with `CreateProcess`'s `bInheritHandles` set, which
+    /// [`std::process::Command`] does. Every inheritable handle in the process goes along, so keep this off unless a
+    /// child actually needs it. A child that does has to be told the handle's value somehow, as it's not looking it
+    /// up by name. [`MemoryMappedFile::handle`] gets you that.
+    pub fn set_inheritable(&self, yes: bool) -> MMFResult<()> {
+        if self.closed.get() {
+            return Err(MMFError::MMF_NotFound);
+        }
+        let flags = if yes { HANDLE_FLAG_INHERIT } else { HANDLE_FLAGS(0) };
+        for handle in Some(self.handle).into_iter().chain(self.file) {
+            // Safety: the handle is ours and open, the OS checks the rest.
+            try_seh(|| unsafe { SetHandleInformation(handle, HANDLE_FLAG_INHERIT.0, flags) })?
+                .map_err(MMFError::from_os)?;
+        }
+        Ok(())
+    }
+
+    /// Get the raw handle to the mapping, e.g. to pass its value on to a child process that inherits it. It's still
+    /// owned by the MMF, so don't close it.
+    pub fn handle(&self) -> HANDLE {
+        self.handle
+    }
+
+    /// Check whether child processes inherit the handle to the MMF, see [`MemoryMappedFile::set_inheritable`].
+    pub fn is_inheritable(&self) -> MMFResult<bool> {
+        if self.closed.get() {
+            return Err(MMFError::MMF_NotFound);
+        }
+        let mut flags = 0;
+        // Safety: the handle is ours and open, and the flags live long enough.
+        try_seh(|| unsafe { GetHandleInformation(self.handle, &mut flags) })?.map_err(MMFError::from_os)?;
+        Ok((flags & HANDLE_FLAG_INHERIT.0) != 0)
+    }
+
+    /// Duplicate the handle to the mapping into another process, for passing the MMF on without a name.
+    ///
+    /// The returned handle is only valid in `process`, which needs `PROCESS_DUP_HANDLE` access, so send its value over
+    /// and have the other end build an MMF from it. Set `inherit` if `process` should pass it on to its own children.
+    /// Closing the handle is up to the other process. To stay in this process, use [`try_clone`][Self::try_clone].
+    pub fn duplicate_into(&self, process: HANDLE, inherit: bool) -> MMFResult<HANDLE> {
+        if self.closed.get() {
+            return Err(MMFError::MMF_NotFound);
+        }
+        duplicate_handle_to(self.handle, process, inherit)
+    }
+
+    /// Get the namespace of the file, if any. If an empty string is returned, it's Local.
+    pub fn namespace(&self) -> String {
+        self.name.split_once('\\').unwrap_or_default().0.to_owned()
+    }
+
+    /// Return the filename the MMF is bound to, which is only the whole name if no namespace is provided.
+    pub fn filename(&self) -> String {
+        self.name.split_once('\\').map(|s| s.1.to_owned()).unwrap_or(self.name.to_string())
+    }
+
+    /// Returns the stored name, which should be `[Namespace\]<FileName>`
     pub fn fullname(&self) -> String {
         self.name.to_string()
     }
 
+    /// Grow the MMF to `new_size` bytes of data, keeping what's there and zeroing out the new bytes. Shrinking errors
+    /// with [`MMFError::ShrinkNotAllowed`], resizing to the current size does nothing.
+    ///
+    /// Windows can't resize a file mapping in place, so this copies everything out, unmaps the view, closes the handle
+    /// and creates a new mapping with the same name before copying everything back in. The lock state is copied along
+    /// with the data, but nobody may be holding a lock or this errors with [`MMFError::ReadLocked`] or
+    /// [`MMFError::WriteLocked`]. If the lock lives at an offset that doesn't fit in the new size, this errors with
+    /// [`MMFError::NotEnoughMemory`] before touching anything. MMFs not [mapped][Access] for reading and writing
+    /// error with [`MMFError::ReadOnly`].
+    ///
+    /// **This is only safe when this is the only handle to the MMF.** Other processes keep the old mapping alive, in
+    /// which case Windows hands us the old mapping again instead of a new one. This is detected, the old mapping is
+    /// mapped again as it was and the error is `ERROR_ALREADY_EXISTS` (see [`MMFError::raw_os_error`]). Anything they
+    /// write in the short window where we don't have a view is invisible to the copy we're making. Resize before
+    /// sharing the MMF, or get everyone to close their handles first.
+    ///
+    /// ## Growing a shared MMF
+    /// The mapping has to go away everywhere before it can come back bigger, so everyone has to play along:
+    ///
+    /// 1. The resizing process takes the write lock, so nobody is halfway through using the data, and tells everyone
+    ///    else to let go. Something outside the MMF works best for that, like one
+    ///    [`SingleConsumerNotifier`][crate::notify::SingleConsumerNotifier] per process.
+    /// 2. Everyone else drops or [closes][MemoryMappedFile::close] their MMF, unmapping their views and closing their
+    ///    handles, and reports back.
+    /// 3. The resizing process releases the write lock and calls this. It's the only one left, so it gets a fresh
+    ///    mapping of the new size.
+    /// 4. Everyone else opens the MMF again with the new size.
+    ///
+    /// If anyone skipped step 2, this fails with `ERROR_ALREADY_EXISTS` as described above, and nothing is lost. If
+    /// everyone stopping isn't an option, a [`GrowableMMF`][crate::growable::GrowableMMF] moves to a new mapping
+    /// instead, which the others follow at their own pace.
+    pub fn resize(&mut self, new_size: NonZeroUsize) -> MMFResult<()> {
+        // Recreating the mapping changes it for everyone, which a copy-on-write view promised not to do.
+        if self.access != Access::ReadWrite {
+            return Err(MMFError::ReadOnly);
+        }
+        if self.readonly || self.closed.get() || self.map_view.is_none() {
+            return Err(MMFError::MMF_NotFound);
+        }
+        if new_size.get() < self.size {
+            return Err(MMFError::ShrinkNotAllowed);
+        } else if new_size.get() == self.size {
+            return Ok(());
+        }
+        check_lock_offset(new_size.get(), self.lock_offset)?;
+        if self.lock.writelocked() {
+            return Err(MMFError::WriteLocked);
+        } else if self.lock.readlocked() {
+            return Err(MMFError::ReadLocked);
+        }
+
+        let lock_size = LOCK::lock_size();
+        let (old_total, new_total) = (self.size + lock_size, new_size.get() + lock_size);
+        let mut contents = vec![0u8; new_total.max(old_total)];
+        // Safety: the view is valid for the whole old size, and the buffer is at least as large.
+        unsafe { self.view_ptr.copy_to(contents.as_mut_ptr(), old_total) };
+
+        // Out with the old. The lock points into the old view, so it must not be used until it's replaced.
+        self.map_view = None;
+        // Safety: we own this handle, and it's not used again after this.
+        _ = try_seh(|| unsafe { CloseHandle(self.handle) });
+
+        let mmf_name = PCSTR::from_raw(self.name.to_ptr());
+        let (dw_low, dw_high) = new_total.split();
+        // Safety: handled through microSEH and we check the last error status right after.
+        let backing = self.file.unwrap_or(INVALID_HANDLE_VALUE);
+        let handle =
+            try_seh(|| unsafe { CreateFileMappingA(backing, None, PAGE_READWRITE, dw_high, dw_low, mmf_name) })?
+                .map_err(MMFError::from_os);
+        let handle = match handle {
+            Ok(handle) => handle,
+            Err(e) => {
+                self.closed.set(true);
+                return Err(e);
+            }
+        };
+        // Somebody else kept the old mapping alive, so that's what we got. Map it as it was.
+        let kept_old = unsafe { GetLastError() } == ERROR_ALREADY_EXISTS;
+        let total = if kept_old { old_total } else { new_total };
+        let map_view = try_seh(|| unsafe { MapViewOfFile(handle, FILE_MAP_ALL_ACCESS, 0, 0, total) })?;
+        if map_view.Value.is_null() {
+            let err = MMFError::from_os(WErr::from_win32());
+            unsafe { _ = CloseHandle(handle) };
+            self.closed.set(true);
+            return Err(err);
+        }
+
+        let view_ptr = map_view.Value.cast::<u8>();
+        if !kept_old {
+            // Safety: the view is valid for `new_total` bytes, and the buffer holds at least that many.
+            unsafe { contents.as_ptr().copy_to(view_ptr, new_total) };
+            (self.size_low_order, self.size_high_order) = (dw_low, dw_high);
+            self.size = new_size.get();
+        }
+        self.handle = handle;
+        self.map_view = Some(map_view.into());
+        self.view_ptr = view_ptr;
+        // Safety: the lock state was copied along, or it's the lock of the mapping that was kept alive.
+        self.lock = unsafe { LOCK::from_existing_named(view_ptr.add(self.lock_offset), self.name.as_str()) };
+        if kept_old {
+            Err(MMFError::from_os(WErr::from(ERROR_ALREADY_EXISTS.to_hresult())))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Duplicate the handle and map a second view of the same section, for an MMF that can be closed independently.
+    ///
+    /// The lock of the clone works on the same bytes, so both instances see each other's locks and writes. Unlike
+    /// opening by name again, this keeps working when the name is gone or taken over by something else, as long as
+    /// this instance is still open. Errors with [`MMFError::MMF_NotFound`] if it isn't.
+    ///
+    /// The clone keeps the [`Access`] and settings of this instance, and gets a lock instance of its own. That makes it
+    /// the way to go for handing an MMF with an [`RWLock`][crate::states::RWLock] to another thread, too.
+    pub fn try_clone(&self) -> MMFResult<Self> {
+        if self.closed.get() || self.map_view.is_none() {
+            return Err(MMFError::MMF_NotFound);
+        }
+        let handle = duplicate_handle(self.handle)?;
+        // The duplicate flushes the file on its own, so it needs a handle of its own too.
+        let file = match self.file.map(duplicate_handle).transpose() {
+            Ok(file) => file,
+            Err(e) => {
+                unsafe { _ = CloseHandle(handle) };
+                return Err(e);
+            }
+        };
+
+        let lock_size = LOCK::lock_size();
+        // Still needs write access for the lock, even when the data is only ever read. Unless the OS won't give us any.
+        let map_view =
+            try_seh(|| unsafe { MapViewOfFile(handle, self.access.map_flags(), 0, 0, self.size + lock_size) })?;
+        if map_view.Value.is_null() {
+            let err = MMFError::from_os(WErr::from_win32());
+            unsafe {
+                _ = CloseHandle(handle);
+                _ = file.map(|file| CloseHandle(file));
+            }
+            return Err(err);
+        }
+
+        let view_ptr = map_view.Value.cast::<u8>();
+        // Safety: this is the same section we already have a lock in, so it's been initialized.
+        let lock = unsafe { LOCK::from_existing_named(view_ptr.add(self.lock_offset), self.name.as_str()) };
+        // A copy-on-write duplicate has private pages of its own, so it gets a private lock of its own too. And a view
+        // of the shared lock of its own, if there's one to watch.
+        let shared_lock = match &self.shared_lock {
+            Some(_) => match Self::watch_lock(handle, self.lock_offset, &self.name) {
+                Ok(shared_lock) => Some(shared_lock),
+                Err(e) => {
+                    unsafe {
+                        _ = UnmapViewOfFile(map_view);
+                        _ = CloseHandle(handle);
+                        _ = file.map(|file| CloseHandle(file));
+                    }
+                    return Err(e);
+                }
+            },
+            None => None,
+        };
+        let (lock, private_lock) = match self.access {
+            Access::CopyOnWrite => {
+                let (lock, buf) = Self::private_lock();
+                (lock, Some(buf))
+            }
+            _ => (lock, None),
+        };
+        Ok(Self {
+            handle,
+            file,
+            name: self.name,
+            size_high_order: self.size_high_order,
+            size_low_order: self.size_low_order,
+            size: self.size,
+            lock,
+            map_view: Some(map_view.into()),
+            view_ptr,
+            lock_offset: self.lock_offset,
+            closed: Cell::new(false),
+            readonly: self.readonly,
+            lock_timeout: self.lock_timeout,
+            spin_budget: self.spin_budget,
+            access: self.access,
+            private_lock,
+            shared_lock,
+        })
+    }
+
+    /// Map a window of `length` bytes into the MMF, starting `offset` bytes in. A `length` of 0 maps everything from
+    /// `offset` to the end.
+    ///
+    /// Offsets are counted from the start of the whole mapping, not the data, so with the lock at the default offset
+    /// the data starts at [`MMFLock::lock_size`]. The offset must be a multiple of [`allocation_granularity`], or this
+    /// errors with [`MMFError::MisalignedData`]. A window reaching past the end of the mapping errors with
+    /// [`MMFError::NotEnoughMemory`]. The region is mapped the same way as the MMF, and read-only if the MMF is.
+    ///
+    /// Handy for huge MMFs that don't fit in the address space in one go, or to give a component only its own part.
+    /// Accessing the region bypasses the lock, so take it yourself if others might be writing.
+    pub fn map_region(&self, offset: u64, length: usize) -> MMFResult<MappedRegion<'_>> {
+        if self.closed.get() || self.map_view.is_none() {
+            return Err(MMFError::MMF_NotFound);
+        }
+        let total = (self.size + LOCK::lock_size()) as u64;
+        if offset % u64::from(allocation_granularity()) != 0 {
+            return Err(MMFError::MisalignedData);
+        }
+        let length = match length {
+            0 => total.checked_sub(offset).filter(|&len| len > 0).ok_or(MMFError::NotEnoughMemory)? as usize,
+            len if offset.checked_add(len as u64).map_or(true, |end| end > total) => {
+                return Err(MMFError::NotEnoughMemory)
+            }
+            len => len,
+        };
+
+        let access = match self.access {
+            Access::ReadWrite if self.readonly => FILE_MAP_READ,
+            access => access.map_flags(),
+        };
+        let view = map_window(self.handle, access, offset, length)?;
+        Ok(MappedRegion { ptr: view.address.Value.cast(), view, offset, len: length, mmf: PhantomData })
+    }
+
+    /// Take a lock with `acquire`, waiting for the MMF to be unlocked in between attempts. Gives up after
+    /// [`lock_timeout`][MemoryMappedFile::lock_timeout].
+    fn acquire_waiting(&self, acquire: impl Fn(&LOCK) -> MMFResult<()>) -> MMFResult<()> {
+        wait_for_lock(&self.lock, self.lock_timeout, acquire)
+    }
+
+    /// Copy `count` bytes out without registering as a reader, for views the lock can't be written through. Starts `at`
+    /// bytes into the data, and doesn't copy past its end.
+    ///
+    /// Waits for writers to leave before copying, and copies again if one showed up while we were at it. That's the
+    /// [seqlock][crate::seqlock] scheme, with the [generation][MMFLock::generation] as the sequence: it changes every
+    /// time a writer leaves, so a writer that came and went entirely during the copy gets caught as well. Locks that
+    /// don't count writes can't tell that apart from no writer at all, so keep writes short with those.
+    ///
+    /// # Safety
+    /// Same as [`read_to_raw`][Mmf::read_to_raw].
+    unsafe fn read_unregistered(&self, at: usize, buffer: *mut u8, count: usize) -> MMFResult<()> {
+        let started = Instant::now();
+        let count = count.min(self.size.saturating_sub(at));
+        let lock = self.shared_lock();
+        loop {
+            if !lock.writelocked() {
+                let before = lock.generation();
+                // Safety: as in `read_to_raw`, the caller vouches for the buffer and the count is clamped to the data.
+                unsafe { copy_from_data(self.view_ptr, self.lock_offset, LOCK::lock_size(), at, buffer, count) };
+                // The copy has to be done before looking at the lock again, or a write could slip in unnoticed.
+                fence(Ordering::Acquire);
+                if !lock.writelocked() && lock.generation() == before {
+                    return Ok(());
+                }
+            }
+            if started.elapsed() >= self.lock_timeout {
+                return Err(MMFError::Timeout);
+            }
+            std::thread::yield_now();
+        }
+    }
+
+    /// Copy `count` bytes starting `at` bytes into the data, for a reader holding the read lock. That's all it takes,
+    /// unless the lock is [private][Access::CopyOnWrite] and there's a shared one to watch, see
+    /// [`read_unregistered`][MemoryMappedFile::read_unregistered].
+    ///
+    /// # Safety
+    /// Same as [`read_to_raw`][Mmf::read_to_raw].
+    unsafe fn copy_locked(&self, at: usize, buffer: *mut u8, count: usize) -> MMFResult<()> {
+        if self.shared_lock.is_some() {
+            return unsafe { self.read_unregistered(at, buffer, count) };
+        }
+        // Safety: the caller vouches for the buffer and the count.
+        unsafe { copy_from_data(self.view_ptr, self.lock_offset, LOCK::lock_size(), at, buffer, count) };
+        Ok(())
+    }
+
+    /// Check that `len` bytes starting `offset` bytes into the data are really there.
+    fn check_range(&self, offset: usize, len: usize) -> MMFResult<()> {
+        if self.closed.get() || self.map_view.is_none() {
+            Err(MMFError::MMF_NotFound)
+        } else if offset.checked_add(len).map_or(true, |end| end > self.size) {
+            Err(MMFError::NotEnoughMemory)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Fill `buf` with the data starting `offset` bytes in, holding a read lock while copying. Returns the amount of
+    /// bytes read, which is always all of `buf`.
+    ///
+    /// Offset 0 is the first byte of data, wherever the lock lives: the lock isn't part of the data, and is skipped
+    /// over as if it isn't there. Errors with [`MMFError::NotEnoughMemory`] if `offset` + `buf.len()` runs past the
+    /// end of the data, without reading anything. Locking works like for [`read`][Mmf::read].
+    pub fn read_at(&self, offset: usize, buf: &mut [u8]) -> MMFResult<usize> {
+        self.check_range(offset, buf.len())?;
+        if buf.is_empty() {
+            return Ok(0);
+        } else if !self.lock.initialized() {
+            return Err(MMFError::Uninitialized);
+        } else if self.access == Access::ReadOnly {
+            // Safety: the range was checked, and the buffer is a valid slice.
+            return unsafe { self.read_unregistered(offset, buf.as_mut_ptr(), buf.len()) }.map(|_| buf.len());
+        }
+        self.acquire_waiting(LOCK::try_lock_read)?;
+        // Safety: as above.
+        let copied = unsafe { self.copy_locked(offset, buf.as_mut_ptr(), buf.len()) };
+        self.lock.unlock_read()?;
+        copied.map(|_| buf.len())
+    }
+
+    /// Like [`read_at`][MemoryMappedFile::read_at], without taking the lock. Whatever is written while copying ends up
+    /// in `buf` as is, halfway done or not.
+    pub fn read_at_unlocked(&self, offset: usize, buf: &mut [u8]) -> MMFResult<usize> {
+        self.check_range(offset, buf.len())?;
+        // Safety: the range was checked, and the buffer is a valid slice.
+        unsafe {
+            copy_from_data(self.view_ptr, self.lock_offset, LOCK::lock_size(), offset, buf.as_mut_ptr(), buf.len())
+        };
+        Ok(buf.len())
+    }
+
+    /// Write all of `data` into the MMF starting `offset` bytes in, holding the write lock while copying. Returns the
+    /// amount of bytes written, which is always all of `data`.
+    ///
+    /// Offsets are counted like for [`read_at`][MemoryMappedFile::read_at], and the same bounds apply: nothing is
+    /// written if it doesn't all fit. Locking works like for [`write`][Mmf::write].
+    pub fn write_at(&self, offset: usize, data: &[u8]) -> MMFResult<usize> {
+        self.check_writable()?;
+        if self.readonly {
+            return Err(MMFError::MMF_NotFound);
+        }
+        self.check_range(offset, data.len())?;
+        if data.is_empty() {
+            return Ok(0);
+        } else if !self.lock.initialized() {
+            return Err(MMFError::Uninitialized);
+        }
+        self.acquire_waiting(LOCK::try_lock_write)?;
+        // Safety: the range was checked, and the data is a valid slice.
+        unsafe { copy_to_data(self.view_ptr, self.lock_offset, LOCK::lock_size(), offset, data.as_ptr(), data.len()) };
+        self.lock.unlock_write().map(|_| data.len())
+    }
+
+    /// Like [`write_at`][MemoryMappedFile::write_at], without taking the lock. Readers might see the write halfway
+    /// done, or even mixed with somebody else's.
+    pub fn write_at_unlocked(&self, offset: usize, data: &[u8]) -> MMFResult<usize> {
+        self.check_writable()?;
+        if self.readonly {
+            return Err(MMFError::MMF_NotFound);
+        }
+        self.check_range(offset, data.len())?;
+        // Safety: the range was checked, and the data is a valid slice.
+        unsafe { copy_to_data(self.view_ptr, self.lock_offset, LOCK::lock_size(), offset, data.as_ptr(), data.len()) };
+        Ok(data.len())
+    }
+
+    /// Get a pointer to the data in the MMF, or a null pointer if it's been closed.
+    ///
+    /// The pointer is valid for [`size`][Mmf::size] bytes for as long as this MMF lives, but any access through it
+    /// bypasses the lock. If the lock lives at an [offset][MemoryMappedFile::new_with_lock_offset], this points to the
+    /// start of the view and the lock sits somewhere in the middle of it, so mind your step.
+    pub fn ptr(&self) -> *mut u8 {
+        if self.closed.get() {
+            return std::ptr::null_mut();
+        }
+        match self.lock_offset {
+            0 => self.view_ptr.wrapping_add(LOCK::lock_size()),
+            _ => self.view_ptr,
+        }
+    }
+
+    /// Get the data as a slice, without copying anything. A slice-shaped [`ptr`][MemoryMappedFile::ptr].
+    ///
+    /// That's the whole data, or only the part in front of the lock if it lives at an
+    /// [offset][MemoryMappedFile::new_with_lock_offset], as a slice has to be contiguous. Like the pointer, this
+    /// bypasses the lock. Take a [read guard][MemoryMappedFile::read_borrow] instead to have the lock keep writers
+    /// out for you.
+    ///
+    /// # Safety
+    /// A `&[u8]` promises the bytes don't change while it lives, and the compiler holds you to that. So nobody may
+    /// write to the data until the slice is gone: not through this instance, not through any other, and not from any
+    /// other process. Holding a read lock through some other means is one way to make sure of that.
+    ///
+    /// # Panics
+    /// If the MMF was closed, as there's no data to look at anymore.
+    pub unsafe fn as_bytes(&self) -> &[u8] {
+        let (ptr, len) = self.contiguous_data();
+        // Safety: the region is checked to be in the view, which lives as long as `self` does.
+        unsafe { std::slice::from_raw_parts(ptr, len) }
+    }
+
+    /// Mutable version of [`as_bytes`][MemoryMappedFile::as_bytes]. The `&mut self` keeps anyone in this instance
+    /// from looking at the data in the meantime, other instances and processes are on their own.
+    ///
+    /// # Safety
+    /// Nobody else may read or write the data until the slice is gone, through any other instance or from any other
+    /// process. Holding the write lock through some other means is one way to make sure of that.
+    ///
+    /// # Panics
+    /// If the MMF was closed, or can't be written to because it was opened read-only or [mapped][Access::ReadOnly]
+    /// that way.
+    pub unsafe fn as_bytes_mut(&mut self) -> &mut [u8] {
+        assert!(!self.readonly && self.check_writable().is_ok(), "Tried to write to an MMF that's read-only");
+        let (ptr, len) = self.contiguous_data();
+        // Safety: as above, and the view is writable.
+        unsafe { std::slice::from_raw_parts_mut(ptr, len) }
+    }
+
+    /// Get an [`io::Read`] + [`io::Seek`] cursor over the data, starting at the front. Handy for feeding the MMF to
+    /// anything that wants a reader, like a deserializer. Every read copies out through
+    /// [`read_at`][MemoryMappedFile::read_at], holding the read lock while it does.
+    pub fn cursor(&self) -> MMFCursor<'_, LOCK> {
+        MMFCursor { mmf: self, pos: 0 }
+    }
+
+    /// Get an [`io::Write`] + [`io::Seek`] cursor over the data, starting at the front. Every write copies in through
+    /// [`write_at`][MemoryMappedFile::write_at], holding the write lock while it does.
+    ///
+    /// # Panics
+    /// If the MMF was closed or can't be written to, see [`as_bytes_mut`][MemoryMappedFile::as_bytes_mut]. Checked up
+    /// front, so the cursor itself never panics.
+    pub fn cursor_mut(&mut self) -> MMFCursorMut<'_, LOCK> {
+        assert!(!self.readonly && self.check_writable().is_ok(), "Tried to write to an MMF that's read-only");
+        assert!(!self.closed.get(), "The MMF is closed, there's no data to write to");
+        MMFCursorMut { mmf: self, pos: 0 }
+    }
+
+    /// The start and length of the data that can be handed out as one slice, see
+    /// [`as_bytes`][MemoryMappedFile::as_bytes]. Panics if the MMF is closed.
+    fn contiguous_data(&self) -> (*mut u8, usize) {
+        let len = if self.lock_offset == 0 { self.size } else { self.lock_offset };
+        let ptr = self.data_region(0, len, 1).expect("The MMF is closed, there's no data to look at");
+        assert!(!ptr.is_null(), "The view of an open MMF should never be null");
+        (ptr, len)
+    }
+
+    /// Borrow the data straight from the view, holding a read lock until the guard is dropped. No copying involved.
+    ///
+    /// The guard derefs to the whole data, or only the part in front of the lock if it lives at an
+    /// [offset][MemoryMappedFile::new_with_lock_offset], as a slice has to be contiguous. Waits for the lock like
+    /// [`read`][Mmf::read] does. MMFs [mapped][Access] read-only can't take a read lock, so they error with
+    /// [`MMFError::ReadOnly`]. Copy the data out with [`read`][Mmf::read] there instead.
+    ///
+    /// Writers can't get in while you're looking, so don't hang on to the guard longer than needed. The borrow keeps
+    /// the MMF from being dropped or resized underneath it.
+    pub fn read_borrow(&self) -> MMFResult<ReadGuard<'_, LOCK>> {
+        let (ptr, len) = self.borrow_region()?;
+        self.acquire_waiting(LOCK::try_lock_read)?;
+        Ok(ReadGuard { mmf: self, ptr, len, _not_send: PhantomData })
+    }
+
+    /// The checks [`read_borrow`][MemoryMappedFile::read_borrow] does before taking the lock. Returns where the data
+    /// to borrow starts and how long it is.
+    fn borrow_region(&self) -> MMFResult<(*mut u8, usize)> {
+        if self.access == Access::ReadOnly {
+            return Err(MMFError::ReadOnly);
+        }
+        let len = if self.lock_offset == 0 { self.size } else { self.lock_offset };
+        let ptr = self.data_region(0, len, 1)?;
+        if !self.lock.initialized() {
+            return Err(MMFError::Uninitialized);
+        }
+        Ok((ptr, len))
+    }
+
+    /// Hand `f` the data to write into directly, holding the write lock while it runs. No copying involved.
+    ///
+    /// Meant for serializers that can write into a buffer you give them: `f` gets the whole data, or the part in front
+    /// of the lock if it lives at an [offset][MemoryMappedFile::new_with_lock_offset], and returns how many bytes it
+    /// wrote. That's returned as is, capped at the size of the slice. Waits for the lock like [`write`][Mmf::write]
+    /// does, and errors like it when the MMF can't be written to. If `f` panics, the lock is released on the way out,
+    /// with whatever `f` got around to writing left in the MMF.
+    pub fn write_with<F: FnOnce(&mut [u8]) -> usize>(&self, f: F) -> MMFResult<usize> {
+        let (ptr, len) = self.write_region()?;
+        self.acquire_waiting(LOCK::try_lock_write)?;
+        // Safety: the region was checked, and we just took the write lock.
+        unsafe { self.write_locked(ptr, len, f) }
+    }
+
+    /// The checks [`write_with`][MemoryMappedFile::write_with] does before taking the lock. Returns where the data to
+    /// write into starts and how long it is.
+    fn write_region(&self) -> MMFResult<(*mut u8, usize)> {
+        self.check_writable()?;
+        if self.readonly {
+            return Err(MMFError::MMF_NotFound);
+        }
+        let len = if self.lock_offset == 0 { self.size } else { self.lock_offset };
+        let ptr = self.data_region(0, len, 1)?;
+        if !self.lock.initialized() {
+            return Err(MMFError::Uninitialized);
+        }
+        Ok((ptr, len))
+    }
+
+    /// The part of [`write_with`][MemoryMappedFile::write_with] after taking the lock: run `f` and release the lock.
+    ///
+    /// # Safety
+    /// The region has to come from [`write_region`][MemoryMappedFile::write_region], and this instance has to hold the
+    /// write lock.
+    unsafe fn write_locked<F: FnOnce(&mut [u8]) -> usize>(&self, ptr: *mut u8, len: usize, f: F) -> MMFResult<usize> {
+        let guard = UnlockGuard { lock: &self.lock, unlock: LOCK::unlock_write };
+        // Safety: the range was checked, and the write lock keeps everyone else out until the guard lets go. The slice
+        // can't outlive the call, `f` only borrows it.
+        let written = f(unsafe { std::slice::from_raw_parts_mut(ptr, len) });
+        guard.release().map(|_| written.min(len))
+    }
+
+    /// Like [`read_borrow`][MemoryMappedFile::read_borrow], awaiting the lock instead of blocking the thread. See
+    /// [`async_tokio`][crate::async_tokio] for how the waiting works. Gives up after the
+    /// [lock timeout][MemoryMappedFile::with_lock_timeout] like its blocking sibling.
+    ///
+    /// The guard isn't `Send`, so drop it before awaiting anything else if the task has to be.
+    #[cfg(feature = "async_tokio")]
+    pub async fn read_borrow_async(&self) -> MMFResult<ReadGuard<'_, LOCK>> {
+        // Checked before waiting so there's no waiting on an MMF that can't be borrowed anyway, and again after, as the
+        // MMF could've been closed while we weren't looking.
+        self.borrow_region()?;
+        crate::async_tokio::acquire(|| self.lock.try_lock_read(), Some(self.lock_timeout)).await?;
+        match self.borrow_region() {
+            Ok((ptr, len)) => Ok(ReadGuard { mmf: self, ptr, len, _not_send: PhantomData }),
+            Err(e) => {
+                self.lock.unlock_read()?;
+                Err(e)
+            }
+        }
+    }
+
+    /// Like [`write_with`][MemoryMappedFile::write_with], awaiting the lock instead of blocking the thread. See
+    /// [`async_tokio`][crate::async_tokio] for how the waiting works. `f` itself runs synchronously while holding the
+    /// lock, as there's no awaiting anything with the write lock held.
+    #[cfg(feature = "async_tokio")]
+    pub async fn write_with_async<F: FnOnce(&mut [u8]) -> usize>(&self, f: F) -> MMFResult<usize> {
+        // Checked before and after waiting, like read_borrow_async does.
+        self.write_region()?;
+        crate::async_tokio::acquire(|| self.lock.try_lock_write(), Some(self.lock_timeout)).await?;
+        match self.write_region() {
+            // Safety: the region was checked with the write lock held.
+            Ok((ptr, len)) => unsafe { self.write_locked(ptr, len, f) },
+            Err(e) => {
+                self.lock.unlock_write()?;
+                Err(e)
+            }
+        }
+    }
+
+    /// Get a pointer to `len` bytes of data `offset` bytes in, aligned for `align`, after checking they're really
+    /// there.
+    ///
+    /// The region has to be contiguous, so with the lock at an offset only the data in front of it is available. Errors
+    /// with [`MMFError::MMF_NotFound`] if the MMF is closed, [`MMFError::NotEnoughMemory`] if the data doesn't fit and
+    /// [`MMFError::MisalignedData`] if the alignment is off.
+    pub(crate) fn data_region(&self, offset: usize, len: usize, align: usize) -> MMFResult<*mut u8> {
+        let ptr = self.ptr().wrapping_add(offset);
+        let available = if self.lock_offset == 0 { self.size } else { self.lock_offset };
+        if self.closed.get() || self.map_view.is_none() {
+            Err(MMFError::MMF_NotFound)
+        } else if offset.checked_add(len).map_or(true, |end| end > available) {
+            Err(MMFError::NotEnoughMemory)
+        } else if ptr as usize % align != 0 {
+            Err(MMFError::MisalignedData)
+        } else {
+            Ok(ptr)
+        }
+    }
+
+    /// View the start of the data as a `T`, without copying anything.
+    ///
+    /// Like [`ptr`][MemoryMappedFile::ptr], this bypasses the lock. Errors if the MMF is closed, the data is too small
+    /// or isn't aligned for `T`, see [`MemoryMappedFile::view_as_slice`] for details.
+    ///
+    /// # Safety
+    /// Same as for [`as_bytes`][MemoryMappedFile::as_bytes]: nobody may write to the data while the reference lives,
+    /// from this process or any other. Any bit pattern being a valid [`Pod`] doesn't help with that.
+    #[cfg(feature = "bytemuck")]
+    pub unsafe fn view_as<T: Pod>(&self) -> MMFResult<&T> {
+        self.view_as_slice(1).map(|slice| &slice[0])
+    }
+
+    /// View the start of the data as `len` consecutive `T`s, without copying anything.
+    ///
+    /// Errors with [`MMFError::NotEnoughMemory`] if that doesn't fit in the data, and [`MMFError::MisalignedData`] if
+    /// the data isn't aligned for `T`. The data starts right behind the lock, which is aligned to 16 bytes. If the lock
+    /// lives at an [offset][MemoryMappedFile::new_with_lock_offset], only the data in front of the lock can be viewed.
+    ///
+    /// # Safety
+    /// See [`view_as`][MemoryMappedFile::view_as].
+    #[cfg(feature = "bytemuck")]
+    pub unsafe fn view_as_slice<T: Pod>(&self, len: usize) -> MMFResult<&[T]> {
+        let bytes = len.checked_mul(std::mem::size_of::<T>()).ok_or(MMFError::NotEnoughMemory)?;
+        let ptr = self.data_region(0, bytes, std::mem::align_of::<T>())?;
+        // Safety: the region is checked to be in the view and aligned, and every bit pattern is a valid `Pod`. The
+        // caller keeps writers out.
+        Ok(unsafe { std::slice::from_raw_parts(ptr.cast::<T>(), len) })
+    }
+
+    /// Mutable version of [`view_as`][MemoryMappedFile::view_as]. Errors like
+    /// [`view_as_slice_mut`][MemoryMappedFile::view_as_slice_mut] when the MMF can't be written to.
+    ///
+    /// # Safety
+    /// Same as for [`as_bytes_mut`][MemoryMappedFile::as_bytes_mut]: nobody else may read or write the data while the
+    /// reference lives, through any other instance or from any other process.
+    #[cfg(feature = "bytemuck")]
+    pub unsafe fn view_as_mut<T: Pod>(&mut self) -> MMFResult<&mut T> {
+        self.view_as_slice_mut(1).map(|slice| &mut slice[0])
+    }
+
+    /// Mutable version of [`view_as_slice`][MemoryMappedFile::view_as_slice]. Errors with [`MMFError::MMF_NotFound`]
+    /// if the MMF is read-only, or [`MMFError::ReadOnly`] if it's [mapped that way][Access::ReadOnly].
+    ///
+    /// # Safety
+    /// See [`view_as_mut`][MemoryMappedFile::view_as_mut].
+    #[cfg(feature = "bytemuck")]
+    pub unsafe fn view_as_slice_mut<T: Pod>(&mut self, len: usize) -> MMFResult<&mut [T]> {
+        self.check_writable()?;
+        if self.readonly {
+            return Err(MMFError::MMF_NotFound);
+        }
+        let bytes = len.checked_mul(std::mem::size_of::<T>()).ok_or(MMFError::NotEnoughMemory)?;
+        let ptr = self.data_region(0, bytes, std::mem::align_of::<T>())?;
+        // Safety: as above, and the `&mut self` keeps anyone in this instance from looking at it in the meantime.
+        Ok(unsafe { std::slice::from_raw_parts_mut(ptr.cast::<T>(), len) })
+    }
+
+    /// Read a `T` from the start of the data, by copying it out under a read lock.
+    ///
+    /// Unlike the [`bytemuck`](https://docs.rs/bytemuck) views, this makes a copy so there are no alignment
+    /// requirements, and the lock is respected. Errors with [`MMFError::NotEnoughMemory`] if a `T` doesn't fit in the
+    /// data, and with anything [`read`][Mmf::read] can error with.
+    #[cfg(feature = "zerocopy")]
+    pub fn read_obj<T: FromBytes>(&self) -> MMFResult<T> {
+        let len = std::mem::size_of::<T>();
+        if len > self.size {
+            return Err(MMFError::NotEnoughMemory);
+        }
+        T::read_from_prefix(&self.read(len)?).ok_or(MMFError::NotEnoughMemory)
+    }
+
+    /// Write a `T` to the start of the data under a write lock. Anything behind it is left alone.
+    ///
+    /// Errors with [`MMFError::NotEnoughMemory`] if a `T` doesn't fit in the data, and with anything
+    /// [`write`][Mmf::write] can error with.
+    #[cfg(feature = "zerocopy")]
+    pub fn write_obj<T: AsBytes>(&mut self, val: &T) -> MMFResult<()> {
+        self.write(val.as_bytes())
+    }
+
+    /// Serialize `val` into the data with [`bincode`](https://docs.rs/bincode), no `repr(C)` or transmuting needed.
+    ///
+    /// **This doesn't take the lock**, making sure nobody reads halfway through is up to you. Take it through whatever
+    /// else you're guarding the MMF with, or serialize into [`write_with`][MemoryMappedFile::write_with] yourself.
+    /// The value goes at the start of the data, like [`as_bytes_mut`][MemoryMappedFile::as_bytes_mut] hands it out.
+    /// Errors with [`MMFError::NotEnoughMemory`] if the serialized value doesn't fit, and with
+    /// [`MMFError::Serialization`] if `val` can't be serialized at all.
+    #[cfg(feature = "serde")]
+    pub fn write_value<T: Serialize>(&mut self, val: &T) -> MMFResult<()> {
+        self.check_writable()?;
+        if self.readonly {
+            return Err(MMFError::MMF_NotFound);
+        }
+        let len = if self.lock_offset == 0 { self.size } else { self.lock_offset };
+        let needed = bincode::serialized_size(val).map_err(|e| MMFError::Serialization(e.to_string()))?;
+        if needed > len as u64 {
+            return Err(MMFError::NotEnoughMemory);
+        }
+        let ptr = self.data_region(0, len, 1)?;
+        // Safety: the region was checked to be in the view, and the view is writable.
+        let data = unsafe { std::slice::from_raw_parts_mut(ptr, len) };
+        bincode::serialize_into(data, val).map_err(|e| MMFError::Serialization(e.to_string()))
+    }
+
+    /// Deserialize a `T` from the start of the data, as written by [`write_value`][MemoryMappedFile::write_value].
+    ///
+    /// **This doesn't take the lock either**, see over there. Errors with [`MMFError::Serialization`] if the data isn't
+    /// a valid `T`, like when nothing was written yet or it was written as something else.
+    #[cfg(feature = "serde")]
+    pub fn read_value<T: DeserializeOwned>(&self) -> MMFResult<T> {
+        let len = if self.lock_offset == 0 { self.size } else { self.lock_offset };
+        let ptr = self.data_region(0, len, 1)?;
+        // Safety: the region was checked to be in the view, which lives as long as `self` does.
+        let data = unsafe { std::slice::from_raw_parts(ptr, len) };
+        bincode::deserialize(data).map_err(|e| MMFError::Serialization(e.to_string()))
+    }
+
+    /// Flush `length` bytes of data starting `offset` bytes in to the backing file. A `length` of 0 flushes everything
+    /// from `offset` to the end of the view.
+    ///
+    /// Writes to an MMF only reach the backing file when the OS feels like it, this makes it feel like it right now.
+    /// That only matters for file-backed MMFs. The page file doesn't care about durability, so there it's a no-op that
+    /// returns `Ok`, and code doesn't have to know what's behind an MMF to flush it. Offsets are counted like for
+    /// [`ptr`][MemoryMappedFile::ptr]. Errors with [`MMFError::NotEnoughMemory`] if the range doesn't fit in the
+    /// data, and with [`MMFError::FlushFailed`] if the OS doesn't cooperate.
+    pub fn flush_range(&self, offset: usize, length: usize) -> MMFResult<()> {
+        if offset.checked_add(length).map_or(true, |end| end > self.size) {
+            return Err(MMFError::NotEnoughMemory);
+        }
+        self.flush_view(self.ptr().wrapping_add(offset), length)
+    }
+
+    /// Flush the whole view to the backing file, the lock included. See [`MemoryMappedFile::flush_range`].
+    pub fn flush_all(&self) -> MMFResult<()> {
+        self.flush_view(self.view_ptr, 0)
+    }
+
+    /// Persist everything written so far, for crash-tolerant designs that need the data to survive this process.
+    ///
+    /// `FlushViewOfFile` only hands the dirty pages to the OS, which writes them out lazily. This flushes the whole
+    /// view, and is the one place that also waits for the [backing file][MemoryMappedFile::from_file] to hit the disk.
+    /// An MMF backed by the page file has nothing to persist, as it's gone once everyone closes it, so there it's just
+    /// as cheap as [`flush_all`][MemoryMappedFile::flush_all] and returns `Ok`. Errors like
+    /// [`flush_range`][MemoryMappedFile::flush_range].
+    pub fn flush(&self) -> MMFResult<()> {
+        self.flush_all()?;
+        match self.file {
+            // Safety: the handle is ours until the MMF is closed, which flushing the view already checked.
+            Some(file) => try_seh(|| unsafe { FlushFileBuffers(file) })?.map_err(MMFError::FlushFailed),
+            None => Ok(()),
+        }
+    }
+
+    /// Flush `length` bytes starting at `start`, which must lie in the view.
+    fn flush_view(&self, start: *mut u8, length: usize) -> MMFResult<()> {
+        if self.closed.get() || self.map_view.is_none() {
+            return Err(MMFError::MMF_NotFound);
+        }
+        // Safety: the range was checked to be in the view, and the OS checks it again anyway.
+        try_seh(|| unsafe { FlushViewOfFile(start.cast(), length) })?.map_err(MMFError::FlushFailed)
+    }
+
+    /// Let go of the lock and turn this into an [`OwnedMMF`], to send it to another thread regardless of the lock.
+    ///
+    /// Locks this instance holds aren't released, so let go of them first. Errors with [`MMFError::MMF_NotFound`] if
+    /// the MMF is closed already.
+    pub fn into_owned(mut self) -> MMFResult<OwnedMMF> {
+        let view = match self.map_view.take() {
+            Some(view) if !self.closed.get() => view,
+            _ => return Err(MMFError::MMF_NotFound),
+        };
+        // The handles move along with the view, so dropping what's left of `self` mustn't close them.
+        self.closed.set(true);
+        Ok(OwnedMMF {
+            handle: self.handle,
+            file: self.file,
+            view,
+            name: self.name,
+            size: self.size,
+            lock_size: LOCK::lock_size(),
+            lock_offset: self.lock_offset,
+            readonly: self.readonly,
+            lock_timeout: self.lock_timeout,
+            spin_budget: self.spin_budget,
+            access: self.access,
+        })
+    }
+
     /// Close the MMF. Don't worry about calling this, it's handled in [`Drop`].
+    ///
+    /// Closing an MMF that's already closed does nothing, so the handle is never closed twice.
     pub fn close(&self) -> MMFResult<()> {
-        self.closed.set(true);
+        if self.closed.replace(true) {
+            return Ok(());
+        }
+        if let Some(file) = self.file {
+            // Safety: same as for the mapping below. The mapping keeps its own reference to the file, so order is moot.
+            _ = try_seh(|| unsafe { CloseHandle(file) });
+        }
         // Safety: microSEH handles the OS side of this error, and the match handles this end.
         match try_seh(|| unsafe { CloseHandle(self.handle) })?.map_err(MMFError::from) {
             Err(MMFError::OS_OK(_)) | Ok(_) => Ok(()),
@@ -360,8 +2505,9 @@ impl<LOCK: MMFLock> Mmf for MemoryMappedFile<LOCK> {
     /// it's marked as uninitialized. The returned error for this is an instance of the
     /// [crate's error enum][crate::err::Error]
     ///
-    /// - 1: Write Protected; the file has a write lock on it which means reading might return incomplete data, or the
-    ///   maximum amount of readers has been reached (this should not happen assuming all implementations are clean).
+    /// - 8: Timeout; the file stayed write locked for longer than the [lock timeout][MemoryMappedFile::lock_timeout].
+    /// - 3: Max readers; the maximum amount of readers has been reached (this should not happen assuming all
+    ///   implementations are clean).
     /// - 2: Invalid block; the lock is telling us this data has not yet been initialized.
     /// - 5: File not found; the MMF isn't opened yet or no map view exists.
     #[inline]
@@ -436,17 +2582,17 @@ impl<LOCK: MMFLock> Mmf for MemoryMappedFile<LOCK> {
         } else if self.map_view.is_some() {
             if !self.lock.initialized() {
                 return Err(MMFError::Uninitialized);
+            } else if self.access == Access::ReadOnly {
+                return unsafe { self.read_unregistered(0, buffer, count) };
             }
-            self.lock.lock_read()?;
+            self.acquire_waiting(LOCK::try_lock_read)?;
 
             // safety: memory may overlap with copy_to. With the size check, we also ensure we don't copy more bytes
             // than what fits in the buffer. If someone gave us a dirty slice, that's on them. Notably, they would
             // get UB from providing a slice with an incorrect internally registered length.
-            unsafe {
-                self.write_ptr.copy_to(buffer, count.min(self.size));
-            }
+            let copied = unsafe { self.copy_locked(0, buffer, count.min(self.size)) };
             self.lock.unlock_read().unwrap();
-            Ok(())
+            copied
         } else {
             Err(MMFError::MMF_NotFound)
         }
@@ -466,20 +2612,21 @@ impl<LOCK: MMFLock> Mmf for MemoryMappedFile<LOCK> {
         } else if count == 0 {
             Err(MMFError::GeneralFailure)
         } else if self.map_view.is_some() {
-            if let Some(mut spinner) = spinner {
-                spinner(&self.lock, usize::MAX)?;
+            // Spinners take the lock, which would fault on a read-only view.
+            if self.access == Access::ReadOnly {
+                return unsafe { self.read_unregistered(0, buffer, count) };
+            } else if let Some(mut spinner) = spinner {
+                spinner(&self.lock, self.spin_budget)?;
             } else {
-                LOCK::spin_and_lock_read(&self.lock, usize::MAX)?;
+                self.acquire_waiting(LOCK::try_lock_read)?;
             }
 
             // safety: memory may be overlapped with copy_to. With the size check, we also ensure we don't copy more
             // bytes than what fits in the buffer. If someone gave us a dirty slice, that's on them.
             // Notably, they would get UB from providing a pointer with too little space.
-            unsafe {
-                self.write_ptr.copy_to(buffer, count.min(self.size));
-            }
+            let copied = unsafe { self.copy_locked(0, buffer, count.min(self.size)) };
             self.lock.unlock_read().unwrap();
-            Ok(())
+            copied
         } else {
             Err(MMFError::MMF_NotFound)
         }
@@ -487,17 +2634,20 @@ impl<LOCK: MMFLock> Mmf for MemoryMappedFile<LOCK> {
 
     /// Attempt to write a complete buffer into the MMF. Uses pointers and memcpy to be fast.
     ///
-    /// This function errors only if the lock could not be acquired or when trying to write more data than fits. Writing
-    /// more data than the MMF can hold is UB so this is prevented by erroring out instead. If the input buffer is
-    /// smaller than the destination file, the end is zeroed out. The start of the buffer is also padded by the lock
+    /// This function errors only if the lock could not be acquired in time or when trying to write more data than fits.
+    /// Writing more data than the MMF can hold is UB so this is prevented by erroring out instead. If the input buffer
+    /// is smaller than the destination file, the end is zeroed out. The start of the buffer is also padded by the lock
     /// bytes to signal and flag locking.The returned error for this is an instance of the
     /// [crate's error enum][crate::err::Error]
     ///
     /// Error codes produced by this function:
-    /// - 0 or 1: Access denied; the lock could not be acquired or the MMF is read-only.
+    /// - 5: File not found; the MMF is closed or read-only.
+    /// - 16: Read-only; the view is [mapped read-only][Access::ReadOnly], so the lock isn't even tried.
+    /// - 8: Timeout; the lock could not be acquired within the [lock timeout][MemoryMappedFile::lock_timeout].
     /// - 4: Not enough memory; the write was blocked because it was too large.
     /// - All errors from [Self::read()] as a read is required to update the lock.
     fn write(&self, buffer: &[u8]) -> MMFResult<()> {
+        self.check_writable()?;
         if self.readonly || self.closed.get() {
             return Err(MMFError::MMF_NotFound);
         }
@@ -506,16 +2656,12 @@ impl<LOCK: MMFLock> Mmf for MemoryMappedFile<LOCK> {
             Err(MMFError::NotEnoughMemory)
         } else if !self.lock.initialized() {
             Err(MMFError::Uninitialized)
-        } else if self.lock.readlocked() {
-            Err(MMFError::ReadLocked)
-        } else if self.lock.writelocked() {
-            Err(MMFError::WriteLocked)
         } else if self.map_view.is_some() {
-            self.lock.lock_write()?;
+            self.acquire_waiting(LOCK::try_lock_write)?;
             let src_ptr = buffer.as_ptr();
             // We ensured this size is correct and filled out when instantiating the MMF, this is just writing the same
             // amount of bytes to the same place in memory.
-            unsafe { src_ptr.copy_to(self.write_ptr, cap) };
+            unsafe { copy_to_data(self.view_ptr, self.lock_offset, LOCK::lock_size(), 0, src_ptr, cap) };
             self.lock.unlock_write()
         } else {
             Err(MMFError::MMF_NotFound)
@@ -526,6 +2672,7 @@ impl<LOCK: MMFLock> Mmf for MemoryMappedFile<LOCK> {
     where
         F: FnMut(&dyn MMFLock, usize) -> MMFResult<()>,
     {
+        self.check_writable()?;
         if self.readonly || self.closed.get() {
             return Err(MMFError::MMF_NotFound);
         }
@@ -534,20 +2681,32 @@ impl<LOCK: MMFLock> Mmf for MemoryMappedFile<LOCK> {
             Err(MMFError::NotEnoughMemory)
         } else if self.map_view.is_some() {
             if let Some(mut spinner) = spinner {
-                spinner(&self.lock, usize::MAX)?;
+                spinner(&self.lock, self.spin_budget)?;
             } else {
-                LOCK::spin_and_lock_write(&self.lock, usize::MAX)?;
+                self.acquire_waiting(LOCK::try_lock_write)?;
             }
             let src_ptr = buffer.as_ptr();
             // We ensured this size is correct and filled out when instantiating the MMF, this is just writing the same
             // amount of bytes to the same place in memory.
-            unsafe { src_ptr.copy_to(self.write_ptr, cap) };
+            unsafe { copy_to_data(self.view_ptr, self.lock_offset, LOCK::lock_size(), 0, src_ptr, cap) };
             self.lock.unlock_write()
         } else {
             Err(MMFError::MMF_NotFound)
         }
     }
 
+    /// Read into a fresh Vec through [`MemoryMappedFile::read_at`], which takes a slice to fill instead.
+    fn read_vec_at(&self, offset: usize, count: usize) -> MMFResult<Vec<u8>> {
+        let mut buf = vec![0; count];
+        MemoryMappedFile::read_at(self, offset, &mut buf)?;
+        Ok(buf)
+    }
+
+    /// Write through [`MemoryMappedFile::write_at`], which also tells you how much was written, all under one lock.
+    fn write_all_at(&self, offset: usize, data: &[u8]) -> MMFResult<()> {
+        MemoryMappedFile::write_at(self, offset, data).map(|_| ())
+    }
+
     /// Returns the size of the data portion of the MMF.
     ///
     /// This allows users to know the MMF's size without exposing it publicly in case someone has a `&mut MMF` because
@@ -557,8 +2716,706 @@ impl<LOCK: MMFLock> Mmf for MemoryMappedFile<LOCK> {
     }
 }
 
+/// A [`MemoryMappedFile`] that can only be read from, and can be cloned because of it.
+///
+/// Cloning duplicates the handle with `DuplicateHandle` and maps a second view of the same section, so every clone is
+/// a full MMF of its own that can be closed or dropped without affecting the others. Handy for giving every thread or
+/// [`spawn_blocking`](https://docs.rs/tokio/latest/tokio/task/fn.spawn_blocking.html) task its own reader, no unsafe
+/// pointer passing required. All the reading happens through [`Deref`], writes are refused like on any MMF opened
+/// for reading.
+///
+/// [`Clone`] can't fail, so it panics if the OS refuses to duplicate the handle or map the view. Use
+/// [`ReadOnlyMMF::try_clone`] if that's not acceptable.
+#[cfg(feature = "impl_mmf")]
+#[derive(Debug)]
+pub struct ReadOnlyMMF<LOCK: MMFLock>(MemoryMappedFile<LOCK>);
+
+#[cfg(feature = "impl_mmf")]
+impl<LOCK: MMFLock> ReadOnlyMMF<LOCK> {
+    /// Open an existing MMF for reading. See [`MemoryMappedFile::open`].
+    pub fn open(size: NonZeroUsize, name: &str, namespace: Namespace) -> MMFResult<Self> {
+        MemoryMappedFile::open_read(size, name, namespace).map(Self)
+    }
+
+    /// Clone this MMF, or tell us why that didn't work.
+    pub fn try_clone(&self) -> MMFResult<Self> {
+        self.0.try_clone().map(Self)
+    }
+
+    /// Get the MMF back. It's still read-only.
+    pub fn into_inner(self) -> MemoryMappedFile<LOCK> {
+        self.0
+    }
+}
+
+/// Turn any MMF into a read-only one. There's no going back.
+#[cfg(feature = "impl_mmf")]
+impl<LOCK: MMFLock> From<MemoryMappedFile<LOCK>> for ReadOnlyMMF<LOCK> {
+    fn from(mut value: MemoryMappedFile<LOCK>) -> Self {
+        value.readonly = true;
+        Self(value)
+    }
+}
+
+/// All the reading is done by the MMF itself.
+#[cfg(feature = "impl_mmf")]
+impl<LOCK: MMFLock> Deref for ReadOnlyMMF<LOCK> {
+    type Target = MemoryMappedFile<LOCK>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+/// Duplicates the handle and maps a new view, see [`ReadOnlyMMF`].
+#[cfg(feature = "impl_mmf")]
+impl<LOCK: MMFLock> Clone for ReadOnlyMMF<LOCK> {
+    /// # Panics
+    /// If the handle can't be duplicated or the view can't be mapped.
+    fn clone(&self) -> Self {
+        self.try_clone().expect("Failed to duplicate the MMF")
+    }
+}
+
+/// A [`MemoryMappedFile`] [mapped read-only][Access::ReadOnly] that only knows how to read, see
+/// [`MemoryMappedFile::open_read_only`].
+///
+/// Unlike [`ReadOnlyMMF`], this doesn't deref to the MMF, so there's no `write` to call in the first place. And unlike
+/// an MMF [opened for reading][MemoryMappedFile::open_read], the OS backs that up: the view is mapped with
+/// `FILE_MAP_READ`, and any write to it faults.
+///
+/// ## Reading without registering
+/// That includes the lock, so readers can't take a read lock and writers can't tell they're there. Reads go by the
+/// seqlock scheme instead: wait for the writer to leave, note the [generation][ReaderMMF::generation], copy the data
+/// and check that no writer showed up and the generation didn't move in the meantime. If either happened, the copy
+/// might be torn, so it's thrown away and made again. Writers are never held up by readers this way, and readers only
+/// ever hand out a copy that no write overlapped with. Locks that don't count writes can only go by the writer being
+/// there before and after, so a write that fits entirely inside a copy slips through with those. [`RWLock`] counts.
+///
+/// Borrowing the data [directly][ReaderMMF::as_bytes] can't do any of that, so it's `unsafe`, and keeping writers out
+/// is up to you.
+///
+/// ```no_run
+/// # use winmmf::{*, states::RWLock};
+/// # use std::num::NonZeroUsize;
+/// let size = NonZeroUsize::new(1024).unwrap();
+/// let feed = MemoryMappedFile::<RWLock>::open_read_only(size, "feed", Namespace::LOCAL).unwrap();
+/// let mut seen = feed.generation();
+/// loop {
+///     if feed.generation() != seen {
+///         seen = feed.generation();
+///         println!("New data: {:?}", feed.read(16).unwrap());
+///     }
+/// #   break;
+/// }
+/// ```
+///
+/// [`RWLock`]: crate::states::RWLock
+#[cfg(feature = "impl_mmf")]
+#[derive(Debug)]
+pub struct ReaderMMF<LOCK: MMFLock>(MemoryMappedFile<LOCK>);
+
+#[cfg(feature = "impl_mmf")]
+impl<LOCK: MMFLock> ReaderMMF<LOCK> {
+    /// Chainable setter for how long reads wait for a writer to leave, see [`MemoryMappedFile::with_lock_timeout`].
+    pub fn with_lock_timeout(self, timeout: Duration) -> Self {
+        Self(self.0.with_lock_timeout(timeout))
+    }
+
+    /// Copy `count` bytes from the start of the data, see [`Mmf::read`]. Errors with [`MMFError::Timeout`] if a writer
+    /// doesn't leave in time, or writers keep getting in the way of the copy.
+    pub fn read(&self, count: usize) -> MMFResult<Vec<u8>> {
+        Mmf::read(&self.0, count)
+    }
+
+    /// Fill `buf` with the data starting `offset` bytes in, see [`MemoryMappedFile::read_at`]. Errors like
+    /// [`read`][ReaderMMF::read].
+    pub fn read_at(&self, offset: usize, buf: &mut [u8]) -> MMFResult<usize> {
+        self.0.read_at(offset, buf)
+    }
+
+    /// Get the data as a slice, without copying or checking on writers, see [`MemoryMappedFile::as_bytes`].
+    ///
+    /// # Safety
+    /// Nobody may write to the data for as long as the slice lives, in this process or any other. A reader can't take
+    /// the lock to make sure of that, so it has to come from somewhere else, like writers only ever writing before
+    /// any reader shows up.
+    ///
+    /// # Panics
+    /// If the MMF was closed.
+    pub unsafe fn as_bytes(&self) -> &[u8] {
+        // Safety: the caller keeps writers out, as promised.
+        self.0.as_bytes()
+    }
+
+    /// Get the write generation, see [`MemoryMappedFile::generation`]. Cheap enough to poll for changes.
+    pub fn generation(&self) -> Option<u32> {
+        self.0.generation()
+    }
+
+    /// Get the size of the data.
+    pub fn size(&self) -> usize {
+        self.0.size
+    }
+
+    /// Open another read-only view of the same MMF, see [`MemoryMappedFile::try_clone`].
+    pub fn try_clone(&self) -> MMFResult<Self> {
+        self.0.try_clone().map(Self)
+    }
+
+    /// Get the MMF back. It's still mapped read-only, so writing through it errors with [`MMFError::ReadOnly`].
+    pub fn into_inner(self) -> MemoryMappedFile<LOCK> {
+        self.0
+    }
+}
+
+/// A [`MemoryMappedFile`] without its lock, owning the section handle and the view. Always [`Send`] and [`Sync`].
+///
+/// An MMF is only `Send` if its lock is (and with the `mmf_send` feature on), and locks like [`RWLock`] borrow the view
+/// they live in, which makes moving one across threads awkward at best. This holds on to everything except the lock:
+/// turn an MMF into one with [`MemoryMappedFile::into_owned`], move it wherever, and turn it back with
+/// [`OwnedMMF::into_mmf`] to get a fresh lock on the other end.
+///
+/// ```no_run
+/// # use winmmf::{*, states::RWLock};
+/// # use std::num::NonZeroUsize;
+/// let mmf = MemoryMappedFile::<RWLock>::new(NonZeroUsize::new(64).unwrap(), "worker", Namespace::LOCAL).unwrap();
+/// let owned = mmf.into_owned().unwrap();
+/// std::thread::spawn(move || owned.into_mmf::<RWLock>().unwrap().write(b"hello from the worker")).join().unwrap();
+/// ```
+///
+/// [`RWLock`]: crate::states::RWLock
+#[cfg(feature = "impl_mmf")]
+#[derive(Debug)]
+pub struct OwnedMMF {
+    /// The [`HANDLE`] to the mapping.
+    handle: HANDLE,
+    /// The file backing the mapping, if any.
+    file: Option<HANDLE>,
+    /// The view, unmapped when this is dropped.
+    view: MemoryMappedView,
+    /// The "filename" portion.
+    name: ztr64,
+    /// The amount of data bytes, the lock comes on top.
+    size: usize,
+    /// The size of the lock the MMF was made with, which the lock it's turned back with has to match.
+    lock_size: usize,
+    /// Where in the view the lock lives.
+    lock_offset: usize,
+    /// Whether the MMF was read-only.
+    readonly: bool,
+    /// How long reads and writes wait for the lock.
+    lock_timeout: Duration,
+    /// How many tries spinners get.
+    spin_budget: usize,
+    /// How the view is mapped.
+    access: Access,
+}
+
+#[cfg(feature = "impl_mmf")]
+impl OwnedMMF {
+    /// Get the start of the whole view, lock included. Valid for [`OwnedMMF::total_size`] bytes, from any thread.
+    pub fn as_ptr(&self) -> *mut u8 {
+        self.view.address.Value.cast()
+    }
+
+    /// Get the raw handle to the mapping. It's still owned by this, so don't close it.
+    pub fn handle(&self) -> HANDLE {
+        self.handle
+    }
+
+    /// Get the amount of data bytes, see [`MemoryMappedFile::capacity`].
+    pub fn capacity(&self) -> usize {
+        self.size
+    }
+
+    /// Get the size of the whole view, see [`MemoryMappedFile::total_size`].
+    pub fn total_size(&self) -> usize {
+        self.size + self.lock_size
+    }
+
+    /// Turn this back into an MMF, with a lock of type `LOCK` built on the spot. Settings like the
+    /// [lock timeout][MemoryMappedFile::with_lock_timeout] carry over from the original MMF.
+    ///
+    /// Errors with [`MMFError::LockViolation`] if the lock isn't the same size as the one the MMF was made with, which
+    /// means it's some other kind of lock and the layout doesn't match. A [copy-on-write][Access::CopyOnWrite] MMF can
+    /// also error mapping the view of the shared lock. `self` is dropped in either case.
+    pub fn into_mmf<LOCK: MMFLock>(self) -> MMFResult<MemoryMappedFile<LOCK>> {
+        if LOCK::lock_size() != self.lock_size {
+            return Err(MMFError::LockViolation);
+        }
+        // Only copy-on-write MMFs opened by name watch the shared lock, see `Access::CopyOnWrite`.
+        let shared_lock = match (self.access, self.file) {
+            (Access::CopyOnWrite, None) => {
+                Some(MemoryMappedFile::<LOCK>::watch_lock(self.handle, self.lock_offset, &self.name)?)
+            }
+            _ => None,
+        };
+        let this = ManuallyDrop::new(self);
+        // Safety: `this` is never dropped, so the view is only unmapped by the MMF it moves into.
+        let view = unsafe { std::ptr::read(&this.view) };
+        let view_ptr = this.as_ptr();
+        // Safety: this is the same view the lock lived in, so it's been initialized.
+        let lock = unsafe { LOCK::from_existing_named(view_ptr.add(this.lock_offset), this.name.as_str()) };
+        let (lock, private_lock) = match this.access {
+            Access::CopyOnWrite => {
+                let (lock, buf) = MemoryMappedFile::<LOCK>::private_lock();
+                (lock, Some(buf))
+            }
+            _ => (lock, None),
+        };
+        let (size_low_order, size_high_order) = (this.size + this.lock_size).split();
+        Ok(MemoryMappedFile {
+            handle: this.handle,
+            file: this.file,
+            name: this.name,
+            size_high_order,
+            size_low_order,
+            size: this.size,
+            lock,
+            map_view: Some(view),
+            view_ptr,
+            lock_offset: this.lock_offset,
+            closed: Cell::new(false),
+            readonly: this.readonly,
+            lock_timeout: this.lock_timeout,
+            spin_budget: this.spin_budget,
+            access: this.access,
+            private_lock,
+            shared_lock,
+        })
+    }
+}
+
+/// Unmap the view, then close the handles. Like for [`MemoryMappedFile`], errors are ignored.
+#[cfg(feature = "impl_mmf")]
+impl Drop for OwnedMMF {
+    fn drop(&mut self) {
+        _ = self.view.unmap();
+        // Safety: both are ours, and nothing else closes them.
+        unsafe {
+            _ = self.file.map(|file| CloseHandle(file));
+            _ = CloseHandle(self.handle);
+        }
+    }
+}
+
+// Safety: the handles and the view are valid for the whole process, not just the thread that made them. Nothing in
+// here is tied to a thread, and the only way to get at the memory is a raw pointer, or turning it back into an MMF
+// with a lock guarding it.
+#[cfg(feature = "impl_mmf")]
+unsafe impl Send for OwnedMMF {}
+#[cfg(feature = "impl_mmf")]
+unsafe impl Sync for OwnedMMF {}
+
+/// The data of a [`MemoryMappedFile`], borrowed under a read lock. See [`MemoryMappedFile::read_borrow`].
+///
+/// Derefs to the bytes in the view, and releases the read lock when dropped. Like the guards of
+/// [`RawRWLock`][crate::raw_lock::RawRWLock], this isn't [`Send`]: the lock keeps track of what it holds per
+/// instance, and the instance stays on the thread that took the lock.
+///
+/// ```no_run
+/// # use winmmf::{*, states::RWLock};
+/// # use std::num::NonZeroUsize;
+/// let size = NonZeroUsize::new(40 << 20).unwrap();
+/// let frames = MemoryMappedFile::<RWLock>::open(size, "frames", Namespace::LOCAL, true).unwrap();
+/// let frame = frames.read_borrow().unwrap();
+/// println!("First pixel: {:?}", &frame[..4]);
+/// ```
+#[cfg(feature = "impl_mmf")]
+#[derive(Debug)]
+pub struct ReadGuard<'a, LOCK: MMFLock> {
+    /// The MMF whose lock is held.
+    mmf: &'a MemoryMappedFile<LOCK>,
+    /// The start of the data.
+    ptr: *mut u8,
+    /// The amount of bytes borrowed.
+    len: usize,
+    /// Keeps the guard on the thread that took the lock.
+    _not_send: PhantomData<*const ()>,
+}
+
+#[cfg(feature = "impl_mmf")]
+impl<LOCK: MMFLock> ReadGuard<'_, LOCK> {
+    /// Leave the first `count` bytes out of the borrow, holding on to the lock. For wrappers with a header of their
+    /// own in front of the data.
+    pub(crate) fn skip(mut self, count: usize) -> Self {
+        let count = count.min(self.len);
+        self.ptr = self.ptr.wrapping_add(count);
+        self.len -= count;
+        self
+    }
+}
+
+#[cfg(feature = "impl_mmf")]
+impl<LOCK: MMFLock> Deref for ReadGuard<'_, LOCK> {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        // Safety: the range was checked when taking the guard, the view outlives the borrow of the MMF, and the read
+        // lock keeps writers out for as long as the guard lives.
+        unsafe { std::slice::from_raw_parts(self.ptr, self.len) }
+    }
+}
+
+#[cfg(feature = "impl_mmf")]
+impl<LOCK: MMFLock> Drop for ReadGuard<'_, LOCK> {
+    fn drop(&mut self) {
+        _ = self.mmf.lock.unlock_read();
+    }
+}
+
+/// Move a cursor at `pos` in `len` bytes as [`io::Seek`] says, making sure it stays within those bytes. The data can't
+/// grow like a file would, so seeking past the end is an [`io::ErrorKind::UnexpectedEof`] rather than a gap.
+#[cfg(feature = "impl_mmf")]
+fn seek_within(pos: usize, len: usize, target: SeekFrom) -> io::Result<usize> {
+    let new = match target {
+        SeekFrom::Start(offset) => i128::from(offset),
+        SeekFrom::End(offset) => len as i128 + i128::from(offset),
+        SeekFrom::Current(offset) => pos as i128 + i128::from(offset),
+    };
+    if new < 0 {
+        Err(io::Error::new(io::ErrorKind::InvalidInput, "Tried to seek in front of the start of the MMF"))
+    } else if new > len as i128 {
+        Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Tried to seek past the end of the MMF"))
+    } else {
+        Ok(new as usize)
+    }
+}
+
+/// Reading cursor over the data of a [`MemoryMappedFile`], see [`MemoryMappedFile::cursor`].
+///
+/// Reads go through [`read_at`][MemoryMappedFile::read_at], so each one takes the read lock and waits for writers
+/// like it does. Anything it errors with is handed back inside an [`io::Error`], like [`MMFError::Timeout`] when a
+/// writer doesn't leave in time, or [`MMFError::MMF_NotFound`] when the MMF was closed in the meantime. The cursor
+/// never goes past the end: reading there gives 0 bytes like any reader at its end, and
+/// [`read_exact`][Read::read_exact] or seeking beyond it errors with [`io::ErrorKind::UnexpectedEof`].
+///
+/// ```no_run
+/// # use winmmf::{*, states::RWLock};
+/// # use std::{io::Read, num::NonZeroUsize};
+/// let size = NonZeroUsize::new(1024).unwrap();
+/// let config = MemoryMappedFile::<RWLock>::open(size, "config", Namespace::LOCAL, true).unwrap();
+/// let mut header = [0; 16];
+/// config.cursor().read_exact(&mut header).unwrap();
+/// ```
+#[cfg(feature = "impl_mmf")]
+#[derive(Debug)]
+pub struct MMFCursor<'a, LOCK: MMFLock> {
+    /// The MMF being read.
+    mmf: &'a MemoryMappedFile<LOCK>,
+    /// Where the next read starts, never past the end.
+    pos: usize,
+}
+
+#[cfg(feature = "impl_mmf")]
+impl<LOCK: MMFLock> MMFCursor<'_, LOCK> {
+    /// Get where the next read starts.
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// Copy `buf.len()` bytes from the current position and move past them. The caller checked they're there.
+    fn copy_out(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        self.mmf.read_at(self.pos, buf).map_err(io::Error::other)?;
+        self.pos += buf.len();
+        Ok(())
+    }
+}
+
+#[cfg(feature = "impl_mmf")]
+impl<LOCK: MMFLock> Read for MMFCursor<'_, LOCK> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let count = buf.len().min(data_len(self.mmf)? - self.pos);
+        self.copy_out(&mut buf[..count]).map(|_| count)
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        if buf.len() > data_len(self.mmf)? - self.pos {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Not enough data left in the MMF"));
+        }
+        self.copy_out(buf)
+    }
+}
+
+#[cfg(feature = "impl_mmf")]
+impl<LOCK: MMFLock> Seek for MMFCursor<'_, LOCK> {
+    fn seek(&mut self, target: SeekFrom) -> io::Result<u64> {
+        self.pos = seek_within(self.pos, data_len(self.mmf)?, target)?;
+        Ok(self.pos as u64)
+    }
+}
+
+/// How much data the cursors can move around in, or an error if the MMF was closed underneath them.
+#[cfg(feature = "impl_mmf")]
+fn data_len<LOCK: MMFLock>(mmf: &MemoryMappedFile<LOCK>) -> io::Result<usize> {
+    match mmf.closed.get() {
+        true => Err(io::Error::other(MMFError::MMF_NotFound)),
+        false => Ok(mmf.size),
+    }
+}
+
+/// Writing cursor over the data of a [`MemoryMappedFile`], see [`MemoryMappedFile::cursor_mut`].
+///
+/// Writes go through [`write_at`][MemoryMappedFile::write_at], so each one takes the write lock and errors like it
+/// does, inside an [`io::Error`]. The data can't grow, so writing at the end or seeking beyond it errors with
+/// [`io::ErrorKind::UnexpectedEof`]. A write that only partly fits writes what fits, and
+/// [`write_all`][Write::write_all] errors on the next bit.
+#[cfg(feature = "impl_mmf")]
+#[derive(Debug)]
+pub struct MMFCursorMut<'a, LOCK: MMFLock> {
+    /// The MMF being written.
+    mmf: &'a mut MemoryMappedFile<LOCK>,
+    /// Where the next write starts, never past the end.
+    pos: usize,
+}
+
+#[cfg(feature = "impl_mmf")]
+impl<LOCK: MMFLock> MMFCursorMut<'_, LOCK> {
+    /// Get where the next write starts.
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+}
+
+#[cfg(feature = "impl_mmf")]
+impl<LOCK: MMFLock> Write for MMFCursorMut<'_, LOCK> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let rest = data_len(self.mmf)? - self.pos;
+        if rest == 0 && !buf.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "No room left in the MMF"));
+        }
+        let count = buf.len().min(rest);
+        self.mmf.write_at(self.pos, &buf[..count]).map_err(io::Error::other)?;
+        self.pos += count;
+        Ok(count)
+    }
+
+    /// Writes go straight into the view, so there's nothing to flush here. See [`MemoryMappedFile::flush`] for getting
+    /// the data to disk.
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "impl_mmf")]
+impl<LOCK: MMFLock> Seek for MMFCursorMut<'_, LOCK> {
+    fn seek(&mut self, target: SeekFrom) -> io::Result<u64> {
+        self.pos = seek_within(self.pos, data_len(self.mmf)?, target)?;
+        Ok(self.pos as u64)
+    }
+}
+
+/// A window into part of a [`MemoryMappedFile`], see [`MemoryMappedFile::map_region`].
+///
+/// The region has a view of its own, which is unmapped when it's dropped. It can't outlive the MMF it came from.
+#[cfg(feature = "impl_mmf")]
+#[derive(Debug)]
+pub struct MappedRegion<'a> {
+    /// The view of the region, kept around for unmapping it.
+    #[allow(dead_code)]
+    view: MemoryMappedView,
+    /// The start of the region.
+    ptr: *mut u8,
+    /// Where the region starts in the MMF.
+    offset: u64,
+    /// The size of the region in bytes.
+    len: usize,
+    /// Ties the region to the MMF, so the handle outlives the view.
+    mmf: PhantomData<&'a ()>,
+}
+
+#[cfg(feature = "impl_mmf")]
+impl MappedRegion<'_> {
+    /// Get a pointer to the start of the region. It's valid for [`len`][MappedRegion::len] bytes for as long as the
+    /// region lives, but mind the lock.
+    pub fn as_ptr(&self) -> *mut u8 {
+        self.ptr
+    }
+
+    /// Where the region starts, counted from the start of the MMF.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// The size of the region in bytes.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Regions are never empty, but clippy insists.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+/// Which lock guards a [`RegionMMF`], see [`MemoryMappedFile::open_region`].
+#[cfg(feature = "impl_mmf")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegionLock {
+    /// The window starts with a lock of its own, guarding only the rest of it. For layouts split into parts that are
+    /// used independently, each with a lock in front, so they don't get in each other's way. Whoever laid out the data
+    /// sets those up with [`MMFLock::from_raw`], opening waits for that like [`MemoryMappedFile::open`] does.
+    Own,
+    /// The window is all data, guarded by the lock of the whole MMF. That lives in another part of the mapping, so it
+    /// gets a tiny view of its own.
+    Shared,
+}
+
+/// A window into part of an MMF, with a lock to guard it, see [`MemoryMappedFile::open_region`].
+///
+/// Unlike a [`MappedRegion`], this doesn't need the whole MMF mapped first: it has a handle of its own, and only maps
+/// the window and its lock. Reads and writes take the lock like those of a [`MemoryMappedFile`] do.
+///
+/// ## Usage
+/// ```no_run
+/// # use winmmf::{*, states::RWLock};
+/// # use std::num::NonZeroUsize;
+/// // Somebody else made a huge MMF, we only need the bit that's ours
+/// let len = NonZeroUsize::new(4096).unwrap();
+/// let ours = MemoryMappedFile::<RWLock>::open_region("atlas", Namespace::LOCAL, 3 << 30, len, RegionLock::Shared);
+/// let mut tile = [0; 64];
+/// ours.unwrap().read_at(0, &mut tile).unwrap();
+/// ```
+#[cfg(feature = "impl_mmf")]
+#[derive(Debug)]
+pub struct RegionMMF<LOCK: MMFLock> {
+    /// The lock guarding the window. It comes first, so it's dropped before the view it points into is unmapped.
+    lock: LOCK,
+    /// The view of the lock of the whole MMF, for [`RegionLock::Shared`].
+    #[allow(dead_code)]
+    lock_view: Option<MemoryMappedView>,
+    /// The view of the window, starting at the allocation granularity boundary in front of it.
+    #[allow(dead_code)]
+    view: MemoryMappedView,
+    /// The [`HANDLE`] to the mapping, closed when dropped.
+    handle: HANDLE,
+    /// The start of the data in the window.
+    ptr: *mut u8,
+    /// Where the window starts in the data of the MMF.
+    offset: u64,
+    /// The amount of data bytes in the window.
+    len: usize,
+    /// How long reads and writes wait for the lock.
+    lock_timeout: Duration,
+}
+
+#[cfg(feature = "impl_mmf")]
+impl<LOCK: MMFLock> RegionMMF<LOCK> {
+    /// Map the window and its lock, see [`MemoryMappedFile::open_region`]. Closing the handle on errors is up to the
+    /// caller.
+    fn map(handle: HANDLE, name: ztr64, offset: u64, len: NonZeroUsize, kind: RegionLock) -> MMFResult<Self> {
+        let lock_size = LOCK::lock_size();
+        let own = if kind == RegionLock::Own { lock_size } else { 0 };
+        let start = offset.checked_add(lock_size as u64).ok_or(MMFError::NotEnoughMemory)?;
+        if own != 0 && start % 4 != 0 {
+            return Err(MMFError::MisalignedLock);
+        }
+        // Views start at a boundary, the window is wherever it is behind that.
+        let skip = start % u64::from(allocation_granularity());
+        let view_len = (skip as usize + own).checked_add(len.get()).ok_or(MMFError::NotEnoughMemory)?;
+        let view = map_window(handle, Access::ReadWrite.map_flags(), start - skip, view_len)?;
+        // Safety: the view is `skip` bytes longer than the window, the lock (if any) and the data.
+        let window = unsafe { view.address.Value.cast::<u8>().add(skip as usize) };
+        let (lock, lock_view) = match kind {
+            // Safety: the lock is right at the start of the window, which is in the view.
+            RegionLock::Own => (unsafe { LOCK::from_existing_named(window, name.as_str()) }, None),
+            RegionLock::Shared => {
+                // A length of 0 maps everything, so a lock without bytes still gets a byte to point at.
+                let lock_view = map_window(handle, Access::ReadWrite.map_flags(), 0, lock_size.max(1))?;
+                // Safety: the lock is at the start of the mapping, and so at the start of the view.
+                let lock = unsafe { LOCK::from_existing_named(lock_view.address.Value.cast(), name.as_str()) };
+                (lock, Some(lock_view))
+            }
+        };
+        lock.init_or_wait(INIT_TIMEOUT)?;
+        Ok(Self {
+            lock,
+            lock_view,
+            view,
+            handle,
+            // Safety: the data is right behind the lock, if there is one.
+            ptr: unsafe { window.add(own) },
+            offset,
+            len: len.get(),
+            lock_timeout: LOCK_TIMEOUT,
+        })
+    }
+
+    /// Chainable setter for how long reads and writes wait for the lock, see [`MemoryMappedFile::with_lock_timeout`].
+    pub fn with_lock_timeout(mut self, timeout: Duration) -> Self {
+        self.lock_timeout = timeout;
+        self
+    }
+
+    /// Get how long reads and writes wait for the lock.
+    pub fn lock_timeout(&self) -> Duration {
+        self.lock_timeout
+    }
+
+    /// Where the window starts in the data of the MMF.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Get the amount of data bytes in the window, without the lock if it has one of its own.
+    pub fn size(&self) -> usize {
+        self.len
+    }
+
+    /// Get a pointer to the start of the data in the window. It's valid for [`size`][RegionMMF::size] bytes for as
+    /// long as the region lives, but mind the lock.
+    pub fn ptr(&self) -> *mut u8 {
+        self.ptr
+    }
+
+    /// Check that `len` bytes starting `offset` bytes into the window are really there.
+    fn check_range(&self, offset: usize, len: usize) -> MMFResult<()> {
+        match offset.checked_add(len) {
+            Some(end) if end <= self.len => Ok(()),
+            _ => Err(MMFError::NotEnoughMemory),
+        }
+    }
+
+    /// Fill `buf` with the data starting `offset` bytes into the window, holding a read lock while copying. Returns
+    /// the amount of bytes read, which is always all of `buf`.
+    ///
+    /// Errors with [`MMFError::NotEnoughMemory`] if that runs past the end of the window, and with
+    /// [`MMFError::Timeout`] if the lock doesn't come free in time.
+    pub fn read_at(&self, offset: usize, buf: &mut [u8]) -> MMFResult<usize> {
+        self.check_range(offset, buf.len())?;
+        wait_for_lock(&self.lock, self.lock_timeout, LOCK::try_lock_read)?;
+        // Safety: the range was checked to be within the window, and we hold the read lock.
+        unsafe { self.ptr.add(offset).copy_to_nonoverlapping(buf.as_mut_ptr(), buf.len()) };
+        self.lock.unlock_read()?;
+        Ok(buf.len())
+    }
+
+    /// Write all of `data` into the window starting `offset` bytes in, holding the write lock while copying. Returns
+    /// the amount of bytes written. Errors like [`read_at`][RegionMMF::read_at].
+    pub fn write_at(&self, offset: usize, data: &[u8]) -> MMFResult<usize> {
+        self.check_range(offset, data.len())?;
+        wait_for_lock(&self.lock, self.lock_timeout, LOCK::try_lock_write)?;
+        // Safety: the range was checked to be within the window, and we hold the write lock.
+        unsafe { self.ptr.add(offset).copy_from_nonoverlapping(data.as_ptr(), data.len()) };
+        self.lock.unlock_write()?;
+        Ok(data.len())
+    }
+}
+
+/// Close the handle, the views are unmapped right after when the fields are dropped.
+#[cfg(feature = "impl_mmf")]
+impl<LOCK: MMFLock> Drop for RegionMMF<LOCK> {
+    fn drop(&mut self) {
+        // Safety: the handle is ours, and nothing else closes it. The views keep the mapping alive until they're gone.
+        unsafe { _ = CloseHandle(self.handle) };
+    }
+}
+
 /// Small struct wrapping a Windows type just to spare my eyes.
-#[derive(Debug, Clone)]
+///
+/// The view is unmapped when this is dropped, so it's deliberately not `Clone`: two of these would unmap it twice.
+#[derive(Debug)]
 pub struct MemoryMappedView {
     /// The address to use for reads and writes
     address: MEMORY_MAPPED_VIEW_ADDRESS,
@@ -598,11 +3455,20 @@ impl Drop for MemoryMappedView {
     }
 }
 
-/// Implement closing the handle to the MMF before dropping it, so the system can clean up resources.
+/// Implement unmapping the view and closing the handle to the MMF before dropping it, so the system can clean up
+/// resources.
 #[cfg(feature = "impl_mmf")]
 impl<LOCK: MMFLock> Drop for MemoryMappedFile<LOCK> {
-    /// Ignore any errors when closing the handle.
+    /// Unmap the view first, then close the handle. Errors are printed to stderr by whatever ran into them and
+    /// otherwise ignored, panicking in a drop is nobody's idea of fun. Call [`close`][MemoryMappedFile::close] first if
+    /// you'd rather handle errors closing the handle yourself.
+    ///
+    /// This only lets go of what this instance holds on to. Locks in the MMF are left alone, so whatever other
+    /// processes hold stays held. The MMF itself lives on for as long as anyone else has a handle to it.
     fn drop(&mut self) {
+        // Dropping the view unmaps it. The lock points into it, but it's never touched again after this.
+        drop(self.map_view.take());
+        drop(self.shared_lock.take());
         self.close().unwrap_or(())
     }
 }
@@ -0,0 +1,127 @@
+#![deny(clippy::missing_docs_in_private_items)]
+#![deny(missing_docs)]
+//! # Telling readers there's something new
+//!
+//! Locks tell you when you may touch the data, not when there's anything worth reading. Without help, readers end up
+//! polling the MMF and comparing it to what they saw last time. The [`SingleConsumerNotifier`] defined here is that
+//! help: a named `Event` next to the MMF, which a writer signals once it's done and a reader waits on. Together with
+//! the MMF that makes for a channel with a single consumer, where the MMF holds the latest message and the event rings
+//! the bell.
+//!
+//! Note the single consumer. Every signal wakes up one waiter, not all of them, so with several readers waiting only
+//! one of them hears about it. If more than one process needs to know, give each its own MMF and notifier, or have the
+//! one that wakes up pass it on.
+//!
+//! The event lives outside the MMF, so the layout of the MMF doesn't change and processes that don't know about the
+//! notifier can keep using it as before. They just won't wake anyone up.
+
+use std::{ffi::CString, time::Duration};
+
+use windows::{
+    core::{Error as WErr, PCSTR},
+    Win32::{
+        Foundation::{CloseHandle, HANDLE, WAIT_OBJECT_0, WAIT_TIMEOUT},
+        System::Threading::{CreateEventA, SetEvent, WaitForSingleObject, INFINITE},
+    },
+};
+
+use super::{
+    err::{Error, MMFResult},
+    mmf::MemoryMappedFile,
+    states::MMFLock,
+};
+
+/// What's appended to the name of the MMF to get the name of the event. Kernel objects share a namespace with file
+/// mappings, so without it the names would collide.
+pub const NOTIFIER_SUFFIX: &str = "_wmmf_notify";
+
+/// A named event for writers to signal a reader with, see [the module docs][self].
+///
+/// The event is auto-reset: every [`signal`][SingleConsumerNotifier::signal] releases a single
+/// [`wait`][SingleConsumerNotifier::wait], so this is for one reader only. Any other reader waiting at the same time
+/// keeps waiting, and which of them gets woken is up to the OS. If nobody is waiting at the time, the event stays
+/// signaled until somebody does, so a reader that was busy doesn't miss it. A bunch of signals without a wait in
+/// between do collapse into one though, so treat a wakeup as "there's something new" rather than "there's exactly one
+/// new thing", and read everything there is.
+///
+/// Signal after releasing the write lock, not while holding it. Otherwise the reader wakes up only to find the lock
+/// still held.
+///
+/// ## Usage
+/// ```no_run
+/// # use winmmf::{notify::SingleConsumerNotifier, states::RWLock, *};
+/// # use std::{num::NonZeroUsize, time::Duration};
+/// let mmf = MemoryMappedFile::<RWLock>::new(NonZeroUsize::new(64).unwrap(), "news", Namespace::LOCAL).unwrap();
+/// let notifier = SingleConsumerNotifier::new(&mmf).unwrap();
+/// // On the writing end
+/// mmf.write(b"Extra! Extra!").unwrap();
+/// notifier.signal().unwrap();
+/// // On the reading end
+/// if notifier.wait(Some(Duration::from_secs(1))).unwrap() {
+///     println!("{:?}", mmf.read(13).unwrap());
+/// }
+/// ```
+#[derive(Debug)]
+pub struct SingleConsumerNotifier {
+    /// The event, closed on drop.
+    event: HANDLE,
+    /// The name of the event, for debugging purposes.
+    name: String,
+}
+
+impl SingleConsumerNotifier {
+    /// Create or open the notifier belonging to `mmf`. Everyone with the same MMF ends up with the same event, no
+    /// matter who gets there first.
+    pub fn new<LOCK: MMFLock>(mmf: &MemoryMappedFile<LOCK>) -> MMFResult<Self> {
+        let name = format!("{}{NOTIFIER_SUFFIX}", mmf.fullname());
+        let event_name = CString::new(name.as_str()).map_err(|_| Error::GeneralFailure)?;
+        // Auto-reset and not signaled to begin with. If the event already exists, the state is left alone.
+        let event =
+            unsafe { CreateEventA(None, false, false, PCSTR(event_name.as_ptr().cast())) }.map_err(Error::from_os)?;
+        Ok(Self { event, name })
+    }
+
+    /// Get the name of the event, which is the full name of the MMF followed by [`NOTIFIER_SUFFIX`].
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Wake up a reader waiting on this notifier, or the next one to wait if there's nobody waiting right now.
+    pub fn signal(&self) -> MMFResult<()> {
+        // Safety: the handle is open for as long as we are.
+        unsafe { SetEvent(self.event) }.map_err(Error::from_os)
+    }
+
+    /// Wait for a writer to [signal][SingleConsumerNotifier::signal], for at most `timeout` or forever if it's
+    /// [`None`].
+    ///
+    /// Returns whether a signal came in. Running out of time isn't an error, that just means there's nothing new.
+    pub fn wait(&self, timeout: Option<Duration>) -> MMFResult<bool> {
+        let timeout_ms = timeout.map_or(INFINITE, |t| t.as_millis().min((INFINITE - 1) as u128) as u32);
+        // Safety: the handle is open for as long as we are.
+        match unsafe { WaitForSingleObject(self.event, timeout_ms) } {
+            WAIT_OBJECT_0 => Ok(true),
+            WAIT_TIMEOUT => Ok(false),
+            _ => Err(Error::from_os(WErr::from_win32())),
+        }
+    }
+}
+
+impl Drop for SingleConsumerNotifier {
+    /// Close the handle. The event lives on for as long as anyone else has it open.
+    fn drop(&mut self) {
+        _ = unsafe { CloseHandle(self.event) };
+    }
+}
+
+/// Send marker for sharing the notifier with other threads.
+///
+/// # Safety
+/// The handle is only ever passed to the OS, and event handles can be used from any thread.
+unsafe impl Send for SingleConsumerNotifier {}
+
+/// Sync marker for sharing the notifier with other threads.
+///
+/// # Safety
+/// Signaling and waiting are atomic on the OS end, no matter how many threads do it at once.
+unsafe impl Sync for SingleConsumerNotifier {}
@@ -0,0 +1,175 @@
+#![deny(clippy::missing_docs_in_private_items)]
+#![deny(missing_docs)]
+//! # One-time initialization across processes
+//!
+//! [`std::sync::Once`] makes sure something happens once per process, which isn't much help when the something is
+//! filling shared memory that every process sees. The [`MMFOnce`] defined here keeps its state in the MMF, so only one
+//! process out of everyone using it gets to run the initialization, and everyone else waits for it to finish.
+//!
+//! This is meant for shared config and the like: data one process writes once, and everyone reads after.
+
+use std::{
+    marker::PhantomData,
+    slice,
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+use super::{
+    err::{Error, MMFResult},
+    states::SpinStrategy,
+};
+
+/// A once-only initialization of a region of shared memory, see [the module docs][self].
+///
+/// The state takes up the first 4 bytes of [`MMFOnce::HEADER_SIZE`], and the data follows right behind. Two bits of the
+/// state are used: [`MMFOnce::INITIALIZED`] and [`MMFOnce::IN_PROGRESS`]. A fresh MMF is all zeroes, which is an
+/// `MMFOnce` that nobody ran yet, so there's nothing to set up.
+///
+/// ## Usage
+/// ```
+/// # use winmmf::once::*;
+/// let mut bop = vec![0u32; (MMFOnce::HEADER_SIZE + 8) / 4];
+/// let once = unsafe { MMFOnce::new(bop.as_mut_ptr().cast(), 8) };
+/// once.call_once(|data| data.copy_from_slice(&42u64.to_le_bytes())).unwrap();
+/// // Already done, so this never runs
+/// once.call_once(|data| data.fill(0)).unwrap();
+/// assert_eq!(once.get(), Some(&42u64.to_le_bytes()[..]));
+/// ```
+#[derive(Debug)]
+pub struct MMFOnce<'a> {
+    /// The state bits, see [`MMFOnce::INITIALIZED`] and [`MMFOnce::IN_PROGRESS`].
+    state: &'a AtomicU32,
+    /// The start of the data.
+    data: *mut u8,
+    /// The amount of data bytes.
+    len: usize,
+    /// How often to check on somebody else's initialization before giving up.
+    max_tries: usize,
+    /// What to do in between checks.
+    strategy: SpinStrategy,
+    /// The data is borrowed for as long as the state is.
+    _data: PhantomData<&'a mut [u8]>,
+}
+
+/// Hands the initialization back if the closure panics, so the next caller can give it a go instead of waiting forever.
+struct ResetOnUnwind<'a>(&'a AtomicU32);
+
+impl Drop for ResetOnUnwind<'_> {
+    fn drop(&mut self) {
+        self.0.store(0, Ordering::Release);
+    }
+}
+
+impl MMFOnce<'_> {
+    /// The amount of bytes in front of the data: the state, padded to keep the data aligned to 16 bytes.
+    pub const HEADER_SIZE: usize = 16;
+    /// State bit set once the initialization finished. Nothing ever clears it.
+    pub const INITIALIZED: u32 = 0b01;
+    /// State bit set while somebody is running the initialization.
+    pub const IN_PROGRESS: u32 = 0b10;
+
+    /// Attach to the once at `pointer`, guarding `len` bytes of data behind the header.
+    ///
+    /// # Safety
+    /// The pointer must be non-null, aligned to 4 and valid for [`Self::HEADER_SIZE`] + `len` bytes for as long as the
+    /// once lives. The bytes must be zeroed or belong to an `MMFOnce` already, and nobody may write to the data other
+    /// than through [`call_once`][MMFOnce::call_once].
+    pub unsafe fn new<'a>(pointer: *mut u8, len: usize) -> MMFOnce<'a> {
+        MMFOnce {
+            state: AtomicU32::from_ptr(pointer.cast()),
+            data: pointer.add(Self::HEADER_SIZE),
+            len,
+            max_tries: usize::MAX,
+            strategy: SpinStrategy::Busy,
+            _data: PhantomData,
+        }
+    }
+
+    /// Chainable setter for how often [`call_once`][MMFOnce::call_once] checks on somebody else's initialization
+    /// before giving up. Waits for as long as it takes by default. Only affects this instance.
+    pub fn with_max_tries(mut self, max_tries: usize) -> Self {
+        self.max_tries = max_tries.max(1);
+        self
+    }
+
+    /// Chainable setter for the [`SpinStrategy`] used while waiting on somebody else. Only affects this instance.
+    pub fn with_spin_strategy(mut self, strategy: SpinStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Get the amount of data bytes.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Check if there's no data at all. Still works as a cross-process flag, if that's all you need.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Check if the initialization finished, by anyone.
+    pub fn is_completed(&self) -> bool {
+        (self.state.load(Ordering::Acquire) & Self::INITIALIZED) != 0
+    }
+
+    /// Get the data, if the initialization finished.
+    pub fn get(&self) -> Option<&[u8]> {
+        // Safety: the region is valid for `len` bytes, and nobody writes to it anymore once it's initialized.
+        self.is_completed().then(|| unsafe { slice::from_raw_parts(self.data, self.len) })
+    }
+
+    /// Run `f` on the data if nobody did yet, or wait for whoever is doing it to finish.
+    ///
+    /// Only one caller across every process using the once gets to run its closure, all others return once it's done
+    /// without running theirs. Errors with [`Error::Timeout`] if somebody else is still at it after the
+    /// [maximum amount of tries][MMFOnce::with_max_tries]. If `f` panics, the initialization is up for grabs again and
+    /// one of the waiting callers runs theirs instead. A process that dies halfway through leaves everyone waiting
+    /// though, so set a limit if that's a concern.
+    pub fn call_once(&self, f: impl FnOnce(&mut [u8])) -> MMFResult<()> {
+        let mut f = Some(f);
+        let mut tries = 0;
+        loop {
+            match self.try_run(&mut f) {
+                Err(Error::Uninitialized) => (),
+                done => return done,
+            }
+            tries += 1;
+            if tries >= self.max_tries {
+                return Err(Error::Timeout);
+            }
+            self.strategy.pause(tries);
+        }
+    }
+
+    /// Like [`call_once`][MMFOnce::call_once], except it doesn't wait. Errors with [`Error::Uninitialized`] if somebody
+    /// else is busy initializing, for callers that have better things to do than block.
+    pub fn try_call_once(&self, f: impl FnOnce(&mut [u8])) -> MMFResult<()> {
+        self.try_run(&mut Some(f))
+    }
+
+    /// Claim the initialization and run `f` if it's up for grabs. `f` is taken out of the option when it runs, so the
+    /// caller can keep trying with the same one.
+    fn try_run(&self, f: &mut Option<impl FnOnce(&mut [u8])>) -> MMFResult<()> {
+        match self.state.compare_exchange(0, Self::IN_PROGRESS, Ordering::Acquire, Ordering::Acquire) {
+            Ok(_) => {
+                let reset = ResetOnUnwind(self.state);
+                // Safety: the region is valid for `len` bytes, and the in progress bit keeps everyone else out.
+                let data = unsafe { slice::from_raw_parts_mut(self.data, self.len) };
+                if let Some(f) = f.take() {
+                    f(data);
+                }
+                std::mem::forget(reset);
+                self.state.store(Self::INITIALIZED, Ordering::Release);
+                Ok(())
+            }
+            Err(state) if (state & Self::INITIALIZED) != 0 => Ok(()),
+            Err(_) => Err(Error::Uninitialized),
+        }
+    }
+}
+
+// Safety: the state is only ever accessed atomically, and the data is only written by the one caller that claimed the
+// initialization. Everyone else only reads it after it's done.
+unsafe impl Send for MMFOnce<'_> {}
+unsafe impl Sync for MMFOnce<'_> {}
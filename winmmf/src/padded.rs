@@ -0,0 +1,196 @@
+#![deny(clippy::missing_docs_in_private_items)]
+#![deny(missing_docs)]
+//! # Bigger headers for less contention
+//!
+//! Every MMF reserves [`lock_size`][MMFLock::lock_size] bytes for its lock, and the data starts right behind them.
+//! That's tight, and it means the lock word and the first bytes of data share a cache line. Every lock or unlock then
+//! fights with whoever is hammering that data, even though they never touch the same bytes. The [`Padded`] lock
+//! defined here wraps any [`MMFLock`] and claims a bigger header, pushing the data onto a cache line of its own.
+//!
+//! The wrapped lock stays at the very start of the header, where it always was, and the rest is padding nobody touches.
+//! Run `cargo bench -p winmmf --bench header` to see what it's worth on your machine.
+
+use std::time::Duration;
+
+use super::{err::MMFResult, states::MMFLock};
+
+/// An [`MMFLock`] claiming a header of `HEADER` bytes, see [the module docs][self].
+///
+/// Every [`MMFLock`] method is forwarded to the wrapped lock, only [`lock_size`][MMFLock::lock_size] changes: it's
+/// `HEADER`, or the size of the wrapped lock if that's bigger still. Seeing as the header goes in front of the data and
+/// views start on a page boundary, that's also the alignment of the data. [`CacheAligned`] is the one you're most
+/// likely after.
+///
+/// ## Alignment constraints
+/// `HEADER` has to be a power of two, and can't be more than 4096 (the smallest page) to keep the alignment promise.
+/// Anything else fails to compile as soon as the lock is used. If the wrapped lock is bigger than `HEADER` and not a
+/// power of two itself, the data is only aligned to the largest power of two dividing its size.
+///
+/// As the header size is part of the layout, everyone opening the MMF has to use the same `HEADER`. MMFs with a
+/// padded lock are **not** compatible with MMFs using the bare lock, or the same lock with different padding.
+///
+/// ## Usage
+/// ```
+/// # use winmmf::{padded::*, states::*};
+/// assert_eq!(Padded::<RWLock, 64>::lock_size(), 64);
+/// // Never smaller than the lock itself
+/// assert_eq!(Padded::<RWLock, 4>::lock_size(), RWLock::SIZE);
+///
+/// let mut bop = vec![0u32; 64 / 4];
+/// let lock = unsafe { CacheAligned::<RWLock>::from_raw(bop.as_mut_ptr().cast()).initialize() };
+/// lock.try_lock_write().unwrap();
+/// assert!(lock.inner().writelocked());
+/// ```
+#[derive(Debug)]
+pub struct Padded<L: MMFLock, const HEADER: usize> {
+    /// The lock doing the actual work.
+    inner: L,
+}
+
+/// A lock claiming a whole 64 byte cache line, which is what x86-64 and most ARM chips use.
+pub type CacheAligned<L> = Padded<L, 64>;
+
+impl<L: MMFLock, const HEADER: usize> Padded<L, HEADER> {
+    /// Refuses to compile for headers that can't keep the alignment promise, see the struct docs.
+    const VALID: () = assert!(
+        HEADER.is_power_of_two() && HEADER <= 4096,
+        "The header has to be a power of two, and no bigger than a page"
+    );
+
+    /// Pad an existing lock. The lock should sit at the start of `HEADER` bytes nobody else uses.
+    pub fn new(inner: L) -> Self {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::VALID;
+        Self { inner }
+    }
+
+    /// Get the wrapped lock.
+    pub fn inner(&self) -> &L {
+        &self.inner
+    }
+
+    /// Get the wrapped lock back.
+    pub fn into_inner(self) -> L {
+        self.inner
+    }
+}
+
+impl<L: MMFLock, const HEADER: usize> MMFLock for Padded<L, HEADER> {
+    fn initialized(&self) -> bool {
+        self.inner.initialized()
+    }
+
+    fn readlocked(&self) -> bool {
+        self.inner.readlocked()
+    }
+
+    fn writelocked(&self) -> bool {
+        self.inner.writelocked()
+    }
+
+    fn locked(&self) -> bool {
+        self.inner.locked()
+    }
+
+    fn is_poisoned(&self) -> bool {
+        self.inner.is_poisoned()
+    }
+
+    fn generation(&self) -> Option<u32> {
+        self.inner.generation()
+    }
+
+    fn try_lock_read(&self) -> MMFResult<()> {
+        self.inner.try_lock_read()
+    }
+
+    fn unlock_read(&self) -> MMFResult<()> {
+        self.inner.unlock_read()
+    }
+
+    fn try_lock_write(&self) -> MMFResult<()> {
+        self.inner.try_lock_write()
+    }
+
+    fn unlock_write(&self) -> MMFResult<()> {
+        self.inner.unlock_write()
+    }
+
+    fn spin(&self, tries: &mut usize) -> MMFResult<bool> {
+        self.inner.spin(tries)
+    }
+
+    fn spin_budget(&self, tries: &mut usize, max: usize) -> MMFResult<bool> {
+        self.inner.spin_budget(tries, max)
+    }
+
+    fn spin_and_lock_read(lock: &Self, max_tries: usize) -> MMFResult<()> {
+        L::spin_and_lock_read(&lock.inner, max_tries)
+    }
+
+    fn spin_and_lock_write(lock: &Self, max_tries: usize) -> MMFResult<()> {
+        L::spin_and_lock_write(&lock.inner, max_tries)
+    }
+
+    fn wait_until_unlocked(&self, timeout: Duration) -> MMFResult<()> {
+        self.inner.wait_until_unlocked(timeout)
+    }
+
+    fn lock_read(&self) -> MMFResult<()> {
+        self.inner.lock_read()
+    }
+
+    fn lock_write(&self) -> MMFResult<()> {
+        self.inner.lock_write()
+    }
+
+    fn lock_read_or_spin(&self, max_tries: usize) -> MMFResult<()> {
+        self.inner.lock_read_or_spin(max_tries)
+    }
+
+    fn lock_write_or_spin(&self, max_tries: usize) -> MMFResult<()> {
+        self.inner.lock_write_or_spin(max_tries)
+    }
+
+    unsafe fn from_existing(pointer: *mut u8) -> Self {
+        Self::new(L::from_existing(pointer))
+    }
+
+    unsafe fn from_raw(pointer: *mut u8) -> Self {
+        Self::new(L::from_raw(pointer))
+    }
+
+    unsafe fn from_existing_named(pointer: *mut u8, name: &str) -> Self {
+        Self::new(L::from_existing_named(pointer, name))
+    }
+
+    unsafe fn from_raw_named(pointer: *mut u8, name: &str) -> Self {
+        Self::new(L::from_raw_named(pointer, name))
+    }
+
+    fn set_init(&self) {
+        self.inner.set_init()
+    }
+
+    fn initialize(self) -> Self {
+        Self::new(self.inner.initialize())
+    }
+
+    fn try_claim_init(&self) -> bool {
+        self.inner.try_claim_init()
+    }
+
+    fn init_or_wait(&self, timeout: Duration) -> MMFResult<bool> {
+        self.inner.init_or_wait(timeout)
+    }
+
+    fn wait_initialized(&self, timeout: Duration) -> MMFResult<()> {
+        self.inner.wait_initialized(timeout)
+    }
+
+    fn lock_size() -> usize {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::VALID;
+        HEADER.max(L::lock_size())
+    }
+}
@@ -0,0 +1,135 @@
+#![deny(clippy::missing_docs_in_private_items)]
+#![deny(missing_docs)]
+//! # `lock_api` support
+//!
+//! Code written against [`lock_api::RwLock`] gets guards, [`map`][lock_api::RwLockReadGuard::map] and everything else
+//! from that ecosystem for free, as long as there's a raw lock underneath. The [`RawRWLock`] defined here is that raw
+//! lock, backed by an [`RWLock`] in shared memory. Only available with the `lock_api` feature.
+//!
+//! Keep in mind that a [`lock_api::RwLock`] stores its data inline, in this process. To guard data in the MMF, put a
+//! pointer or slice into it (or `()`, and reach for the MMF yourself while holding a guard).
+
+use lock_api::{GuardNoSend, RawRwLock, RawRwLockDowngrade};
+
+use super::states::{MMFLock, RWLock};
+
+/// A [`lock_api::RwLock`] over an [`RWLock`] in shared memory, guarding `T` in this process.
+pub type SharedRwLock<'a, T> = lock_api::RwLock<RawRWLock<'a>, T>;
+
+/// Adapter implementing [`RawRwLock`] and [`RawRwLockDowngrade`] on top of an [`RWLock`].
+///
+/// The blocking methods wait like [`lock_read`][MMFLock::lock_read] and [`lock_write`][MMFLock::lock_write] do, and
+/// panic if the lock errors, as `lock_api` has no way to report errors. That happens for locks that aren't initialized
+/// or are poisoned, that ran out of [spins][crate::states::RWLockBuilder::max_spin_count], or for the first writer
+/// after [recovering an abandoned lock][RWLock::recover_abandoned]. Use the `try_` versions to stay clear of that.
+///
+/// `lock_api` wants a constant to build raw locks from, which is [`RawRWLock::INIT`]. It isn't attached to any lock and
+/// panics when used, so build the [`lock_api::RwLock`] from an attached one with [`lock_api::RwLock::from_raw`]:
+///
+/// ```
+/// # use winmmf::{raw_lock::*, states::*};
+/// let mut bop = vec![0u32; RWLock::SIZE / 4];
+/// let lock = unsafe { RWLock::from_raw(bop.as_mut_ptr().cast()).initialize() };
+/// let shared = SharedRwLock::from_raw(RawRWLock::new(lock), 42);
+/// {
+///     let reader = shared.read();
+///     assert!(shared.try_write().is_none());
+///     assert_eq!(*reader, 42);
+/// }
+/// *shared.write() += 1;
+/// assert_eq!(*shared.read(), 43);
+/// ```
+///
+/// ## Threads
+/// An [`RWLock`] tracks what it holds per instance, not per thread. So while a [`SharedRwLock`] is `Sync`, sharing one
+/// between threads makes them all a single holder as far as the lock is concerned. Their read locks add up against the
+/// [local reader limit][RWLock::with_local_limit], and once that's hit the blocking methods panic rather than wait.
+/// Nor can [`snapshot`][RWLock::snapshot] tell whose lock is whose. Give every thread its own instance, made with
+/// [`RWLock::try_clone`], and keep the exclusion between threads where it belongs: in shared memory.
+///
+/// The guard marker is [`GuardNoSend`]. That's not because releasing on another thread would break anything, it's the
+/// same instance doing the releasing either way. It's to nudge towards the above: a guard that can't leave its thread
+/// goes with a lock that doesn't need to either.
+#[derive(Debug)]
+pub struct RawRWLock<'a> {
+    /// The lock doing the actual work, or `None` for [`RawRWLock::INIT`].
+    inner: Option<RWLock<'a>>,
+}
+
+impl<'a> RawRWLock<'a> {
+    /// Wrap an [`RWLock`], which should be initialized already.
+    pub fn new(lock: RWLock<'a>) -> Self {
+        Self { inner: Some(lock) }
+    }
+
+    /// Get the wrapped lock, if there is one.
+    pub fn inner(&self) -> Option<&RWLock<'a>> {
+        self.inner.as_ref()
+    }
+
+    /// Get the wrapped lock, or panic if this is [`RawRWLock::INIT`].
+    fn lock(&self) -> &RWLock<'a> {
+        self.inner.as_ref().expect("RawRWLock::INIT isn't attached to a lock, use RwLock::from_raw")
+    }
+}
+
+impl<'a> From<RWLock<'a>> for RawRWLock<'a> {
+    fn from(value: RWLock<'a>) -> Self {
+        Self::new(value)
+    }
+}
+
+// Safety: the RWLock provides the exclusion, across threads and processes. Shared and exclusive locks are only ever
+// released through the instance that took them, which is all its bookkeeping needs (see the struct docs).
+unsafe impl RawRwLock for RawRWLock<'_> {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const INIT: Self = Self { inner: None };
+
+    type GuardMarker = GuardNoSend;
+
+    fn lock_shared(&self) {
+        if let Err(e) = self.lock().lock_read() {
+            panic!("Failed to take a read lock: {e}");
+        }
+    }
+
+    fn try_lock_shared(&self) -> bool {
+        self.lock().try_lock_read().is_ok()
+    }
+
+    unsafe fn unlock_shared(&self) {
+        let res = self.lock().unlock_read();
+        debug_assert!(res.is_ok(), "Released a read lock that wasn't held: {res:?}");
+    }
+
+    fn lock_exclusive(&self) {
+        if let Err(e) = self.lock().lock_write() {
+            panic!("Failed to take the write lock: {e}");
+        }
+    }
+
+    fn try_lock_exclusive(&self) -> bool {
+        self.lock().try_lock_write().is_ok()
+    }
+
+    unsafe fn unlock_exclusive(&self) {
+        let res = self.lock().unlock_write();
+        debug_assert!(res.is_ok(), "Released a write lock that wasn't held: {res:?}");
+    }
+
+    fn is_locked(&self) -> bool {
+        self.lock().locked()
+    }
+
+    fn is_locked_exclusive(&self) -> bool {
+        self.lock().writelocked()
+    }
+}
+
+// Safety: downgrading never lets go of the lock in between, see `RWLock::downgrade_write`.
+unsafe impl RawRwLockDowngrade for RawRWLock<'_> {
+    unsafe fn downgrade(&self) {
+        let res = self.lock().downgrade_write();
+        debug_assert!(res.is_ok(), "Downgraded a write lock that wasn't held: {res:?}");
+    }
+}
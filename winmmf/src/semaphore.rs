@@ -0,0 +1,165 @@
+#![deny(clippy::missing_docs_in_private_items)]
+#![deny(missing_docs)]
+//! # Counting semaphores in shared memory
+//!
+//! An [`RWLock`][crate::states::RWLock] answers "may I touch this?" with yes or no. Some resources come in a fixed
+//! amount instead, like slots in a pool or the amount of workers allowed to hammer a device at once. The [`Semaphore`]
+//! defined here counts those, and lives in an MMF the same way an `RWLock` does, so every process mapping it shares the
+//! count.
+//!
+//! There's no bookkeeping of who took what: any instance can release permits, whether it acquired them or not. The
+//! semaphore only makes sure the count never drops below zero or climbs above the maximum.
+
+use std::{
+    num::NonZeroU32,
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+use super::{
+    err::{Error, MMFResult},
+    states::SpinStrategy,
+};
+
+/// A counting semaphore living in shared memory, see [the module docs][self].
+///
+/// Takes up [`Semaphore::SIZE`] bytes: the amount of permits available, followed by the maximum. A maximum of 0 means
+/// nobody created the semaphore yet, which is how [`Semaphore::from_existing`] tells it apart from garbage. Or zeroes,
+/// anyway.
+///
+/// ## Usage
+/// ```
+/// # use winmmf::{semaphore::*, *};
+/// # use std::num::NonZeroU32;
+/// let mut bop = vec![0u32; Semaphore::SIZE / 4];
+/// let pool = unsafe { Semaphore::create(bop.as_mut_ptr().cast(), NonZeroU32::new(3).unwrap()) };
+/// pool.try_acquire(2).unwrap();
+/// assert!(matches!(pool.try_acquire(2), Err(Error::Exhausted)));
+/// pool.release(2).unwrap();
+/// assert_eq!(pool.available(), 3);
+/// ```
+#[derive(Debug)]
+pub struct Semaphore<'a> {
+    /// The amount of permits nobody acquired yet.
+    count: &'a AtomicU32,
+    /// The amount of permits there are in total, 0 if the semaphore isn't initialized.
+    max: &'a AtomicU32,
+    /// What to do in between attempts in [`Semaphore::acquire`].
+    strategy: SpinStrategy,
+}
+
+impl Semaphore<'_> {
+    /// The amount of bytes claimed by the semaphore: the available permits and the maximum.
+    pub const SIZE: usize = 8;
+
+    /// Build the semaphore from a pointer, without touching the data behind it.
+    ///
+    /// # Safety
+    /// The pointer must be non-null, aligned to 4 and valid for [`Self::SIZE`] bytes for as long as the semaphore
+    /// lives.
+    unsafe fn from_ptr<'a>(pointer: *mut u8) -> Semaphore<'a> {
+        let words = pointer.cast::<u32>();
+        Semaphore {
+            count: AtomicU32::from_ptr(words),
+            max: AtomicU32::from_ptr(words.add(1)),
+            strategy: SpinStrategy::Busy,
+        }
+    }
+
+    /// Create a fresh semaphore at `pointer`, with all `max` permits available.
+    ///
+    /// # Safety
+    /// The pointer must be non-null, aligned to 4 and valid for [`Self::SIZE`] bytes for as long as the semaphore
+    /// lives.
+    /// Whatever was there before is overwritten, including a semaphore others are using.
+    pub unsafe fn create<'a>(pointer: *mut u8, max: NonZeroU32) -> Semaphore<'a> {
+        let semaphore = Self::from_ptr(pointer);
+        // The count goes first, so anyone who sees the maximum also sees the count.
+        semaphore.count.store(max.get(), Ordering::Release);
+        semaphore.max.store(max.get(), Ordering::Release);
+        semaphore
+    }
+
+    /// Attach to a semaphore somebody else [created][Semaphore::create] at `pointer`.
+    ///
+    /// Errors with [`Error::MisalignedLock`] if the pointer isn't aligned to 4 bytes, and with [`Error::Uninitialized`]
+    /// if there's no semaphore there yet.
+    ///
+    /// # Safety
+    /// The pointer must be non-null and valid for [`Self::SIZE`] bytes for as long as the semaphore lives.
+    pub unsafe fn from_existing<'a>(pointer: *mut u8) -> MMFResult<Semaphore<'a>> {
+        if pointer as usize % 4 != 0 {
+            return Err(Error::MisalignedLock);
+        }
+        let semaphore = Self::from_ptr(pointer);
+        if semaphore.max.load(Ordering::Acquire) == 0 {
+            return Err(Error::Uninitialized);
+        }
+        Ok(semaphore)
+    }
+
+    /// Chainable setter for the [`SpinStrategy`] used by [`Semaphore::acquire`]. Only affects this instance.
+    pub fn with_spin_strategy(mut self, strategy: SpinStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Get the [`SpinStrategy`] this instance uses.
+    pub fn spin_strategy(&self) -> SpinStrategy {
+        self.strategy
+    }
+
+    /// Get the amount of permits there are in total.
+    pub fn max(&self) -> u32 {
+        self.max.load(Ordering::Acquire)
+    }
+
+    /// Get the amount of permits available right now. Only a snapshot, somebody might take or return some right after.
+    pub fn available(&self) -> u32 {
+        self.count.load(Ordering::Acquire)
+    }
+
+    /// Take `n` permits if they're available right now, or error with [`Error::Exhausted`] if they aren't.
+    ///
+    /// Taking 0 permits always works. Errors with [`Error::PermitsExceeded`] if `n` is more than the
+    /// [maximum][Semaphore::max], as that's never going to work out.
+    pub fn try_acquire(&self, n: u32) -> MMFResult<()> {
+        if n > self.max() {
+            return Err(Error::PermitsExceeded);
+        }
+        self.count
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |count| count.checked_sub(n))
+            .map(|_| ())
+            .map_err(|_| Error::Exhausted)
+    }
+
+    /// Take `n` permits, spinning until they're available according to the [`SpinStrategy`].
+    ///
+    /// There's no limit on how long this spins, so make sure whoever holds the permits gives them back eventually. All
+    /// `n` permits are taken at once, which means somebody asking for fewer can get in first. Asking for a lot while
+    /// others keep taking a few at a time can take a while. Errors like [`Semaphore::try_acquire`], minus the
+    /// [`Error::Exhausted`].
+    pub fn acquire(&self, n: u32) -> MMFResult<()> {
+        let mut tries = 0;
+        loop {
+            match self.try_acquire(n) {
+                Err(Error::Exhausted) => {
+                    tries += 1;
+                    self.strategy.pause(tries);
+                }
+                res => return res,
+            }
+        }
+    }
+
+    /// Give `n` permits back.
+    ///
+    /// Errors with [`Error::PermitsExceeded`] if that would make more permits available than the
+    /// [maximum][Semaphore::max], in which case nothing is given back. Somebody released permits they never took.
+    pub fn release(&self, n: u32) -> MMFResult<()> {
+        let max = self.max();
+        self.count
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |count| count.checked_add(n).filter(|c| *c <= max))
+            .map(|_| ())
+            .map_err(|_| Error::PermitsExceeded)
+    }
+}
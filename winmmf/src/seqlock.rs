@@ -0,0 +1,186 @@
+#![deny(clippy::missing_docs_in_private_items)]
+#![deny(missing_docs)]
+//! # Sequence locks for data that's read a lot more than it's written
+//!
+//! An [`RWLock`][crate::states::RWLock] makes every reader write to the lock word, and when there are a lot of them
+//! that cache line spends more time bouncing between cores than anything else. The [`SeqLock`] defined here doesn't
+//! let readers write anything at all. The writer bumps a sequence number before and after it writes, and readers just
+//! read: if the sequence was odd (somebody was writing) or changed while they were reading, they try again.
+//!
+//! That makes readers very cheap and writers never wait on readers, at the cost of readers doing their work twice every
+//! now and then. It works best for small data that's published often and read by many, like sensor readings.
+
+use std::{
+    marker::PhantomData,
+    slice,
+    sync::atomic::{fence, AtomicU32, Ordering},
+};
+
+use super::{
+    err::{Error, MMFResult},
+    states::SpinStrategy,
+};
+
+/// How often readers and writers try before giving up, unless [configured otherwise][SeqLock::with_max_tries].
+pub const DEFAULT_MAX_TRIES: usize = 1 << 16;
+
+/// A sequence lock over a region of shared memory, see [the module docs][self].
+///
+/// The sequence number takes up the first 4 bytes of [`SeqLock::HEADER_SIZE`], and the data follows right behind. A
+/// fresh MMF is all zeroes, which is a perfectly fine sequence lock with nobody writing, so there's nothing to
+/// initialize.
+///
+/// Readers copy the data out while a writer might be changing it, and throw the copy away if that happened. Only a
+/// copy that made it through untouched is handed over, so readers never get to see a torn write.
+///
+/// ## Usage
+/// ```
+/// # use winmmf::seqlock::*;
+/// let mut bop = vec![0u32; (SeqLock::HEADER_SIZE + 8) / 4];
+/// let lock = unsafe { SeqLock::new(bop.as_mut_ptr().cast(), 8) };
+/// lock.write_seqlock(|data| data.copy_from_slice(&42u64.to_le_bytes())).unwrap();
+/// let reading = lock.read_seqlock(|data| u64::from_le_bytes(data.try_into().unwrap()));
+/// assert_eq!(reading, Some(42));
+/// ```
+#[derive(Debug)]
+pub struct SeqLock<'a> {
+    /// The sequence number, odd while somebody is writing.
+    sequence: &'a AtomicU32,
+    /// The start of the data.
+    data: *mut u8,
+    /// The amount of data bytes.
+    len: usize,
+    /// How often to try before giving up.
+    max_tries: usize,
+    /// What to do in between tries.
+    strategy: SpinStrategy,
+    /// The data is borrowed for as long as the sequence number is.
+    _data: PhantomData<&'a mut [u8]>,
+}
+
+impl SeqLock<'_> {
+    /// The amount of bytes in front of the data: the sequence number, padded to keep the data aligned to 16 bytes.
+    pub const HEADER_SIZE: usize = 16;
+
+    /// Attach to the sequence lock at `pointer`, guarding `len` bytes of data behind the header.
+    ///
+    /// # Safety
+    /// The pointer must be non-null, aligned to 4 and valid for [`Self::HEADER_SIZE`] + `len` bytes for as long as the
+    /// lock lives. The bytes must be zeroed or belong to a sequence lock already, and everyone writing to the data must
+    /// go through a sequence lock.
+    pub unsafe fn new<'a>(pointer: *mut u8, len: usize) -> SeqLock<'a> {
+        SeqLock {
+            sequence: AtomicU32::from_ptr(pointer.cast()),
+            data: pointer.add(Self::HEADER_SIZE),
+            len,
+            max_tries: DEFAULT_MAX_TRIES,
+            strategy: SpinStrategy::Busy,
+            _data: PhantomData,
+        }
+    }
+
+    /// Chainable setter for how often reads and writes try before giving up, [`DEFAULT_MAX_TRIES`] by default. Only
+    /// affects this instance.
+    pub fn with_max_tries(mut self, max_tries: usize) -> Self {
+        self.max_tries = max_tries.max(1);
+        self
+    }
+
+    /// Chainable setter for the [`SpinStrategy`] used in between tries. Only affects this instance.
+    pub fn with_spin_strategy(mut self, strategy: SpinStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Get the amount of data bytes.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Check if there's no data at all, which makes for a rather boring lock.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Get the current sequence number. It goes up by two for every write, and is odd while a write is going on.
+    pub fn sequence(&self) -> u32 {
+        self.sequence.load(Ordering::Acquire)
+    }
+
+    /// Copy the data out until the copy was made without a writer changing it along the way, then run `f` on that
+    /// copy and return what it returns.
+    ///
+    /// Returns [`None`] without calling `f` if that didn't work out within the
+    /// [maximum amount of tries][SeqLock::with_max_tries]. That only happens if writers keep at it nonstop, or one of
+    /// them died halfway through a write and the sequence number is stuck at odd.
+    pub fn read_seqlock<T>(&self, f: impl FnOnce(&[u8]) -> T) -> Option<T> {
+        let mut copy = vec![0; self.len];
+        for tries in 1..=self.max_tries {
+            let before = self.sequence.load(Ordering::Acquire);
+            if before % 2 == 0 {
+                self.copy_out(&mut copy);
+                // Keeps the reads of the data from moving past the second look at the sequence number.
+                fence(Ordering::Acquire);
+                if self.sequence.load(Ordering::Relaxed) == before {
+                    return Some(f(&copy));
+                }
+            }
+            self.strategy.pause(tries);
+        }
+        None
+    }
+
+    /// Copy the data into `copy`, which has to be exactly [`len`][SeqLock::len] bytes.
+    ///
+    /// A writer may be changing the data while this runs, so it never makes a reference to it. Every byte is read
+    /// volatile instead, straight from the pointer, and whatever came out is only trusted once the sequence number
+    /// says nobody was writing.
+    fn copy_out(&self, copy: &mut [u8]) {
+        for (at, byte) in copy.iter_mut().enumerate() {
+            // Safety: the region is valid for `len` bytes, which is how long the copy is.
+            *byte = unsafe { self.data.add(at).read_volatile() };
+        }
+    }
+
+    /// Let `f` change the data, bumping the sequence number before and after so readers know.
+    ///
+    /// Writers take turns: the first bump only happens when nobody else is writing, which is checked and done in one
+    /// go. Errors with [`Error::Timeout`] if somebody else kept writing for the
+    /// [maximum amount of tries][SeqLock::with_max_tries]. If `f` panics the sequence number stays odd, and neither
+    /// readers nor writers get anywhere until somebody recreates the lock.
+    pub fn write_seqlock<T>(&self, f: impl FnOnce(&mut [u8]) -> T) -> MMFResult<T> {
+        let mut sequence = self.sequence.load(Ordering::Relaxed);
+        let mut tries = 0;
+        loop {
+            if sequence % 2 == 0 {
+                match self.sequence.compare_exchange_weak(
+                    sequence,
+                    sequence.wrapping_add(1),
+                    Ordering::Acquire,
+                    Ordering::Relaxed,
+                ) {
+                    Ok(_) => break,
+                    Err(current) => sequence = current,
+                }
+                continue;
+            }
+            tries += 1;
+            if tries >= self.max_tries {
+                return Err(Error::Timeout);
+            }
+            self.strategy.pause(tries);
+            sequence = self.sequence.load(Ordering::Relaxed);
+        }
+        // Keeps the writes to the data from moving in front of the odd sequence number.
+        fence(Ordering::Release);
+        // Safety: the region is valid for `len` bytes, and the odd sequence number keeps other writers out.
+        let res = f(unsafe { slice::from_raw_parts_mut(self.data, self.len) });
+        self.sequence.store(sequence.wrapping_add(2), Ordering::Release);
+        Ok(res)
+    }
+}
+
+// Safety: the sequence number is only ever accessed atomically, and the data only by one writer at a time. Readers
+// racing the writer is the whole point, and they throw away whatever they read while that happened.
+unsafe impl Send for SeqLock<'_> {}
+unsafe impl Sync for SeqLock<'_> {}
@@ -20,12 +20,48 @@
 //! any damages, be they to your PC or your (mental) health.
 
 use core::fmt;
+#[cfg(feature = "tracing")]
+use fixedstr::ztr64;
+#[cfg(feature = "metrics")]
+use std::sync::atomic::AtomicU64;
+#[cfg(feature = "lock_hooks")]
+use std::sync::Arc;
+#[cfg(feature = "impl_lock")]
+use std::sync::{
+    atomic::{fence, AtomicU32, AtomicU8, Ordering},
+    OnceLock,
+};
 use std::{
+    collections::hash_map::RandomState,
+    hash::BuildHasher,
     ops::AddAssign,
-    sync::atomic::{fence, AtomicU32, AtomicU8, Ordering},
+    task::Poll,
+    thread,
+    time::{Duration, Instant},
+};
+#[cfg(feature = "wake_by_address")]
+use windows::Win32::System::Threading::{WaitOnAddress, WakeByAddressAll};
+#[cfg(feature = "impl_lock")]
+use windows::Win32::{
+    Foundation::{CloseHandle, FILETIME, HANDLE, STILL_ACTIVE},
+    System::Threading::{
+        GetCurrentProcess, GetCurrentProcessId, GetExitCodeProcess, GetProcessTimes, OpenProcess,
+        PROCESS_QUERY_LIMITED_INFORMATION,
+    },
 };
 
 use super::err::{Error, MMFResult};
+#[cfg(feature = "lock_hooks")]
+use super::hooks::{HookClock, LockHooks};
+
+/// How many tries spinning on an [`RWLock`] takes before it shows up in traces, with the `tracing` feature. After
+/// that, it's traced again every time the amount of tries doubles.
+#[cfg(all(feature = "impl_lock", feature = "tracing"))]
+pub const TRACE_SPINS: usize = 1 << 10;
+
+/// How many tries a single call to [`poll_lock_read`][MMFLock::poll_lock_read] or
+/// [`poll_lock_write`][MMFLock::poll_lock_write] spins for at most, before handing control back.
+pub const POLL_TRIES: usize = 16;
 
 /// Blanket trait for implementing locks to be used with MMFs.
 ///
@@ -35,6 +71,10 @@ use super::err::{Error, MMFResult};
 /// form of synchronizing access accross thread and application boundaries.
 ///
 /// Users are free to use the default lock and MMF implementations independently of one another.
+///
+/// The methods taking, releasing or creating a lock are `#[track_caller]`, and so are their implementations. Should one
+/// of them panic, the message points at the line that called it instead of somewhere in here. Implementations that
+/// call into helpers of their own have to mark those as well for that to hold.
 pub trait MMFLock {
     /// Check if the data this lock is has been initialized for use
     fn initialized(&self) -> bool;
@@ -46,28 +86,222 @@ pub trait MMFLock {
     fn writelocked(&self) -> bool;
     /// Checks if there are any acitve locks, including the initialization locks.
     fn locked(&self) -> bool;
-    /// Acquire a readlock, if at all possible. Otherwise error.
-    fn lock_read(&self) -> MMFResult<()>;
+    /// Checks if the lock is in the impossible state of being both read- and writelocked.
+    ///
+    /// The default implementation checks [`readlocked`][MMFLock::readlocked] and
+    /// [`writelocked`][MMFLock::writelocked], implementations with more insight into their state should override it.
+    fn is_poisoned(&self) -> bool {
+        self.writelocked() && self.readlocked()
+    }
+    /// Get the write generation: a counter bumped every time the write lock is released, so readers can tell whether
+    /// anything was written since they last looked without taking a lock. Wraps around after `u32::MAX` writes.
+    ///
+    /// Locks that don't count writes return [`None`], which is the default.
+    fn generation(&self) -> Option<u32> {
+        None
+    }
+    /// Acquire a readlock, if at all possible right now. Otherwise error.
+    #[track_caller]
+    fn try_lock_read(&self) -> MMFResult<()>;
     /// Release a readlock, clearing the readlock state if this was the last lock.
+    #[track_caller]
     fn unlock_read(&self) -> MMFResult<()>;
-    /// Lock this file for writing if possible.
-    fn lock_write(&self) -> MMFResult<()>;
+    /// Lock this file for writing if possible right now. Otherwise error.
+    #[track_caller]
+    fn try_lock_write(&self) -> MMFResult<()>;
     /// Nuke all existing write locks as there can only be one, legally.
+    #[track_caller]
     fn unlock_write(&self) -> MMFResult<()>;
     /// Spin and return true while the lock is held
     fn spin(&self, tries: &mut usize) -> MMFResult<bool>;
+    /// Like [`spin`][MMFLock::spin], except the caller picks how many tries there are. Errors with [`Error::Timeout`]
+    /// once `tries` reaches `max` and the lock is still held, so callers don't rely on the lock's own limit (which
+    /// defaults to never) to get out of a loop.
+    ///
+    /// ```
+    /// # use winmmf::{states::*, err::Error};
+    /// let mut bop = vec![0u32; RWLock::SIZE / 4];
+    /// let lock = unsafe { RWLock::from_raw(bop.as_mut_ptr().cast()).initialize() };
+    /// let other = unsafe { RWLock::from_existing(bop.as_mut_ptr().cast()) };
+    /// lock.try_lock_write().unwrap();
+    /// let mut tries = 0;
+    /// let res = loop {
+    ///     match other.spin_budget(&mut tries, 3) {
+    ///         Ok(true) => continue,
+    ///         done => break done,
+    ///     }
+    /// };
+    /// assert!(matches!(res, Err(Error::Timeout)));
+    /// assert_eq!(tries, 3);
+    /// ```
+    fn spin_budget(&self, tries: &mut usize, max: usize) -> MMFResult<bool> {
+        match self.spin(tries)? {
+            true if *tries >= max => Err(Error::Timeout),
+            held => Ok(held),
+        }
+    }
     /// Spin until the lock can be taken, then take it.
+    #[track_caller]
     fn spin_and_lock_read(lock: &Self, max_tries: usize) -> MMFResult<()>
     where
         Self: Sized;
     /// Spin until the lock can be taken, then take it.
+    #[track_caller]
     fn spin_and_lock_write(lock: &Self, max_tries: usize) -> MMFResult<()>
     where
         Self: Sized;
+    /// Wait until nobody holds the lock anymore, or error with [`Error::Timeout`] once `timeout` runs out.
+    ///
+    /// Rather than spinning hot, this yields to the scheduler for the first few checks and then sleeps in between
+    /// them, with the sleeps growing up to about a millisecond. Locks held by this very instance count as well, so
+    /// don't wait on yourself.
+    fn wait_until_unlocked(&self, timeout: Duration) -> MMFResult<()> {
+        let started = Instant::now();
+        let mut checks = 0u32;
+        while self.locked() {
+            if started.elapsed() >= timeout {
+                return Err(Error::Timeout);
+            }
+            match checks.checked_sub(16) {
+                None => thread::yield_now(),
+                Some(slept) => thread::sleep(Duration::from_micros(1 << slept.min(10))),
+            }
+            checks += 1;
+        }
+        Ok(())
+    }
+    /// Acquire a readlock, waiting for it as long as it takes.
+    ///
+    /// The default implementation keeps calling [`try_lock_read`][MMFLock::try_lock_read], letting
+    /// [`spin`][MMFLock::spin] do the waiting in between. It only gives up on errors that won't go away by waiting, or
+    /// when `spin` itself errors (like an [`RWLock`] running into its spin limit). Locks with better ways to wait
+    /// should override this. Waiting for a lock this very instance holds never ends well, so don't.
+    ///
+    /// [`MemoryMappedFile`][crate::mmf::MemoryMappedFile] doesn't call this, as it has a timeout to stick to and this
+    /// has no way of being told about it. It uses [`try_lock_read`][MMFLock::try_lock_read] and
+    /// [`wait_until_unlocked`][MMFLock::wait_until_unlocked] instead, so override that one as well.
+    #[track_caller]
+    fn lock_read(&self) -> MMFResult<()> {
+        self.lock_read_or_spin(usize::MAX)
+    }
+    /// Lock this file for writing, waiting for it as long as it takes.
+    ///
+    /// See [`lock_read`][MMFLock::lock_read], this behaves the same way.
+    #[track_caller]
+    fn lock_write(&self) -> MMFResult<()> {
+        self.lock_write_or_spin(usize::MAX)
+    }
+    /// Take a read lock, spinning in between attempts for at most `max_tries` times.
+    ///
+    /// Errors that won't go away by spinning (see [`Error::is_retryable`]) and [`Error::Abandoned`] are returned as
+    /// is, running out of tries results in [`Error::Timeout`].
+    #[track_caller]
+    fn lock_read_or_spin(&self, max_tries: usize) -> MMFResult<()> {
+        let mut tries = 0;
+        loop {
+            match self.try_lock_read() {
+                Ok(()) => return Ok(()),
+                Err(e) if !e.worth_waiting() => return Err(e),
+                Err(_) if tries >= max_tries => return Err(Error::Timeout),
+                Err(_) => _ = self.spin_budget(&mut tries, max_tries)?,
+            }
+        }
+    }
+    /// Take the write lock, spinning in between attempts for at most `max_tries` times.
+    ///
+    /// See [`lock_read_or_spin`][MMFLock::lock_read_or_spin] for the errors.
+    #[track_caller]
+    fn lock_write_or_spin(&self, max_tries: usize) -> MMFResult<()> {
+        let mut tries = 0;
+        loop {
+            match self.try_lock_write() {
+                Ok(()) => return Ok(()),
+                Err(e) if !e.worth_waiting() => return Err(e),
+                Err(_) if tries >= max_tries => return Err(Error::Timeout),
+                Err(_) => _ = self.spin_budget(&mut tries, max_tries)?,
+            }
+        }
+    }
+    /// Take a read lock without waiting around for it, for loops that can't block: a game's frame loop, or an event
+    /// loop of your own.
+    ///
+    /// Spins for at most [`POLL_TRIES`] tries, like [`lock_read_or_spin`][MMFLock::lock_read_or_spin] does, and returns
+    /// [`Poll::Pending`] if the lock is still held after that. Call it again next time around with the same `tries`,
+    /// it keeps counting across calls so the lock's own spin limit still applies. Returns [`Poll::Ready`] once the
+    /// lock is taken. Errors like `lock_read_or_spin`, except running out of tries is just pending.
+    ///
+    /// The lock's own way of spinning is used in between tries, so pick one that doesn't sleep if every frame counts.
+    ///
+    /// ## Usage
+    /// ```
+    /// # use std::task::Poll;
+    /// # use winmmf::states::*;
+    /// let mut bop = vec![0u32; RWLock::SIZE / 4];
+    /// let lock = unsafe { RWLock::from_raw(bop.as_mut_ptr().cast()).initialize() };
+    /// let mut tries = 0;
+    /// // Once per frame
+    /// if let Poll::Ready(()) = lock.poll_lock_read(&mut tries).unwrap() {
+    ///     // Read away, and start counting from 0 for the next lock
+    ///     lock.unlock_read().unwrap();
+    ///     tries = 0;
+    /// }
+    /// ```
+    #[track_caller]
+    fn poll_lock_read(&self, tries: &mut usize) -> MMFResult<Poll<()>> {
+        poll_with(self, tries, Self::try_lock_read)
+    }
+    /// Take the write lock without waiting around for it.
+    ///
+    /// See [`poll_lock_read`][MMFLock::poll_lock_read], this behaves the same way.
+    #[track_caller]
+    fn poll_lock_write(&self, tries: &mut usize) -> MMFResult<Poll<()>> {
+        poll_with(self, tries, Self::try_lock_write)
+    }
+    /// Take a read lock like [`lock_read`][MMFLock::lock_read], run `f` and release the lock again. Returns whatever
+    /// `f` returned.
+    ///
+    /// If taking the lock fails, `f` isn't run and the error is returned. The lock is released even if `f` panics, so
+    /// there's no way to forget about it. Errors from releasing the lock are returned as well, throwing away what `f`
+    /// returned, except when unwinding from a panic where there's nowhere to return them to.
+    ///
+    /// ## Usage
+    /// ```
+    /// # use winmmf::states::*;
+    /// let mut bop = vec![0u32; RWLock::SIZE / 4];
+    /// let lock = unsafe { RWLock::from_raw(bop.as_mut_ptr().cast()).initialize() };
+    /// let readers = lock.with_read(|| lock.snapshot().reader_count).unwrap();
+    /// assert_eq!(readers, 1);
+    /// assert!(!lock.locked());
+    /// ```
+    #[track_caller]
+    fn with_read<R>(&self, f: impl FnOnce() -> R) -> MMFResult<R>
+    where
+        Self: Sized,
+    {
+        self.lock_read()?;
+        let guard = UnlockGuard { lock: self, unlock: Self::unlock_read };
+        let res = f();
+        guard.release().map(|_| res)
+    }
+    /// Take the write lock like [`lock_write`][MMFLock::lock_write], run `f` and release the lock again. Returns
+    /// whatever `f` returned.
+    ///
+    /// See [`with_read`][MMFLock::with_read], this behaves the same way.
+    #[track_caller]
+    fn with_write<R>(&self, f: impl FnOnce() -> R) -> MMFResult<R>
+    where
+        Self: Sized,
+    {
+        self.lock_write()?;
+        let guard = UnlockGuard { lock: self, unlock: Self::unlock_write };
+        let res = f();
+        guard.release().map(|_| res)
+    }
     /// Create a new lock at the location of an existing pointer.
     ///
     /// # Safety
     /// Only call this to a pointer where the underlying data is from the same trait impl.
+    #[track_caller]
     unsafe fn from_existing(pointer: *mut u8) -> Self
     where
         Self: Sized;
@@ -75,15 +309,135 @@ pub trait MMFLock {
     ///
     /// # Safety
     /// Only pass this a pointer with enough space to hold the lock.
+    #[track_caller]
     unsafe fn from_raw(pointer: *mut u8) -> Self
     where
         Self: Sized;
-    /// Set the lock's first byte to an initialized state.
+    /// Create a new lock at the location of an existing pointer, for an MMF with the given name.
+    ///
+    /// Locks that need more than just a pointer (like named kernel objects) can use the name of the MMF to derive
+    /// whatever they need. Defaults to [`from_existing`][MMFLock::from_existing].
+    ///
+    /// # Safety
+    /// See [`from_existing`][MMFLock::from_existing].
+    #[track_caller]
+    unsafe fn from_existing_named(pointer: *mut u8, _name: &str) -> Self
+    where
+        Self: Sized,
+    {
+        Self::from_existing(pointer)
+    }
+    /// Create a new lock from a raw pointer, for an MMF with the given name.
+    ///
+    /// Locks that need more than just a pointer (like named kernel objects) can use the name of the MMF to derive
+    /// whatever they need. Defaults to [`from_raw`][MMFLock::from_raw].
+    ///
+    /// # Safety
+    /// See [`from_raw`][MMFLock::from_raw].
+    #[track_caller]
+    unsafe fn from_raw_named(pointer: *mut u8, _name: &str) -> Self
+    where
+        Self: Sized,
+    {
+        Self::from_raw(pointer)
+    }
+    /// Mark the lock as initialized.
     fn set_init(&self);
     /// Self-consuming wrapper to chain initialization with [`set_init`][`MMFLock::set_init`]
     fn initialize(self) -> Self
     where
         Self: Sized;
+    /// Claim the right to initialize the lock. Returns whether this instance won, and should call
+    /// [`set_init`][MMFLock::set_init] when done setting things up.
+    ///
+    /// The default implementation can't tell anybody else it's busy initializing, and just checks if the lock isn't
+    /// [initialized][MMFLock::initialized] yet.
+    fn try_claim_init(&self) -> bool {
+        !self.initialized()
+    }
+    /// Initialize the lock if nobody else is, or wait for whoever is to finish. Returns whether this instance did the
+    /// initializing, or [`Error::Uninitialized`] if the other party took longer than `timeout`.
+    ///
+    /// Use this rather than [`set_init`][MMFLock::set_init] when more than one process might be setting up the lock.
+    fn init_or_wait(&self, timeout: Duration) -> MMFResult<bool> {
+        if self.try_claim_init() {
+            self.set_init();
+            return Ok(true);
+        }
+        self.wait_initialized(timeout).map(|_| false)
+    }
+    /// Wait for whoever is initializing the lock to finish, without offering to do it ourselves. Errors with
+    /// [`Error::Uninitialized`] if it took longer than `timeout`.
+    ///
+    /// Meant for openers that can't or shouldn't write to the lock, like read-only views. Anything the initializing
+    /// party wrote before [`set_init`][MMFLock::set_init] is visible once this returns.
+    fn wait_initialized(&self, timeout: Duration) -> MMFResult<()> {
+        let started = Instant::now();
+        while !self.initialized() {
+            if started.elapsed() >= timeout {
+                return Err(Error::Uninitialized);
+            }
+            thread::yield_now();
+        }
+        Ok(())
+    }
+    /// The amount of bytes this lock claims behind the pointer it's created from.
+    ///
+    /// MMF implementations should reserve this many bytes before the data region. Defaults to the size of a single
+    /// `u32`, which is all a lock needs if it doesn't track anything beyond its own state.
+    fn lock_size() -> usize
+    where
+        Self: Sized,
+    {
+        4
+    }
+}
+
+/// Make at most [`POLL_TRIES`] more `attempt`s to take `lock`, spinning in between, for
+/// [`poll_lock_read`][MMFLock::poll_lock_read] and [`poll_lock_write`][MMFLock::poll_lock_write].
+#[track_caller]
+fn poll_with<L: MMFLock + ?Sized>(
+    lock: &L,
+    tries: &mut usize,
+    attempt: impl Fn(&L) -> MMFResult<()>,
+) -> MMFResult<Poll<()>> {
+    let limit = tries.saturating_add(POLL_TRIES);
+    loop {
+        match attempt(lock) {
+            Ok(()) => return Ok(Poll::Ready(())),
+            Err(e) if !e.worth_waiting() => return Err(e),
+            Err(_) if *tries >= limit => return Ok(Poll::Pending),
+            // The budget of this call ran out, not the lock's own. That one errors with something else.
+            Err(_) => match lock.spin_budget(tries, limit) {
+                Err(Error::Timeout) => return Ok(Poll::Pending),
+                res => _ = res?,
+            },
+        }
+    }
+}
+
+/// Releases a lock when dropped, for [`MMFLock::with_read`] and [`MMFLock::with_write`] to clean up after a panic.
+pub(crate) struct UnlockGuard<'a, L: MMFLock> {
+    /// The lock to release.
+    pub(crate) lock: &'a L,
+    /// How to release it.
+    pub(crate) unlock: fn(&L) -> MMFResult<()>,
+}
+
+impl<L: MMFLock> UnlockGuard<'_, L> {
+    /// Release the lock now, and tell the caller how that went.
+    pub(crate) fn release(self) -> MMFResult<()> {
+        let res = (self.unlock)(self.lock);
+        std::mem::forget(self);
+        res
+    }
+}
+
+impl<L: MMFLock> Drop for UnlockGuard<'_, L> {
+    /// Only reached when unwinding, there's nobody to report errors to.
+    fn drop(&mut self) {
+        _ = (self.unlock)(self.lock);
+    }
 }
 
 impl fmt::Debug for dyn MMFLock {
@@ -101,6 +455,153 @@ impl fmt::Debug for dyn MMFLock {
     }
 }
 
+/// A lock that doesn't lock.
+///
+/// For when the MMF is only ever touched from one thread, or when access is synchronized through some other means
+/// (like a named mutex). Every operation succeeds, the lock always claims to be initialized and never claims to be
+/// held. It also doesn't take up any space, so an MMF using it starts its data region at the very first byte of the
+/// view. That also means that MMFs using this lock are **not** compatible with MMFs using any other lock.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct NoLock;
+
+impl MMFLock for NoLock {
+    /// Always initialized, there's nothing to initialize.
+    fn initialized(&self) -> bool {
+        true
+    }
+    /// Never locked.
+    fn readlocked(&self) -> bool {
+        false
+    }
+    /// Never locked.
+    fn writelocked(&self) -> bool {
+        false
+    }
+    /// Never locked.
+    fn locked(&self) -> bool {
+        false
+    }
+    /// Never poisoned either.
+    fn is_poisoned(&self) -> bool {
+        false
+    }
+    /// No-op.
+    fn try_lock_read(&self) -> MMFResult<()> {
+        Ok(())
+    }
+    /// No-op.
+    fn unlock_read(&self) -> MMFResult<()> {
+        Ok(())
+    }
+    /// No-op.
+    fn try_lock_write(&self) -> MMFResult<()> {
+        Ok(())
+    }
+    /// No-op.
+    fn unlock_write(&self) -> MMFResult<()> {
+        Ok(())
+    }
+    /// Nothing to spin on, this is never held.
+    fn spin(&self, tries: &mut usize) -> MMFResult<bool> {
+        tries.add_assign(1);
+        Ok(false)
+    }
+    /// No-op.
+    fn spin_and_lock_read(_lock: &Self, _max_tries: usize) -> MMFResult<()> {
+        Ok(())
+    }
+    /// No-op.
+    fn spin_and_lock_write(_lock: &Self, _max_tries: usize) -> MMFResult<()> {
+        Ok(())
+    }
+    /// The pointer is ignored, so this is safe to call with anything.
+    unsafe fn from_existing(_pointer: *mut u8) -> Self {
+        Self
+    }
+    /// The pointer is ignored, so this is safe to call with anything.
+    unsafe fn from_raw(_pointer: *mut u8) -> Self {
+        Self
+    }
+    /// No-op.
+    fn set_init(&self) {}
+    /// No-op.
+    fn initialize(self) -> Self {
+        self
+    }
+    /// Doesn't need any space in the MMF.
+    fn lock_size() -> usize {
+        0
+    }
+}
+
+/// How strict the default lock implementation should be about memory ordering.
+///
+/// Every profile keeps the lock _correct_ in the sense that acquiring a lock is an acquire operation and releasing it
+/// is a release operation. What changes is the amount of extra synchronization sprinkled on top:
+///
+/// - [`Strict`][OrderingProfile::Strict] makes every atomic operation sequentially consistent, and every fence too.
+///   Slowest option, but it's the easiest to reason about if you're also doing other atomic things with the MMF.
+/// - [`Default`][OrderingProfile::Default] uses acquire/release semantics and surrounds every operation with explicit
+///   `AcqRel` fences. This is what the lock has always done.
+/// - [`Relaxed`][OrderingProfile::Relaxed] uses the same acquire/release semantics, but skips the explicit fences.
+///   These are redundant for the lock itself, as the read-modify-write operations already carry the required ordering.
+///   The catch is that data you access _outside_ of the lock's protection (i.e. without holding it) is no longer
+///   guaranteed to be ordered with respect to the lock state checks. If you only touch the data while holding a lock,
+///   this is safe to use.
+#[cfg(feature = "impl_lock")]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum OrderingProfile {
+    /// Sequential consistency for everything.
+    Strict,
+    /// Acquire/release semantics with explicit fences around every operation.
+    #[default]
+    Default,
+    /// Acquire/release semantics without the redundant fences.
+    Relaxed,
+}
+
+/// What to do in between attempts when spinning on a lock.
+///
+/// Spinning in a hot loop is fine when the lock is only ever held for a few instructions, but when many threads or
+/// processes wait on the same lock they'll all be hammering the same cache line. The other strategies trade some
+/// latency for being less of a nuisance to everyone else.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SpinStrategy {
+    /// Retry immediately, with only a spin loop hint to the CPU.
+    #[default]
+    Busy,
+    /// Yield the rest of the time slice to the OS scheduler before retrying.
+    Yield,
+    /// Spin `busy` times, then sleep for a random duration between zero and `base_ns * 2^n` nanoseconds, capped at
+    /// `cap_ns`. The randomness (jitter) prevents waiters from all waking up at the same time.
+    ExponentialBackoff {
+        /// Base duration of the backoff, in nanoseconds.
+        base_ns: u64,
+        /// Upper bound for a single backoff, in nanoseconds.
+        cap_ns: u64,
+        /// Amount of tries to busy spin before backing off.
+        busy: usize,
+    },
+}
+
+impl SpinStrategy {
+    /// Wait in between attempts, according to the strategy. `tries` is the amount of attempts made so far.
+    pub fn pause(&self, tries: usize) {
+        match *self {
+            Self::Busy => std::hint::spin_loop(),
+            Self::Yield => thread::yield_now(),
+            Self::ExponentialBackoff { busy, .. } if tries <= busy => std::hint::spin_loop(),
+            Self::ExponentialBackoff { base_ns, cap_ns, busy } => {
+                let exp = (tries - busy).min(63) as u32;
+                let ceiling = base_ns.saturating_mul(1u64.checked_shl(exp).unwrap_or(u64::MAX)).min(cap_ns);
+                // RandomState is seeded randomly, which makes for cheap jitter without pulling in a dependency.
+                let jitter = RandomState::new().hash_one(tries) % ceiling.saturating_add(1);
+                thread::sleep(Duration::from_nanos(jitter));
+            }
+        }
+    }
+}
+
 /// Packed binary data to represent the locking state of the MMF.
 ///
 /// The wrapper implementation must set these bytes depending on the situation and actions being taken.
@@ -109,7 +610,8 @@ impl fmt::Debug for dyn MMFLock {
 ///
 /// This atomic will be split into the following data:
 /// - First bit: write lock state. A single writer should prevent all other access.
-/// - First byte: after the writelock we have a few left, this serves for tracking init state.
+/// - First byte: after the writelock we have 7 bits left. These are yours, see [`RWLock::SPARE_MASK`]. The lock never
+///   looks at them, and only clearing the poison touches the byte they live in.
 /// - The remaining three are for read lock counting. Beware though, that while this allows you to have a count of up to
 ///   16_777_215 locks, the OS limits all processes to that number of open handles. This means nobody should ever be
 ///   remotely close to the actual limit. It also means that if for some reason there _are_ (2^24) - 1 locks, we get to
@@ -124,25 +626,90 @@ impl fmt::Debug for dyn MMFLock {
 /// OS. Just change the `current_lock` and `HOLDING_` constants to their 32-bit counterparts, then shift `HOLDING_W` 24
 /// bits to the left. The reason the default implementation doesn't do this, is that it was written to ensure it's safe
 /// to use. Weird OS quirks when going over the default limits don't fit that bill, so limiting the amount of open
-/// handles allows for guaranteeing safety assuming a sane system configuration.
+/// handles allows for guaranteeing safety assuming a sane system configuration. The 127 is only the default, and can
+/// be lowered with [`RWLock::with_local_limit`]. Hitting it errors with
+/// [`Error::LocalReaderLimit`][crate::err::Error::LocalReaderLimit], so it can be told apart from the global limit.
+///
+/// The same goes for threads. An instance can be shared between threads, but it'll keep a single tally of what "it"
+/// holds, so one thread can happily release the lock another thread took. Give every thread its own instance through
+/// [`RWLock::try_clone`] instead, so each of them only ever releases what it holds.
+///
+/// Behind the lock state, the lock keeps track of who holds the write lock, whether they're still doing something and
+/// how often anyone wrote. This takes another 28 bytes, bringing the total footprint of the lock to [`RWLock::SIZE`]
+/// bytes:
+/// - Bytes 0..4: the lock state described above.
+/// - Bytes 4..8: the process ID of the current write lock holder, or 0 if nobody holds it.
+/// - Bytes 8..12: a stamp derived from the holder's process creation time, to tell apart processes that got the same
+///   PID after the original owner died. See [`RWLock::force_unlock_if_dead`] for why you'd care.
+/// - Bytes 12..16: a heartbeat counter, bumped by the write lock holder through [`RWLock::heartbeat`]. See
+///   [`RWLock::is_writer_alive`].
+/// - Bytes 16..20: the write generation, bumped every time the write lock is released. See
+///   [`MMFLock::generation`] and [`RWLock::has_changed`].
+/// - Bytes 20..24: the init state. Exactly [`RWLock::INITIALIZED`] once the lock is usable, anything else (including
+///   the zeroes of a fresh MMF nobody set up yet) means it isn't.
+/// - Bytes 24..28: the abandoned flag, set when a dead writer's lock was recovered and the next writer still has to be
+///   told. See [`RWLock::recover_abandoned`].
+/// - Bytes 28..32: reserved, to keep the data behind the lock aligned to 16 bytes.
+///
+/// ## Compatibility
+/// Up to 0.3, the init state lived in the first byte of the lock state: all ones meant uninitialized, which also set
+/// the write bit, and anything else meant initialized. Setting all 7 spare bits made an initialized lock look
+/// uninitialized, and a zeroed lock counted as initialized. Since 0.4 the init state has its own word, so 0.3 and 0.4
+/// can't share an MMF. Make sure every process using it runs the same version, or at least one from the same side of
+/// that line.
 #[cfg(feature = "impl_lock")]
-#[derive(Debug)]
 pub struct RWLock<'a> {
     /// An Atomic reference to the first 4 bytes in the MemoryMappedView.
     /// Alignment is not an issue considering Windows aligns views to pointers by default.
     chunk: &'a AtomicU32,
+    /// The PID of the process holding the write lock, 0 if it's not held.
+    owner_pid: &'a AtomicU32,
+    /// Truncated creation time of the process holding the write lock, to guard against PID reuse.
+    owner_stamp: &'a AtomicU32,
+    /// Counter the write lock holder bumps every now and then, to show it's still alive.
+    heartbeat: &'a AtomicU32,
+    /// Counter bumped on every write unlock, so readers can tell whether anything changed.
+    generation: &'a AtomicU32,
+    /// Whether the lock is initialized, see [`RWLock::INITIALIZED`].
+    init: &'a AtomicU32,
+    /// Set while the next writer has yet to hear the previous one died, see [`RWLock::recover_abandoned`].
+    abandoned: &'a AtomicU32,
     /// Current internal lock state, used to prevent us from releasing locks we don't hold.
     current_lock: AtomicU8,
+    /// The memory ordering strategy used for all operations on the lock.
+    ordering: OrderingProfile,
+    /// What to do in between attempts when spinning.
+    strategy: SpinStrategy,
+    /// The maximum amount of tries when spinning before giving up.
+    max_spins: usize,
+    /// The maximum amount of read locks this instance may hold at once.
+    local_limit: u8,
+    /// Whether spinning recovers write locks abandoned by dead processes, see [`RWLock::with_robust`].
+    robust: bool,
+    /// Diagnostic callback, called with the amount of tries so far whenever spinning finds the lock held.
+    on_contention: Option<fn(usize)>,
+    /// Process-local counters for lock acquisitions and contention.
+    #[cfg(feature = "metrics")]
+    counters: LockCounters,
+    /// Callbacks timing the lock, see [`RWLock::with_hooks`].
+    #[cfg(feature = "lock_hooks")]
+    hooks: Option<Arc<dyn LockHooks>>,
+    /// Since when this instance has been waiting for or holding what, for the hooks.
+    #[cfg(feature = "lock_hooks")]
+    clock: HookClock,
+    /// The name of the MMF the lock lives in, if it was made with one. Only used to tell locks apart in traces.
+    #[cfg(feature = "tracing")]
+    name: ztr64,
 }
 
 #[cfg(feature = "impl_lock")]
 impl RWLock<'_> {
-    /// Mask to check if the lock is initialized
-    pub const INITIALIZE_MASK: u32 = 255 << 24;
     /// Mask to check if it's locked for WRITING
     pub const WRITE_LOCK_MASK: u32 = 0b1 << 31;
     /// Mask to check if it's locked for READING
-    pub const READ_LOCK_MASK: u32 = !Self::INITIALIZE_MASK;
+    pub const READ_LOCK_MASK: u32 = 0x00FF_FFFF;
+    /// Mask for the 7 bits in the lock state that the lock doesn't use. Do with them what you will.
+    pub const SPARE_MASK: u32 = !(Self::WRITE_LOCK_MASK | Self::READ_LOCK_MASK);
 
     /// Bitmask to check if we're holding the write lock ourselves. One bit to rule them all.
     pub const HOLDING_W: u8 = 0b10000000;
@@ -150,210 +717,1533 @@ impl RWLock<'_> {
     ///
     /// Any of these mean we hold a lock, all of these means we **can't hold any more read locks**.
     pub const HOLDING_R: u8 = !Self::HOLDING_W;
-}
 
-#[cfg(feature = "impl_lock")]
-/// Implements a good enough implementation of a lock for MMFs
-impl MMFLock for RWLock<'_> {
-    /// Construct a lock from existing pointers.
-    ///
-    /// This is meant to be used with some external mechanism to allow reading and writing lock state directly to and
-    /// from some larger struct. The lock will claim the first four bytes behind this pointer; if you do not intend to
-    /// have the lock state at the start of the data, make sure to offset the pointer provided.
-    ///
-    /// # Safety
-    /// there is no way of ensuring this pointer is valid after the first byte without moving out of bounds when
-    /// it's not. Users should take care to ensure the first 4 bytes in the pointer are valid. A lock created through
-    /// this method provides NO guarantees about the lock states and assumes the user has zeroed out all values behind
-    /// it if necessary. This initializer is meant to be used with a pointer for an existing lock, or a pointer whose
-    /// values you know will provide correct results. If the data behind the pointer is wrong, this effectively
-    /// constructs a poisoned lock.
-    /// It _is_ safe to assume the size and alignment are valid on Windows, however, as pointers are 0x4/0x4 or 0x8/0x8
-    /// depending on 32-bit or 64-bit. Either is safe for use with AtomicU32 which is 0x4/0x4 on these platforms.
-    ///
-    /// ## Panics
-    /// This function _will_ panic if called with a null pointer; ensuring initialization is hard, but ensuring non-null
-    /// should not prove difficult to anyone working with raw pointers.
-    ///
-    /// ## example
-    /// ```
-    /// # use std::sync::atomic::AtomicU32;
-    /// # use winmmf::{states::*, *};
-    /// # unsafe {
-    /// let bop = AtomicU32::new(0);
-    /// let ptr = bop.as_ptr();
-    /// let lock = RWLock::from_raw(ptr.cast());
-    /// lock.set_init();
-    /// // You're now free to do anything with the lock while `bop` lives
-    /// let new_ptr = bop.as_ptr();
-    /// let other_lock = RWLock::from_existing(new_ptr.cast());
-    ///
-    /// lock.lock_write().unwrap();
-    ///
-    /// assert!(other_lock.writelocked());
-    /// assert!(!other_lock.readlocked());
-    /// assert!(other_lock.lock_read().is_err());
-    ///
-    /// assert!(other_lock.unlock_write().is_err());
-    /// assert!(lock.unlock_write().is_ok());
-    /// # }
-    /// ```
-    unsafe fn from_existing(pointer: *mut u8) -> Self {
-        if pointer.is_null() {
-            panic!("Never, ever pass a null pointer into a lock!")
-        }
-        Self { chunk: AtomicU32::from_ptr(pointer.cast()), current_lock: AtomicU8::new(0) }
-    }
+    /// Value of the init state after [`from_raw`][MMFLock::from_raw], before anyone claimed or finished
+    /// initialization.
+    pub const UNINITIALIZED: u32 = 0xFF;
+    /// Value of the init state while somebody is [initializing][MMFLock::try_claim_init] the lock. Nobody can take
+    /// any lock until it's done.
+    pub const INITIALIZING: u32 = 0x5A;
+    /// Value of the init state once the lock is usable. Checked exactly, every other value means it isn't.
+    pub const INITIALIZED: u32 = 0xA5;
 
-    /// Similar to [`Self::from_existing`], except it clears all state and ensures [`Self::initialized`] returns false.
+    /// The amount of bytes claimed by the lock: the lock state, the owner PID, the owner stamp, the heartbeat, the
+    /// write generation, the init state, the abandoned flag and one reserved word, 4 bytes each. That's 32 bytes, a
+    /// multiple of 16.
+    pub const SIZE: usize = 32;
+
+    /// Build the lock from a pointer, without touching the data behind it.
     ///
     /// # Safety
-    /// The same safety bounds apply as for `from_existing` with the exception of poisoned lock risks. It does mean,
-    /// however, that it invalidates any other locks that use the same pointer and clears any data.
-    unsafe fn from_raw(pointer: *mut u8) -> Self {
-        if pointer.is_null() {
-            panic!("Never, ever pass a null pointer into a lock!")
+    /// The pointer must be non-null and valid for [`Self::SIZE`] bytes.
+    unsafe fn from_ptr(pointer: *mut u8, current_lock: u8) -> Self {
+        let words = pointer.cast::<u32>();
+        Self {
+            chunk: AtomicU32::from_ptr(words),
+            owner_pid: AtomicU32::from_ptr(words.add(1)),
+            owner_stamp: AtomicU32::from_ptr(words.add(2)),
+            heartbeat: AtomicU32::from_ptr(words.add(3)),
+            generation: AtomicU32::from_ptr(words.add(4)),
+            init: AtomicU32::from_ptr(words.add(5)),
+            abandoned: AtomicU32::from_ptr(words.add(6)),
+            current_lock: AtomicU8::new(current_lock),
+            ordering: OrderingProfile::Default,
+            strategy: SpinStrategy::Busy,
+            max_spins: usize::MAX,
+            local_limit: Self::HOLDING_R,
+            robust: false,
+            on_contention: None,
+            #[cfg(feature = "metrics")]
+            counters: LockCounters::default(),
+            #[cfg(feature = "lock_hooks")]
+            hooks: None,
+            #[cfg(feature = "lock_hooks")]
+            clock: HookClock::default(),
+            #[cfg(feature = "tracing")]
+            name: ztr64::new(),
         }
-        let lock = Self { chunk: AtomicU32::from_ptr(pointer.cast()), current_lock: AtomicU8::new(255) };
-        lock.chunk.store(Self::INITIALIZE_MASK, Ordering::Release);
-        lock
     }
 
-    /// Mark this lock as initialized if it isn't yet.
+    /// Get a snapshot of this instance's lock metrics.
     ///
-    /// In pre-0.3 versions of this crate, this would clear existing locks. This is a bad idea though, as a naive caller
-    /// might not realize they're not the only process using the MMF.
-    fn set_init(&self) {
-        fence(Ordering::AcqRel);
-        _ = self.chunk.compare_exchange(Self::INITIALIZE_MASK, 0, Ordering::Release, Ordering::Relaxed);
-        _ = self.current_lock.compare_exchange(255, 0, Ordering::Release, Ordering::Relaxed);
-        fence(Ordering::AcqRel);
+    /// These only count operations on this instance, other instances (in this process or any other) keep their own.
+    #[cfg(feature = "metrics")]
+    pub fn metrics(&self) -> LockMetrics {
+        LockMetrics {
+            read_contended: self.counters.read_contended.load(Ordering::Relaxed),
+            write_contended: self.counters.write_contended.load(Ordering::Relaxed),
+            read_acquired: self.counters.read_acquired.load(Ordering::Relaxed),
+            write_acquired: self.counters.write_acquired.load(Ordering::Relaxed),
+        }
     }
 
-    /// Thin wrapper around [`Self::set_init`] that returns self for chaining calls.
+    /// Decode a raw lock state and init state into something humans can read. The spare bits are left out.
     ///
-    /// # Safety
-    /// The same safety concerns apply as for `set_init`.
+    /// Usable on values read from somewhere else (e.g. a dump of the shared memory). For a lock at hand, its
+    /// [`Display`][fmt::Display] implementation is shorter.
     ///
     /// ## Usage
     /// ```
-    /// # use std::sync::atomic::AtomicU32;
-    /// # use winmmf::{states::*, *};
-    /// let bop = AtomicU32::new(0);
-    /// let ptr = bop.as_ptr();
-    /// let lock = unsafe { RWLock::from_existing(ptr.cast()).initialize() };
-    /// assert!(lock.initialized());
+    /// # use winmmf::states::RWLock;
+    /// assert_eq!(RWLock::describe(3, RWLock::INITIALIZED), "RWLock{ init=true, writers=0, readers=3 }");
+    /// assert_eq!(RWLock::describe(0, RWLock::UNINITIALIZED), "RWLock{ init=false, writers=0, readers=0 }");
     /// ```
-    fn initialize(self) -> Self {
-        self.set_init();
-        self
+    pub fn describe(raw: u32, init: u32) -> String {
+        let init = init == Self::INITIALIZED;
+        let writers = u32::from((raw & Self::WRITE_LOCK_MASK) != 0);
+        let readers = raw & Self::READ_LOCK_MASK;
+        let poisoned = if writers > 0 && readers > 0 { ", poisoned=true" } else { "" };
+        format!("RWLock{{ init={init}, writers={writers}, readers={readers}{poisoned} }}")
     }
 
-    /// Check if this lock has been initialized at all.
+    /// Capture the state of the lock at this point in time.
     ///
-    /// Regardless of locking state, and abuse of the 7 empty bits, a lock _should_ not have all bits on the first byte
-    /// set to one. If it does, either the lock isn't initialized, or the user is not being very smart.
-    #[inline(always)]
-    fn initialized(&self) -> bool {
+    /// This issues a single `AcqRel` fence and then reads the shared and local state once each, so all fields are
+    /// derived from the same two values rather than from separate calls that might each see a different state.
+    pub fn snapshot(&self) -> LockSnapshot {
         fence(Ordering::AcqRel);
-        (self.chunk.load(Ordering::Acquire) & Self::INITIALIZE_MASK) < Self::INITIALIZE_MASK
-            || self.current_lock.load(Ordering::Acquire) < 255
+        self.snapshot_of(self.chunk.load(Ordering::Acquire), self.current_lock.load(Ordering::Acquire))
     }
 
-    /// Check if the lock is held for reading. This should only prevent new write locks.
-    #[inline(always)]
-    fn readlocked(&self) -> bool {
-        fence(Ordering::AcqRel);
-        (self.chunk.load(Ordering::Acquire) & Self::READ_LOCK_MASK) > 0
-            || (self.current_lock.load(Ordering::Acquire) & Self::HOLDING_R) > 0
+    /// Build a snapshot from the shared state and the local state, as loaded by whoever is asking.
+    fn snapshot_of(&self, shared: u32, local: u8) -> LockSnapshot {
+        let initialized = self.init.load(Ordering::Acquire) == Self::INITIALIZED || local < 255;
+        // An uninitialized instance has every local bit set, that doesn't mean it holds anything.
+        let local = if local == 255 { 0 } else { local };
+        LockSnapshot {
+            write_locked: (shared & Self::WRITE_LOCK_MASK) != 0,
+            reader_count: shared & Self::READ_LOCK_MASK,
+            initialized,
+            local_reader_count: local & Self::HOLDING_R,
+            local_write_held: (local & Self::HOLDING_W) != 0,
+        }
     }
 
-    /// Check if the lock is held for writing. This should prevent ALL other locking operations.
-    #[inline(always)]
-    fn writelocked(&self) -> bool {
-        fence(Ordering::AcqRel);
-        (self.chunk.load(Ordering::Acquire) & Self::WRITE_LOCK_MASK) == Self::WRITE_LOCK_MASK
-            || (self.current_lock.load(Ordering::Acquire) & Self::HOLDING_W) == Self::HOLDING_W
+    /// Peek at the raw lock state with a single `Relaxed` load and no fences, see [`RWLock::peek_writelocked`].
+    pub fn peek_raw(&self) -> u32 {
+        self.chunk.load(Ordering::Relaxed)
     }
 
-    /// Check if the locks are any non-zero value. Uninitialized locks are considered held.
-    #[inline(always)]
-    fn locked(&self) -> bool {
-        fence(Ordering::AcqRel);
-        self.chunk.load(Ordering::Acquire) > 0 || self.current_lock.load(Ordering::Acquire) > 0
+    /// Best-effort check whether anyone holds the write lock, for dashboards and other monitoring that can't afford
+    /// a fence per look.
+    ///
+    /// This is a single `Relaxed` load and doesn't synchronize with anything: the answer may be stale by the time you
+    /// get it, and seeing the lock free says nothing about what's in the data. So never use it to decide whether to
+    /// touch the data. That's what the locking methods are for, [`writelocked`][MMFLock::writelocked] included.
+    ///
+    /// ```
+    /// # use winmmf::states::*;
+    /// let mut bop = vec![0u32; RWLock::SIZE / 4];
+    /// let lock = unsafe { RWLock::from_raw(bop.as_mut_ptr().cast()).initialize() };
+    /// // Fine: reporting what the lock looked like a moment ago.
+    /// println!("writer: {}, readers: {}", lock.peek_writelocked(), lock.peek_readers());
+    /// // Wrong: by the time the write happens, somebody else may have taken the lock.
+    /// // if !lock.peek_writelocked() { write_to_the_data(); }
+    /// // Right: take the lock, which does the synchronizing.
+    /// if lock.try_lock_write().is_ok() {
+    ///     assert!(lock.peek_writelocked());
+    ///     lock.unlock_write().unwrap();
+    /// }
+    /// ```
+    pub fn peek_writelocked(&self) -> bool {
+        (self.peek_raw() & Self::WRITE_LOCK_MASK) != 0
     }
 
-    /// Increment the counter for read locks ***if and only if*** we can safely lock this for reading
-    fn lock_read(&self) -> MMFResult<()> {
-        if !self.initialized() {
-            Err(Error::Uninitialized)
-        } else if self.writelocked() {
-            Err(Error::WriteLocked)
-        } else {
-            fence(Ordering::AcqRel);
-            let ret = self
-                .chunk
-                .fetch_update(Ordering::AcqRel, Ordering::Acquire, |lock| {
-                    if (lock & Self::READ_LOCK_MASK) == Self::READ_LOCK_MASK
-                        || self.current_lock.load(Ordering::Acquire) == Self::HOLDING_R
-                    {
-                        None
-                    } else {
-                        self.current_lock.fetch_add(1, Ordering::AcqRel);
-                        Some(lock + 1)
-                    }
-                })
-                .map(|_| ())
-                .map_err(|_| Error::MaxReaders);
-            fence(Ordering::AcqRel);
-            ret
+    /// Best-effort amount of readers across everyone using the lock. Same caveats as
+    /// [`peek_writelocked`][RWLock::peek_writelocked].
+    pub fn peek_readers(&self) -> u32 {
+        self.peek_raw() & Self::READ_LOCK_MASK
+    }
+
+    /// Get a [builder][RWLockBuilder] to configure a lock before creating it.
+    pub fn builder() -> RWLockBuilder {
+        RWLockBuilder::default()
+    }
+
+    /// Like [`from_existing`][MMFLock::from_existing], for a lock that lives `offset` bytes behind `pointer`.
+    ///
+    /// Errors with [`Error::MisalignedLock`] if that puts the lock at an address that isn't aligned to 4 bytes.
+    ///
+    /// # Safety
+    /// The same rules apply as for `from_existing`, for the pointer after applying the offset.
+    #[track_caller]
+    pub unsafe fn from_existing_at(pointer: *mut u8, offset: usize) -> MMFResult<Self> {
+        let pointer = pointer.wrapping_add(offset);
+        if pointer as usize % 4 != 0 {
+            return Err(Error::MisalignedLock);
         }
+        Ok(Self::from_existing(pointer))
     }
 
-    /// Decrease the read lock counter if we can safely do so.
-    fn unlock_read(&self) -> MMFResult<()> {
-        if !self.initialized() {
-            Err(Error::Uninitialized)
-        } else if self.writelocked() {
-            Err(Error::WriteLocked)
-        } else {
-            fence(Ordering::AcqRel);
-            let ret = self
-                .chunk
-                .fetch_update(Ordering::AcqRel, Ordering::Acquire, |lock| {
-                    if (lock & Self::READ_LOCK_MASK) == 0 || self.current_lock.load(Ordering::Acquire) == 0 {
-                        None
-                    } else {
-                        self.current_lock.fetch_sub(1, Ordering::AcqRel);
-                        Some(lock - 1)
-                    }
-                })
-                .map(|_| ())
-                .map_err(|_| Error::MaxReaders);
-            fence(Ordering::AcqRel);
-            ret
+    /// Like [`from_raw`][MMFLock::from_raw], for a lock that lives `offset` bytes behind `pointer`.
+    ///
+    /// Errors with [`Error::MisalignedLock`] if that puts the lock at an address that isn't aligned to 4 bytes.
+    ///
+    /// # Safety
+    /// The same rules apply as for `from_raw`, for the pointer after applying the offset.
+    #[track_caller]
+    pub unsafe fn from_raw_at(pointer: *mut u8, offset: usize) -> MMFResult<Self> {
+        let pointer = pointer.wrapping_add(offset);
+        if pointer as usize % 4 != 0 {
+            return Err(Error::MisalignedLock);
         }
+        Ok(Self::from_raw(pointer))
     }
 
-    /// Set the write lock bit to 1 if possible.
-    fn lock_write(&self) -> MMFResult<()> {
-        if !self.initialized() {
+    /// Create an independent instance over the same shared state, holding no locks of its own.
+    ///
+    /// The new instance gets the same configuration (ordering, spin strategy, spin and reader limits and contention
+    /// callback, robust mode, hooks), but starts with fresh bookkeeping and metrics. This is what you want for handing
+    /// the lock to another thread. Errors with [`Error::Uninitialized`] if the shared state isn't initialized, as the
+    /// clone would have no way to tell.
+    pub fn try_clone(&self) -> MMFResult<Self> {
+        if !self.shared_initialized() {
+            return Err(Error::Uninitialized);
+        }
+        // Safety: the pointer came from a lock that's still alive, and was valid for SIZE bytes when it was created.
+        let mut clone = unsafe { Self::from_ptr(self.chunk.as_ptr().cast(), 0) };
+        clone.ordering = self.ordering;
+        clone.strategy = self.strategy;
+        clone.max_spins = self.max_spins;
+        clone.local_limit = self.local_limit;
+        clone.robust = self.robust;
+        clone.on_contention = self.on_contention;
+        #[cfg(feature = "tracing")]
+        {
+            clone.name = self.name;
+        }
+        #[cfg(feature = "lock_hooks")]
+        {
+            clone.hooks = self.hooks.clone();
+        }
+        Ok(clone)
+    }
+
+    /// Check if the shared state is initialized, i.e. nobody is or still has to be initializing it.
+    fn shared_initialized(&self) -> bool {
+        self.order_fence();
+        self.init.load(self.load_order()) == Self::INITIALIZED
+    }
+
+    /// Called whenever spinning runs into a held lock, to apply the spin strategy and notify the contention callback.
+    #[inline(always)]
+    fn contended(&self, tries: usize) {
+        if let Some(callback) = self.on_contention {
+            callback(tries);
+        }
+        if self.robust && tries.is_power_of_two() {
+            _ = self.recover_abandoned();
+        }
+        #[cfg(feature = "tracing")]
+        if tries >= TRACE_SPINS && tries.is_power_of_two() {
+            self.trace_event("Still spinning on the lock", Some(tries));
+        }
+        self.strategy.pause(tries);
+    }
+
+    /// Chainable setter for the [`SpinStrategy`] used by [`spin`][MMFLock::spin] and the spinning lock methods.
+    pub fn with_spin_strategy(mut self, strategy: SpinStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Get the [`SpinStrategy`] this lock uses.
+    pub fn spin_strategy(&self) -> SpinStrategy {
+        self.strategy
+    }
+
+    /// Chainable setter for the maximum amount of read locks this instance may hold at once.
+    ///
+    /// The limit is clamped to `1..=`[`Self::HOLDING_R`], as that's all the bookkeeping can track. Lower it to catch
+    /// leaked read locks early while debugging. It's purely local: the shared state doesn't change, so instances and
+    /// processes with different limits work together just fine.
+    pub fn with_local_limit(mut self, limit: u8) -> Self {
+        self.local_limit = limit.clamp(1, Self::HOLDING_R);
+        self
+    }
+
+    /// Get the maximum amount of read locks this instance may hold at once.
+    pub fn local_limit(&self) -> u8 {
+        self.local_limit
+    }
+
+    /// Chainable setter for robust mode: while spinning, check every now and then whether the writer holding the lock
+    /// died, and [recover the lock][RWLock::recover_abandoned] if it did. Off by default. Only affects this instance.
+    ///
+    /// The checks happen after 1, 2, 4, 8... tries, so a lock held by a live writer for a long time costs next to
+    /// nothing. Waits that don't spin, like [`wait_until_unlocked`][MMFLock::wait_until_unlocked], don't check.
+    pub fn with_robust(mut self, robust: bool) -> Self {
+        self.robust = robust;
+        self
+    }
+
+    /// Check if this instance recovers abandoned write locks while spinning, see [`RWLock::with_robust`].
+    pub fn is_robust(&self) -> bool {
+        self.robust
+    }
+
+    /// Chainable setter for [`LockHooks`] to tell how long this instance waits for and holds the lock, with the
+    /// `lock_hooks` feature. None by default. Only affects this instance and its [clones][RWLock::try_clone], which
+    /// share the hooks.
+    ///
+    /// The waits count from the first attempt that found the lock taken, until one gets through. An instance that
+    /// gives up and tries again later counts the time in between as well, as it can't tell the two apart.
+    #[cfg(feature = "lock_hooks")]
+    pub fn with_hooks(mut self, hooks: Arc<dyn LockHooks>) -> Self {
+        self.hooks = Some(hooks);
+        self
+    }
+
+    /// Tell the hooks how an attempt to take a read or write lock went, starting and stopping the clocks to match.
+    #[cfg(feature = "lock_hooks")]
+    fn hook_acquire<T>(&self, res: &MMFResult<T>, write: bool) {
+        let Some(hooks) = &self.hooks else {
+            return;
+        };
+        let waiting = if write { &self.clock.write_waiting } else { &self.clock.read_waiting };
+        match res {
+            Ok(_) if write => {
+                HookClock::start(&self.clock.write_held);
+                hooks.on_acquire_write(HookClock::stop(waiting));
+            }
+            Ok(_) => {
+                // Only the first read lock of the instance starts the clock, the last one out stops it.
+                if (self.current_lock.load(self.load_order()) & Self::HOLDING_R) == 1 {
+                    HookClock::start(&self.clock.read_held);
+                }
+                hooks.on_acquire_read(HookClock::stop(waiting));
+            }
+            Err(e) if e.worth_waiting() => HookClock::start_once(waiting),
+            Err(_) => _ = HookClock::stop(waiting),
+        }
+    }
+
+    /// Tell the hooks a read or write lock was released.
+    #[cfg(feature = "lock_hooks")]
+    fn hook_release(&self, write: bool) {
+        let Some(hooks) = &self.hooks else {
+            return;
+        };
+        if write {
+            hooks.on_release_write(HookClock::stop(&self.clock.write_held));
+        } else if (self.current_lock.load(self.load_order()) & Self::HOLDING_R) == 0 {
+            hooks.on_release_read(HookClock::stop(&self.clock.read_held));
+        }
+    }
+
+    /// Chainable setter for the [`OrderingProfile`] this lock uses. Only affects this instance.
+    ///
+    /// ## Usage
+    /// ```
+    /// # use winmmf::{states::*, *};
+    /// let mut bop = vec![0u32; RWLock::SIZE / 4];
+    /// let ptr = bop.as_mut_ptr();
+    /// let lock = unsafe { RWLock::from_raw(ptr.cast()).initialize().with_ordering(OrderingProfile::Relaxed) };
+    /// assert_eq!(lock.ordering(), OrderingProfile::Relaxed);
+    /// ```
+    pub fn with_ordering(mut self, ordering: OrderingProfile) -> Self {
+        self.ordering = ordering;
+        self
+    }
+
+    /// Get the [`OrderingProfile`] this lock uses.
+    pub fn ordering(&self) -> OrderingProfile {
+        self.ordering
+    }
+
+    /// Issue the fence required by the ordering profile, if any.
+    #[inline(always)]
+    fn order_fence(&self) {
+        match self.ordering {
+            OrderingProfile::Strict => fence(Ordering::SeqCst),
+            OrderingProfile::Default => fence(Ordering::AcqRel),
+            OrderingProfile::Relaxed => {}
+        }
+    }
+
+    /// Ordering to use for plain loads.
+    #[inline(always)]
+    fn load_order(&self) -> Ordering {
+        match self.ordering {
+            OrderingProfile::Strict => Ordering::SeqCst,
+            _ => Ordering::Acquire,
+        }
+    }
+
+    /// Ordering to use for plain stores.
+    #[inline(always)]
+    fn store_order(&self) -> Ordering {
+        match self.ordering {
+            OrderingProfile::Strict => Ordering::SeqCst,
+            _ => Ordering::Release,
+        }
+    }
+
+    /// Ordering to use for read-modify-write operations.
+    #[inline(always)]
+    fn rmw_order(&self) -> Ordering {
+        match self.ordering {
+            OrderingProfile::Strict => Ordering::SeqCst,
+            _ => Ordering::AcqRel,
+        }
+    }
+
+    /// Get the PID of the process holding the write lock, if it's held and the holder registered itself.
+    pub fn write_owner(&self) -> Option<u32> {
+        self.order_fence();
+        match self.owner_pid.load(self.load_order()) {
+            0 => None,
+            pid => Some(pid),
+        }
+    }
+
+    /// Let everyone know the write lock holder is still alive and kicking. Returns whether a beat was recorded.
+    ///
+    /// Only the instance holding the write lock can do this. Call it every now and then while holding on to the write
+    /// lock for a longer time, so others can tell a busy writer apart from a stuck one.
+    pub fn heartbeat(&self) -> bool {
+        if (self.current_lock.load(self.load_order()) & Self::HOLDING_W) == 0 {
+            return false;
+        }
+        self.heartbeat.fetch_add(1, self.rmw_order());
+        self.order_fence();
+        true
+    }
+
+    /// Get the current heartbeat count of the write lock holder. It's reset to 0 whenever the write lock is acquired.
+    pub fn writer_heartbeat(&self) -> u32 {
+        self.order_fence();
+        self.heartbeat.load(self.load_order())
+    }
+
+    /// Count the writes since the [generation][MMFLock::generation] was `since`, wrapping around like the generation
+    /// does. Only off if there were a multiple of 2^32 writes in between, at which point you have bigger problems.
+    pub fn writes_since(&self, since: u32) -> u32 {
+        self.order_fence();
+        self.generation.load(self.load_order()).wrapping_sub(since)
+    }
+
+    /// Check if anything was written since the [generation][MMFLock::generation] was `since`.
+    ///
+    /// This is what makes cheap poll loops: remember the generation, take a read lock and read the data, and only take
+    /// the lock again once this says something changed. Grab the generation before taking the read lock, not after,
+    /// so a write landing in between means reading twice rather than missing it.
+    ///
+    /// ## Usage
+    /// ```
+    /// # use winmmf::states::*;
+    /// let mut bop = vec![0u32; RWLock::SIZE / 4];
+    /// let lock = unsafe { RWLock::from_raw(bop.as_mut_ptr().cast()).initialize() };
+    /// let seen = lock.generation().unwrap();
+    /// assert!(!lock.has_changed(seen));
+    /// lock.try_lock_write().unwrap();
+    /// lock.unlock_write().unwrap();
+    /// assert!(lock.has_changed(seen));
+    /// ```
+    pub fn has_changed(&self, since: u32) -> bool {
+        self.writes_since(since) != 0
+    }
+
+    /// Emit a `trace` level event about this lock, with its name, its shared state (raw and decoded) and this
+    /// instance's bookkeeping attached.
+    #[cfg(feature = "tracing")]
+    fn trace_event(&self, message: &str, tries: Option<usize>) {
+        let chunk = self.chunk.load(Ordering::Relaxed);
+        tracing::trace!(
+            target: "winmmf::lock",
+            name = self.name.as_str(),
+            chunk = format_args!("{chunk:#010x}"),
+            state = %Self::describe(chunk, self.init.load(Ordering::Relaxed)),
+            current_lock = format_args!("{:#04x}", self.current_lock.load(Ordering::Relaxed)),
+            tries,
+            "{message}"
+        );
+    }
+
+    /// Bump the write generation, right before the write lock is released.
+    fn bump_generation(&self) {
+        self.generation.fetch_add(1, self.rmw_order());
+    }
+
+    /// Check if the process holding the write lock is still running.
+    ///
+    /// Returns false if nobody holds the write lock. If it's held but the holder didn't register itself, there's no way
+    /// to tell, so it's assumed to be alive.
+    ///
+    /// Putting it all together, recovering from a stale lock goes like this:
+    /// 1. Notice the lock is stale: the write lock is held, and [`RWLock::writer_heartbeat`] hasn't moved for longer
+    ///    than you'd expect the writer to be busy.
+    /// 2. Check the writer is actually dead with this method. A stuck writer is not a dead writer.
+    /// 3. Clear the lock with [`RWLock::force_unlock_write`]. Or use [`RWLock::force_unlock_if_dead`], which does 2 and
+    ///    3 in one go without the `unsafe`.
+    pub fn is_writer_alive(&self) -> bool {
+        self.order_fence();
+        if (self.chunk.load(self.load_order()) & Self::WRITE_LOCK_MASK) == 0 {
+            return false;
+        }
+        match (self.owner_pid.load(self.load_order()), self.owner_stamp.load(self.load_order())) {
+            (0, _) => true,
+            (pid, stamp) => process_alive(pid, stamp),
+        }
+    }
+
+    /// Reset a [poisoned][MMFLock::is_poisoned] lock to an unlocked, but initialized state.
+    ///
+    /// This clears the write bit, the reader count and the registered write lock owner. Any of the spare bits in the
+    /// first byte are left alone. If the lock isn't poisoned, this does nothing. If this instance still thinks it holds
+    /// a lock, this errors with [`Error::WriteLocked`] or [`Error::ReadLocked`] as releasing those should come first.
+    ///
+    /// Whatever data was behind the lock can't be trusted after this, as it's unknown who did what while the lock was
+    /// in its poisoned state.
+    #[track_caller]
+    pub fn clear_poison(&self) -> MMFResult<()> {
+        if !self.initialized() {
+            return Err(Error::Uninitialized);
+        }
+        match self.current_lock.load(self.load_order()) {
+            0 => {}
+            held if (held & Self::HOLDING_W) != 0 => return Err(Error::WriteLocked),
+            _ => return Err(Error::ReadLocked),
+        }
+        self.order_fence();
+        let cleared = self.chunk.fetch_update(self.rmw_order(), self.load_order(), |lock| {
+            ((lock & Self::WRITE_LOCK_MASK) != 0 && (lock & Self::READ_LOCK_MASK) != 0)
+                .then_some(lock & Self::SPARE_MASK)
+        });
+        if cleared.is_ok() {
+            self.owner_pid.store(0, self.store_order());
+            self.owner_stamp.store(0, self.store_order());
+        }
+        self.order_fence();
+        if cleared.is_ok() {
+            self.wake();
+        }
+        Ok(())
+    }
+
+    /// Clear the write lock, no questions asked.
+    ///
+    /// This bypasses the ownership check [`unlock_write`][MMFLock::unlock_write] does, clears the registered owner and
+    /// resets this instance's local state to holding nothing at all. Read locks held by this instance are forgotten,
+    /// not released. It's meant for recovering from a writer that crashed while holding the lock, which would
+    /// otherwise lock everyone out forever. With the `lock_hooks` feature, whatever this instance held counts as
+    /// released as far as its [hooks][RWLock::with_hooks] are concerned.
+    ///
+    /// # Safety
+    /// If the original writer is still alive, it will happily keep writing while others read or write along with it.
+    /// The caller must guarantee the writer is dead. The usual way to check that on Windows is opening the process
+    /// with `OpenProcess(SYNCHRONIZE, false, pid)` and calling `WaitForSingleObject(handle, 0)` on it: if that returns
+    /// `WAIT_OBJECT_0` the process has exited. [`RWLock::write_owner`] tells you which PID to check, and
+    /// [`RWLock::force_unlock_if_dead`] does all of this for you, minus the `unsafe`.
+    #[track_caller]
+    pub unsafe fn force_unlock_write(&self) {
+        self.order_fence();
+        self.owner_pid.store(0, self.store_order());
+        self.owner_stamp.store(0, self.store_order());
+        // Whatever the writer got done, it might have written something.
+        self.bump_generation();
+        self.chunk.fetch_and(!Self::WRITE_LOCK_MASK, self.rmw_order());
+        #[cfg(feature = "lock_hooks")]
+        let held = self.current_lock.load(self.load_order());
+        self.current_lock.store(0, self.store_order());
+        self.order_fence();
+        self.wake();
+        #[cfg(feature = "lock_hooks")]
+        {
+            if (held & Self::HOLDING_W) != 0 {
+                self.hook_release(true);
+            }
+            if (held & Self::HOLDING_R) != 0 {
+                self.hook_release(false);
+            }
+        }
+    }
+
+    /// Clear the write lock if the process that holds it no longer exists.
+    ///
+    /// Normally, only the instance holding the write lock can release it. That's a problem if the holder crashed, as
+    /// everyone else is now locked out forever. This method checks the registered owner with `OpenProcess` and
+    /// `GetExitCodeProcess`, and if it's gone (or the PID now belongs to a process created at a different time) the
+    /// write lock is cleared. Returns whether a lock was cleared.
+    ///
+    /// If the write lock isn't held, or the holder didn't register itself (e.g. it's an older version of this crate),
+    /// nothing is cleared. If the holder is still alive, nothing is cleared either. Locks held by this process are
+    /// never touched, use [`unlock_write`][MMFLock::unlock_write] on the instance holding it instead.
+    pub fn force_unlock_if_dead(&self) -> MMFResult<bool> {
+        self.clear_dead_writer(false)
+    }
+
+    /// Like [`RWLock::force_unlock_if_dead`], except the next writer gets told about it: the first
+    /// [`try_lock_write`][MMFLock::try_lock_write] after the recovery, by any instance in any process, errors with
+    /// [`Error::Abandoned`] instead of taking the lock. It's modeled on POSIX robust mutexes, where the first one in
+    /// after the owner died gets `EOWNERDEAD`. The difference is that here, the lock isn't held after being told: check
+    /// or repair the data however you see fit, then try again to get the lock for real. Readers aren't told, so if
+    /// they can't deal with half-written data, make sure a writer gets there first.
+    ///
+    /// Returns whether this call cleared the lock. When several instances try to recover the same lock at once, only
+    /// one of them gets `true`, and only one writer gets told. Robust instances (see [`RWLock::with_robust`]) call
+    /// this on their own while spinning.
+    ///
+    /// ```
+    /// # use winmmf::{states::*, err::Error};
+    /// let mut bop = vec![0u32; RWLock::SIZE / 4];
+    /// let lock = unsafe { RWLock::from_raw(bop.as_mut_ptr().cast()).initialize() };
+    /// // Nobody holds the lock, so there's nothing to recover
+    /// assert!(!lock.recover_abandoned().unwrap());
+    /// lock.try_lock_write().unwrap();
+    /// assert!(matches!(lock.try_lock_write(), Err(Error::WriteLocked)));
+    /// ```
+    pub fn recover_abandoned(&self) -> MMFResult<bool> {
+        self.clear_dead_writer(true)
+    }
+
+    /// Clear the write lock if the process that registered itself as holding it is gone. Raises the abandoned flag
+    /// before clearing if `flag` is set, so no writer can get in between without being told.
+    fn clear_dead_writer(&self, flag: bool) -> MMFResult<bool> {
+        if !self.initialized() {
+            return Err(Error::Uninitialized);
+        }
+        self.order_fence();
+        let state = self.chunk.load(self.load_order());
+        let (pid, stamp) = (self.owner_pid.load(self.load_order()), self.owner_stamp.load(self.load_order()));
+        if (state & Self::WRITE_LOCK_MASK) == 0 || pid == 0 || process_alive(pid, stamp) {
+            return Ok(false);
+        }
+        // Only clear the owner if nobody else beat us to it and took the lock in the meantime.
+        if self.owner_pid.compare_exchange(pid, 0, self.rmw_order(), self.load_order()).is_err() {
+            return Ok(false);
+        }
+        self.owner_stamp.store(0, self.store_order());
+        self.bump_generation();
+        if flag {
+            self.abandoned.store(1, self.store_order());
+        }
+        let cleared = self
+            .chunk
+            .fetch_update(self.rmw_order(), self.load_order(), |lock| {
+                ((lock & Self::WRITE_LOCK_MASK) != 0).then_some(lock & !Self::WRITE_LOCK_MASK)
+            })
+            .is_ok();
+        self.order_fence();
+        if cleared {
+            #[cfg(feature = "tracing")]
+            self.trace_event("Cleared a write lock abandoned by a dead process", None);
+            self.wake();
+        }
+        Ok(cleared)
+    }
+
+    /// Take over a write lock somebody else holds, without ever letting go of it in between. Registers this process as
+    /// the owner and this instance as the holder. Does nothing if the write lock isn't held at all.
+    ///
+    /// Only for locks that know for sure the holder gave up on it, like a [`LeasedLock`][crate::lease::LeasedLock]
+    /// with an expired lease. Anyone else would end up with two writers.
+    pub(crate) fn adopt_write(&self) -> bool {
+        self.order_fence();
+        if (self.chunk.load(self.load_order()) & Self::WRITE_LOCK_MASK) == 0 {
+            return false;
+        }
+        let (pid, stamp) = current_owner();
+        self.heartbeat.store(0, self.store_order());
+        self.owner_stamp.store(stamp, self.store_order());
+        self.owner_pid.store(pid, self.store_order());
+        self.current_lock.fetch_or(Self::HOLDING_W, self.rmw_order());
+        self.order_fence();
+        true
+    }
+
+    /// Hand the write lock this instance holds over to `recipient`, another instance over the same lock state.
+    ///
+    /// Holding the write lock is tracked per instance, so taking it on one thread and releasing it on another normally
+    /// doesn't work. This moves the bookkeeping to the recipient without ever letting go of the lock in the shared
+    /// state, so nobody else can sneak in between. Afterwards the recipient holds the write lock and can
+    /// [release][MMFLock::unlock_write] it, and this instance holds nothing. The registered owner stays the same, as
+    /// both instances are in this process.
+    ///
+    /// Errors leave both instances as they were:
+    /// - [`Error::NotLocked`] if this instance doesn't hold the write lock, like after handing it over already. If
+    ///   somebody [forced it open][RWLock::force_unlock_write] in the meantime, this instance forgets it held it.
+    /// - [`Error::LockViolation`] if the recipient is over a different lock state.
+    /// - [`Error::WriteLocked`] or [`Error::ReadLocked`] if the recipient holds locks of its own already.
+    ///
+    /// ## Usage
+    /// ```
+    /// # use winmmf::states::*;
+    /// let mut bop = vec![0u32; RWLock::SIZE / 4];
+    /// let lock = unsafe { RWLock::from_raw(bop.as_mut_ptr().cast()).initialize() };
+    /// let worker = lock.try_clone().unwrap();
+    /// lock.try_lock_write().unwrap();
+    /// lock.transfer_write(&worker).unwrap();
+    /// assert!(lock.transfer_write(&worker).is_err());
+    /// std::thread::scope(|s| {
+    ///     s.spawn(move || worker.unlock_write().unwrap());
+    /// });
+    /// assert!(!lock.locked());
+    /// ```
+    #[track_caller]
+    pub fn transfer_write(&self, recipient: &RWLock) -> MMFResult<()> {
+        if (self.current_lock.load(self.load_order()) & Self::HOLDING_W) == 0 {
+            return Err(Error::NotLocked);
+        } else if std::ptr::eq(self, recipient) {
+            return Ok(());
+        } else if self.chunk.as_ptr() != recipient.chunk.as_ptr() {
+            return Err(Error::LockViolation);
+        }
+        match recipient.current_lock.load(recipient.load_order()) {
+            0 => {}
+            held if (held & Self::HOLDING_W) != 0 => return Err(Error::WriteLocked),
+            _ => return Err(Error::ReadLocked),
+        }
+        self.order_fence();
+        if (self.chunk.load(self.load_order()) & Self::WRITE_LOCK_MASK) == 0 {
+            self.forget_write();
+            return Err(Error::NotLocked);
+        }
+        // The recipient gets it before we let go of it, so it's never held by nobody. Only claim it if the recipient
+        // still holds nothing, as somebody might be using it on another thread.
+        recipient
+            .current_lock
+            .compare_exchange(0, Self::HOLDING_W, recipient.rmw_order(), recipient.load_order())
+            .map_err(|_| Error::LockViolation)?;
+        self.forget_write();
+        self.order_fence();
+        #[cfg(feature = "lock_hooks")]
+        recipient.hook_acquire(&Ok(()), true);
+        Ok(())
+    }
+
+    /// Turn the write lock this instance holds into a read lock, without letting go of the lock in between.
+    ///
+    /// Nobody else gets to write between the last write and the read that follows it, which releasing the write lock
+    /// and taking a read lock can't promise. Other readers can join in right away. Counts as a finished write for the
+    /// [generation][MMFLock::generation]. Errors with [`Error::NotLocked`] if this instance doesn't hold the write
+    /// lock, or if somebody [force unlocked][RWLock::force_unlock_write] it in the meantime. This instance holds
+    /// nothing in the latter case.
+    #[track_caller]
+    pub fn downgrade_write(&self) -> MMFResult<()> {
+        if (self.current_lock.load(self.load_order()) & Self::HOLDING_W) == 0 {
+            return Err(Error::NotLocked);
+        }
+        self.order_fence();
+        self.owner_pid.store(0, self.store_order());
+        self.owner_stamp.store(0, self.store_order());
+        self.bump_generation();
+        // Writers never share the lock with readers, so the reader count is ours to start at 1.
+        let swapped = self.chunk.fetch_update(self.rmw_order(), self.load_order(), |lock| {
+            ((lock & Self::WRITE_LOCK_MASK) != 0)
+                .then_some((lock & !(Self::WRITE_LOCK_MASK | Self::READ_LOCK_MASK)) + 1)
+        });
+        if swapped.is_err() {
+            self.forget_write();
+            return Err(Error::NotLocked);
+        }
+        // A writer can't hold any read locks, so this is all it holds now.
+        self.current_lock.store(1, self.store_order());
+        self.order_fence();
+        self.wake();
+        #[cfg(feature = "lock_hooks")]
+        {
+            self.hook_release(true);
+            self.hook_acquire(&Ok(()), false);
+        }
+        #[cfg(feature = "tracing")]
+        self.trace_event("Write lock downgraded", None);
+        Ok(())
+    }
+
+    /// Forget this instance holds the write lock, leaving the shared state alone. For when somebody else
+    /// [adopted][RWLock::adopt_write] it, forced it open or was handed it. The hooks hear about it as a release.
+    pub(crate) fn forget_write(&self) {
+        #[cfg(feature = "lock_hooks")]
+        if (self.current_lock.load(self.load_order()) & Self::HOLDING_W) != 0 {
+            self.hook_release(true);
+        }
+        self.current_lock.fetch_and(!Self::HOLDING_W, self.rmw_order());
+    }
+
+    /// Wake up anyone in this process sleeping in [`RWLock::wait_for_unlock`]. Does nothing without the
+    /// `wake_by_address` feature, as nobody sleeps then.
+    #[inline(always)]
+    fn wake(&self) {
+        // Safety: the address is the lock word, which lives as long as we do. Waking nobody is fine.
+        #[cfg(feature = "wake_by_address")]
+        unsafe {
+            WakeByAddressAll(self.chunk.as_ptr().cast())
+        };
+    }
+
+    /// Sleep until nobody holds the write lock, or error with [`Error::Timeout`] once `timeout` runs out. Doesn't take
+    /// the lock, so try to take it afterwards and expect to lose that race every now and then.
+    ///
+    /// Rather than spinning, this sleeps on the lock word with `WaitOnAddress`, and
+    /// [`unlock_write`][MMFLock::unlock_write] wakes it up with `WakeByAddressAll`. That only works within a single
+    /// process though: a writer in another process, or one built without the `wake_by_address` feature, never wakes
+    /// anyone. So the sleeps are cut short every millisecond or so to check again, which is still a lot cheaper than
+    /// spinning. Waking up late is all that can happen when mixing processes with and without the feature, the lock
+    /// state is the same either way.
+    ///
+    /// Errors with [`Error::Uninitialized`] if the lock isn't initialized, and with [`Error::WriteLocked`] if this very
+    /// instance holds the write lock, as waiting on yourself would never end.
+    #[cfg(feature = "wake_by_address")]
+    pub fn wait_for_unlock(&self, timeout: Duration) -> MMFResult<()> {
+        /// Longest sleep in between checks, for wakeups that never come.
+        const SLICE: Duration = Duration::from_millis(1);
+        if !self.initialized() {
+            return Err(Error::Uninitialized);
+        } else if (self.current_lock.load(self.load_order()) & Self::HOLDING_W) != 0 {
+            return Err(Error::WriteLocked);
+        }
+        let started = Instant::now();
+        loop {
+            self.order_fence();
+            let state = self.chunk.load(self.load_order());
+            if (state & Self::WRITE_LOCK_MASK) == 0 {
+                return Ok(());
+            }
+            let Some(left) = timeout.checked_sub(started.elapsed()).filter(|left| !left.is_zero()) else {
+                return Err(Error::Timeout);
+            };
+            // The unlock may land right after loading the state and before going to sleep. `WaitOnAddress` compares
+            // the lock word against the captured state before sleeping and returns right away if it changed, so the
+            // wakeup isn't lost. Spurious wakeups and timeouts end up back at the check above.
+            let sleep = left.min(SLICE).as_millis().max(1) as u32;
+            // Safety: both pointers are valid for 4 bytes, the lock word for as long as we do and the state for the
+            // duration of the call.
+            _ = unsafe { WaitOnAddress(self.chunk.as_ptr().cast(), (&state as *const u32).cast(), 4, sleep) };
+        }
+    }
+
+    /// Try to acquire the write lock until it works or `timeout` runs out.
+    ///
+    /// Returns `Ok(true)` when the lock was acquired and `Ok(false)` if the timeout elapsed first. Errors that won't go
+    /// away by waiting (see [`Error::is_retryable`]) and [`Error::Abandoned`] are returned right away. Rather than
+    /// counting tries, the clock is checked in between attempts, backing off according to the lock's [`SpinStrategy`].
+    /// This does mean the timeout can be overshot by however long a single backoff round takes.
+    #[track_caller]
+    pub fn lock_write_timeout(&self, timeout: Duration) -> MMFResult<bool> {
+        self.acquire_within(timeout, || self.try_lock_write())
+    }
+
+    /// Try to acquire a read lock until it works or `timeout` runs out.
+    ///
+    /// See [`RWLock::lock_write_timeout`], this behaves the same way.
+    #[track_caller]
+    pub fn lock_read_timeout(&self, timeout: Duration) -> MMFResult<bool> {
+        self.acquire_within(timeout, || self.try_lock_read())
+    }
+
+    /// Take a read lock like [`try_lock_read`][MMFLock::try_lock_read] does, returning the amount of readers right
+    /// after ours registered. That's 1 for the first reader in.
+    ///
+    /// The count comes from the very compare-and-swap that registered us, so it's exact at that moment, unlike a
+    /// [`snapshot`][RWLock::snapshot] taken afterwards. Other readers may have come and gone by the time you look at
+    /// it, but a count of 1 does mean nobody else held a read lock when we got ours. Handy for lazy initialization.
+    #[track_caller]
+    pub fn try_lock_read_counting(&self) -> MMFResult<u32> {
+        let res = if !self.initialized() {
+            Err(Error::Uninitialized)
+        } else if self.is_poisoned() {
+            Err(Error::Poisoned)
+        } else if self.writelocked() {
+            Err(Error::WriteLocked)
+        } else if (self.current_lock.load(self.load_order()) & Self::HOLDING_R) >= self.local_limit {
+            Err(Error::LocalReaderLimit)
+        } else {
+            self.order_fence();
+            // The checks above are only a fast path, a writer can show up right after them. What counts is the value
+            // being swapped out, and our local state only changes once the shared state definitely has.
+            let mut lock = self.chunk.load(self.load_order());
+            let ret = loop {
+                // This instance might think it's initialized while somebody else is still busy with the shared state.
+                if !self.shared_initialized() {
+                    break Err(Error::Uninitialized);
+                } else if (lock & Self::WRITE_LOCK_MASK) != 0 {
+                    break Err(if (lock & Self::READ_LOCK_MASK) != 0 { Error::Poisoned } else { Error::WriteLocked });
+                } else if (lock & Self::READ_LOCK_MASK) == Self::READ_LOCK_MASK {
+                    break Err(Error::MaxReaders);
+                }
+                // The counter isn't saturated, so this can't carry into the bits above it.
+                match self.chunk.compare_exchange_weak(lock, lock + 1, self.rmw_order(), self.load_order()) {
+                    Ok(_) => {
+                        self.current_lock.fetch_add(1, self.rmw_order());
+                        break Ok((lock & Self::READ_LOCK_MASK) + 1);
+                    }
+                    Err(current) => lock = current,
+                }
+            };
+            self.order_fence();
+            ret
+        };
+        #[cfg(feature = "metrics")]
+        self.counters.record(&res, &self.counters.read_acquired, &self.counters.read_contended);
+        #[cfg(feature = "lock_hooks")]
+        self.hook_acquire(&res, false);
+        #[cfg(feature = "tracing")]
+        if res.is_ok() {
+            self.trace_event("Read lock acquired", None);
+        }
+        res
+    }
+
+    /// Take a read lock like [`lock_read`][MMFLock::lock_read] does, returning the amount of readers right after ours
+    /// registered. See [`try_lock_read_counting`][RWLock::try_lock_read_counting].
+    ///
+    /// ```
+    /// # use winmmf::states::*;
+    /// let mut bop = vec![0u32; RWLock::SIZE / 4];
+    /// let lock = unsafe { RWLock::from_raw(bop.as_mut_ptr().cast()).initialize() };
+    /// if lock.lock_read_counting().unwrap() == 1 {
+    ///     // First one in, set things up
+    /// }
+    /// # lock.unlock_read().unwrap();
+    /// ```
+    #[track_caller]
+    pub fn lock_read_counting(&self) -> MMFResult<u32> {
+        self.acquire_spinning(|| self.try_lock_read_counting())
+    }
+
+    /// Take the write lock like [`try_lock_write`][MMFLock::try_lock_write] does, returning what the lock looked like
+    /// right before we took it.
+    ///
+    /// Like for [`try_lock_read_counting`][RWLock::try_lock_read_counting], the shared state comes from the
+    /// compare-and-swap that took the lock, and the local state from the update marking it as ours. Seeing as the
+    /// write lock is only ever taken from a free lock, that snapshot shows no readers and no writer, unless this
+    /// instance thought it held a read lock. Anything else is a bug worth reporting.
+    #[track_caller]
+    pub fn try_lock_write_state(&self) -> MMFResult<LockSnapshot> {
+        let res = if !self.initialized() {
             Err(Error::Uninitialized)
+        } else if self.is_poisoned() {
+            Err(Error::Poisoned)
         } else if self.writelocked() {
             Err(Error::WriteLocked)
         } else if self.readlocked() {
             Err(Error::ReadLocked)
+        } else if self.abandoned.load(self.load_order()) != 0
+            && self.abandoned.compare_exchange(1, 0, self.rmw_order(), self.load_order()).is_ok()
+        {
+            // Only one writer gets to hear about it, whoever swapped the flag out.
+            Err(Error::Abandoned)
         } else {
-            fence(Ordering::AcqRel);
-            self.chunk
-                .fetch_update(Ordering::AcqRel, Ordering::Acquire, |lock| {
-                    self.current_lock.fetch_or(Self::HOLDING_W, Ordering::AcqRel);
-                    Some(lock | Self::WRITE_LOCK_MASK)
-                })
-                .map(|_| ())
-                .map_err(|_| Error::GeneralFailure)
+            self.order_fence();
+            // A plain CAS loop, so our local state only changes once the shared state definitely has.
+            let mut lock = self.chunk.load(self.load_order());
+            loop {
+                if !self.shared_initialized() {
+                    break Err(Error::Uninitialized);
+                } else if (lock & Self::WRITE_LOCK_MASK) != 0 {
+                    break Err(Error::WriteLocked);
+                } else if (lock & Self::READ_LOCK_MASK) != 0 {
+                    break Err(Error::ReadLocked);
+                }
+                match self.chunk.compare_exchange_weak(
+                    lock,
+                    lock | Self::WRITE_LOCK_MASK,
+                    self.rmw_order(),
+                    self.load_order(),
+                ) {
+                    Ok(_) => {
+                        let local = self.current_lock.fetch_or(Self::HOLDING_W, self.rmw_order());
+                        let (pid, stamp) = current_owner();
+                        self.heartbeat.store(0, self.store_order());
+                        self.owner_stamp.store(stamp, self.store_order());
+                        self.owner_pid.store(pid, self.store_order());
+                        break Ok(self.snapshot_of(lock, local));
+                    }
+                    Err(current) => lock = current,
+                }
+            }
+        };
+        #[cfg(feature = "metrics")]
+        self.counters.record(&res, &self.counters.write_acquired, &self.counters.write_contended);
+        #[cfg(feature = "lock_hooks")]
+        self.hook_acquire(&res, true);
+        #[cfg(feature = "tracing")]
+        if res.is_ok() {
+            self.trace_event("Write lock acquired", None);
+        }
+        res
+    }
+
+    /// Take the write lock like [`lock_write`][MMFLock::lock_write] does, returning what the lock looked like right
+    /// before we took it. See [`try_lock_write_state`][RWLock::try_lock_write_state].
+    #[track_caller]
+    pub fn lock_write_state(&self) -> MMFResult<LockSnapshot> {
+        self.acquire_spinning(|| self.try_lock_write_state())
+    }
+
+    /// Keep trying `acquire` until it succeeds or fails for good, spinning in between like
+    /// [`lock_read_or_spin`][MMFLock::lock_read_or_spin] does without a limit on the tries.
+    fn acquire_spinning<T>(&self, acquire: impl Fn() -> MMFResult<T>) -> MMFResult<T> {
+        let mut tries = 0;
+        loop {
+            match acquire() {
+                Ok(value) => return Ok(value),
+                Err(e) if !e.worth_waiting() => return Err(e),
+                Err(_) => _ = self.spin_budget(&mut tries, usize::MAX)?,
+            }
+        }
+    }
+
+    /// Keep trying `acquire` until it succeeds, fails for good, or the timeout elapses.
+    fn acquire_within(&self, timeout: Duration, acquire: impl Fn() -> MMFResult<()>) -> MMFResult<bool> {
+        let started = Instant::now();
+        let mut tries = 0;
+        loop {
+            match acquire() {
+                Ok(()) => return Ok(true),
+                Err(e) if !e.worth_waiting() => return Err(e),
+                Err(_) if started.elapsed() >= timeout => return Ok(false),
+                Err(_) => {
+                    tries += 1;
+                    self.contended(tries);
+                }
+            }
+        }
+    }
+}
+
+/// Counts of lock operations on a single [`RWLock`] instance, see [`RWLock::metrics`].
+#[cfg(feature = "metrics")]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct LockMetrics {
+    /// Read lock attempts that failed because of another lock.
+    pub read_contended: u64,
+    /// Write lock attempts that failed because of another lock.
+    pub write_contended: u64,
+    /// Successfully acquired read locks.
+    pub read_acquired: u64,
+    /// Successfully acquired write locks.
+    pub write_acquired: u64,
+}
+
+/// The live counters behind [`LockMetrics`]. These live next to `current_lock`, not in the shared memory.
+#[cfg(feature = "metrics")]
+#[derive(Debug, Default)]
+struct LockCounters {
+    /// See [`LockMetrics::read_contended`].
+    read_contended: AtomicU64,
+    /// See [`LockMetrics::write_contended`].
+    write_contended: AtomicU64,
+    /// See [`LockMetrics::read_acquired`].
+    read_acquired: AtomicU64,
+    /// See [`LockMetrics::write_acquired`].
+    write_acquired: AtomicU64,
+}
+
+#[cfg(feature = "metrics")]
+impl LockCounters {
+    /// Bump the relevant counter for the result of a locking attempt. Errors other than contention aren't counted.
+    fn record<T>(&self, res: &MMFResult<T>, acquired: &AtomicU64, contended: &AtomicU64) {
+        match res {
+            Ok(_) => _ = acquired.fetch_add(1, Ordering::Relaxed),
+            Err(Error::ReadLocked | Error::WriteLocked | Error::MaxReaders) => {
+                _ = contended.fetch_add(1, Ordering::Relaxed)
+            }
+            Err(_) => {}
         }
     }
+}
+
+/// The state of an [`RWLock`] at a single point in time, see [`RWLock::snapshot`].
+#[cfg(feature = "impl_lock")]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct LockSnapshot {
+    /// Whether the shared state has the write bit set.
+    pub write_locked: bool,
+    /// The amount of readers registered in the shared state, across all instances.
+    pub reader_count: u32,
+    /// Whether the lock was initialized.
+    pub initialized: bool,
+    /// The amount of read locks held by this instance.
+    pub local_reader_count: u8,
+    /// Whether this instance holds the write lock.
+    pub local_write_held: bool,
+}
+
+/// One-line summary for logging purposes.
+#[cfg(feature = "impl_lock")]
+impl fmt::Display for LockSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "init={}, write={}, readers={} (local: write={}, readers={})",
+            self.initialized, self.write_locked, self.reader_count, self.local_write_held, self.local_reader_count
+        )
+    }
+}
+
+/// One-liner for log lines, like `RWLock[W:0 R:3]`: whether the write lock is held and how many readers there are,
+/// across everyone. ` poisoned` and ` uninit` are tacked on when they apply. See [`RWLock::describe`] for a wordier
+/// take on the same, and [`Debug`][fmt::Debug] for what this instance holds.
+#[cfg(feature = "impl_lock")]
+impl fmt::Display for RWLock<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.snapshot();
+        write!(f, "RWLock[W:{} R:{}", u8::from(state.write_locked), state.reader_count)?;
+        if state.write_locked && state.reader_count > 0 {
+            f.write_str(" poisoned")?;
+        }
+        if !state.initialized {
+            f.write_str(" uninit")?;
+        }
+        f.write_str("]")
+    }
+}
+
+/// Both the shared state and what this instance holds, from a single [`snapshot`][RWLock::snapshot]. The settings of
+/// the instance are left out, they don't tell you anything about who holds what.
+#[cfg(feature = "impl_lock")]
+impl fmt::Debug for RWLock<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.snapshot();
+        f.debug_struct("RWLock")
+            .field("global", &format_args!("{{ write: {}, readers: {} }}", state.write_locked, state.reader_count))
+            .field(
+                "local",
+                &format_args!("{{ write_held: {}, read_count: {} }}", state.local_write_held, state.local_reader_count),
+            )
+            .field("initialized", &state.initialized)
+            .finish()
+    }
+}
+
+/// Builder for [`RWLock`], to set all of the knobs and dials before the lock is used for the first time.
+///
+/// ## Usage
+/// ```
+/// # use winmmf::{states::*, *};
+/// let mut bop = vec![0u32; RWLock::SIZE / 4];
+/// let lock = unsafe {
+///     RWLock::builder()
+///         .max_spin_count(1_000)
+///         .strategy(SpinStrategy::Yield)
+///         .on_contention(|tries| eprintln!("Still waiting after {tries} tries"))
+///         .build(bop.as_mut_ptr().cast())
+/// };
+/// assert!(lock.initialized());
+/// assert_eq!(lock.spin_strategy(), SpinStrategy::Yield);
+/// ```
+#[cfg(feature = "impl_lock")]
+#[derive(Debug, Clone, Copy)]
+pub struct RWLockBuilder {
+    /// See [`OrderingProfile`].
+    ordering: OrderingProfile,
+    /// See [`SpinStrategy`].
+    strategy: SpinStrategy,
+    /// Maximum amount of tries when spinning.
+    max_spins: usize,
+    /// Maximum amount of read locks held by the instance at once.
+    local_limit: u8,
+    /// Whether spinning recovers abandoned write locks.
+    robust: bool,
+    /// Diagnostic callback for contention when spinning.
+    on_contention: Option<fn(usize)>,
+}
+
+#[cfg(feature = "impl_lock")]
+impl Default for RWLockBuilder {
+    /// The same settings a lock gets when created through [`MMFLock::from_raw`].
+    fn default() -> Self {
+        Self {
+            ordering: OrderingProfile::Default,
+            strategy: SpinStrategy::Busy,
+            max_spins: usize::MAX,
+            local_limit: RWLock::HOLDING_R,
+            robust: false,
+            on_contention: None,
+        }
+    }
+}
+
+#[cfg(feature = "impl_lock")]
+impl RWLockBuilder {
+    /// Set the [`OrderingProfile`] for the lock.
+    pub fn ordering(mut self, ordering: OrderingProfile) -> Self {
+        self.ordering = ordering;
+        self
+    }
+
+    /// Set the [`SpinStrategy`] for the lock.
+    pub fn strategy(mut self, strategy: SpinStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Set the maximum amount of tries when spinning, before [`spin`][MMFLock::spin] errors and the spinning lock
+    /// methods give up. Defaults to `usize::MAX`.
+    pub fn max_spin_count(mut self, max_spins: usize) -> Self {
+        self.max_spins = max_spins;
+        self
+    }
+
+    /// Set the maximum amount of read locks the instance may hold at once. See [`RWLock::with_local_limit`].
+    pub fn local_limit(mut self, limit: u8) -> Self {
+        self.local_limit = limit;
+        self
+    }
+
+    /// Set whether spinning recovers write locks abandoned by dead processes. See [`RWLock::with_robust`].
+    pub fn robust(mut self, robust: bool) -> Self {
+        self.robust = robust;
+        self
+    }
+
+    /// Set a callback that gets called with the amount of tries so far, whenever spinning runs into a held lock.
+    pub fn on_contention(mut self, callback: fn(usize)) -> Self {
+        self.on_contention = Some(callback);
+        self
+    }
+
+    /// Apply the configuration to a lock.
+    fn apply<'a>(&self, mut lock: RWLock<'a>) -> RWLock<'a> {
+        lock.ordering = self.ordering;
+        lock.strategy = self.strategy;
+        lock.max_spins = self.max_spins;
+        lock.robust = self.robust;
+        lock.on_contention = self.on_contention;
+        lock.with_local_limit(self.local_limit)
+    }
+
+    /// Create a fresh lock using [`MMFLock::from_raw`], configure it and initialize it.
+    ///
+    /// # Safety
+    /// See [`MMFLock::from_raw`].
+    #[track_caller]
+    pub unsafe fn build<'a>(&self, pointer: *mut u8) -> RWLock<'a> {
+        self.apply(RWLock::from_raw(pointer)).initialize()
+    }
+
+    /// Attach to an existing lock using [`MMFLock::from_existing`] and configure it.
+    ///
+    /// # Safety
+    /// See [`MMFLock::from_existing`].
+    #[track_caller]
+    pub unsafe fn build_existing<'a>(&self, pointer: *mut u8) -> RWLock<'a> {
+        self.apply(RWLock::from_existing(pointer))
+    }
+}
+
+#[cfg(feature = "impl_lock")]
+/// The PID and creation stamp of the current process, as registered in the write lock owner fields.
+fn current_owner() -> (u32, u32) {
+    /// Computing this once is enough, it doesn't change while the process lives.
+    static OWNER: OnceLock<(u32, u32)> = OnceLock::new();
+    *OWNER.get_or_init(|| {
+        // Safety: the pseudo handle for the current process is always valid and doesn't need closing.
+        unsafe { (GetCurrentProcessId(), process_stamp(GetCurrentProcess()).unwrap_or(0)) }
+    })
+}
+
+#[cfg(feature = "impl_lock")]
+/// Get a stamp for a process handle, derived from the process creation time in milliseconds.
+///
+/// This is truncated to 32 bits, which wraps around every 49 days. Considering it's only used to tell apart two
+/// processes with the same PID, that's plenty.
+fn process_stamp(process: HANDLE) -> Option<u32> {
+    let (mut created, mut exited, mut kernel, mut user) =
+        (FILETIME::default(), FILETIME::default(), FILETIME::default(), FILETIME::default());
+    // Safety: all pointers are to locals that live for the duration of the call.
+    unsafe { GetProcessTimes(process, &mut created, &mut exited, &mut kernel, &mut user) }.ok()?;
+    let ticks = ((created.dwHighDateTime as u64) << 32) | created.dwLowDateTime as u64;
+    // Never return 0, that's what "no stamp registered" looks like.
+    Some(((ticks / 10_000) as u32).max(1))
+}
+
+#[cfg(feature = "impl_lock")]
+/// Check if the process with the given PID lives, and is the same process that registered the stamp.
+///
+/// A stamp of 0 means the owner didn't register one, in which case only the PID is checked.
+fn process_alive(pid: u32, stamp: u32) -> bool {
+    if (pid, stamp) == current_owner() {
+        return true;
+    }
+    // Safety: OpenProcess errors if the process doesn't exist, and we close the handle when done.
+    let Ok(process) = (unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid) }) else {
+        return false;
+    };
+    let mut code = 0;
+    // Safety: the handle was just opened with the rights required for these calls.
+    let running = unsafe { GetExitCodeProcess(process, &mut code) }.is_ok() && code == STILL_ACTIVE.0 as u32;
+    let same = stamp == 0 || process_stamp(process).map_or(true, |s| s == stamp);
+    unsafe { _ = CloseHandle(process) };
+    running && same
+}
+
+#[cfg(feature = "impl_lock")]
+/// Implements a good enough implementation of a lock for MMFs
+impl MMFLock for RWLock<'_> {
+    /// Construct a lock from existing pointers.
+    ///
+    /// This is meant to be used with some external mechanism to allow reading and writing lock state directly to and
+    /// from some larger struct. The lock will claim the first [`Self::SIZE`] bytes behind this pointer; if you do not
+    /// intend to have the lock state at the start of the data, make sure to offset the pointer provided.
+    ///
+    /// # Safety
+    /// there is no way of ensuring this pointer is valid after the first byte without moving out of bounds when
+    /// it's not. Users should take care to ensure the first [`Self::SIZE`] bytes in the pointer are valid. A lock
+    /// created through this method provides NO guarantees about the lock states and assumes the user has zeroed out all
+    /// values behind it if necessary. This initializer is meant to be used with a pointer for an existing lock, or a
+    /// pointer whose values you know will provide correct results. If the data behind the pointer is wrong, this
+    /// effectively constructs a poisoned lock.
+    /// It _is_ safe to assume the size and alignment are valid on Windows, however, as pointers are 0x4/0x4 or 0x8/0x8
+    /// depending on 32-bit or 64-bit. Either is safe for use with AtomicU32 which is 0x4/0x4 on these platforms.
+    ///
+    /// ## Panics
+    /// This function _will_ panic if called with a null pointer; ensuring initialization is hard, but ensuring non-null
+    /// should not prove difficult to anyone working with raw pointers. The panic points at the line calling this.
+    ///
+    /// ## example
+    /// ```
+    /// # use winmmf::{states::*, *};
+    /// # unsafe {
+    /// let mut bop = vec![0u32; RWLock::SIZE / 4];
+    /// let ptr = bop.as_mut_ptr();
+    /// let lock = RWLock::from_raw(ptr.cast());
+    /// lock.set_init();
+    /// // You're now free to do anything with the lock while `bop` lives
+    /// let new_ptr = bop.as_mut_ptr();
+    /// let other_lock = RWLock::from_existing(new_ptr.cast());
+    ///
+    /// lock.try_lock_write().unwrap();
+    ///
+    /// assert!(other_lock.writelocked());
+    /// assert!(!other_lock.readlocked());
+    /// assert!(other_lock.try_lock_read().is_err());
+    ///
+    /// assert!(other_lock.unlock_write().is_err());
+    /// assert!(lock.unlock_write().is_ok());
+    /// # }
+    /// ```
+    unsafe fn from_existing(pointer: *mut u8) -> Self {
+        if pointer.is_null() {
+            panic!("Never, ever pass a null pointer into a lock!")
+        }
+        Self::from_ptr(pointer, 0)
+    }
+
+    /// Similar to [`Self::from_existing`], except it clears all state and ensures [`Self::initialized`] returns false.
+    ///
+    /// # Safety
+    /// The same safety bounds apply as for `from_existing` with the exception of poisoned lock risks. It does mean,
+    /// however, that it invalidates any other locks that use the same pointer and clears any data.
+    unsafe fn from_raw(pointer: *mut u8) -> Self {
+        if pointer.is_null() {
+            panic!("Never, ever pass a null pointer into a lock!")
+        }
+        let lock = Self::from_ptr(pointer, 255);
+        lock.owner_pid.store(0, Ordering::Release);
+        lock.owner_stamp.store(0, Ordering::Release);
+        lock.heartbeat.store(0, Ordering::Release);
+        lock.generation.store(0, Ordering::Release);
+        lock.abandoned.store(0, Ordering::Release);
+        lock.chunk.store(0, Ordering::Release);
+        lock.init.store(Self::UNINITIALIZED, Ordering::Release);
+        lock
+    }
+
+    /// Like [`from_existing`][MMFLock::from_existing], remembering the name for traces with the `tracing` feature.
+    ///
+    /// # Safety
+    /// See [`from_existing`][MMFLock::from_existing].
+    #[cfg(feature = "tracing")]
+    unsafe fn from_existing_named(pointer: *mut u8, name: &str) -> Self {
+        let mut lock = Self::from_existing(pointer);
+        lock.name = name.into();
+        lock
+    }
+
+    /// Like [`from_raw`][MMFLock::from_raw], remembering the name for traces with the `tracing` feature.
+    ///
+    /// # Safety
+    /// See [`from_raw`][MMFLock::from_raw].
+    #[cfg(feature = "tracing")]
+    unsafe fn from_raw_named(pointer: *mut u8, name: &str) -> Self {
+        let mut lock = Self::from_raw(pointer);
+        lock.name = name.into();
+        lock
+    }
+
+    /// Mark this lock as initialized if it isn't yet, or finish an initialization claimed through
+    /// [`try_claim_init`][MMFLock::try_claim_init].
+    ///
+    /// In pre-0.3 versions of this crate, this would clear existing locks. This is a bad idea though, as a naive caller
+    /// might not realize they're not the only process using the MMF. Only the init state is touched, so that's no
+    /// longer a concern.
+    fn set_init(&self) {
+        self.order_fence();
+        self.init.store(Self::INITIALIZED, Ordering::Release);
+        _ = self.current_lock.compare_exchange(255, 0, Ordering::Release, Ordering::Relaxed);
+        self.order_fence();
+    }
+
+    /// Thin wrapper around [`Self::set_init`] that returns self for chaining calls.
+    ///
+    /// # Safety
+    /// The same safety concerns apply as for `set_init`.
+    ///
+    /// ## Usage
+    /// ```
+    /// # use winmmf::{states::*, *};
+    /// let mut bop = vec![0u32; RWLock::SIZE / 4];
+    /// let ptr = bop.as_mut_ptr();
+    /// let lock = unsafe { RWLock::from_existing(ptr.cast()).initialize() };
+    /// assert!(lock.initialized());
+    /// ```
+    fn initialize(self) -> Self {
+        self.set_init();
+        self
+    }
+
+    /// Move the shared state from uninitialized to [`RWLock::INITIALIZING`], if nobody beat us to it.
+    fn try_claim_init(&self) -> bool {
+        self.order_fence();
+        let won = self
+            .init
+            .compare_exchange(Self::UNINITIALIZED, Self::INITIALIZING, self.rmw_order(), self.load_order())
+            .is_ok();
+        self.order_fence();
+        won
+    }
+
+    /// Like the default implementation, except it waits on the shared state. Backs off according to the lock's
+    /// [`SpinStrategy`] while waiting.
+    fn init_or_wait(&self, timeout: Duration) -> MMFResult<bool> {
+        if self.try_claim_init() {
+            self.set_init();
+            return Ok(true);
+        }
+        self.wait_initialized(timeout).map(|_| false)
+    }
+
+    /// Like the default implementation, except it waits on the shared state: an instance attached to an existing lock
+    /// counts itself as initialized, which is exactly what can't be trusted here. Backs off according to the lock's
+    /// [`SpinStrategy`] while waiting.
+    ///
+    /// ## Why this is enough
+    /// Initializing goes [`from_raw`][MMFLock::from_raw] (the init state becomes [`RWLock::UNINITIALIZED`]),
+    /// [`try_claim_init`][MMFLock::try_claim_init] ([`RWLock::INITIALIZING`]), setting up whatever else lives in the
+    /// MMF, and finally [`set_init`][MMFLock::set_init]. That last one stores [`RWLock::INITIALIZED`] with `Release`
+    /// ordering, after everything else. Waiting loads it with `Acquire` at least, whatever the [`OrderingProfile`], so
+    /// once this sees the magic value, it sees everything written before it too. Any other value in the init word,
+    /// like the zeroes of a fresh MMF or leftovers from an older version, keeps it waiting.
+    fn wait_initialized(&self, timeout: Duration) -> MMFResult<()> {
+        let started = Instant::now();
+        let mut tries = 0;
+        while !self.shared_initialized() {
+            if started.elapsed() >= timeout {
+                return Err(Error::Uninitialized);
+            }
+            tries += 1;
+            self.strategy.pause(tries);
+        }
+        _ = self.current_lock.compare_exchange(255, 0, Ordering::Release, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// The lock state, owner PID and owner stamp. See [`RWLock::SIZE`].
+    fn lock_size() -> usize {
+        Self::SIZE
+    }
+
+    /// Check if this lock has been initialized at all.
+    ///
+    /// The init state must be exactly [`RWLock::INITIALIZED`], regardless of locking state and whatever is in the
+    /// spare bits. An instance that was initialized or attached to an existing lock also counts itself as initialized.
+    #[inline(always)]
+    fn initialized(&self) -> bool {
+        self.shared_initialized() || self.current_lock.load(self.load_order()) < 255
+    }
+
+    /// Check if the lock is held for reading. This should only prevent new write locks.
+    #[inline(always)]
+    fn readlocked(&self) -> bool {
+        self.order_fence();
+        (self.chunk.load(self.load_order()) & Self::READ_LOCK_MASK) > 0
+            || (self.current_lock.load(self.load_order()) & Self::HOLDING_R) > 0
+    }
+
+    /// Check if the lock is held for writing. This should prevent ALL other locking operations.
+    #[inline(always)]
+    fn writelocked(&self) -> bool {
+        self.order_fence();
+        (self.chunk.load(self.load_order()) & Self::WRITE_LOCK_MASK) == Self::WRITE_LOCK_MASK
+            || (self.current_lock.load(self.load_order()) & Self::HOLDING_W) == Self::HOLDING_W
+    }
+
+    /// Check if the shared state has both the write bit and a nonzero reader count.
+    ///
+    /// Only the shared state is considered here, as this instance can't ever hold both kinds of locks.
+    #[inline(always)]
+    fn is_poisoned(&self) -> bool {
+        self.order_fence();
+        let lock = self.chunk.load(self.load_order());
+        (lock & Self::WRITE_LOCK_MASK) != 0 && (lock & Self::READ_LOCK_MASK) != 0
+    }
+
+    fn generation(&self) -> Option<u32> {
+        self.order_fence();
+        Some(self.generation.load(self.load_order()))
+    }
+
+    /// Check if the locks are any non-zero value, ignoring the spare bits. Uninitialized locks are considered held.
+    #[inline(always)]
+    fn locked(&self) -> bool {
+        self.order_fence();
+        (self.chunk.load(self.load_order()) & !Self::SPARE_MASK) > 0
+            || self.current_lock.load(self.load_order()) > 0
+            || !self.shared_initialized()
+    }
+
+    /// Increment the counter for read locks ***if and only if*** we can safely lock this for reading
+    fn try_lock_read(&self) -> MMFResult<()> {
+        self.try_lock_read_counting().map(|_| ())
+    }
+
+    /// Decrease the read lock counter if we can safely do so.
+    ///
+    /// Errors with [`Error::NotLocked`] if this instance doesn't hold a read lock. If it does, but the shared counter
+    /// is already at zero, somebody released more than they held and the lock is [`Error::Poisoned`].
+    fn unlock_read(&self) -> MMFResult<()> {
+        let res = if !self.initialized() {
+            Err(Error::Uninitialized)
+        } else if self.writelocked() {
+            Err(Error::WriteLocked)
+        } else if (self.current_lock.load(self.load_order()) & Self::HOLDING_R) == 0 {
+            Err(Error::NotLocked)
+        } else {
+            self.order_fence();
+            let mut lock = self.chunk.load(self.load_order());
+            let ret = loop {
+                if (lock & Self::READ_LOCK_MASK) == 0 {
+                    break Err(Error::Poisoned);
+                }
+                match self.chunk.compare_exchange_weak(lock, lock - 1, self.rmw_order(), self.load_order()) {
+                    Ok(_) => {
+                        self.current_lock.fetch_sub(1, self.rmw_order());
+                        break Ok(());
+                    }
+                    Err(current) => lock = current,
+                }
+            };
+            self.order_fence();
+            ret
+        };
+        #[cfg(feature = "lock_hooks")]
+        if res.is_ok() {
+            self.hook_release(false);
+        }
+        #[cfg(feature = "tracing")]
+        if res.is_ok() {
+            self.trace_event("Read lock released", None);
+        }
+        res
+    }
+
+    /// Set the write lock bit to 1 if possible.
+    fn try_lock_write(&self) -> MMFResult<()> {
+        self.try_lock_write_state().map(|_| ())
+    }
 
     /// Release a write lock if we're the ones holding it
     fn unlock_write(&self) -> MMFResult<()> {
@@ -361,64 +2251,87 @@ impl MMFLock for RWLock<'_> {
             return Ok(());
         }
         if !self.initialized() {
-            Err(Error::Uninitialized)
-        } else {
-            fence(Ordering::AcqRel);
-            self.chunk
-                .fetch_update(Ordering::AcqRel, Ordering::Acquire, |lock| {
-                    if (self.current_lock.load(Ordering::Acquire) & Self::HOLDING_W) == 0 {
-                        None
-                    } else {
-                        self.current_lock.fetch_xor(Self::HOLDING_W, Ordering::AcqRel);
-                        Some(lock ^ Self::WRITE_LOCK_MASK)
-                    }
-                })
-                .map(|_| ())
-                .map_err(|_| Error::GeneralFailure)
+            return Err(Error::Uninitialized);
+        }
+        if (self.current_lock.load(self.load_order()) & Self::HOLDING_W) == 0 {
+            return Err(Error::GeneralFailure);
+        }
+        self.order_fence();
+        // Unregister before the lock is released, or we might clear the next owner's registration.
+        self.owner_pid.store(0, self.store_order());
+        self.owner_stamp.store(0, self.store_order());
+        // Bumped while still holding the lock, so a reader never sees the new generation alongside the old data.
+        self.bump_generation();
+        let mut lock = self.chunk.load(self.load_order());
+        // If somebody force unlocked us in the meantime, there's nothing left to clear in the shared state.
+        while (lock & Self::WRITE_LOCK_MASK) != 0 {
+            match self.chunk.compare_exchange_weak(
+                lock,
+                lock & !Self::WRITE_LOCK_MASK,
+                self.rmw_order(),
+                self.load_order(),
+            ) {
+                Ok(_) => break,
+                Err(current) => lock = current,
+            }
         }
+        self.current_lock.fetch_and(!Self::HOLDING_W, self.rmw_order());
+        self.order_fence();
+        self.wake();
+        #[cfg(feature = "lock_hooks")]
+        self.hook_release(true);
+        #[cfg(feature = "tracing")]
+        self.trace_event("Write lock released", None);
+        Ok(())
     }
 
     /// Very naive spinning implementation. Runs a finite amount of times.
     ///
-    /// This spinning implementation just checks if the lock is held for as many times as it needs to. If it encounters
-    /// the upper bound of the native pointer size before the lock is released, it returns an error.
+    /// This spinning implementation just checks if the lock is held for as many times as it needs to, waiting in
+    /// between according to the lock's [`SpinStrategy`]. If it encounters the configured maximum amount of tries
+    /// (defaulting to the upper bound of the native pointer size) before the lock is released, it returns an error.
     /// If uni taught me one thing, it would be that `while true` on locks will eventually lead to the big funny.
     fn spin(&self, tries: &mut usize) -> MMFResult<bool> {
         tries.add_assign(1);
         let held = self.locked();
-        if usize::MAX.eq(tries) && held {
+        if self.max_spins.le(tries) && held {
             Err(Error::LockViolation)
         } else {
+            if held {
+                self.contended(*tries);
+            }
             Ok(held)
         }
     }
 
-    /// Very crude implementation of spinning with no backoff
+    /// Crude implementation of spinning, backing off according to the lock's [`SpinStrategy`]
     fn spin_and_lock_read(lock: &Self, max_tries: usize) -> MMFResult<()> {
         let mut tries = 0;
         if !lock.initialized() {
             return Err(Error::Uninitialized);
         }
-        while lock.lock_read().is_err() {
+        while lock.try_lock_read().is_err() {
             tries += 1;
-            if tries >= max_tries {
+            if tries >= max_tries.min(lock.max_spins) {
                 return Err(Error::WriteLocked);
             }
+            lock.contended(tries);
         }
         Ok(())
     }
 
-    /// Very crude implementation of spinning with no backoff
+    /// Crude implementation of spinning, backing off according to the lock's [`SpinStrategy`]
     fn spin_and_lock_write(lock: &Self, max_tries: usize) -> MMFResult<()> {
         let mut tries = 0;
         if !lock.initialized() {
             return Err(Error::Uninitialized);
         }
-        while lock.lock_write().is_err() {
+        while lock.try_lock_write().is_err() {
             tries += 1;
-            if tries >= max_tries {
+            if tries >= max_tries.min(lock.max_spins) {
                 return Err(Error::LockViolation);
             }
+            lock.contended(tries);
         }
         Ok(())
     }
@@ -0,0 +1,275 @@
+#![deny(clippy::missing_docs_in_private_items)]
+#![deny(missing_docs)]
+//! # First come, first served
+//!
+//! An [`RWLock`][crate::states::RWLock] goes to whoever happens to try at the right moment. Under contention that can
+//! be the same few threads over and over again, while an unlucky one waits for as long as the others keep at it. The
+//! [`TicketLock`] defined here hands out tickets instead, like the deli counter: everyone waits for their number to be
+//! called, so the lock is taken in the order it was asked for. The price is throughput, as the lock can't go to
+//! whoever is ready first, and readers don't get to share.
+
+use std::sync::atomic::{AtomicU32, AtomicU8, Ordering};
+
+use super::{
+    err::{Error, MMFResult},
+    states::{MMFLock, SpinStrategy},
+};
+
+/// A fair lock living in shared memory, see [the module docs][self].
+///
+/// Takes up [`TicketLock::SIZE`] bytes: a word with the tickets, followed by a word with the state.
+///
+/// ## The ticket word
+/// The lower 16 bits are the ticket being served, the upper 16 bits the next ticket to hand out. Taking a ticket adds
+/// one to the upper half, which simply drops off the top when it wraps around. Releasing the lock adds one to the
+/// lower half, which is masked so it never carries into the upper one. Both wrap around at `2^16` and are only ever
+/// compared for equality, so wrapping is fine as long as fewer than 65536 tickets are out at once. That's the holder
+/// and everyone waiting in line, across all processes. More than that and two of them end up with the same number.
+///
+/// ## The state word
+/// Bit 31 tells the lock is [initialized][MMFLock::initialized], the lowest two bits whether it's held for
+/// [reading][TicketLock::READ] or [writing][TicketLock::WRITE]. Readers don't share: a read lock keeps everyone else
+/// out just like a write lock does, it's only tracked separately so the probes and unlocks make sense.
+///
+/// ## Who gets to queue
+/// Only [`lock_read`][MMFLock::lock_read] and [`lock_write`][MMFLock::lock_write] (and the methods built on them, like
+/// [`with_write`][MMFLock::with_write]) take a ticket. Once taken, a ticket has to be served, there's no leaving the
+/// line without holding everyone behind you up forever. So those wait for as long as it takes, and never time out.
+///
+/// Everything with a limit, like the `try_` and `poll_` versions, [`lock_write_or_spin`][MMFLock::lock_write_or_spin]
+/// and the reads and writes of a [`MemoryMappedFile`][crate::mmf::MemoryMappedFile], only ever takes the lock when the
+/// line is empty. They can't jump the queue, but they don't get a place in it either, and might wait for a long time
+/// while others keep queueing.
+///
+/// The same goes for processes dying: a holder or waiter that's gone never gives its ticket back. There's no recovering
+/// from that, other than everyone agreeing to start over with a fresh lock.
+///
+/// ## Usage
+/// ```
+/// # use winmmf::{states::*, ticket::*, *};
+/// let mut bop = vec![0u32; TicketLock::SIZE / 4];
+/// let lock = unsafe { TicketLock::from_raw(bop.as_mut_ptr().cast()).initialize() };
+/// let other = unsafe { TicketLock::from_existing(bop.as_mut_ptr().cast()) };
+/// lock.lock_write().unwrap();
+/// assert!(matches!(other.try_lock_read(), Err(Error::WriteLocked)));
+/// lock.unlock_write().unwrap();
+/// other.lock_read().unwrap();
+/// assert!(lock.readlocked());
+/// ```
+#[derive(Debug)]
+pub struct TicketLock<'a> {
+    /// The ticket being served in the lower half, the next ticket in the upper half.
+    tickets: &'a AtomicU32,
+    /// Whether the lock is initialized, and how it's held.
+    state: &'a AtomicU32,
+    /// What this instance holds: nothing, [`TicketLock::READ`] or [`TicketLock::WRITE`].
+    held: AtomicU8,
+    /// What to do while waiting for our ticket to be called.
+    strategy: SpinStrategy,
+}
+
+impl TicketLock<'_> {
+    /// The amount of bytes claimed by the lock: the tickets and the state.
+    pub const SIZE: usize = 8;
+    /// State bit set once the lock is initialized.
+    pub const INITIALIZED: u32 = 1 << 31;
+    /// State value for a lock held for reading.
+    pub const READ: u32 = 0b01;
+    /// State value for a lock held for writing.
+    pub const WRITE: u32 = 0b10;
+    /// The bits of the state telling how the lock is held.
+    const MODE_MASK: u32 = 0b11;
+    /// One ticket, in the upper half of the ticket word.
+    const NEXT_ONE: u32 = 1 << 16;
+
+    /// Build the lock from a pointer, without touching the data behind it.
+    ///
+    /// # Safety
+    /// The pointer must be non-null, aligned to 4 and valid for [`Self::SIZE`] bytes for as long as the lock lives.
+    unsafe fn from_ptr<'a>(pointer: *mut u8) -> TicketLock<'a> {
+        let words = pointer.cast::<u32>();
+        TicketLock {
+            tickets: AtomicU32::from_ptr(words),
+            state: AtomicU32::from_ptr(words.add(1)),
+            held: AtomicU8::new(0),
+            strategy: SpinStrategy::Yield,
+        }
+    }
+
+    /// Chainable setter for the [`SpinStrategy`] used while waiting in line. Yields by default, as the wait can take a
+    /// while with a long line. Only affects this instance.
+    pub fn with_spin_strategy(mut self, strategy: SpinStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Get the amount of tickets handed out but not served yet, the holder included. 0 if nobody holds the lock.
+    pub fn queue_len(&self) -> u16 {
+        let (serving, next) = split(self.tickets.load(Ordering::Acquire));
+        next.wrapping_sub(serving)
+    }
+
+    /// Take the lock in `mode` if the line is empty, without queueing.
+    fn try_lock(&self, mode: u32) -> MMFResult<()> {
+        if !self.initialized() {
+            return Err(Error::Uninitialized);
+        } else if self.held.load(Ordering::Acquire) != 0 {
+            return Err(self.busy());
+        }
+        let tickets = self.tickets.load(Ordering::Acquire);
+        let (serving, next) = split(tickets);
+        if serving != next {
+            return Err(self.busy());
+        }
+        self.tickets
+            .compare_exchange(tickets, tickets.wrapping_add(Self::NEXT_ONE), Ordering::Acquire, Ordering::Relaxed)
+            .map_err(|_| self.busy())?;
+        self.hold(mode);
+        Ok(())
+    }
+
+    /// Take a ticket and wait for it to be called, then hold the lock in `mode`.
+    fn lock_fair(&self, mode: u32) -> MMFResult<()> {
+        if !self.initialized() {
+            return Err(Error::Uninitialized);
+        } else if self.held.load(Ordering::Acquire) != 0 {
+            // Queueing for a lock we hold would wait for ourselves, forever.
+            return Err(self.busy());
+        }
+        let (_, ticket) = split(self.tickets.fetch_add(Self::NEXT_ONE, Ordering::Acquire));
+        let mut tries = 0;
+        while split(self.tickets.load(Ordering::Acquire)).0 != ticket {
+            tries += 1;
+            self.strategy.pause(tries);
+        }
+        self.hold(mode);
+        Ok(())
+    }
+
+    /// Note that the lock is ours now, held in `mode`.
+    fn hold(&self, mode: u32) {
+        self.state.fetch_or(mode, Ordering::AcqRel);
+        self.held.store(mode as u8, Ordering::Release);
+    }
+
+    /// Let go of the lock held in `mode`, calling the next ticket.
+    fn unlock(&self, mode: u32) -> MMFResult<()> {
+        if u32::from(self.held.load(Ordering::Acquire)) != mode {
+            return Err(Error::NotLocked);
+        }
+        self.held.store(0, Ordering::Release);
+        self.state.fetch_and(!Self::MODE_MASK, Ordering::AcqRel);
+        // Only the holder ever touches the lower half, so nobody changes it under us. The upper half might though.
+        _ = self.tickets.fetch_update(Ordering::Release, Ordering::Relaxed, |tickets| {
+            Some((tickets & !0xFFFF) | (tickets.wrapping_add(1) & 0xFFFF))
+        });
+        Ok(())
+    }
+
+    /// The error for a lock somebody else holds or is waiting for.
+    fn busy(&self) -> Error {
+        match self.state.load(Ordering::Acquire) & Self::MODE_MASK {
+            Self::READ => Error::ReadLocked,
+            _ => Error::WriteLocked,
+        }
+    }
+}
+
+/// Split the ticket word into the ticket being served and the next one.
+fn split(tickets: u32) -> (u16, u16) {
+    (tickets as u16, (tickets >> 16) as u16)
+}
+
+impl MMFLock for TicketLock<'_> {
+    fn initialized(&self) -> bool {
+        (self.state.load(Ordering::Acquire) & Self::INITIALIZED) != 0
+    }
+
+    fn readlocked(&self) -> bool {
+        (self.state.load(Ordering::Acquire) & Self::MODE_MASK) == Self::READ
+    }
+
+    fn writelocked(&self) -> bool {
+        (self.state.load(Ordering::Acquire) & Self::MODE_MASK) == Self::WRITE
+    }
+
+    /// Held, or somebody is waiting in line for it. Uninitialized locks are considered held.
+    fn locked(&self) -> bool {
+        !self.initialized() || self.queue_len() != 0
+    }
+
+    fn is_poisoned(&self) -> bool {
+        (self.state.load(Ordering::Acquire) & Self::MODE_MASK) == Self::MODE_MASK
+    }
+
+    /// Takes the lock if the line is empty, see [`TicketLock`] on why this doesn't queue.
+    fn try_lock_read(&self) -> MMFResult<()> {
+        self.try_lock(Self::READ)
+    }
+
+    fn unlock_read(&self) -> MMFResult<()> {
+        self.unlock(Self::READ)
+    }
+
+    /// Takes the lock if the line is empty, see [`TicketLock`] on why this doesn't queue.
+    fn try_lock_write(&self) -> MMFResult<()> {
+        self.try_lock(Self::WRITE)
+    }
+
+    fn unlock_write(&self) -> MMFResult<()> {
+        self.unlock(Self::WRITE)
+    }
+
+    fn spin(&self, tries: &mut usize) -> MMFResult<bool> {
+        *tries += 1;
+        let held = self.locked();
+        if held {
+            self.strategy.pause(*tries);
+        }
+        Ok(held)
+    }
+
+    fn spin_and_lock_read(lock: &Self, max_tries: usize) -> MMFResult<()> {
+        lock.lock_read_or_spin(max_tries)
+    }
+
+    fn spin_and_lock_write(lock: &Self, max_tries: usize) -> MMFResult<()> {
+        lock.lock_write_or_spin(max_tries)
+    }
+
+    /// Take a ticket and wait for it to be called, in the order it was asked for. Never gives up once in line.
+    fn lock_read(&self) -> MMFResult<()> {
+        self.lock_fair(Self::READ)
+    }
+
+    /// Take a ticket and wait for it to be called, in the order it was asked for. Never gives up once in line.
+    fn lock_write(&self) -> MMFResult<()> {
+        self.lock_fair(Self::WRITE)
+    }
+
+    /// Attach to an existing lock at `pointer`, which must be valid for [`TicketLock::SIZE`] bytes.
+    unsafe fn from_existing(pointer: *mut u8) -> Self {
+        Self::from_ptr(pointer)
+    }
+
+    /// Put a fresh, uninitialized lock at `pointer`, which must be valid for [`TicketLock::SIZE`] bytes.
+    unsafe fn from_raw(pointer: *mut u8) -> Self {
+        let lock = Self::from_ptr(pointer);
+        lock.tickets.store(0, Ordering::Release);
+        lock.state.store(0, Ordering::Release);
+        lock
+    }
+
+    fn set_init(&self) {
+        self.state.fetch_or(Self::INITIALIZED, Ordering::AcqRel);
+    }
+
+    fn initialize(self) -> Self {
+        self.set_init();
+        self
+    }
+
+    fn lock_size() -> usize {
+        Self::SIZE
+    }
+}
@@ -0,0 +1,46 @@
+use crate::{async_tokio::*, mmf::*, states::*};
+use std::{num::NonZeroUsize, time::Duration};
+
+// The default test runtime runs everything on one thread. If waiting for the lock blocked it, the task releasing the
+// lock would never get to run, and these would hang.
+#[tokio::test]
+pub async fn test_lock_write_async() {
+    let mut buf = vec![0u32; RWLock::SIZE / 4];
+    let ptr = buf.as_mut_ptr();
+    let lock = unsafe { RWLock::from_raw(ptr.cast()).initialize() };
+    let other = unsafe { RWLock::from_existing(ptr.cast()) };
+    lock.try_lock_write().expect("Failed to lock");
+
+    let releaser = async {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        lock.unlock_write()
+    };
+    let (waited, released) = tokio::join!(other.lock_write_async(), releaser);
+    released.expect("Failed to unlock");
+    waited.expect("Failed to lock after waiting");
+    assert!(other.snapshot().local_write_held);
+    other.unlock_write().expect("Failed to unlock");
+
+    lock.lock_read_async().await.expect("Failed to lock an unlocked lock");
+    lock.unlock_read().expect("Failed to unlock");
+}
+
+#[tokio::test]
+pub async fn test_mmf_async() {
+    let size = NonZeroUsize::new(32).unwrap();
+    let mmf = MemoryMappedFile::<RWLock>::new(size, "test_mmf_async", Namespace::LOCAL).expect("creation failed");
+    let other = mmf.try_clone().expect("Failed to clone");
+    let guard = mmf.read_borrow().expect("Failed to borrow");
+
+    let writer = other.write_with_async(|data| {
+        data[..5].copy_from_slice(b"async");
+        5
+    });
+    let reader = async {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        drop(guard);
+    };
+    let (written, _) = tokio::join!(writer, reader);
+    assert_eq!(written.expect("Failed to write"), 5);
+    assert_eq!(&mmf.read_borrow_async().await.expect("Failed to borrow")[..5], b"async");
+}
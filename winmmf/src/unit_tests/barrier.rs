@@ -0,0 +1,44 @@
+use crate::{barrier::*, states::SpinStrategy, Error};
+use std::{
+    num::NonZeroU32,
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+#[test]
+pub fn test_barrier_generations() {
+    let mut buf = vec![0u32; Barrier::SIZE / 4];
+    let ptr = buf.as_mut_ptr() as usize;
+    let participants = NonZeroU32::new(4).unwrap();
+    let (leaders, passed) = (AtomicU32::new(0), AtomicU32::new(0));
+    std::thread::scope(|s| {
+        for _ in 0..participants.get() {
+            s.spawn(|| {
+                let barrier = unsafe { Barrier::new((ptr as *mut u32).cast(), participants) }
+                    .with_spin_strategy(SpinStrategy::Yield);
+                for round in 0..50u32 {
+                    // Everyone made it through the round before last, or we wouldn't have gotten through the last one
+                    assert!(passed.load(Ordering::Acquire) >= round.saturating_sub(1) * participants.get());
+                    if barrier.wait().expect("Failed to wait") {
+                        leaders.fetch_add(1, Ordering::AcqRel);
+                    }
+                    passed.fetch_add(1, Ordering::AcqRel);
+                }
+            });
+        }
+    });
+    assert_eq!(leaders.load(Ordering::Acquire), 50);
+    let barrier = unsafe { Barrier::new((ptr as *mut u32).cast(), participants) };
+    assert_eq!(barrier.generation(), 50);
+}
+
+#[test]
+pub fn test_barrier_too_many() {
+    let mut buf = vec![0u32; Barrier::SIZE / 4];
+    let ptr = buf.as_mut_ptr();
+    let barrier = unsafe { Barrier::new(ptr.cast(), NonZeroU32::new(1).unwrap()) };
+    assert!(barrier.wait().expect("Failed to wait"));
+    // Pretend somebody thinks there are two participants and is already waiting
+    unsafe { AtomicU32::from_ptr(ptr).store(1, Ordering::Release) };
+    assert!(matches!(barrier.wait(), Err(Error::LockViolation)));
+    assert_eq!(unsafe { AtomicU32::from_ptr(ptr).load(Ordering::Acquire) }, 1);
+}
@@ -0,0 +1,31 @@
+use crate::{checksum::*, mmf::*, states::RWLock, Error};
+use std::num::NonZeroUsize;
+
+#[test]
+pub fn test_crc32() {
+    assert_eq!(crc32(b""), 0);
+    assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    assert_eq!(crc32(b"The quick brown fox jumps over the lazy dog"), 0x414F_A339);
+}
+
+#[test]
+pub fn test_checksum() {
+    let size = NonZeroUsize::new(32).unwrap();
+    let mmf = MemoryMappedFile::<RWLock>::new(size, "test_checksum", Namespace::LOCAL).expect("creation failed");
+    let checked = MMFWithChecksum::new(mmf);
+    assert!(matches!(checked.validate(), Err(Error::ChecksumMismatch)));
+
+    checked.write_committed(b"Trust, but verify").expect("Failed to write");
+    {
+        let data = checked.read_validated().expect("Failed to read");
+        assert_eq!(data.len(), 28);
+        assert_eq!(&data[..17], b"Trust, but verify");
+    }
+    assert!(matches!(checked.write_committed(&[0; 29]), Err(Error::NotEnoughMemory)));
+
+    // Flip a bit behind its back
+    checked.inner().write_at_unlocked(10, b"V").expect("Failed to write");
+    assert!(matches!(checked.validate(), Err(Error::ChecksumMismatch)));
+    checked.write_committed(b"Fixed").expect("Failed to write");
+    checked.validate().expect("Still broken");
+}
@@ -0,0 +1,28 @@
+use crate::{counter::*, mmf::*, states::RWLock, Error};
+use std::num::NonZeroUsize;
+
+#[test]
+pub fn test_counter_from_mmf() {
+    let mmf = MemoryMappedFile::<RWLock>::new(NonZeroUsize::new(16).unwrap(), "test_counter", Namespace::LOCAL)
+        .expect("creation failed");
+    assert!(matches!(AtomicCounter::from_mmf(&mmf, 2), Err(Error::MisalignedData)));
+    assert!(matches!(AtomicCounter::from_mmf(&mmf, 16), Err(Error::NotEnoughMemory)));
+    let counter = AtomicCounter::from_mmf(&mmf, 12).expect("Failed to attach");
+    assert_eq!(counter.load(), 0);
+
+    std::thread::scope(|s| {
+        for _ in 0..4 {
+            s.spawn(|| {
+                let other = AtomicCounter::from_mmf(&mmf, 12).expect("Failed to attach");
+                for _ in 0..1000 {
+                    other.increment();
+                }
+            });
+        }
+    });
+    assert_eq!(counter.load(), 4000);
+    assert_eq!(counter.compare_exchange(1, 2), Err(4000));
+    counter.store(0);
+    assert_eq!(counter.decrement(), u32::MAX);
+    assert_eq!(&mmf.read(16).expect("Failed to read")[12..], &u32::MAX.to_ne_bytes());
+}
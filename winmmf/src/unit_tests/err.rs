@@ -0,0 +1,52 @@
+use crate::Error;
+use windows::core::{Error as WErr, HRESULT};
+
+#[test]
+pub fn test_error_classification() {
+    let cases = [
+        (Error::ReadLocked, true, false, false),
+        (Error::WriteLocked, true, false, false),
+        (Error::Uninitialized, false, true, false),
+        (Error::MaxReaders, false, true, false),
+        (Error::NotEnoughMemory, false, false, false),
+        (Error::MMF_NotFound, false, false, false),
+        (Error::LockViolation, false, true, false),
+        (Error::Poisoned, false, true, false),
+        (Error::Timeout, true, false, false),
+        (Error::Abandoned, true, false, false),
+        (Error::NotLocked, false, false, false),
+        (Error::AccessDenied, false, false, true),
+        (Error::MisalignedLock, false, false, false),
+        (Error::LocalReaderLimit, false, true, false),
+        (Error::MisalignedData, false, false, false),
+        (Error::GeneralFailure, false, false, false),
+        (Error::OS_Err(WErr::from(HRESULT(5))), false, false, true),
+        (Error::OS_OK(WErr::from(HRESULT(0))), false, false, true),
+        (Error::FlushFailed(WErr::from(HRESULT(5))), false, false, true),
+        (Error::ReadOnly, false, false, false),
+        (Error::BufferFull, true, false, false),
+        (Error::MissingPrivilege, false, false, true),
+        (Error::Exhausted, true, false, false),
+        (Error::LargePageUnavailable(Some(1300)), false, false, false),
+        (Error::LargePageUnavailable(None), false, false, false),
+        (Error::ShrinkNotAllowed, false, false, false),
+        (Error::Serialization(String::from("nope")), false, false, false),
+        (Error::ChecksumMismatch, false, false, false),
+        (Error::MagicMismatch, false, false, false),
+        (Error::IncompatibleVersion(0x0200), false, false, false),
+        (Error::Moved, false, false, false),
+        (Error::PermitsExceeded, false, false, false),
+    ];
+    for (err, retryable, fatal, os) in cases {
+        assert_eq!(err.is_retryable(), retryable, "{err:?}");
+        assert_eq!(err.is_fatal(), fatal, "{err:?}");
+        assert_eq!(err.is_os_error(), os, "{err:?}");
+    }
+}
+
+#[test]
+pub fn test_large_page_os_code() {
+    assert_eq!(Error::LargePageUnavailable(Some(1300)).raw_os_error(), Some(1300));
+    assert_eq!(Error::LargePageUnavailable(None).raw_os_error(), None);
+    assert!(Error::LargePageUnavailable(Some(1314)).to_string().starts_with("E1314: "));
+}
@@ -0,0 +1,82 @@
+use crate::{growable::*, mmf::*, states::RWLock, Error};
+use std::{num::NonZeroUsize, thread::sleep, time::Duration};
+
+/// Set for the child half of [`test_growable_follow`], so it knows it was spawned for it.
+const GROWABLE_CHILD: &str = "WINMMF_TEST_GROWABLE_CHILD";
+
+#[test]
+pub fn test_growable_resize() {
+    let size = NonZeroUsize::new(64).unwrap();
+    let mut ours =
+        GrowableMMF::<RWLock>::create(size, "test_growable_resize", Namespace::LOCAL).expect("creation failed");
+    let mut theirs = GrowableMMF::<RWLock>::open(size, "test_growable_resize", Namespace::LOCAL).expect("open failed");
+    ours.write_at(0, b"Small beginnings").expect("Failed to write");
+
+    assert!(matches!(ours.resize(NonZeroUsize::new(32).unwrap()), Err(Error::ShrinkNotAllowed)));
+    ours.resize(size).expect("Same size should do nothing");
+    assert_eq!(ours.version(), 0);
+
+    ours.resize(NonZeroUsize::new(4096).unwrap()).expect("Failed to grow");
+    assert_eq!((ours.version(), ours.size()), (1, 4096));
+    assert!(theirs.moved().expect("Failed to check"));
+    let mut buf = [0; 16];
+    assert!(matches!(theirs.read_at(0, &mut buf), Err(Error::Moved)));
+    assert!(matches!(theirs.write_at(0, b"Lost"), Err(Error::Moved)));
+
+    // Growing from a stale mapping follows first, and newcomers go straight to the latest version
+    theirs.resize(NonZeroUsize::new(8192).unwrap()).expect("Failed to grow after following");
+    assert_eq!((theirs.version(), theirs.size()), (2, 8192));
+    let late = GrowableMMF::<RWLock>::open(size, "test_growable_resize", Namespace::LOCAL).expect("open failed");
+    assert_eq!(late.version(), 2);
+    late.read_at(0, &mut buf).expect("Failed to read");
+    assert_eq!(&buf, b"Small beginnings");
+    assert!(ours.follow().expect("Failed to follow"));
+    assert_eq!(ours.size(), 8192);
+}
+
+#[test]
+pub fn test_growable_follow() {
+    let size = NonZeroUsize::new(64).unwrap();
+    let mut mmf =
+        GrowableMMF::<RWLock>::create(size, "test_growable_follow", Namespace::LOCAL).expect("creation failed");
+    mmf.write_at(0, b"Before").expect("Failed to write");
+
+    // The test binary doubles as the helper, running only the child half below.
+    let mut child = std::process::Command::new(std::env::current_exe().expect("No test binary?"))
+        .args(["unit_tests::growable::test_growable_follow_child", "--exact", "--nocapture"])
+        .env(GROWABLE_CHILD, "1")
+        .spawn()
+        .expect("Failed to spawn the child");
+    let mut ready = [0];
+    while ready != [1] {
+        sleep(Duration::from_millis(10));
+        mmf.read_at(63, &mut ready).expect("Failed to read");
+    }
+    mmf.resize(NonZeroUsize::new(4096).unwrap()).expect("Failed to grow");
+    assert!(child.wait().expect("Child went missing").success());
+
+    let mut buf = [0; 8];
+    mmf.read_at(0, &mut buf).expect("Failed to read");
+    assert_eq!(&buf, b"Followed");
+}
+
+#[test]
+pub fn test_growable_follow_child() {
+    // Only does anything when spawned by test_growable_follow.
+    if std::env::var(GROWABLE_CHILD).is_err() {
+        return;
+    }
+    let size = NonZeroUsize::new(64).unwrap();
+    let mut mmf = GrowableMMF::<RWLock>::open(size, "test_growable_follow", Namespace::LOCAL).expect("open failed");
+    assert_eq!(mmf.version(), 0);
+    mmf.write_at(63, &[1]).expect("Failed to signal");
+    while !mmf.moved().expect("Failed to check") {
+        sleep(Duration::from_millis(10));
+    }
+    assert!(mmf.follow().expect("Failed to follow"));
+    assert_eq!((mmf.version(), mmf.size()), (1, 4096));
+    let mut buf = [0; 6];
+    mmf.read_at(0, &mut buf).expect("Failed to read");
+    assert_eq!(&buf, b"Before");
+    mmf.write_at(0, b"Followed").expect("Failed to write");
+}
@@ -0,0 +1,41 @@
+use crate::{header::*, mmf::*, states::RWLock, Error};
+use std::num::NonZeroUsize;
+
+#[test]
+pub fn test_header_bytes() {
+    let header = MMFHeader::new(0xBEEF);
+    assert_eq!(&header.to_bytes()[..6], b"WMMF\x00\x01");
+    assert_eq!(MMFHeader::from_bytes(&header.to_bytes()), header);
+    assert_eq!((header.major(), header.minor()), (1, 0));
+    header.validate().expect("Our own header is invalid");
+
+    // Newer minor versions are fine, other major versions and strangers aren't
+    assert!(MMFHeader { version: MMF_CURRENT_VERSION + 1, ..header }.validate().is_ok());
+    assert!(matches!(MMFHeader { version: 0x0200, ..header }.validate(), Err(Error::IncompatibleVersion(0x0200))));
+    assert!(matches!(MMFHeader { magic: *b"NOPE", ..header }.validate(), Err(Error::MagicMismatch)));
+    assert!(matches!(MMFHeader { data_offset: 4, ..header }.validate(), Err(Error::MagicMismatch)));
+}
+
+#[test]
+pub fn test_versioned_mmf() {
+    let size = NonZeroUsize::new(64).unwrap();
+    let mmf = MemoryMappedFile::<RWLock>::new(size, "test_versioned_mmf", Namespace::LOCAL).expect("creation failed");
+    // Nothing written yet, so no magic either
+    let blank = VersionedMMF::open(mmf.try_clone().expect("Failed to clone"));
+    assert!(matches!(blank, Err(Error::MagicMismatch)));
+
+    let ours = VersionedMMF::create(mmf, 7).expect("Failed to write the header");
+    assert_eq!(ours.size(), 64 - MMFHeader::DATA_OFFSET as usize);
+    ours.write_at(0, b"Versioned and proud").expect("Failed to write");
+
+    let theirs = VersionedMMF::open(ours.inner().try_clone().expect("Failed to clone")).expect("Failed to open");
+    assert_eq!(theirs.header(), &MMFHeader::new(7));
+    let mut buf = [0; 19];
+    theirs.read_at(0, &mut buf).expect("Failed to read");
+    assert_eq!(&buf, b"Versioned and proud");
+
+    // Somebody from the future
+    ours.inner().write_at(4, &0x0200u16.to_le_bytes()).expect("Failed to write");
+    let future = VersionedMMF::open(ours.into_inner());
+    assert!(matches!(future, Err(Error::IncompatibleVersion(0x0200))));
+}
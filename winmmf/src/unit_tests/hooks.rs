@@ -0,0 +1,80 @@
+use crate::{hooks::*, states::*};
+use std::{
+    sync::{Arc, Mutex},
+    thread::sleep,
+    time::Duration,
+};
+
+/// Hooks writing down everything they're told.
+#[derive(Default)]
+struct Recorder(Mutex<Vec<(&'static str, Duration)>>);
+
+impl LockHooks for Recorder {
+    fn on_acquire_read(&self, waited: Duration) {
+        self.0.lock().unwrap().push(("acquire_read", waited));
+    }
+
+    fn on_release_read(&self, held: Duration) {
+        self.0.lock().unwrap().push(("release_read", held));
+    }
+
+    fn on_acquire_write(&self, waited: Duration) {
+        self.0.lock().unwrap().push(("acquire_write", waited));
+    }
+
+    fn on_release_write(&self, held: Duration) {
+        self.0.lock().unwrap().push(("release_write", held));
+    }
+}
+
+#[test]
+pub fn test_lock_hooks() {
+    let recorder = Arc::new(Recorder::default());
+    let mut buf = vec![0u32; RWLock::SIZE / 4];
+    let lock = unsafe { RWLock::from_raw(buf.as_mut_ptr().cast()).initialize() }.with_hooks(recorder.clone());
+    let other = unsafe { RWLock::from_existing(buf.as_mut_ptr().cast()) };
+    let short = Duration::from_millis(5);
+
+    lock.try_lock_write().expect("Failed to lock");
+    sleep(short);
+    lock.unlock_write().expect("Failed to unlock");
+
+    // Waiting counts from the first attempt bouncing off, only the last read lock out stops the clock
+    other.try_lock_write().expect("Failed to lock");
+    assert!(lock.try_lock_read().is_err());
+    sleep(short);
+    other.unlock_write().expect("Failed to unlock");
+    lock.try_lock_read().expect("Failed to lock");
+    lock.try_lock_read().expect("Failed to lock");
+    sleep(short);
+    lock.unlock_read().expect("Failed to unlock");
+    lock.unlock_read().expect("Failed to unlock");
+
+    let events = recorder.0.lock().unwrap();
+    let names: Vec<_> = events.iter().map(|(name, _)| *name).collect();
+    assert_eq!(names, ["acquire_write", "release_write", "acquire_read", "acquire_read", "release_read"]);
+    assert_eq!(events[0].1, Duration::ZERO);
+    assert!(events[1].1 >= short);
+    assert!(events[2].1 >= short);
+    assert_eq!(events[3].1, Duration::ZERO);
+    assert!(events[4].1 >= short);
+}
+
+#[test]
+pub fn test_lock_hooks_forced() {
+    let recorder = Arc::new(Recorder::default());
+    let mut buf = vec![0u32; RWLock::SIZE / 4];
+    let lock = unsafe { RWLock::from_raw(buf.as_mut_ptr().cast()).initialize() }.with_hooks(recorder.clone());
+    let other = unsafe { RWLock::from_existing(buf.as_mut_ptr().cast()) };
+
+    // Forcing our own lock open still ends it, as does finding out somebody else did
+    lock.try_lock_write().expect("Failed to lock");
+    unsafe { lock.force_unlock_write() };
+    lock.try_lock_write().expect("Failed to lock");
+    unsafe { other.force_unlock_write() };
+    assert!(matches!(lock.downgrade_write(), Err(crate::Error::NotLocked)));
+    assert!(matches!(lock.downgrade_write(), Err(crate::Error::NotLocked)));
+
+    let names: Vec<_> = recorder.0.lock().unwrap().iter().map(|(name, _)| *name).collect();
+    assert_eq!(names, ["acquire_write", "release_write", "acquire_write", "release_write"]);
+}
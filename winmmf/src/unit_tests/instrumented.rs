@@ -0,0 +1,26 @@
+use crate::{instrumented::*, states::*, Error};
+
+#[test]
+pub fn test_instrumented_lock() {
+    let mut buf = vec![0u32; RWLock::SIZE / 4];
+    let ptr = buf.as_mut_ptr();
+    let lock = unsafe { InstrumentedLock::<RWLock>::from_raw(ptr.cast()).initialize() };
+    let other = unsafe { InstrumentedLock::<RWLock>::from_existing(ptr.cast()) };
+
+    lock.try_lock_write().expect("Failed to lock");
+    assert!(matches!(other.try_lock_read(), Err(Error::WriteLocked)));
+    // Every attempt and spin in between counts, not just the call
+    assert!(matches!(other.lock_read_or_spin(3), Err(Error::Timeout)));
+    assert!(other.try_lock_write().is_err());
+    let mut tries = 0;
+    assert!(other.spin(&mut tries).expect("Failed to spin"));
+    lock.unlock_write().expect("Failed to unlock");
+    other.lock_read().expect("Failed to lock");
+
+    assert_eq!(lock.stats(), LockStats { write_acquired: 1, ..Default::default() });
+    assert_eq!(
+        other.stats(),
+        LockStats { read_acquired: 1, read_failed: 4, write_failed: 1, spins: 4, ..Default::default() }
+    );
+    assert!(other.inner().readlocked());
+}
@@ -0,0 +1,114 @@
+use crate::{ipc::*, mmf::Namespace, Error};
+use std::num::NonZeroUsize;
+
+#[test]
+pub fn test_ring_buffer() {
+    let mut producer = RingBuffer::create("test_ring_buffer", Namespace::LOCAL, NonZeroUsize::new(8).unwrap())
+        .expect("creation failed");
+    let mut consumer = RingBuffer::open("test_ring_buffer", Namespace::LOCAL).expect("opening failed");
+    assert_eq!(consumer.capacity(), 8);
+    assert!(consumer.is_empty().unwrap());
+
+    let mut buf = [0u8; 8];
+    assert_eq!(consumer.pop(&mut buf).unwrap(), 0);
+    assert_eq!(producer.push(b"Hello, world").unwrap(), 8);
+    assert_eq!(producer.push(b"!").unwrap(), 0);
+    assert_eq!(consumer.pop(&mut buf[..5]).unwrap(), 5);
+    assert_eq!(&buf[..5], b"Hello");
+
+    // Goes around the end of the data
+    assert_eq!(producer.push(b"!?!?").unwrap(), 4);
+    assert_eq!(consumer.len().unwrap(), 7);
+    assert_eq!(consumer.pop(&mut buf).unwrap(), 7);
+    assert_eq!(&buf[..7], b", w!?!?");
+    assert!(producer.is_empty().unwrap());
+}
+
+#[test]
+pub fn test_ring_buffer_threads() {
+    let mut producer = RingBuffer::create("test_ring_buffer_threads", Namespace::LOCAL, NonZeroUsize::new(7).unwrap())
+        .expect("creation failed");
+    let mut consumer = RingBuffer::open("test_ring_buffer_threads", Namespace::LOCAL).expect("opening failed");
+    let input: Vec<u8> = (0..=255).cycle().take(10_000).collect();
+
+    let sent = input.clone();
+    let writer = std::thread::spawn(move || {
+        let mut pushed = 0;
+        while pushed < sent.len() {
+            pushed += producer.push(&sent[pushed..]).expect("Failed to push");
+        }
+    });
+    let mut output = Vec::with_capacity(input.len());
+    let mut buf = [0u8; 5];
+    while output.len() < input.len() {
+        let popped = consumer.pop(&mut buf).expect("Failed to pop");
+        output.extend_from_slice(&buf[..popped]);
+    }
+    writer.join().unwrap();
+    assert_eq!(output, input);
+}
+
+#[test]
+pub fn test_ring_buffer_capacity() {
+    let too_big = NonZeroUsize::new(MAX_CAPACITY + 1).unwrap();
+    assert!(matches!(
+        RingBuffer::create("test_ring_buffer_capacity", Namespace::LOCAL, too_big),
+        Err(Error::NotEnoughMemory)
+    ));
+}
+
+#[test]
+pub fn test_message_queue() {
+    let sender = MessageQueue::create("test_message_queue", Namespace::LOCAL, NonZeroUsize::new(24).unwrap())
+        .expect("creation failed");
+    let receiver = MessageQueue::open("test_message_queue", Namespace::LOCAL).expect("opening failed");
+    assert_eq!(receiver.capacity(), 24);
+
+    let mut buf = Vec::new();
+    assert!(!receiver.recv(&mut buf).unwrap());
+    sender.send(b"short").unwrap();
+    sender.send(b"a bit long").unwrap();
+    // 9 + 14 bytes used, no room for even an empty message
+    assert!(matches!(sender.send(b""), Err(Error::BufferFull)));
+    assert!(matches!(sender.send(&[0; 21]), Err(Error::NotEnoughMemory)));
+    assert_eq!(receiver.sequence().unwrap(), 2);
+
+    assert!(receiver.recv(&mut buf).unwrap());
+    assert_eq!(buf, b"short");
+    // Goes around the end of the data
+    sender.send(b"wrap").unwrap();
+    assert!(receiver.recv(&mut buf).unwrap());
+    assert_eq!(buf, b"a bit long");
+    assert!(receiver.recv(&mut buf).unwrap());
+    assert_eq!(buf, b"wrap");
+    assert!(!receiver.recv(&mut buf).unwrap());
+    assert_eq!(buf, b"wrap");
+}
+
+#[test]
+pub fn test_locked_ring_buffer() {
+    let producer = LockedRingBuffer::create("test_locked_ring_buffer", Namespace::LOCAL, NonZeroUsize::new(8).unwrap())
+        .expect("creation failed");
+    let consumer = LockedRingBuffer::open("test_locked_ring_buffer", Namespace::LOCAL).expect("opening failed");
+    assert_eq!(consumer.capacity(), 8);
+
+    // Popping from an empty buffer
+    let mut buf = [0u8; 8];
+    assert_eq!(consumer.pop(&mut buf).unwrap(), 0);
+    assert!(consumer.is_empty().unwrap());
+
+    // Pushing to a full buffer is all or nothing
+    producer.push(b"Hello").unwrap();
+    assert!(matches!(producer.push(b"world"), Err(Error::BufferFull)));
+    assert!(matches!(producer.push(b"Too long!"), Err(Error::NotEnoughMemory)));
+    producer.push(b", w").unwrap();
+    assert_eq!(consumer.len().unwrap(), 8);
+    assert_eq!(consumer.pop(&mut buf[..5]).unwrap(), 5);
+    assert_eq!(&buf[..5], b"Hello");
+
+    // Goes around the end of the data
+    producer.push(b"orld").unwrap();
+    assert_eq!(consumer.pop(&mut buf).unwrap(), 7);
+    assert_eq!(&buf[..7], b", world");
+    assert!(producer.is_empty().unwrap());
+}
@@ -0,0 +1,84 @@
+use crate::{kernel_lock::KernelLock, states::*, Error};
+use std::time::Duration;
+
+#[test]
+pub fn test_kernel_lock_timeout() {
+    let mut buf = vec![0u32; RWLock::SIZE / 4];
+    let ptr = buf.as_mut_ptr();
+    let name = "Local\\test_kernel_lock_timeout";
+    let lock = unsafe { KernelLock::from_raw_named(ptr.cast(), name).initialize() };
+    let other = unsafe { KernelLock::from_existing_named(ptr.cast(), name) }.with_timeout(Duration::from_millis(10));
+    assert!(lock.is_kernel_backed());
+
+    lock.lock_write().expect("Failed to lock");
+    assert!(matches!(other.lock_read(), Err(Error::Timeout)));
+    assert!(other.state().writelocked());
+    lock.unlock_write().expect("Failed to unlock");
+    assert!(other.lock_read().is_ok());
+    assert!(other.unlock_read().is_ok());
+}
+
+#[test]
+pub fn test_kernel_lock_fallback() {
+    let mut buf = vec![0u32; RWLock::SIZE / 4];
+    let lock = unsafe { KernelLock::from_raw(buf.as_mut_ptr().cast()).initialize() };
+    assert!(!lock.is_kernel_backed());
+    lock.lock_write().expect("Failed to lock");
+    assert!(lock.try_lock_read().is_err());
+    lock.unlock_write().expect("Failed to unlock");
+}
+
+#[test]
+pub fn test_kernel_lock_abandoned() {
+    let mut buf = vec![0u32; RWLock::SIZE / 4];
+    let ptr = buf.as_mut_ptr() as usize;
+    let name = "Local\\test_kernel_lock_abandoned";
+    let lock = unsafe { KernelLock::from_raw_named(ptr as *mut u8, name).initialize() }
+        .with_timeout(Duration::from_millis(10));
+    lock.lock_write().expect("Failed to lock");
+    // The mutex would let us right back in, the lock doesn't
+    assert!(matches!(lock.lock_write(), Err(Error::WriteLocked)));
+    assert!(matches!(lock.lock_read(), Err(Error::WriteLocked)));
+    lock.unlock_write().expect("Failed to unlock");
+
+    // A thread exiting with the write lock abandons the mutex, leaving the event reset
+    std::thread::spawn(move || {
+        let writer = unsafe { KernelLock::from_existing_named(ptr as *mut u8, name) };
+        writer.lock_write().expect("Failed to lock");
+    })
+    .join()
+    .unwrap();
+    // Our own process is still alive, so the lock in the MMF stays taken. Readers are let in to find out, and go back
+    // to waiting for it
+    assert!(matches!(lock.lock_read(), Err(Error::Timeout)));
+    assert!(lock.state().writelocked());
+    unsafe { lock.state().force_unlock_write() };
+    lock.lock_read().expect("Failed to lock");
+    lock.unlock_read().expect("Failed to unlock");
+}
+
+#[test]
+pub fn test_kernel_lock_wait_until_unlocked() {
+    let mut buf = vec![0u32; RWLock::SIZE / 4];
+    let ptr = buf.as_mut_ptr() as usize;
+    let name = "Local\\test_kernel_lock_wait_until_unlocked";
+    let lock = unsafe { KernelLock::from_raw_named(ptr as *mut u8, name).initialize() };
+    lock.lock_write().expect("Failed to lock");
+    assert!(matches!(lock.wait_until_unlocked(Duration::from_secs(1)), Err(Error::WriteLocked)));
+    lock.unlock_write().expect("Failed to unlock");
+
+    // The mutex has to be released by the thread that took it, so the writer gets a thread of its own
+    let writer = std::thread::spawn(move || {
+        let writer = unsafe { KernelLock::from_existing_named(ptr as *mut u8, name) };
+        writer.lock_write().expect("Failed to lock");
+        std::thread::sleep(Duration::from_millis(50));
+        writer.unlock_write().expect("Failed to unlock");
+    });
+    while !lock.writelocked() {
+        std::thread::yield_now();
+    }
+    assert!(matches!(lock.wait_until_unlocked(Duration::from_millis(5)), Err(Error::Timeout)));
+    lock.wait_until_unlocked(Duration::from_secs(5)).expect("Failed to wait");
+    assert!(!lock.locked());
+    writer.join().unwrap();
+}
@@ -0,0 +1,53 @@
+use crate::{
+    lease::LeasedLock,
+    mmf::{MemoryMappedFile, Mmf, Namespace},
+    states::*,
+    Error,
+};
+use std::{num::NonZeroUsize, time::Duration};
+
+#[test]
+pub fn test_lease_reclaimed() {
+    let mut buf = vec![0u64; LeasedLock::SIZE / 8];
+    let ptr = buf.as_mut_ptr().cast();
+    let holder = unsafe { LeasedLock::from_raw(ptr).initialize() }.with_lease(Duration::ZERO);
+    let other = unsafe { LeasedLock::from_existing(ptr) };
+
+    holder.try_lock_write().expect("Failed to lock");
+    assert!(holder.lease_expired());
+    // Taken over instead of waiting for the holder
+    other.try_lock_write().expect("Failed to reclaim");
+    assert_eq!(other.state().write_owner(), Some(std::process::id()));
+    assert!(matches!(holder.renew_lease(), Err(Error::LockViolation)));
+    assert!(matches!(holder.unlock_write(), Err(Error::LockViolation)));
+    assert!(other.writelocked());
+    other.unlock_write().expect("Failed to unlock");
+    assert!(!holder.locked());
+}
+
+#[test]
+pub fn test_lease_renewed() {
+    let mut buf = vec![0u64; LeasedLock::SIZE / 8];
+    let ptr = buf.as_mut_ptr().cast();
+    let holder = unsafe { LeasedLock::from_raw(ptr).initialize() }.with_lease(Duration::from_secs(60));
+    let other = unsafe { LeasedLock::from_existing(ptr) };
+
+    assert!(matches!(holder.renew_lease(), Err(Error::NotLocked)));
+    holder.try_lock_write().expect("Failed to lock");
+    holder.renew_lease().expect("Failed to renew");
+    assert!(!other.lease_expired());
+    assert!(matches!(other.try_lock_write(), Err(Error::WriteLocked)));
+    holder.unlock_write().expect("Failed to unlock");
+    other.try_lock_write().expect("Failed to lock");
+    other.unlock_write().expect("Failed to unlock");
+}
+
+#[test]
+pub fn test_leased_mmf() {
+    let size = NonZeroUsize::new(64).unwrap();
+    let mmf = MemoryMappedFile::<LeasedLock>::new(size, "test_leased_mmf", Namespace::LOCAL).expect("creation failed");
+    unsafe { windows::Win32::Foundation::SetLastError(windows::Win32::Foundation::WIN32_ERROR(0)) };
+    mmf.write(b"leased").expect("Failed to write");
+    assert_eq!(&mmf.read(6).expect("Failed to read"), b"leased");
+    assert_eq!(mmf.ptr() as usize % 16, 0);
+}
@@ -1,4 +1,4 @@
-use crate::states::RWLock;
+use crate::states::{MMFLock, NoLock, RWLock};
 
 use crate::mmf::*;
 use std::num::NonZeroUsize;
@@ -117,3 +117,1017 @@ pub fn test_no_exist_after_close() {
     drop(file3);
     assert_ne!(&readback, input);
 }
+
+#[test]
+pub fn test_generation_poll() {
+    let size = NonZeroUsize::new(64).unwrap();
+    let file1 =
+        MemoryMappedFile::<RWLock>::new(size, "test_generation_poll", Namespace::LOCAL).expect("creation failed");
+    let file2 = MemoryMappedFile::<RWLock>::open(size, "test_generation_poll", Namespace::LOCAL, false)
+        .expect("opening failed");
+    let seen = file2.generation().expect("RWLock counts writes");
+    assert!(!file2.has_changed(seen));
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    file1.write(b"News!").expect("Failed to write");
+    assert!(file2.has_changed(seen));
+    assert_eq!(file2.generation(), file1.generation());
+
+    let unlocked = MemoryMappedFile::<NoLock>::new(size, "test_generation_poll_nolock", Namespace::LOCAL)
+        .expect("creation failed");
+    assert_eq!(unlocked.generation(), None);
+    assert!(unlocked.has_changed(0));
+}
+
+#[test]
+pub fn test_no_lock() {
+    let input = b"This is a testing string to ensure WinMMF Just Works:TM:";
+    let file1 = MemoryMappedFile::<NoLock>::new(NonZeroUsize::new(64).unwrap(), "test_no_lock", Namespace::LOCAL)
+        .expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    file1.write(input).expect("Failed to write");
+    let file2 =
+        MemoryMappedFile::<NoLock>::open(NonZeroUsize::new(64).unwrap(), "test_no_lock", Namespace::LOCAL, false)
+            .expect("2nd open failed");
+    let readback = file2.read(input.len()).expect("Failed to read");
+    // Without a lock there's nothing in front of the data, so offset 0 is where the write landed.
+    let mut buf = [0u8; 64];
+    assert_eq!(file2.read_at(0, &mut buf).expect("Failed to read at 0"), 64);
+
+    drop(file1);
+    drop(file2);
+    assert_eq!(&readback, input);
+    assert_eq!(&buf[..input.len()], input);
+}
+
+#[test]
+pub fn test_os_error_code() {
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    let err =
+        MemoryMappedFile::<RWLock>::open(NonZeroUsize::new(64).unwrap(), "test_no_such_mmf", Namespace::LOCAL, false)
+            .expect_err("Opened an MMF that doesn't exist");
+    assert_eq!(err.raw_os_error(), Some(WFoundation::ERROR_FILE_NOT_FOUND.0 as i32));
+
+    // Backslashes are reserved for namespaces, so this name is no good at all.
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    let err =
+        MemoryMappedFile::<RWLock>::open(NonZeroUsize::new(64).unwrap(), "no\\such\\ns", Namespace::CUSTOM, false)
+            .expect_err("Opened an MMF with an invalid name");
+    assert!(err.raw_os_error().is_some_and(|code| code != 0));
+}
+
+#[test]
+pub fn test_namespace_names() {
+    for namespace in [Namespace::LOCAL, Namespace::GLOBAL, Namespace::SESSION(3), Namespace::CUSTOM] {
+        let full = namespace.format_name("MyMap");
+        assert_eq!(Namespace::parse_name(full.as_str()), (namespace, "MyMap"));
+    }
+    assert_eq!(Namespace::GLOBAL.format_name("MyMap").as_str(), "Global\\MyMap");
+    assert_eq!(Namespace::SESSION(3).format_name("MyMap").as_str(), "Session\\3\\MyMap");
+    assert_eq!(Namespace::parse_name("Session\\nope\\MyMap"), (Namespace::CUSTOM, "Session\\nope\\MyMap"));
+    assert_eq!(Namespace::try_from(3), Err(()));
+}
+
+#[test]
+pub fn test_open_or_create() {
+    let size = NonZeroUsize::new(64).unwrap();
+    let (file1, created) = MemoryMappedFile::<RWLock>::open_or_create(size, "test_open_or_create", Namespace::LOCAL)
+        .expect("creation failed");
+    let (file2, opened) = MemoryMappedFile::<RWLock>::open_or_create(size, "test_open_or_create", Namespace::LOCAL)
+        .expect("opening failed");
+    assert_eq!(created, Disposition::Created);
+    assert_eq!(opened, Disposition::Opened);
+
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    file1.write(b"shared").expect("Failed to write");
+    assert_eq!(&file2.read(6).expect("Failed to read"), b"shared");
+}
+
+#[test]
+pub fn test_lock_offset() {
+    let size = NonZeroUsize::new(64).unwrap();
+    let misaligned =
+        MemoryMappedFile::<RWLock>::new_with_lock_offset(size, "test_lock_offset_bad", Namespace::LOCAL, 6);
+    assert!(matches!(misaligned, Err(crate::Error::MisalignedLock)));
+
+    let input = b"This is a testing string that goes right around the lock";
+    let file1 = MemoryMappedFile::<RWLock>::new_with_lock_offset(size, "test_lock_offset", Namespace::LOCAL, 8)
+        .expect("creation failed");
+    let file2 = MemoryMappedFile::<RWLock>::open_with_lock_offset(size, "test_lock_offset", Namespace::LOCAL, false, 8)
+        .expect("opening failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    file1.write(input).expect("Failed to write");
+    assert_eq!(&file2.read(input.len()).expect("Failed to read"), input);
+
+    let (file3, disposition) =
+        MemoryMappedFile::<RWLock>::open_or_create_with_lock_offset(size, "test_lock_offset", Namespace::LOCAL, 8)
+            .expect("opening failed");
+    assert_eq!(disposition, Disposition::Opened);
+    assert_eq!(&file3.read(input.len()).expect("Failed to read"), input);
+}
+
+#[test]
+pub fn test_ptr_and_close() {
+    let file1 = MemoryMappedFile::<RWLock>::new(NonZeroUsize::new(64).unwrap(), "test_ptr_and_close", Namespace::LOCAL)
+        .expect("creation failed");
+    let ptr = file1.ptr();
+    assert!(!ptr.is_null());
+    unsafe { b"raw".as_ptr().copy_to(ptr, 3) };
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    assert_eq!(&file1.read(3).expect("Failed to read"), b"raw");
+    assert_eq!(file1.size(), 64);
+
+    assert!(file1.close().is_ok());
+    assert!(file1.close().is_ok());
+    assert!(file1.ptr().is_null());
+}
+
+#[test]
+pub fn test_resize() {
+    let input = b"This is a testing string to ensure WinMMF Just Works:TM:";
+    let mut file1 = MemoryMappedFile::<RWLock>::new(NonZeroUsize::new(64).unwrap(), "test_resize", Namespace::LOCAL)
+        .expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    file1.write(input).expect("Failed to write");
+
+    file1.resize(NonZeroUsize::new(4096).unwrap()).expect("Failed to resize");
+    assert_eq!(file1.size(), 4096);
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    let readback = file1.read(4096).expect("Failed to read");
+    assert_eq!(&readback[..input.len()], input);
+    assert!(readback[input.len()..].iter().all(|&b| b == 0));
+    file1.write(&[1; 4096]).expect("Failed to write to the grown MMF");
+
+    assert!(matches!(file1.resize(NonZeroUsize::new(64).unwrap()), Err(crate::Error::ShrinkNotAllowed)));
+    file1.resize(NonZeroUsize::new(4096).unwrap()).expect("Failed to resize to the same size");
+    assert_eq!(file1.size(), 4096);
+}
+
+#[test]
+pub fn test_read_only_clone() {
+    let input = b"This is a testing string to ensure WinMMF Just Works:TM:";
+    let size = NonZeroUsize::new(64).unwrap();
+    let file1 =
+        MemoryMappedFile::<RWLock>::new(size, "test_read_only_clone", Namespace::LOCAL).expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    file1.write(input).expect("Failed to write");
+
+    let reader = ReadOnlyMMF::<RWLock>::open(size, "test_read_only_clone", Namespace::LOCAL).expect("opening failed");
+    let clone = reader.clone();
+    assert!(!clone.is_writable());
+    assert!(clone.write(input).is_err());
+    reader.close().expect("Failed to close the original");
+    drop(reader);
+
+    let readback = std::thread::spawn(move || clone.read(input.len()).expect("Failed to read from the clone"))
+        .join()
+        .unwrap();
+    assert_eq!(&readback, input);
+}
+
+#[cfg(feature = "bytemuck")]
+#[test]
+pub fn test_view_as() {
+    #[repr(C)]
+    #[derive(Debug, Clone, Copy, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+    struct Header {
+        magic: u32,
+        count: u32,
+        stamp: u64,
+    }
+
+    let size = NonZeroUsize::new(64).unwrap();
+    let mut file1 = MemoryMappedFile::<RWLock>::new(size, "test_view_as", Namespace::LOCAL).expect("creation failed");
+    let file2 = MemoryMappedFile::<RWLock>::open_read(size, "test_view_as", Namespace::LOCAL).expect("opening failed");
+    let header = Header { magic: 0x4D4D4621, count: 3, stamp: 42 };
+    // Safety: nobody else is around to touch the data while the views live.
+    unsafe {
+        *file1.view_as_mut::<Header>().expect("Failed to view mutably") = header;
+        assert_eq!(file2.view_as::<Header>().expect("Failed to view"), &header);
+        assert_eq!(file2.view_as_slice::<u32>(2).expect("Failed to view as a slice"), &[header.magic, header.count]);
+
+        assert!(matches!(file2.view_as_slice::<Header>(5), Err(crate::Error::NotEnoughMemory)));
+        assert!(matches!(file2.view_as_slice::<u8>(65), Err(crate::Error::NotEnoughMemory)));
+        let mut file2 = file2;
+        assert!(matches!(file2.view_as_mut::<Header>(), Err(crate::Error::MMF_NotFound)));
+    }
+}
+
+#[cfg(feature = "zerocopy")]
+#[test]
+pub fn test_read_write_obj() {
+    #[repr(C)]
+    #[derive(Debug, PartialEq, zerocopy::AsBytes, zerocopy::FromBytes, zerocopy::FromZeroes)]
+    struct Header {
+        magic: u32,
+        count: u32,
+        stamp: u64,
+    }
+
+    let size = NonZeroUsize::new(64).unwrap();
+    let mut file1 =
+        MemoryMappedFile::<RWLock>::new(size, "test_read_write_obj", Namespace::LOCAL).expect("creation failed");
+    let file2 =
+        MemoryMappedFile::<RWLock>::open_read(size, "test_read_write_obj", Namespace::LOCAL).expect("opening failed");
+    let header = Header { magic: 0x4D4D4621, count: 3, stamp: 42 };
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    file1.write_obj(&header).expect("Failed to write");
+    assert_eq!(file2.read_obj::<Header>().expect("Failed to read"), header);
+    assert_eq!(file2.read_obj::<u32>().expect("Failed to read"), header.magic);
+
+    assert!(matches!(file2.read_obj::<[Header; 5]>(), Err(crate::Error::NotEnoughMemory)));
+    assert!(matches!(file1.write_obj(&[0u8; 65]), Err(crate::Error::NotEnoughMemory)));
+    let mut file2 = file2;
+    assert!(matches!(file2.write_obj(&header), Err(crate::Error::MMF_NotFound)));
+}
+
+#[test]
+pub fn test_map_region() {
+    let granularity = allocation_granularity() as usize;
+    let size = NonZeroUsize::new(granularity * 2).unwrap();
+    let file1 = MemoryMappedFile::<RWLock>::new(size, "test_map_region", Namespace::LOCAL).expect("creation failed");
+    let region = file1.map_region(granularity as u64, 16).expect("Failed to map a region");
+    assert_eq!((region.offset(), region.len()), (granularity as u64, 16));
+    unsafe { b"region".as_ptr().copy_to(region.as_ptr(), 6) };
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    let readback = file1.read(granularity * 2).expect("Failed to read");
+    // The region is counted from the start of the mapping, the data from the end of the lock
+    let start = granularity - RWLock::SIZE;
+    assert_eq!(&readback[start..start + 6], b"region");
+
+    let tail = file1.map_region(granularity as u64, 0).expect("Failed to map the tail");
+    assert_eq!(tail.len(), granularity + RWLock::SIZE);
+    assert!(matches!(file1.map_region(1, 16), Err(crate::Error::MisalignedData)));
+    assert!(matches!(file1.map_region(0, granularity * 3), Err(crate::Error::NotEnoughMemory)));
+}
+
+#[test]
+pub fn test_lock_timeout() {
+    let input = b"This is a testing string to ensure WinMMF Just Works:TM:";
+    let size = NonZeroUsize::new(64).unwrap();
+    let file1 = MemoryMappedFile::<RWLock>::new(size, "test_mmf_lock_timeout", Namespace::LOCAL)
+        .expect("creation failed")
+        .with_lock_timeout(std::time::Duration::from_millis(10));
+    assert_eq!(file1.lock_timeout(), std::time::Duration::from_millis(10));
+    let file2 = MemoryMappedFile::<RWLock>::open(size, "test_mmf_lock_timeout", Namespace::LOCAL, false)
+        .expect("opening failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+
+    let writer = unsafe { RWLock::from_existing(file2.ptr().sub(RWLock::SIZE)) };
+    writer.try_lock_write().expect("Failed to lock");
+    assert!(matches!(file1.write(input), Err(crate::Error::Timeout)));
+    assert!(matches!(file1.read(input.len()), Err(crate::Error::Timeout)));
+    writer.unlock_write().expect("Failed to unlock");
+    file1.write(input).expect("Failed to write");
+}
+
+#[test]
+pub fn test_drop_releases_handles() {
+    use windows::Win32::System::Threading::{GetCurrentProcess, GetProcessHandleCount};
+    let handle_count = || {
+        let mut count = 0;
+        unsafe { GetProcessHandleCount(GetCurrentProcess(), &mut count) }.expect("Failed to count handles");
+        count
+    };
+    let size = NonZeroUsize::new(4096).unwrap();
+    // Warm up, so anything allocated once per process doesn't count against us
+    drop(MemoryMappedFile::<RWLock>::new(size, "test_drop_releases_handles", Namespace::LOCAL));
+
+    let before = handle_count();
+    for _ in 0..256 {
+        let mmf = MemoryMappedFile::<RWLock>::new(size, "test_drop_releases_handles", Namespace::LOCAL)
+            .expect("creation failed");
+        drop(mmf);
+    }
+    // Other tests run in parallel and open handles of their own, so leave some slack.
+    assert!(handle_count() < before + 64, "handles leaked: {before} before, {} after", handle_count());
+}
+
+#[test]
+pub fn test_flush() {
+    let input = b"This is a testing string to ensure WinMMF Just Works:TM:";
+    let file1 = MemoryMappedFile::<RWLock>::new(NonZeroUsize::new(64).unwrap(), "test_flush", Namespace::LOCAL)
+        .expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    file1.write(input).expect("Failed to write");
+    file1.flush_range(0, input.len()).expect("Failed to flush the range");
+    file1.flush_range(32, 0).expect("Failed to flush the tail");
+    file1.flush_all().expect("Failed to flush everything");
+    file1.flush().expect("Nothing to persist is no reason to fail");
+    assert!(matches!(file1.flush_range(32, 33), Err(crate::Error::NotEnoughMemory)));
+    file1.close().expect("Failed to close");
+    assert!(matches!(file1.flush_all(), Err(crate::Error::MMF_NotFound)));
+    assert!(matches!(file1.flush(), Err(crate::Error::MMF_NotFound)));
+}
+
+#[test]
+pub fn test_read_only_access() {
+    let input = b"Look, don't touch";
+    let size = NonZeroUsize::new(64).unwrap();
+    let file1 =
+        MemoryMappedFile::<RWLock>::new(size, "test_read_only_access", Namespace::LOCAL).expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    file1.write(input).expect("Failed to write");
+
+    let reader =
+        MemoryMappedFile::<RWLock>::open_with_access(size, "test_read_only_access", Namespace::LOCAL, Access::ReadOnly)
+            .expect("opening failed");
+    assert_eq!(reader.access(), Access::ReadOnly);
+    assert!(!reader.is_writable());
+    assert!(matches!(reader.write(input), Err(crate::Error::ReadOnly)));
+    assert!(matches!(reader.write_spin(input, None::<fn(&dyn MMFLock, usize) -> _>), Err(crate::Error::ReadOnly)));
+    #[cfg(feature = "bytemuck")]
+    let mut reader = reader;
+    #[cfg(feature = "bytemuck")]
+    assert!(matches!(unsafe { reader.view_as_mut::<u32>() }, Err(crate::Error::ReadOnly)));
+    assert_eq!(&reader.read(input.len()).expect("Failed to read"), input);
+
+    // Readers can't register with the lock, but still wait for a writer to leave.
+    let reader = reader.with_lock_timeout(std::time::Duration::ZERO);
+    let lock = unsafe { RWLock::from_existing(file1.ptr().sub(RWLock::SIZE)) };
+    lock.try_lock_write().unwrap();
+    assert!(matches!(reader.read(input.len()), Err(crate::Error::Timeout)));
+    lock.unlock_write().unwrap();
+    assert_eq!(&reader.read(input.len()).expect("Failed to read"), input);
+}
+
+#[test]
+pub fn test_open_read_only() {
+    let size = NonZeroUsize::new(64).unwrap();
+    let file1 =
+        MemoryMappedFile::<RWLock>::new(size, "test_open_read_only", Namespace::LOCAL).expect("creation failed");
+    let reader = MemoryMappedFile::<RWLock>::open_read_only(size, "test_open_read_only", Namespace::LOCAL)
+        .expect("opening failed")
+        .with_lock_timeout(std::time::Duration::ZERO);
+    let seen = reader.generation();
+    file1.write(b"Fresh off the press").expect("Failed to write");
+    assert_ne!(reader.generation(), seen);
+    assert_eq!(&reader.read(19).expect("Failed to read"), b"Fresh off the press");
+    let mut buf = [0; 5];
+    assert_eq!(reader.try_clone().expect("Failed to clone").read_at(6, &mut buf).expect("Failed to read"), 5);
+    assert_eq!(&buf, b"off t");
+
+    // Readers don't register, so they don't hold up writers, and wait for them instead
+    file1.lock().try_lock_write().expect("Failed to lock");
+    assert!(matches!(reader.read(19), Err(crate::Error::Timeout)));
+    file1.lock().unlock_write().expect("Failed to unlock");
+    assert!(!file1.lock().readlocked());
+    assert!(matches!(reader.into_inner().write(b"nope"), Err(crate::Error::ReadOnly)));
+}
+
+#[test]
+pub fn test_copy_on_write() {
+    let input = b"The original, as published";
+    let local = b"Scribbles only I can see!!";
+    let size = NonZeroUsize::new(64).unwrap();
+    let file1 = MemoryMappedFile::<RWLock>::new(size, "test_copy_on_write", Namespace::LOCAL).expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    file1.write(input).expect("Failed to write");
+
+    let cow = MemoryMappedFile::<RWLock>::open_copy_on_write(size, "test_copy_on_write", Namespace::LOCAL)
+        .expect("opening failed");
+    assert_eq!(cow.access(), Access::CopyOnWrite);
+    assert_eq!(&cow.read(input.len()).expect("Failed to read"), input);
+    cow.write(local).expect("Failed to write locally");
+    assert_eq!(&cow.read(local.len()).expect("Failed to read"), local);
+    assert_eq!(&file1.read(input.len()).expect("Failed to read the original"), input);
+}
+
+#[test]
+pub fn test_copy_on_write_private_lock() {
+    let input = b"Shared and proud of it";
+    let size = NonZeroUsize::new(64).unwrap();
+    let file1 = MemoryMappedFile::<RWLock>::new(size, "test_copy_on_write_private_lock", Namespace::LOCAL)
+        .expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    file1.write(input).expect("Failed to write");
+    let file2 = MemoryMappedFile::<RWLock>::open_write(size, "test_copy_on_write_private_lock", Namespace::LOCAL)
+        .expect("opening failed");
+    let mut cow =
+        MemoryMappedFile::<RWLock>::open_copy_on_write(size, "test_copy_on_write_private_lock", Namespace::LOCAL)
+            .expect("opening failed");
+
+    // The shared lock is held, but the copy-on-write instance only ever looks at its own
+    let shared = unsafe { RWLock::from_existing(file2.ptr().sub(RWLock::SIZE)) };
+    shared.try_lock_write().unwrap();
+    cow.write(b"What if").expect("Failed to write locally");
+    // Reads do watch it though, even now the page with the lock is private
+    cow = cow.with_lock_timeout(std::time::Duration::from_millis(10));
+    assert!(matches!(cow.read(7), Err(crate::Error::Timeout)));
+    shared.unlock_write().unwrap();
+    assert!(!shared.locked());
+
+    assert_eq!(&cow.read(7).expect("Failed to read"), b"What if");
+    assert_eq!(&file2.read(input.len()).expect("Failed to read"), input);
+    assert!(matches!(cow.resize(NonZeroUsize::new(128).unwrap()), Err(crate::Error::ReadOnly)));
+}
+
+#[test]
+pub fn test_large_pages() {
+    let input = b"Big pages for big dreams";
+    let size = NonZeroUsize::new(64).unwrap();
+    let file1 = match MemoryMappedFile::<RWLock>::new_large_pages(size, "test_large_pages", Namespace::LOCAL) {
+        Ok(file) => file,
+        // Most accounts don't have SeLockMemoryPrivilege, nothing to test then
+        Err(crate::Error::LargePageUnavailable(None)) => {
+            assert!(large_page_minimum().is_none());
+            return;
+        }
+        Err(e @ crate::Error::LargePageUnavailable(Some(code))) => {
+            let expected =
+                [WFoundation::ERROR_NOT_ALL_ASSIGNED.0 as i32, WFoundation::ERROR_PRIVILEGE_NOT_HELD.0 as i32];
+            assert!(expected.contains(&code), "{e}");
+            assert_eq!(e.raw_os_error(), Some(code));
+            return;
+        }
+        Err(e) => panic!("creation failed: {e}"),
+    };
+    let page = large_page_minimum().expect("created without large page support").get();
+    assert_eq!((file1.size() + RWLock::SIZE) % page, 0);
+    assert!(file1.size() >= size.get());
+
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    file1.write(input).expect("Failed to write");
+    assert_eq!(&file1.read(input.len()).expect("Failed to read"), input);
+}
+
+#[test]
+pub fn test_file_backed() {
+    let input = b"Written to disk, eventually";
+    let path = std::env::temp_dir().join("winmmf_test_file_backed.bin");
+    _ = std::fs::remove_file(&path);
+    let (file1, created) = MemoryMappedFile::<RWLock>::from_file(&path, 64, "test_file_backed", Namespace::LOCAL)
+        .expect("creation failed");
+    assert_eq!(created, Disposition::Created);
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    file1.write(input).expect("Failed to write");
+    file1.flush().expect("Failed to flush");
+    drop(file1);
+
+    let on_disk = std::fs::read(&path).expect("Failed to read the file");
+    assert_eq!(on_disk.len(), 64 + RWLock::SIZE);
+    assert_eq!(&on_disk[RWLock::SIZE..RWLock::SIZE + input.len()], input);
+
+    // A size of 0 maps the whole file, and the data is still there
+    let handle = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(&path)
+        .expect("Failed to open the file");
+    let raw = WFoundation::HANDLE(std::os::windows::io::AsRawHandle::as_raw_handle(&handle));
+    let (file2, _) = MemoryMappedFile::<RWLock>::from_file_handle(raw, 0, "test_file_backed", Namespace::LOCAL)
+        .expect("reopening failed");
+    drop(handle);
+    assert_eq!(file2.size(), 64);
+    assert_eq!(&file2.read(input.len()).expect("Failed to read"), input);
+    drop(file2);
+    _ = std::fs::remove_file(&path);
+}
+
+#[test]
+pub fn test_read_write_at() {
+    let size = NonZeroUsize::new(16).unwrap();
+    let file1 = MemoryMappedFile::<RWLock>::new(size, "test_read_write_at", Namespace::LOCAL).expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    // Exact fit at the end, and one past it
+    assert_eq!(file1.write_at(12, b"tail").expect("Failed to write"), 4);
+    assert!(matches!(file1.write_at(13, b"tail"), Err(crate::Error::NotEnoughMemory)));
+    assert!(matches!(file1.write_at(usize::MAX, b"t"), Err(crate::Error::NotEnoughMemory)));
+    assert_eq!(file1.write_at_unlocked(0, b"head").expect("Failed to write"), 4);
+
+    let mut buf = [0u8; 4];
+    assert_eq!(file1.read_at(12, &mut buf).expect("Failed to read"), 4);
+    assert_eq!(&buf, b"tail");
+    assert!(matches!(file1.read_at(13, &mut buf), Err(crate::Error::NotEnoughMemory)));
+    assert_eq!(file1.read_at_unlocked(0, &mut buf).expect("Failed to read"), 4);
+    assert_eq!(&buf, b"head");
+    assert_eq!(&file1.read(16).expect("Failed to read")[..], b"head\0\0\0\0\0\0\0\0tail");
+
+    // With the lock in the middle, offsets still only count the data
+    let file2 =
+        MemoryMappedFile::<RWLock>::new_with_lock_offset(size, "test_read_write_at_offset", Namespace::LOCAL, 8)
+            .expect("creation failed");
+    file2.write_at(6, b"across").expect("Failed to write");
+    let mut across = [0u8; 6];
+    file2.read_at(6, &mut across).expect("Failed to read");
+    assert_eq!(&across, b"across");
+    assert_eq!(&file2.read(12).expect("Failed to read")[6..], b"across");
+}
+
+#[test]
+pub fn test_security_attributes() {
+    assert!(SecurityAttributes::from_sddl("this isn't SDDL").is_err());
+    let security = SecurityAttributes::from_sddl("D:P(A;;GA;;;SY)(A;;GA;;;BA)(A;;GA;;;OW)")
+        .expect("Failed to parse SDDL")
+        .inheritable(true);
+    assert!(security.is_inheritable());
+    assert!(!security.descriptor().0.is_null());
+
+    let size = NonZeroUsize::new(64).unwrap();
+    let file1 = MemoryMappedFile::<RWLock>::new_with_security(
+        size,
+        "test_security_attributes",
+        Namespace::LOCAL,
+        Some(&security),
+    )
+    .expect("creation failed");
+    let (file2, opened) = MemoryMappedFile::<RWLock>::open_or_create_with_security(
+        size,
+        "test_security_attributes",
+        Namespace::LOCAL,
+        None,
+    )
+    .expect("opening failed");
+    assert_eq!(opened, Disposition::Opened);
+
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    file1.write(b"guarded").expect("Failed to write");
+    assert_eq!(&file2.read(7).expect("Failed to read"), b"guarded");
+}
+
+#[test]
+pub fn test_capacity() {
+    let size = NonZeroUsize::new(64).unwrap();
+    let mmf = MemoryMappedFile::<RWLock>::new(size, "test_capacity", Namespace::LOCAL).expect("creation failed");
+    assert_eq!(mmf.capacity(), 64);
+    assert_eq!(mmf.capacity(), mmf.size());
+    assert_eq!(mmf.capacity(), mmf.total_size() - RWLock::lock_size());
+
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    let data = [7u8; 64];
+    assert_eq!(mmf.write_at(0, &data).expect("Failed to write"), mmf.capacity());
+    assert!(mmf.write_at(1, &data).is_err());
+}
+
+#[test]
+pub fn test_spin_budget() {
+    let size = NonZeroUsize::new(64).unwrap();
+    let writer = MemoryMappedFile::<RWLock>::new(size, "test_spin_budget", Namespace::LOCAL).expect("creation failed");
+    let reader = MemoryMappedFile::<RWLock>::open(size, "test_spin_budget", Namespace::LOCAL, false)
+        .expect("opening failed")
+        .with_spin_budget(5);
+    assert_eq!(writer.spin_budget(), SPIN_BUDGET);
+    assert_eq!(reader.spin_budget(), 5);
+
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    let mut budget = 0;
+    let spinner = |lock: &dyn MMFLock, max: usize| {
+        budget = max;
+        lock.lock_read_or_spin(max)
+    };
+    writer.lock().try_lock_write().expect("Failed to lock");
+    assert!(matches!(reader.read_spin(4, Some(spinner)), Err(crate::Error::Timeout)));
+    assert_eq!(budget, 5);
+    writer.lock().unlock_write().expect("Failed to unlock");
+}
+
+#[test]
+pub fn test_owned_send() {
+    let size = NonZeroUsize::new(64).unwrap();
+    let mmf = MemoryMappedFile::<RWLock>::new(size, "test_owned_send", Namespace::LOCAL).expect("creation failed");
+    let reader =
+        MemoryMappedFile::<RWLock>::open(size, "test_owned_send", Namespace::LOCAL, false).expect("opening failed");
+    let owned = mmf.into_owned().expect("Failed to let go of the lock");
+    assert_eq!(owned.capacity(), 64);
+    assert_eq!(owned.total_size(), reader.total_size());
+
+    let input = b"Written by a worker";
+    let worker = std::thread::spawn(move || {
+        unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+        let mmf = owned.into_mmf::<RWLock>().expect("Failed to get a lock back");
+        mmf.write(input).expect("Failed to write from the worker");
+        mmf
+    });
+    let mmf = worker.join().expect("The worker panicked");
+    assert_eq!(&reader.read(input.len()).expect("Failed to read"), input);
+    assert_eq!(&mmf.read(input.len()).expect("Failed to read"), input);
+}
+
+#[test]
+pub fn test_inheritable() {
+    let size = NonZeroUsize::new(64).unwrap();
+    let mmf = MemoryMappedFile::<RWLock>::new(size, "test_inheritable", Namespace::LOCAL).expect("creation failed");
+    assert!(!mmf.is_inheritable().expect("Failed to check"));
+    mmf.set_inheritable(true).expect("Failed to set");
+    assert!(mmf.is_inheritable().expect("Failed to check"));
+    mmf.set_inheritable(false).expect("Failed to set");
+    assert!(!mmf.is_inheritable().expect("Failed to check"));
+
+    let security = SecurityAttributes::new().inheritable(true);
+    let inherited =
+        MemoryMappedFile::<RWLock>::new_with_security(size, "test_inheritable_sa", Namespace::LOCAL, Some(&security))
+            .expect("creation failed");
+    assert!(inherited.is_inheritable().expect("Failed to check"));
+
+    mmf.close().expect("Failed to close");
+    assert!(matches!(mmf.set_inheritable(true), Err(crate::Error::MMF_NotFound)));
+}
+
+#[test]
+pub fn test_exists() {
+    assert!(!MemoryMappedFile::<RWLock>::exists("test_exists", Namespace::LOCAL).expect("Failed to check"));
+    let size = NonZeroUsize::new(64).unwrap();
+    let mmf = MemoryMappedFile::<RWLock>::new(size, "test_exists", Namespace::LOCAL).expect("creation failed");
+    assert!(MemoryMappedFile::<RWLock>::exists("test_exists", Namespace::LOCAL).expect("Failed to check"));
+    drop(mmf);
+    assert!(!MemoryMappedFile::<RWLock>::exists("test_exists", Namespace::LOCAL).expect("Failed to check"));
+}
+
+#[test]
+pub fn test_try_clone() {
+    let size = NonZeroUsize::new(64).unwrap();
+    let mmf = MemoryMappedFile::<RWLock>::new(size, "test_try_clone", Namespace::LOCAL).expect("creation failed");
+    let clone = mmf.try_clone().expect("Failed to clone");
+    assert_ne!(clone.handle(), mmf.handle());
+
+    let input = b"Written through the clone";
+    clone.write(input).expect("Failed to write through the clone");
+    assert_eq!(&mmf.read(input.len()).expect("Failed to read"), input);
+
+    // The clone holds on to the section, so it outlives the original.
+    mmf.close().expect("Failed to close");
+    assert!(mmf.try_clone().is_err());
+    assert_eq!(&clone.read(input.len()).expect("Failed to read"), input);
+}
+
+#[test]
+pub fn test_create_options() {
+    let size = NonZeroUsize::new(64).unwrap();
+    let mmf = CreateOptions::new()
+        .lock_offset(32)
+        .create::<RWLock>(size, "test_create_options", Namespace::LOCAL)
+        .expect("creation failed");
+    assert_eq!(mmf.size(), 64);
+
+    // Large pages are never this small, so this fails one way or another.
+    let res = CreateOptions::new()
+        .large_pages(true)
+        .create::<RWLock>(size, "test_create_options_lp", Namespace::LOCAL);
+    assert!(matches!(res, Err(crate::Error::MisalignedData | crate::Error::LargePageUnavailable(_))), "{res:?}");
+}
+
+#[test]
+pub fn test_mmf_read_write_at() {
+    /// Goes through the trait, like code that works with any MMF would.
+    fn patch(mmf: &impl Mmf, offset: usize, data: &[u8]) -> crate::MMFResult<Vec<u8>> {
+        mmf.write_all_at(offset, data)?;
+        mmf.read_vec_at(offset, data.len())
+    }
+
+    let size = NonZeroUsize::new(16).unwrap();
+    let mmf =
+        MemoryMappedFile::<RWLock>::new(size, "test_mmf_read_write_at", Namespace::LOCAL).expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    assert_eq!(patch(&mmf, 4, b"middle").expect("Failed to patch"), b"middle");
+    assert!(matches!(patch(&mmf, 12, b"tail!"), Err(crate::Error::NotEnoughMemory)));
+    assert!(matches!(mmf.read_vec_at(12, 5), Err(crate::Error::NotEnoughMemory)));
+    assert_eq!(mmf.read_vec_at(16, 0).expect("Failed to read"), b"");
+    assert_eq!(&mmf.read(10).expect("Failed to read")[4..], b"middle");
+}
+
+/// The environment variable [`test_inherited_handle`] passes the handle value to its child in.
+const INHERITED_HANDLE: &str = "WINMMF_TEST_INHERITED_HANDLE";
+
+#[test]
+pub fn test_inherited_handle() {
+    let size = NonZeroUsize::new(64).unwrap();
+    let mmf = CreateOptions::new()
+        .inheritable(true)
+        .create::<RWLock>(size, "test_inherited_handle", Namespace::LOCAL)
+        .expect("creation failed");
+    assert!(mmf.is_inheritable().expect("Failed to check"));
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    mmf.write(b"From your parent").expect("Failed to write");
+
+    // The test binary doubles as the helper, running only the child half below.
+    let status = std::process::Command::new(std::env::current_exe().expect("No test binary?"))
+        .args(["unit_tests::mmf::test_inherited_child", "--exact", "--nocapture"])
+        .env(INHERITED_HANDLE, (mmf.handle().0 as usize).to_string())
+        .status()
+        .expect("Failed to spawn the child");
+    assert!(status.success());
+    assert_eq!(&mmf.read(16).expect("Failed to read"), b"From your child\0");
+}
+
+#[test]
+pub fn test_inherited_child() {
+    // Only does anything when spawned by test_inherited_handle.
+    let Ok(value) = std::env::var(INHERITED_HANDLE) else {
+        return;
+    };
+    let handle = windows::Win32::Foundation::HANDLE(value.parse::<usize>().expect("Not a handle") as _);
+    let size = NonZeroUsize::new(64).unwrap();
+    let mmf = unsafe { MemoryMappedFile::<RWLock>::from_raw_handle(handle, size, Access::ReadWrite) }
+        .expect("Failed to map the inherited handle");
+    assert_eq!(mmf.fullname(), "");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    assert_eq!(&mmf.read(16).expect("Failed to read"), b"From your parent");
+    mmf.write(b"From your child\0").expect("Failed to write");
+}
+
+#[test]
+pub fn test_read_borrow() {
+    let size = NonZeroUsize::new(64).unwrap();
+    let mmf = MemoryMappedFile::<RWLock>::new(size, "test_read_borrow", Namespace::LOCAL).expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    mmf.write(b"Borrowed, not copied").expect("Failed to write");
+    {
+        let guard = mmf.read_borrow().expect("Failed to borrow");
+        assert_eq!(guard.len(), 64);
+        assert_eq!(&guard[..20], b"Borrowed, not copied");
+        assert!(mmf.lock().readlocked());
+        assert!(matches!(mmf.lock().try_lock_write(), Err(crate::Error::ReadLocked)));
+    }
+    assert!(!mmf.lock().locked());
+
+    let offset =
+        MemoryMappedFile::<RWLock>::new_with_lock_offset(size, "test_read_borrow_offset", Namespace::LOCAL, 16)
+            .expect("creation failed");
+    assert_eq!(offset.read_borrow().expect("Failed to borrow").len(), 16);
+}
+
+#[test]
+pub fn test_file_access() {
+    let path = std::env::temp_dir().join("winmmf_test_file_access.bin");
+    let mut contents = vec![0u8; RWLock::SIZE + 32];
+    contents[RWLock::SIZE..RWLock::SIZE + 7].copy_from_slice(b"On disk");
+    std::fs::write(&path, &contents).expect("Failed to write the file");
+
+    let (cow, _) = MemoryMappedFile::<RWLock>::from_file_with_access(
+        &path,
+        0,
+        "test_file_cow",
+        Namespace::LOCAL,
+        Access::CopyOnWrite,
+    )
+    .expect("mapping failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    assert_eq!(&cow.read(7).expect("Failed to read"), b"On disk");
+    cow.write(b"In here").expect("Failed to write");
+    assert_eq!(&cow.read(7).expect("Failed to read"), b"In here");
+
+    let (ro, _) = MemoryMappedFile::<RWLock>::from_file_with_access(
+        &path,
+        32,
+        "test_file_ro",
+        Namespace::LOCAL,
+        FileAccess::ReadOnly,
+    )
+    .expect("mapping failed");
+    assert_eq!(&ro.read(7).expect("Failed to read"), b"On disk");
+    assert!(ro.write(b"Nope").is_err());
+    drop((cow, ro));
+
+    assert_eq!(std::fs::read(&path).expect("Failed to read the file"), contents);
+    _ = std::fs::remove_file(&path);
+    assert!(
+        MemoryMappedFile::<RWLock>::from_file_with_access(&path, 0, "x", Namespace::LOCAL, Access::ReadOnly).is_err()
+    );
+}
+
+#[test]
+pub fn test_custom_dacl() {
+    let size = NonZeroUsize::new(64).unwrap();
+    // Everyone gets to read, and only SYSTEM gets to write. The creator keeps the access it created the MMF with.
+    let read_only = SecurityAttributes::from_sddl("D:P(A;;GA;;;SY)(A;;GR;;;WD)").expect("Failed to parse SDDL");
+    let mmf =
+        MemoryMappedFile::<RWLock>::new_with_security(size, "test_custom_dacl", Namespace::LOCAL, Some(&read_only))
+            .expect("creation failed");
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    mmf.write(b"Look, don't touch").expect("Failed to write");
+    let err = MemoryMappedFile::<RWLock>::open_write(size, "test_custom_dacl", Namespace::LOCAL).unwrap_err();
+    assert!(matches!(err, crate::Error::AccessDenied), "{err:?}");
+    let reader =
+        MemoryMappedFile::<RWLock>::open_with_access(size, "test_custom_dacl", Namespace::LOCAL, Access::ReadOnly)
+            .expect("opening for reading failed");
+    assert_eq!(&reader.read(17).expect("Failed to read"), b"Look, don't touch");
+
+    // Mappings of files get the same treatment
+    let path = std::env::temp_dir().join("winmmf_test_custom_dacl.bin");
+    _ = std::fs::remove_file(&path);
+    let (file, _) = MemoryMappedFile::<RWLock>::from_file_with_security(
+        &path,
+        64,
+        "test_custom_dacl_file",
+        Namespace::LOCAL,
+        Some(&read_only),
+    )
+    .expect("creation failed");
+    let err = MemoryMappedFile::<RWLock>::open_write(size, "test_custom_dacl_file", Namespace::LOCAL).unwrap_err();
+    assert!(matches!(err, crate::Error::AccessDenied), "{err:?}");
+    drop(file);
+    _ = std::fs::remove_file(&path);
+
+    // Sharing with user sessions means the global namespace, which takes SeCreateGlobalPrivilege to create in.
+    let shared = SecurityAttributes::shared_with_users(true).expect("Failed to parse SDDL");
+    let global =
+        match MemoryMappedFile::<RWLock>::new_with_security(size, "test_custom_dacl", Namespace::GLOBAL, Some(&shared))
+        {
+            Ok(global) => global,
+            Err(crate::Error::AccessDenied) => return,
+            Err(e) => panic!("creation failed: {e}"),
+        };
+    let opened = MemoryMappedFile::<RWLock>::open_write(size, "test_custom_dacl", Namespace::GLOBAL)
+        .expect("opening the global MMF failed");
+    opened.write(b"Hello from a session").expect("Failed to write");
+    assert_eq!(&global.read(20).expect("Failed to read"), b"Hello from a session");
+}
+
+#[test]
+pub fn test_write_with() {
+    let size = NonZeroUsize::new(32).unwrap();
+    let mmf = MemoryMappedFile::<RWLock>::new(size, "test_write_with", Namespace::LOCAL).expect("creation failed");
+    let written = mmf
+        .write_with(|data| {
+            assert_eq!(data.len(), 32);
+            assert!(mmf.lock().writelocked());
+            data[..9].copy_from_slice(b"Serialize");
+            9
+        })
+        .expect("Failed to write");
+    assert_eq!(written, 9);
+    assert!(!mmf.lock().locked());
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    assert_eq!(&mmf.read(9).expect("Failed to read"), b"Serialize");
+    assert_eq!(mmf.write_with(|_| usize::MAX).expect("Failed to write"), 32);
+
+    // A panicking closure doesn't leave the lock behind.
+    let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| mmf.write_with(|_| panic!("Oops"))));
+    assert!(res.is_err());
+    assert!(!mmf.lock().locked());
+}
+
+#[test]
+pub fn test_as_bytes() {
+    let size = NonZeroUsize::new(32).unwrap();
+    let mut mmf = MemoryMappedFile::<RWLock>::new(size, "test_as_bytes", Namespace::LOCAL).expect("creation failed");
+    let reader =
+        MemoryMappedFile::<RWLock>::open_read(size, "test_as_bytes", Namespace::LOCAL).expect("opening failed");
+    // Safety: nobody else is around to touch the data while the slices live.
+    unsafe {
+        mmf.as_bytes_mut()[..5].copy_from_slice(b"Slice");
+        assert_eq!(mmf.as_bytes().len(), 32);
+        assert_eq!(&reader.as_bytes()[..5], b"Slice");
+    }
+
+    let mut read_only = reader;
+    let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| _ = unsafe { read_only.as_bytes_mut() }));
+    assert!(res.is_err());
+    mmf.close().expect("Failed to close");
+    assert!(std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| _ = unsafe { mmf.as_bytes() })).is_err());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+pub fn test_read_write_value() {
+    #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+    struct Config {
+        name: String,
+        workers: Vec<u16>,
+    }
+
+    let size = NonZeroUsize::new(64).unwrap();
+    let mut file1 =
+        MemoryMappedFile::<RWLock>::new(size, "test_read_write_value", Namespace::LOCAL).expect("creation failed");
+    let mut file2 =
+        MemoryMappedFile::<RWLock>::open_read(size, "test_read_write_value", Namespace::LOCAL).expect("opening failed");
+    let config = Config { name: String::from("serde"), workers: vec![1, 2, 3] };
+    file1.write_value(&config).expect("Failed to write");
+    assert_eq!(file2.read_value::<Config>().expect("Failed to read"), config);
+
+    assert!(matches!(file1.write_value(&vec![0u8; 64]), Err(crate::Error::NotEnoughMemory)));
+    assert!(matches!(file2.write_value(&config), Err(crate::Error::MMF_NotFound)));
+    file1.write_value(&u64::MAX).expect("Failed to write");
+    assert!(matches!(file2.read_value::<Config>(), Err(crate::Error::Serialization(_))));
+}
+
+#[test]
+pub fn test_cursor() {
+    use std::io::{ErrorKind, Read, Seek, SeekFrom, Write};
+    let size = NonZeroUsize::new(16).unwrap();
+    let mut mmf = MemoryMappedFile::<RWLock>::new(size, "test_cursor", Namespace::LOCAL)
+        .expect("creation failed")
+        .with_lock_timeout(std::time::Duration::ZERO);
+    let mut writer = mmf.cursor_mut();
+    writer.write_all(b"Cursors").expect("Failed to write");
+    assert_eq!(writer.seek(SeekFrom::End(-4)).expect("Failed to seek"), 12);
+    assert_eq!(writer.write(b"!!!!!!").expect("Failed to write"), 4);
+    assert_eq!(writer.write(b"!").unwrap_err().kind(), ErrorKind::UnexpectedEof);
+    assert_eq!(writer.seek(SeekFrom::Current(1)).unwrap_err().kind(), ErrorKind::UnexpectedEof);
+    assert_eq!(writer.seek(SeekFrom::Current(-17)).unwrap_err().kind(), ErrorKind::InvalidInput);
+    assert_eq!(writer.position(), 16);
+
+    let mut reader = mmf.cursor();
+    let mut word = [0; 7];
+    reader.read_exact(&mut word).expect("Failed to read");
+    assert_eq!(&word, b"Cursors");
+    reader.seek(SeekFrom::Start(12)).expect("Failed to seek");
+    assert_eq!(reader.read_exact(&mut word).unwrap_err().kind(), ErrorKind::UnexpectedEof);
+    assert_eq!(reader.position(), 12);
+    let mut rest = Vec::new();
+    assert_eq!(reader.read_to_end(&mut rest).expect("Failed to read"), 4);
+    assert_eq!(rest, b"!!!!");
+    assert_eq!(reader.read(&mut word).expect("Failed to read"), 0);
+
+    // Cursors respect the lock like any other read
+    let lock = unsafe { RWLock::from_existing(mmf.ptr().sub(RWLock::SIZE)) };
+    lock.try_lock_write().unwrap();
+    reader.rewind().expect("Failed to seek");
+    let err = reader.read(&mut word).unwrap_err();
+    assert!(matches!(
+        err.into_inner().and_then(|e| e.downcast::<crate::Error>().ok()).as_deref(),
+        Some(crate::Error::Timeout)
+    ));
+    lock.unlock_write().unwrap();
+}
+
+#[test]
+pub fn test_file_trailing_lock() {
+    let path = std::env::temp_dir().join("winmmf_test_file_trailing_lock.bin");
+    _ = std::fs::remove_file(&path);
+    let (mmf, created) = MemoryMappedFile::<RWLock>::from_file_with_trailing_lock(
+        &path,
+        16,
+        "test_file_trailing_lock",
+        Namespace::LOCAL,
+        Access::ReadWrite,
+    )
+    .expect("creation failed");
+    assert_eq!(created, Disposition::Created);
+    unsafe { SetLastError(WFoundation::WIN32_ERROR(0)) };
+    mmf.write(b"Plain old bytes!").expect("Failed to write");
+    mmf.flush().expect("Failed to flush");
+    drop(mmf);
+
+    // Anyone reading the file sees the data first, and the lock after.
+    let on_disk = std::fs::read(&path).expect("Failed to read the file");
+    assert_eq!(on_disk.len(), 16 + RWLock::SIZE);
+    assert_eq!(&on_disk[..16], b"Plain old bytes!");
+
+    let (mmf, _) = MemoryMappedFile::<RWLock>::from_file_with_trailing_lock(
+        &path,
+        0,
+        "test_file_trailing_lock",
+        Namespace::LOCAL,
+        Access::ReadOnly,
+    )
+    .expect("reopening failed");
+    assert_eq!(mmf.size(), 16);
+    assert_eq!(&mmf.read(16).expect("Failed to read"), b"Plain old bytes!");
+    drop(mmf);
+    _ = std::fs::remove_file(&path);
+
+    // A lock right behind 13 bytes of data would be misaligned, and the file isn't even created for it.
+    assert!(matches!(
+        MemoryMappedFile::<RWLock>::from_file_with_trailing_lock(
+            &path,
+            13,
+            "test_file_trailing_lock_odd",
+            Namespace::LOCAL,
+            Access::ReadWrite,
+        ),
+        Err(crate::Error::MisalignedLock)
+    ));
+    assert!(!path.exists());
+}
+
+#[test]
+pub fn test_open_region() {
+    let granularity = allocation_granularity() as usize;
+    let size = NonZeroUsize::new(granularity * 2).unwrap();
+    let file1 = MemoryMappedFile::<RWLock>::new(size, "test_open_region", Namespace::LOCAL).expect("creation failed");
+    // Nowhere near a boundary, the region has to skip a bit of its view
+    let offset = granularity + 3;
+    file1.write_at(offset, b"Far away").expect("Failed to write");
+
+    let len = NonZeroUsize::new(16).unwrap();
+    let shared = MemoryMappedFile::<RWLock>::open_region(
+        "test_open_region",
+        Namespace::LOCAL,
+        offset as u64,
+        len,
+        RegionLock::Shared,
+    )
+    .expect("Failed to open the region")
+    .with_lock_timeout(std::time::Duration::from_millis(10));
+    assert_eq!((shared.offset(), shared.size()), (offset as u64, 16));
+    let mut buf = [0; 8];
+    shared.read_at(0, &mut buf).expect("Failed to read");
+    assert_eq!(&buf, b"Far away");
+    shared.write_at(8, b"and back").expect("Failed to write");
+    let mut buf = [0; 16];
+    file1.read_at(offset, &mut buf).expect("Failed to read");
+    assert_eq!(&buf, b"Far awayand back");
+    assert!(matches!(shared.write_at(12, b"too long"), Err(crate::Error::NotEnoughMemory)));
+
+    // The shared lock is the one the whole MMF uses, a lock of its own isn't
+    let _part = unsafe { RWLock::from_raw(file1.ptr().add(64)).initialize() };
+    let own = MemoryMappedFile::<RWLock>::open_region("test_open_region", Namespace::LOCAL, 64, len, RegionLock::Own)
+        .expect("Failed to open the region");
+    let writer = unsafe { RWLock::from_existing(file1.ptr().sub(RWLock::SIZE)) };
+    writer.try_lock_write().expect("Failed to lock");
+    assert!(matches!(shared.read_at(0, &mut buf), Err(crate::Error::Timeout)));
+    own.write_at(0, b"Minding my own").expect("Failed to write");
+    writer.unlock_write().expect("Failed to unlock");
+    file1.read_at(64 + RWLock::SIZE, &mut buf[..14]).expect("Failed to read");
+    assert_eq!(&buf[..14], b"Minding my own");
+}
@@ -1,3 +1,32 @@
+#[cfg(feature = "async_tokio")]
+mod async_tokio;
+mod barrier;
+#[cfg(feature = "impl_mmf")]
+mod checksum;
+mod counter;
+mod err;
+#[cfg(feature = "impl_mmf")]
+mod growable;
+#[cfg(feature = "impl_mmf")]
+mod header;
+#[cfg(feature = "lock_hooks")]
+mod hooks;
+#[cfg(feature = "metrics")]
+mod instrumented;
+#[cfg(feature = "impl_mmf")]
+mod ipc;
+mod kernel_lock;
+mod lease;
 mod mmf;
+#[cfg(feature = "impl_mmf")]
+mod notify;
+mod once;
+mod padded;
+#[cfg(feature = "lock_api")]
+mod raw_lock;
+mod semaphore;
+mod seqlock;
+mod states;
+mod ticket;
 #[allow(unused_imports)]
 use mmf::*;
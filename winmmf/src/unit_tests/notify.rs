@@ -0,0 +1,31 @@
+use crate::{notify::*, states::RWLock, *};
+use std::{
+    num::NonZeroUsize,
+    time::{Duration, Instant},
+};
+
+#[test]
+pub fn test_notifier() {
+    let input = b"Hot off the press";
+    let mmf = MemoryMappedFile::<RWLock>::new(NonZeroUsize::new(64).unwrap(), "test_notifier", Namespace::LOCAL)
+        .expect("creation failed");
+    let writer = SingleConsumerNotifier::new(&mmf).expect("Failed to create the notifier");
+    let reader = SingleConsumerNotifier::new(&mmf).expect("Failed to open the notifier");
+    assert_eq!(reader.name(), format!("{}{NOTIFIER_SUFFIX}", mmf.fullname()));
+    assert!(!reader.wait(Some(Duration::from_millis(10))).expect("Failed to wait"));
+
+    std::thread::scope(|s| {
+        let waiter = s.spawn(|| {
+            let started = Instant::now();
+            assert!(reader.wait(Some(Duration::from_secs(5))).expect("Failed to wait"));
+            assert!(started.elapsed() < Duration::from_secs(5));
+            assert_eq!(&mmf.read(input.len()).expect("Failed to read"), input);
+        });
+        std::thread::sleep(Duration::from_millis(20));
+        mmf.write(input).expect("Failed to write");
+        writer.signal().expect("Failed to signal");
+        waiter.join().unwrap();
+    });
+    // Auto-reset, the signal was used up by the waiter
+    assert!(!reader.wait(Some(Duration::ZERO)).expect("Failed to wait"));
+}
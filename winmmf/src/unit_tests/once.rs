@@ -0,0 +1,52 @@
+use crate::{once::*, Error};
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+
+#[test]
+pub fn test_once() {
+    let mut buf = vec![0u32; (MMFOnce::HEADER_SIZE + 8) / 4];
+    let ptr = buf.as_mut_ptr();
+    let once = unsafe { MMFOnce::new(ptr.cast(), 8) };
+    assert!(!once.is_completed());
+    assert_eq!(once.get(), None);
+
+    // Somebody else is busy
+    unsafe { AtomicU32::from_ptr(ptr).store(MMFOnce::IN_PROGRESS, Ordering::Release) };
+    assert!(matches!(once.try_call_once(|_| unreachable!()), Err(Error::Uninitialized)));
+    let impatient = unsafe { MMFOnce::new(ptr.cast(), 8) }.with_max_tries(10);
+    assert!(matches!(impatient.call_once(|_| unreachable!()), Err(Error::Timeout)));
+
+    // And then they died, their panic hands it to us
+    unsafe { AtomicU32::from_ptr(ptr).store(0, Ordering::Release) };
+    let panicked = std::panic::catch_unwind(|| once.call_once(|_| panic!("oh no")));
+    assert!(panicked.is_err());
+    assert!(!once.is_completed());
+
+    once.call_once(|data| data.fill(3)).expect("Failed to initialize");
+    once.call_once(|data| data.fill(4)).expect("Already initialized is fine");
+    assert!(once.is_completed());
+    assert_eq!(once.get(), Some(&[3; 8][..]));
+}
+
+#[test]
+pub fn test_once_threads() {
+    let mut buf = vec![0u32; (MMFOnce::HEADER_SIZE + 4) / 4];
+    let ptr = buf.as_mut_ptr() as usize;
+    let runs = AtomicUsize::new(0);
+    std::thread::scope(|s| {
+        for i in 0..8u8 {
+            let runs = &runs;
+            s.spawn(move || {
+                let once = unsafe { MMFOnce::new((ptr as *mut u32).cast(), 4) };
+                once.call_once(|data| {
+                    runs.fetch_add(1, Ordering::AcqRel);
+                    data.fill(i + 1);
+                })
+                .expect("Failed to initialize");
+                // Whoever won, everyone sees the same data once call_once returns
+                let data = once.get().expect("Returned before it was done");
+                assert!(data.iter().all(|b| *b == data[0] && *b != 0));
+            });
+        }
+    });
+    assert_eq!(runs.load(Ordering::Acquire), 1);
+}
@@ -0,0 +1,22 @@
+use crate::{mmf::*, padded::*, states::*};
+use std::num::NonZeroUsize;
+
+#[test]
+pub fn test_padded_header() {
+    assert_eq!(Padded::<NoLock, 4>::lock_size(), 4);
+    assert_eq!(Padded::<RWLock, 16>::lock_size(), RWLock::SIZE);
+    assert_eq!(CacheAligned::<RWLock>::lock_size(), 64);
+
+    let size = NonZeroUsize::new(64).unwrap();
+    let mmf = MemoryMappedFile::<CacheAligned<RWLock>>::new(size, "test_padded_header", Namespace::LOCAL)
+        .expect("creation failed");
+    assert_eq!(mmf.ptr() as usize % 64, 0);
+    mmf.write(b"On a line of its own").expect("Failed to write");
+    assert_eq!(mmf.read(20).expect("Failed to read"), b"On a line of its own");
+
+    // The lock itself didn't move, a bare one at the start of the view sees the padded one
+    let bare = unsafe { RWLock::from_existing(mmf.ptr().sub(64)) };
+    mmf.lock().try_lock_write().expect("Failed to lock");
+    assert!(bare.writelocked());
+    mmf.lock().unlock_write().expect("Failed to unlock");
+}
@@ -0,0 +1,33 @@
+use crate::{raw_lock::*, states::*};
+use lock_api::RwLockWriteGuard;
+
+#[test]
+pub fn test_shared_rwlock() {
+    let mut buf = vec![0u32; RWLock::SIZE / 4];
+    let ptr = buf.as_mut_ptr();
+    let lock = unsafe { RWLock::from_raw(ptr.cast()).initialize() };
+    let other = unsafe { RWLock::from_existing(ptr.cast()) };
+    let shared = SharedRwLock::from_raw(RawRWLock::new(lock), vec![1u8, 2, 3]);
+
+    let mut writer = shared.write();
+    writer.push(4);
+    assert!(other.writelocked());
+    assert!(shared.try_read().is_none());
+    let reader = RwLockWriteGuard::downgrade(writer);
+    // Downgrading never let go, and others can read along now
+    assert!(!other.writelocked());
+    other.try_lock_read().expect("Failed to lock");
+    assert_eq!(other.snapshot().reader_count, 2);
+    other.unlock_read().expect("Failed to unlock");
+    assert_eq!(*reader, [1, 2, 3, 4]);
+    drop(reader);
+    assert!(!other.locked());
+    assert!(!shared.is_locked());
+}
+
+#[test]
+#[should_panic]
+pub fn test_shared_rwlock_init_panics() {
+    let shared: SharedRwLock<'_, ()> = SharedRwLock::new(());
+    drop(shared.read());
+}
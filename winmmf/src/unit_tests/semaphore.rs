@@ -0,0 +1,48 @@
+use crate::{semaphore::*, Error};
+use std::{
+    num::NonZeroU32,
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+#[test]
+pub fn test_semaphore() {
+    let mut buf = vec![0u32; Semaphore::SIZE / 4];
+    let ptr = buf.as_mut_ptr();
+    assert!(matches!(unsafe { Semaphore::from_existing(ptr.cast()) }, Err(Error::Uninitialized)));
+    let pool = unsafe { Semaphore::create(ptr.cast(), NonZeroU32::new(3).unwrap()) };
+    let other = unsafe { Semaphore::from_existing(ptr.cast()) }.expect("Failed to attach");
+    assert_eq!((other.max(), other.available()), (3, 3));
+
+    pool.try_acquire(2).expect("Failed to acquire");
+    assert!(matches!(other.try_acquire(2), Err(Error::Exhausted)));
+    other.try_acquire(1).expect("Failed to acquire");
+    assert!(matches!(other.try_acquire(4), Err(Error::PermitsExceeded)));
+    other.try_acquire(0).expect("Nothing is always available");
+
+    other.release(3).expect("Failed to release");
+    assert!(matches!(pool.release(1), Err(Error::PermitsExceeded)));
+    assert_eq!(pool.available(), 3);
+}
+
+#[test]
+pub fn test_semaphore_threads() {
+    let mut buf = vec![0u32; Semaphore::SIZE / 4];
+    let pool = unsafe { Semaphore::create(buf.as_mut_ptr().cast(), NonZeroU32::new(2).unwrap()) }
+        .with_spin_strategy(crate::states::SpinStrategy::Yield);
+    let (inside, peak) = (AtomicU32::new(0), AtomicU32::new(0));
+    std::thread::scope(|s| {
+        for _ in 0..8 {
+            s.spawn(|| {
+                for _ in 0..100 {
+                    pool.acquire(1).expect("Failed to acquire");
+                    peak.fetch_max(inside.fetch_add(1, Ordering::AcqRel) + 1, Ordering::AcqRel);
+                    std::thread::yield_now();
+                    inside.fetch_sub(1, Ordering::AcqRel);
+                    pool.release(1).expect("Failed to release");
+                }
+            });
+        }
+    });
+    assert!(peak.load(Ordering::Acquire) <= 2);
+    assert_eq!(pool.available(), 2);
+}
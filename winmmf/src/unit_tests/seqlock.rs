@@ -0,0 +1,48 @@
+use crate::{seqlock::*, Error};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+#[test]
+pub fn test_seqlock() {
+    let mut buf = vec![0u32; (SeqLock::HEADER_SIZE + 16) / 4];
+    let ptr = buf.as_mut_ptr();
+    let lock = unsafe { SeqLock::new(ptr.cast(), 16) };
+    assert_eq!(lock.read_seqlock(|data| data.to_vec()), Some(vec![0; 16]));
+
+    let written = lock.write_seqlock(|data| {
+        data.fill(7);
+        data.len()
+    });
+    assert_eq!(written.unwrap(), 16);
+    assert_eq!(lock.sequence(), 2);
+    assert_eq!(lock.read_seqlock(|data| data.to_vec()), Some(vec![7; 16]));
+
+    // A writer that never finished
+    unsafe { AtomicU32::from_ptr(ptr).store(3, Ordering::Release) };
+    let stuck = unsafe { SeqLock::new(ptr.cast(), 16) }.with_max_tries(10);
+    assert_eq!(stuck.read_seqlock(|data| data[0]), None);
+    assert!(matches!(stuck.write_seqlock(|_| ()), Err(Error::Timeout)));
+}
+
+#[test]
+pub fn test_seqlock_no_torn_reads() {
+    let mut buf = vec![0u32; (SeqLock::HEADER_SIZE + 64) / 4];
+    let lock = unsafe { SeqLock::new(buf.as_mut_ptr().cast(), 64) };
+    let done = AtomicBool::new(false);
+    std::thread::scope(|s| {
+        for _ in 0..3 {
+            s.spawn(|| {
+                while !done.load(Ordering::Acquire) {
+                    // Every write fills the data with a single value, so a mix means a torn read got through.
+                    if let Some(consistent) = lock.read_seqlock(|data| data.iter().all(|b| *b == data[0])) {
+                        assert!(consistent);
+                    }
+                }
+            });
+        }
+        for round in 0..10_000u32 {
+            lock.write_seqlock(|data| data.fill(round as u8)).expect("Failed to write");
+        }
+        done.store(true, Ordering::Release);
+    });
+    assert_eq!(lock.sequence(), 20_000);
+}
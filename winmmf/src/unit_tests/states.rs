@@ -0,0 +1,737 @@
+use crate::{states::*, Error};
+use std::sync::atomic::{AtomicU32, Ordering};
+
+#[test]
+pub fn test_write_owner_registered() {
+    let mut buf = vec![0u32; RWLock::SIZE / 4];
+    let lock = unsafe { RWLock::from_raw(buf.as_mut_ptr().cast()).initialize() };
+    assert_eq!(lock.write_owner(), None);
+    lock.try_lock_write().expect("Failed to lock");
+    assert_eq!(lock.write_owner(), Some(std::process::id()));
+    lock.unlock_write().expect("Failed to unlock");
+    assert_eq!(lock.write_owner(), None);
+}
+
+#[test]
+pub fn test_force_unlock_if_dead() {
+    let mut buf = vec![0u32; RWLock::SIZE / 4];
+    let ptr = buf.as_mut_ptr();
+    let lock = unsafe { RWLock::from_raw(ptr.cast()).initialize() };
+    let other = unsafe { RWLock::from_existing(ptr.cast()) };
+    lock.try_lock_write().expect("Failed to lock");
+    // We're very much alive, so this shouldn't do anything
+    assert!(!other.force_unlock_if_dead().expect("Failed to check owner"));
+    assert!(other.writelocked());
+
+    // PIDs are multiples of 4 on Windows, so this one can't exist.
+    unsafe { AtomicU32::from_ptr(ptr.add(1)).store(u32::MAX - 2, Ordering::Release) };
+    assert!(other.force_unlock_if_dead().expect("Failed to check owner"));
+    assert!(!other.writelocked());
+    assert_eq!(other.write_owner(), None);
+    assert!(other.try_lock_write().is_ok());
+}
+
+#[test]
+pub fn test_poison_detection() {
+    let mut buf = vec![0u32; RWLock::SIZE / 4];
+    let ptr = buf.as_mut_ptr();
+    let lock = unsafe { RWLock::from_raw(ptr.cast()).initialize() };
+    assert!(!lock.is_poisoned());
+
+    // Write bit and a reader at the same time
+    unsafe { AtomicU32::from_ptr(ptr).store(RWLock::WRITE_LOCK_MASK | 1, Ordering::Release) };
+    assert!(lock.is_poisoned());
+    assert!(matches!(lock.try_lock_read(), Err(Error::Poisoned)));
+    assert!(matches!(lock.try_lock_write(), Err(Error::Poisoned)));
+
+    lock.clear_poison().expect("Failed to clear poison");
+    assert!(!lock.is_poisoned());
+    assert!(!lock.locked());
+    assert!(lock.try_lock_read().is_ok());
+    assert!(lock.clear_poison().is_err());
+}
+
+#[test]
+pub fn test_describe() {
+    let init = RWLock::INITIALIZED;
+    assert_eq!(RWLock::describe(0, init), "RWLock{ init=true, writers=0, readers=0 }");
+    assert_eq!(RWLock::describe(3, init), "RWLock{ init=true, writers=0, readers=3 }");
+    assert_eq!(RWLock::describe(RWLock::WRITE_LOCK_MASK, init), "RWLock{ init=true, writers=1, readers=0 }");
+    assert_eq!(
+        RWLock::describe(RWLock::WRITE_LOCK_MASK | 3, init),
+        "RWLock{ init=true, writers=1, readers=3, poisoned=true }"
+    );
+    assert_eq!(RWLock::describe(0, RWLock::UNINITIALIZED), "RWLock{ init=false, writers=0, readers=0 }");
+    assert_eq!(RWLock::describe(0, 0), "RWLock{ init=false, writers=0, readers=0 }");
+    assert_eq!(RWLock::describe(RWLock::SPARE_MASK | 2, init), "RWLock{ init=true, writers=0, readers=2 }");
+
+    let mut buf = vec![0u32; RWLock::SIZE / 4];
+    let lock = unsafe { RWLock::from_raw(buf.as_mut_ptr().cast()).initialize() };
+    lock.try_lock_read().expect("Failed to lock");
+    let other = unsafe { RWLock::from_existing(buf.as_mut_ptr().cast()) };
+    other.try_lock_read().expect("Failed to lock");
+    assert_eq!(lock.to_string(), "RWLock[W:0 R:2]");
+    assert_eq!(
+        format!("{lock:?}"),
+        concat!(
+            "RWLock { global: { write: false, readers: 2 }, ",
+            "local: { write_held: false, read_count: 1 }, initialized: true }"
+        )
+    );
+}
+
+#[cfg(feature = "metrics")]
+#[test]
+pub fn test_metrics() {
+    let mut buf = vec![0u32; RWLock::SIZE / 4];
+    let ptr = buf.as_mut_ptr();
+    let lock = unsafe { RWLock::from_raw(ptr.cast()).initialize() };
+    let other = unsafe { RWLock::from_existing(ptr.cast()) };
+
+    lock.try_lock_write().expect("Failed to lock");
+    assert!(other.try_lock_read().is_err());
+    assert!(other.try_lock_write().is_err());
+    lock.unlock_write().expect("Failed to unlock");
+    other.try_lock_read().expect("Failed to lock");
+
+    assert_eq!(lock.metrics(), LockMetrics { write_acquired: 1, ..Default::default() });
+    assert_eq!(
+        other.metrics(),
+        LockMetrics { read_contended: 1, write_contended: 1, read_acquired: 1, write_acquired: 0 }
+    );
+}
+
+#[test]
+pub fn test_snapshot() {
+    let mut buf = vec![0u32; RWLock::SIZE / 4];
+    let ptr = buf.as_mut_ptr();
+    let lock = unsafe { RWLock::from_raw(ptr.cast()) };
+    assert_eq!(lock.snapshot(), LockSnapshot::default());
+    lock.set_init();
+
+    let other = unsafe { RWLock::from_existing(ptr.cast()) };
+    lock.try_lock_read().expect("Failed to lock");
+    other.try_lock_read().expect("Failed to lock");
+    assert_eq!(
+        lock.snapshot(),
+        LockSnapshot {
+            write_locked: false,
+            reader_count: 2,
+            initialized: true,
+            local_reader_count: 1,
+            local_write_held: false
+        }
+    );
+    assert_eq!(lock.snapshot().to_string(), "init=true, write=false, readers=2 (local: write=false, readers=1)");
+}
+
+#[test]
+pub fn test_lock_counting() {
+    let mut buf = vec![0u32; RWLock::SIZE / 4];
+    let ptr = buf.as_mut_ptr();
+    let lock = unsafe { RWLock::from_raw(ptr.cast()).initialize() };
+    let other = unsafe { RWLock::from_existing(ptr.cast()) };
+    assert!(matches!(lock.lock_read_counting(), Ok(1)));
+    assert!(matches!(other.lock_read_counting(), Ok(2)));
+    assert!(matches!(lock.try_lock_write_state(), Err(Error::ReadLocked)));
+    lock.unlock_read().expect("Failed to unlock");
+    other.unlock_read().expect("Failed to unlock");
+
+    let before = lock.lock_write_state().expect("Failed to lock");
+    assert_eq!(before, LockSnapshot { initialized: true, ..Default::default() });
+    assert!(lock.snapshot().local_write_held);
+    lock.unlock_write().expect("Failed to unlock");
+    assert!(matches!(other.try_lock_read_counting(), Ok(1)));
+}
+
+#[test]
+pub fn test_lock_write_contended() {
+    let mut buf = vec![0u32; RWLock::SIZE / 4];
+    let ptr = buf.as_mut_ptr() as usize;
+    let _lock = unsafe { RWLock::from_raw((ptr as *mut u32).cast()).initialize() };
+    let holding = AtomicU32::new(0);
+    std::thread::scope(|s| {
+        for _ in 0..2 {
+            s.spawn(|| {
+                let lock = unsafe { RWLock::from_existing((ptr as *mut u32).cast()) };
+                for _ in 0..10_000 {
+                    let acquired = lock.try_lock_write().is_ok();
+                    // Only the instance that actually got the lock may believe it holds it
+                    assert_eq!(acquired, lock.snapshot().local_write_held);
+                    if acquired {
+                        assert_eq!(holding.fetch_add(1, Ordering::AcqRel), 0);
+                        holding.fetch_sub(1, Ordering::AcqRel);
+                        lock.unlock_write().expect("Failed to unlock");
+                    }
+                    assert!(!lock.snapshot().local_write_held);
+                }
+            });
+        }
+    });
+}
+
+#[test]
+pub fn test_force_unlock_write() {
+    let mut buf = vec![0u32; RWLock::SIZE / 4];
+    let ptr = buf.as_mut_ptr();
+    let crashed = unsafe { RWLock::from_raw(ptr.cast()).initialize() };
+    let other = unsafe { RWLock::from_existing(ptr.cast()) };
+    crashed.try_lock_write().expect("Failed to lock");
+    assert!(other.try_lock_read().is_err());
+    assert!(other.unlock_write().is_err());
+
+    unsafe { other.force_unlock_write() };
+    assert!(!other.writelocked());
+    assert_eq!(other.write_owner(), None);
+    assert!(other.try_lock_read().is_ok());
+    assert!(other.unlock_read().is_ok());
+}
+
+#[test]
+pub fn test_unlock_read_not_locked() {
+    let mut buf = vec![0u32; RWLock::SIZE / 4];
+    let ptr = buf.as_mut_ptr();
+    let lock = unsafe { RWLock::from_raw(ptr.cast()).initialize() };
+    let other = unsafe { RWLock::from_existing(ptr.cast()) };
+    assert!(matches!(lock.unlock_read(), Err(Error::NotLocked)));
+
+    // Somebody else holding a read lock doesn't mean we can release it
+    other.try_lock_read().expect("Failed to lock");
+    assert!(matches!(lock.unlock_read(), Err(Error::NotLocked)));
+
+    // Shared counter was released from under us, which should never happen
+    lock.try_lock_read().expect("Failed to lock");
+    unsafe { AtomicU32::from_ptr(ptr).store(0, Ordering::Release) };
+    assert!(matches!(lock.unlock_read(), Err(Error::Poisoned)));
+}
+
+#[test]
+pub fn test_lock_timeout() {
+    let mut buf = vec![0u32; RWLock::SIZE / 4];
+    let ptr = buf.as_mut_ptr() as usize;
+    let lock = unsafe { RWLock::from_raw((ptr as *mut u32).cast()).initialize() };
+    let (locked_tx, locked_rx) = std::sync::mpsc::channel();
+    let (done_tx, done_rx) = std::sync::mpsc::channel::<()>();
+    let holder = std::thread::spawn(move || {
+        let other = unsafe { RWLock::from_existing((ptr as *mut u32).cast()) };
+        other.try_lock_write().expect("Failed to lock");
+        locked_tx.send(()).unwrap();
+        _ = done_rx.recv();
+        other.unlock_write().expect("Failed to unlock");
+    });
+    locked_rx.recv().unwrap();
+
+    let timeout = std::time::Duration::from_millis(50);
+    let started = std::time::Instant::now();
+    assert!(!lock.lock_write_timeout(timeout).expect("Fatal error while waiting"));
+    assert!(!lock.lock_read_timeout(timeout).expect("Fatal error while waiting"));
+    let elapsed = started.elapsed();
+    assert!(elapsed >= timeout * 2 && elapsed < timeout * 20, "Took {elapsed:?}");
+
+    done_tx.send(()).unwrap();
+    holder.join().unwrap();
+    assert!(lock.lock_write_timeout(timeout).expect("Fatal error while waiting"));
+    lock.unlock_write().expect("Failed to unlock");
+}
+
+#[test]
+pub fn test_heartbeat() {
+    let mut buf = vec![0u32; RWLock::SIZE / 4];
+    let ptr = buf.as_mut_ptr();
+    let lock = unsafe { RWLock::from_raw(ptr.cast()).initialize() };
+    let other = unsafe { RWLock::from_existing(ptr.cast()) };
+    assert!(!other.is_writer_alive());
+    assert!(!lock.heartbeat());
+
+    lock.try_lock_write().expect("Failed to lock");
+    assert!(lock.heartbeat());
+    assert!(lock.heartbeat());
+    assert!(!other.heartbeat());
+    assert_eq!(other.writer_heartbeat(), 2);
+    assert!(other.is_writer_alive());
+
+    // PIDs are multiples of 4 on Windows, so this one can't exist.
+    unsafe { AtomicU32::from_ptr(ptr.add(1)).store(u32::MAX - 2, Ordering::Release) };
+    assert!(!other.is_writer_alive());
+    unsafe { other.force_unlock_write() };
+    assert!(other.try_lock_write().is_ok());
+    assert_eq!(other.writer_heartbeat(), 0);
+}
+
+#[test]
+pub fn test_generation() {
+    let mut buf = vec![0u32; RWLock::SIZE / 4];
+    let ptr = buf.as_mut_ptr();
+    let lock = unsafe { RWLock::from_raw(ptr.cast()).initialize() };
+    let other = unsafe { RWLock::from_existing(ptr.cast()) };
+    assert_eq!(lock.generation(), Some(0));
+
+    other.try_lock_write().expect("Failed to lock");
+    assert!(!lock.has_changed(0));
+    other.unlock_write().expect("Failed to unlock");
+    assert!(lock.has_changed(0));
+    assert_eq!(lock.generation(), Some(1));
+    // Reads don't count
+    other.try_lock_read().expect("Failed to lock");
+    other.unlock_read().expect("Failed to unlock");
+    assert!(!lock.has_changed(1));
+
+    // Wrapping around still counts as a change
+    unsafe { AtomicU32::from_ptr(ptr.add(4)).store(u32::MAX, Ordering::Release) };
+    let seen = lock.generation().unwrap();
+    lock.try_lock_write().expect("Failed to lock");
+    lock.unlock_write().expect("Failed to unlock");
+    assert_eq!(lock.generation(), Some(0));
+    assert!(lock.has_changed(seen));
+    assert_eq!(lock.writes_since(seen), 1);
+}
+
+#[test]
+pub fn test_transfer_write() {
+    let mut buf = vec![0u32; RWLock::SIZE / 4];
+    let lock = unsafe { RWLock::from_raw(buf.as_mut_ptr().cast()).initialize() };
+    let worker = lock.try_clone().expect("Failed to clone");
+    let mut elsewhere = vec![0u32; RWLock::SIZE / 4];
+    let stranger = unsafe { RWLock::from_raw(elsewhere.as_mut_ptr().cast()).initialize() };
+    assert!(matches!(lock.transfer_write(&worker), Err(Error::NotLocked)));
+
+    lock.try_lock_write().expect("Failed to lock");
+    assert!(matches!(lock.transfer_write(&stranger), Err(Error::LockViolation)));
+    lock.transfer_write(&worker).expect("Failed to hand over");
+    assert!(lock.writelocked());
+    // Handing it over twice doesn't work, and neither does releasing what we no longer hold
+    assert!(matches!(lock.transfer_write(&worker), Err(Error::NotLocked)));
+    assert!(lock.unlock_write().is_err());
+    assert_eq!((worker.snapshot().local_write_held, lock.snapshot().local_write_held), (true, false));
+
+    // And back again, across threads
+    std::thread::scope(|s| {
+        s.spawn(|| worker.transfer_write(&lock).expect("Failed to hand back"));
+    });
+    lock.transfer_write(&lock).expect("Handing it to yourself changes nothing");
+    lock.unlock_write().expect("Failed to unlock");
+    assert!(!lock.locked());
+    assert!(matches!(worker.unlock_write(), Ok(())));
+}
+
+#[test]
+pub fn test_spare_bits() {
+    let mut buf = vec![0u32; RWLock::SIZE / 4];
+    let ptr = buf.as_mut_ptr();
+    // Zeroes aren't a valid init state, somebody has to set the lock up first
+    assert!(matches!(unsafe { RWLock::from_existing(ptr.cast()) }.try_lock_read(), Err(Error::Uninitialized)));
+    let lock = unsafe { RWLock::from_raw(ptr.cast()).initialize() };
+    unsafe { AtomicU32::from_ptr(ptr).fetch_or(RWLock::SPARE_MASK, Ordering::AcqRel) };
+
+    let other = unsafe { RWLock::from_existing(ptr.cast()) };
+    assert!(other.initialized());
+    assert!(!other.locked());
+    other.try_lock_write().expect("Failed to lock");
+    assert!(lock.try_lock_read().is_err());
+    other.unlock_write().expect("Failed to unlock");
+    lock.try_lock_read().expect("Failed to lock");
+    lock.unlock_read().expect("Failed to unlock");
+    // Nobody touched the spare bits along the way
+    assert_eq!(unsafe { AtomicU32::from_ptr(ptr).load(Ordering::Acquire) }, RWLock::SPARE_MASK);
+}
+
+#[test]
+pub fn test_init_handshake() {
+    let mut buf = vec![0u32; RWLock::SIZE / 4];
+    let ptr = buf.as_mut_ptr();
+    let winner = unsafe { RWLock::from_raw(ptr.cast()) };
+    let loser = unsafe { RWLock::from_existing(ptr.cast()) };
+    assert!(winner.try_claim_init());
+    assert!(!loser.try_claim_init());
+    // The winner is still busy, so the loser has to wait and can't lock anything
+    assert!(matches!(loser.init_or_wait(std::time::Duration::from_millis(10)), Err(Error::Uninitialized)));
+    assert!(loser.try_lock_write().is_err());
+
+    winner.set_init();
+    assert!(!loser.init_or_wait(std::time::Duration::from_millis(10)).expect("Failed to wait for init"));
+    assert!(loser.try_lock_write().is_ok());
+    assert!(loser.unlock_write().is_ok());
+}
+
+#[test]
+pub fn test_lock_or_spin() {
+    let mut buf = vec![0u32; RWLock::SIZE / 4];
+    let ptr = buf.as_mut_ptr();
+    let lock = unsafe { RWLock::from_raw(ptr.cast()).initialize() };
+    let other = unsafe { RWLock::from_existing(ptr.cast()) };
+    lock.lock_read_or_spin(10).expect("Failed to lock");
+    assert!(matches!(other.lock_write_or_spin(10), Err(Error::Timeout)));
+    lock.unlock_read().expect("Failed to unlock");
+    other.lock_write_or_spin(10).expect("Failed to lock");
+    assert!(matches!(lock.lock_read_or_spin(10), Err(Error::Timeout)));
+    other.unlock_write().expect("Failed to unlock");
+}
+
+#[test]
+pub fn test_try_clone_threads() {
+    let mut buf = vec![0u32; RWLock::SIZE / 4];
+    let lock = unsafe { RWLock::from_raw(buf.as_mut_ptr().cast()) };
+    assert!(matches!(lock.try_clone(), Err(Error::Uninitialized)));
+    lock.set_init();
+
+    let (first, second) = (lock.try_clone().expect("Failed to clone"), lock.try_clone().expect("Failed to clone"));
+    std::thread::scope(|s| {
+        s.spawn(|| first.try_lock_read().expect("Failed to lock"));
+        s.spawn(|| second.try_lock_read().expect("Failed to lock"));
+    });
+    assert_eq!(lock.snapshot().reader_count, 2);
+    // Neither the original nor a clone can release what another instance holds
+    assert!(matches!(lock.unlock_read(), Err(Error::NotLocked)));
+    first.unlock_read().expect("Failed to unlock");
+    assert!(matches!(first.unlock_read(), Err(Error::NotLocked)));
+    assert_eq!(second.snapshot().local_reader_count, 1);
+    second.unlock_read().expect("Failed to unlock");
+    assert!(!lock.readlocked());
+}
+
+#[test]
+pub fn test_lock_at_offset() {
+    let mut buf = vec![0u32; RWLock::SIZE / 4 + 4];
+    let ptr = buf.as_mut_ptr().cast::<u8>();
+    assert!(matches!(unsafe { RWLock::from_raw_at(ptr, 6) }, Err(Error::MisalignedLock)));
+    let lock = unsafe { RWLock::from_raw_at(ptr, 8) }.expect("Aligned offset was rejected").initialize();
+    let other = unsafe { RWLock::from_existing_at(ptr, 8) }.expect("Aligned offset was rejected");
+    lock.try_lock_write().expect("Failed to lock");
+    assert!(other.writelocked());
+    assert_eq!(buf[0..2], [0, 0]);
+}
+
+#[test]
+pub fn test_rwlock_send_sync() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<RWLock>();
+    let mut buf = vec![0u32; RWLock::SIZE / 4];
+    let lock = std::sync::Arc::new(unsafe { RWLock::from_raw(buf.as_mut_ptr().cast()).initialize() });
+    let remote = lock.clone();
+    std::thread::scope(|s| s.spawn(move || remote.try_lock_write().expect("Failed to lock")).join().unwrap());
+    assert!(lock.writelocked());
+}
+
+#[test]
+pub fn test_wait_until_unlocked() {
+    let mut buf = vec![0u32; RWLock::SIZE / 4];
+    let ptr = buf.as_mut_ptr();
+    let lock = unsafe { RWLock::from_raw(ptr.cast()).initialize() };
+    let other = unsafe { RWLock::from_existing(ptr.cast()) };
+    assert!(lock.wait_until_unlocked(std::time::Duration::ZERO).is_ok());
+
+    other.try_lock_write().expect("Failed to lock");
+    let timeout = std::time::Duration::from_millis(20);
+    assert!(matches!(lock.wait_until_unlocked(timeout), Err(Error::Timeout)));
+    std::thread::scope(|s| {
+        s.spawn(|| {
+            std::thread::sleep(timeout);
+            other.unlock_write().expect("Failed to unlock");
+        });
+        assert!(lock.wait_until_unlocked(std::time::Duration::from_secs(5)).is_ok());
+    });
+}
+
+#[cfg(feature = "wake_by_address")]
+#[test]
+pub fn test_wait_for_unlock() {
+    let mut buf = vec![0u32; RWLock::SIZE / 4];
+    let ptr = buf.as_mut_ptr();
+    let lock = unsafe { RWLock::from_raw(ptr.cast()).initialize() };
+    let other = unsafe { RWLock::from_existing(ptr.cast()) };
+    assert!(lock.wait_for_unlock(std::time::Duration::ZERO).is_ok());
+
+    other.try_lock_write().expect("Failed to lock");
+    assert!(matches!(other.wait_for_unlock(std::time::Duration::from_secs(5)), Err(Error::WriteLocked)));
+    let timeout = std::time::Duration::from_millis(20);
+    assert!(matches!(lock.wait_for_unlock(timeout), Err(Error::Timeout)));
+    std::thread::scope(|s| {
+        s.spawn(|| {
+            std::thread::sleep(timeout);
+            other.unlock_write().expect("Failed to unlock");
+        });
+        let started = std::time::Instant::now();
+        assert!(lock.wait_for_unlock(std::time::Duration::from_secs(5)).is_ok());
+        assert!(started.elapsed() < std::time::Duration::from_secs(5));
+    });
+    lock.try_lock_write().expect("Failed to lock");
+}
+
+#[test]
+pub fn test_local_reader_limit() {
+    let mut buf = vec![0u32; RWLock::SIZE / 4];
+    let ptr = buf.as_mut_ptr();
+    let lock = unsafe { RWLock::from_raw(ptr.cast()).initialize().with_local_limit(2) };
+    let other = unsafe { RWLock::from_existing(ptr.cast()) };
+    assert_eq!(lock.local_limit(), 2);
+    assert_eq!(other.local_limit(), RWLock::HOLDING_R);
+
+    lock.try_lock_read().expect("Failed to lock");
+    lock.try_lock_read().expect("Failed to lock");
+    assert!(matches!(lock.try_lock_read(), Err(Error::LocalReaderLimit)));
+    // The limit is local, so anyone else can still get in
+    other.try_lock_read().expect("Failed to lock");
+    assert_eq!(lock.snapshot().reader_count, 3);
+
+    // The global limit is a different beast
+    unsafe { AtomicU32::from_ptr(ptr).store(RWLock::READ_LOCK_MASK, Ordering::Release) };
+    assert!(matches!(other.try_lock_read(), Err(Error::MaxReaders)));
+}
+
+#[test]
+pub fn test_blocking_lock() {
+    let mut buf = vec![0u32; RWLock::SIZE / 4];
+    let ptr = buf.as_mut_ptr() as usize;
+    let lock = unsafe { RWLock::from_raw((ptr as *mut u32).cast()).initialize() };
+    lock.try_lock_write().expect("Failed to lock");
+
+    std::thread::scope(|s| {
+        let waiter = s.spawn(move || {
+            let other = unsafe { RWLock::from_existing((ptr as *mut u32).cast()) };
+            assert!(matches!(other.try_lock_read(), Err(Error::WriteLocked)));
+            other.lock_read().expect("Failed to wait for the lock");
+            other.unlock_read().expect("Failed to unlock");
+        });
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        lock.unlock_write().expect("Failed to unlock");
+        waiter.join().unwrap();
+    });
+    lock.lock_write().expect("Failed to lock");
+}
+
+#[test]
+pub fn test_no_poison_under_contention() {
+    let mut buf = vec![0u32; RWLock::SIZE / 4];
+    let lock = unsafe { RWLock::from_raw(buf.as_mut_ptr().cast()).initialize() };
+    let done = std::sync::atomic::AtomicBool::new(false);
+    let workers: Vec<_> = (0..4).map(|_| lock.try_clone().expect("Failed to clone")).collect();
+
+    std::thread::scope(|s| {
+        s.spawn(|| {
+            while !done.load(Ordering::Relaxed) {
+                assert!(!lock.is_poisoned(), "Read and write locked at the same time");
+            }
+        });
+        let handles: Vec<_> = workers
+            .iter()
+            .enumerate()
+            .map(|(i, worker)| {
+                s.spawn(move || {
+                    for round in 0..20_000 {
+                        // Half the threads start with reading, the other half with writing, so both keep racing.
+                        if (round + i) % 2 == 0 {
+                            if worker.try_lock_read().is_ok() {
+                                worker.unlock_read().expect("Failed to unlock");
+                            }
+                        } else if worker.try_lock_write().is_ok() {
+                            assert!(!worker.is_poisoned(), "Readers got in while write locked");
+                            worker.unlock_write().expect("Failed to unlock");
+                        }
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        done.store(true, Ordering::Relaxed);
+    });
+    assert!(!lock.locked());
+}
+
+#[test]
+pub fn test_with_read_write() {
+    let mut buf = vec![0u32; RWLock::SIZE / 4];
+    let ptr = buf.as_mut_ptr();
+    let uninit = unsafe { RWLock::from_raw(ptr.cast()) };
+    assert!(matches!(uninit.with_read(|| unreachable!()), Err(Error::Uninitialized)));
+
+    let lock = unsafe { RWLock::from_raw(ptr.cast()).initialize() };
+    assert!(lock.with_write(|| lock.writelocked()).expect("Failed to lock"));
+    assert!(lock.with_read(|| lock.readlocked() && !lock.writelocked()).expect("Failed to lock"));
+    assert!(!lock.locked());
+
+    // Panicking releases the lock on the way out
+    let panicked = std::panic::catch_unwind(|| lock.with_write(|| panic!("oh no")));
+    assert!(panicked.is_err());
+    assert!(!lock.locked());
+    assert_eq!(lock.generation(), Some(2));
+}
+
+#[test]
+pub fn test_spin_budget() {
+    let mut buf = vec![0u32; RWLock::SIZE / 4];
+    let ptr = buf.as_mut_ptr();
+    let lock = unsafe { RWLock::from_raw(ptr.cast()).initialize() };
+    let other = unsafe { RWLock::from_existing(ptr.cast()) };
+    let mut tries = 0;
+    assert!(!other.spin_budget(&mut tries, 2).expect("Failed to spin"));
+
+    lock.try_lock_write().expect("Failed to lock");
+    tries = 0;
+    assert!(other.spin_budget(&mut tries, 2).expect("Failed to spin"));
+    assert!(matches!(other.spin_budget(&mut tries, 2), Err(Error::Timeout)));
+    lock.unlock_write().expect("Failed to unlock");
+    assert!(!other.spin_budget(&mut tries, 2).expect("Failed to spin"));
+}
+
+#[cfg(feature = "tracing")]
+#[test]
+pub fn test_tracing() {
+    use std::{
+        fmt::{Debug, Write},
+        sync::{Arc, Mutex},
+    };
+    use tracing::{
+        field::{Field, Visit},
+        span, Event, Metadata, Subscriber,
+    };
+
+    /// Keeps every event as a line of text.
+    struct Recorder(Arc<Mutex<Vec<String>>>);
+    /// Writes the fields of an event into a line.
+    struct Line<'a>(&'a mut String);
+
+    impl Visit for Line<'_> {
+        fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+            _ = write!(self.0, "{}={:?} ", field.name(), value);
+        }
+    }
+
+    impl Subscriber for Recorder {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+        fn new_span(&self, _: &span::Attributes<'_>) -> span::Id {
+            span::Id::from_u64(1)
+        }
+        fn record(&self, _: &span::Id, _: &span::Record<'_>) {}
+        fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+        fn event(&self, event: &Event<'_>) {
+            let mut line = String::new();
+            event.record(&mut Line(&mut line));
+            self.0.lock().unwrap().push(line);
+        }
+        fn enter(&self, _: &span::Id) {}
+        fn exit(&self, _: &span::Id) {}
+    }
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    tracing::subscriber::with_default(Recorder(events.clone()), || {
+        let mut buf = vec![0u32; RWLock::SIZE / 4];
+        let lock = unsafe { RWLock::from_raw_named(buf.as_mut_ptr().cast(), "Local\\traced") }.initialize();
+        lock.try_lock_read().expect("Failed to lock");
+        lock.unlock_read().expect("Failed to unlock");
+        lock.try_lock_write().expect("Failed to lock");
+        // Failing doesn't count as acquiring
+        assert!(lock.try_lock_write().is_err());
+        lock.unlock_write().expect("Failed to unlock");
+    });
+
+    let events = events.lock().unwrap();
+    let messages = ["Read lock acquired", "Read lock released", "Write lock acquired", "Write lock released"];
+    assert_eq!(events.len(), messages.len());
+    for (event, message) in events.iter().zip(messages) {
+        assert!(event.contains(message), "{event}");
+        assert!(event.contains(r#"name="Local\\traced""#), "{event}");
+    }
+    assert!(events[0].contains("chunk=0x00000001"), "{}", events[0]);
+    assert!(events[0].contains("current_lock=0x01"), "{}", events[0]);
+    assert!(events[2].contains("writers=1"), "{}", events[2]);
+}
+
+#[test]
+pub fn test_recover_abandoned() {
+    let mut buf = vec![0u32; RWLock::SIZE / 4];
+    let ptr = buf.as_mut_ptr();
+    let dead = unsafe { RWLock::from_raw(ptr.cast()).initialize() };
+    let robust = unsafe { RWLock::from_existing(ptr.cast()) }.with_robust(true);
+    let other = unsafe { RWLock::from_existing(ptr.cast()) };
+    assert!(robust.is_robust());
+    dead.try_lock_write().expect("Failed to lock");
+    assert!(!other.recover_abandoned().expect("Failed to check owner"));
+
+    // PIDs are multiples of 4 on Windows, so this one can't exist.
+    unsafe { AtomicU32::from_ptr(ptr.add(1)).store(u32::MAX - 2, Ordering::Release) };
+    // Spinning finds out on its own, and only the first writer after that gets told
+    robust.lock_read_or_spin(8).expect("Failed to lock");
+    robust.unlock_read().expect("Failed to unlock");
+    assert!(!other.recover_abandoned().expect("Failed to check owner"));
+    assert!(matches!(other.lock_write_or_spin(8), Err(Error::Abandoned)));
+    assert!(!other.writelocked());
+    other.try_lock_write().expect("Failed to lock");
+    other.unlock_write().expect("Failed to unlock");
+    robust.try_lock_write().expect("Failed to lock");
+}
+
+#[test]
+pub fn test_init_race() {
+    const DATA: usize = 256;
+    for _ in 0..200 {
+        // Garbage everywhere, like memory nobody set up yet.
+        let mut buf = vec![0xEEEE_EEEEu32; (RWLock::SIZE + DATA) / 4];
+        let ptr = buf.as_mut_ptr() as usize;
+        let opener = std::thread::spawn(move || {
+            let lock = unsafe { RWLock::from_existing(ptr as *mut u8) };
+            lock.wait_initialized(std::time::Duration::from_secs(5))
+                .expect("Initialization never finished");
+            let data = unsafe { std::slice::from_raw_parts((ptr as *const u8).add(RWLock::SIZE), DATA) };
+            assert!(data.iter().all(|&b| b == 0x42), "Saw a half-initialized MMF");
+        });
+        let creator = std::thread::spawn(move || {
+            let lock = unsafe { RWLock::from_raw(ptr as *mut u8) };
+            assert!(lock.try_claim_init());
+            unsafe { (ptr as *mut u8).add(RWLock::SIZE).write_bytes(0x42, DATA) };
+            lock.set_init();
+        });
+        creator.join().expect("The creator panicked");
+        opener.join().expect("The opener panicked");
+        drop(buf);
+    }
+
+    let mut buf = vec![0u32; RWLock::SIZE / 4];
+    let lock = unsafe { RWLock::from_existing(buf.as_mut_ptr().cast()) };
+    assert!(matches!(lock.wait_initialized(std::time::Duration::from_millis(10)), Err(Error::Uninitialized)));
+}
+
+#[test]
+pub fn test_peek() {
+    let mut buf = vec![0u32; RWLock::SIZE / 4];
+    let lock = unsafe { RWLock::from_raw(buf.as_mut_ptr().cast()).initialize() };
+    assert!(!lock.peek_writelocked());
+    lock.try_lock_read().expect("Failed to lock");
+    lock.try_lock_read().expect("Failed to lock");
+    assert_eq!(lock.peek_readers(), 2);
+    assert_eq!(lock.peek_raw(), 2);
+    lock.unlock_read().expect("Failed to unlock");
+    lock.unlock_read().expect("Failed to unlock");
+    lock.try_lock_write().expect("Failed to lock");
+    assert!(lock.peek_writelocked());
+    assert_eq!(lock.peek_readers(), 0);
+    lock.unlock_write().expect("Failed to unlock");
+}
+
+#[test]
+pub fn test_poll_lock_write() {
+    let mut buf = vec![0u32; RWLock::SIZE / 4];
+    let lock = unsafe { RWLock::from_raw(buf.as_mut_ptr().cast()).initialize() };
+    let other = unsafe { RWLock::from_existing(buf.as_mut_ptr().cast()) };
+    lock.try_lock_read().expect("Failed to lock");
+
+    // Every frame spins a bit and hands control back, counting on from where the last one left off
+    let mut tries = 0;
+    for frame in 1..=3 {
+        assert!(other.poll_lock_write(&mut tries).expect("Failed to poll").is_pending());
+        assert_eq!(tries, frame * POLL_TRIES);
+    }
+    lock.unlock_read().expect("Failed to unlock");
+    assert!(other.poll_lock_write(&mut tries).expect("Failed to poll").is_ready());
+    assert_eq!(tries, 3 * POLL_TRIES);
+    assert!(other.writelocked());
+
+    // Running into the lock's own spin limit is an error, not pending
+    let mut tries = 0;
+    let limited = unsafe { RWLock::builder().max_spin_count(POLL_TRIES / 2).build_existing(buf.as_mut_ptr().cast()) };
+    assert!(matches!(limited.poll_lock_read(&mut tries), Err(Error::LockViolation)));
+    other.unlock_write().expect("Failed to unlock");
+}
@@ -0,0 +1,62 @@
+use crate::{states::*, ticket::*, Error};
+use std::{
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+#[test]
+pub fn test_ticket_lock() {
+    let mut buf = vec![0u32; TicketLock::SIZE / 4];
+    let ptr = buf.as_mut_ptr();
+    let lock = unsafe { TicketLock::from_raw(ptr.cast()) };
+    assert!(matches!(lock.try_lock_write(), Err(Error::Uninitialized)));
+    let lock = lock.initialize();
+    let other = unsafe { TicketLock::from_existing(ptr.cast()) };
+
+    lock.try_lock_read().expect("Failed to lock");
+    assert!(lock.readlocked() && !lock.writelocked());
+    assert!(matches!(other.try_lock_write(), Err(Error::ReadLocked)));
+    assert!(matches!(other.unlock_read(), Err(Error::NotLocked)));
+    assert!(matches!(other.lock_read_or_spin(3), Err(Error::Timeout)));
+    assert_eq!(lock.queue_len(), 1);
+    lock.unlock_read().expect("Failed to unlock");
+    assert!(!other.locked());
+
+    // The ticket word wraps around without the halves bleeding into each other
+    unsafe { *ptr = 0xFFFF_FFFF };
+    let lock = unsafe { TicketLock::from_existing(ptr.cast()) };
+    lock.lock_write().expect("Failed to lock");
+    assert_eq!(lock.queue_len(), 1);
+    lock.unlock_write().expect("Failed to unlock");
+    assert_eq!(unsafe { *ptr }, 0);
+}
+
+#[test]
+pub fn test_ticket_order() {
+    const WAITERS: usize = 8;
+    let mut buf = vec![0u32; TicketLock::SIZE / 4];
+    let addr = buf.as_mut_ptr() as usize;
+    let lock = unsafe { TicketLock::from_raw(addr as *mut u8).initialize() };
+    let order = Arc::new(Mutex::new(Vec::new()));
+
+    lock.lock_write().expect("Failed to lock");
+    let waiters: Vec<_> = (0..WAITERS)
+        .map(|id| {
+            let order = Arc::clone(&order);
+            let waiter = thread::spawn(move || {
+                let lock = unsafe { TicketLock::from_existing(addr as *mut u8) };
+                lock.with_write(|| order.lock().unwrap().push(id)).expect("Failed to lock");
+            });
+            // Let this one get in line before sending in the next
+            while usize::from(lock.queue_len()) < id + 2 {
+                thread::sleep(Duration::from_millis(1));
+            }
+            waiter
+        })
+        .collect();
+    lock.unlock_write().expect("Failed to unlock");
+    waiters.into_iter().for_each(|waiter| waiter.join().unwrap());
+    assert_eq!(*order.lock().unwrap(), (0..WAITERS).collect::<Vec<_>>());
+    assert!(!lock.locked());
+}